@@ -0,0 +1,843 @@
+//! The X API as the MCP tools see it
+//!
+//! [`XMcpServer`](crate::XMcpServer) calls X only through the [`XApi`] trait,
+//! which [`XClient`] implements. Implement it to run the tools against
+//! another backend, or against canned data in tests. Every method has a
+//! default that fails with "not supported", so a fake only needs the methods
+//! the tools it exercises call.
+
+use crate::circuit::{CircuitState, CircuitStatus};
+use crate::client::XClient;
+use crate::error::{XError, XResult};
+use crate::stream::TweetStream;
+use crate::types::{
+    ConversationTree, Includes, ListPage, Media, RateLimit, ReplyContext, SearchPage,
+    SearchTweetsParams, StreamRule, Trend, TrendLocation, Tweet, TweetIdRange, TweetLookup,
+    TweetPage, TwitterList, User, UserLookup,
+};
+#[cfg(feature = "write-ops")]
+use crate::types::{
+    BlockStatus, BookmarkStatus, CreatedList, FollowStatus, ListMemberStatus, MuteStatus,
+    PostTweetRequest, SentMessage, ThreadResult,
+};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+
+/// Future returned by the [`XApi`] methods
+pub type ApiFuture<'a, T> = Pin<Box<dyn Future<Output = XResult<T>> + Send + 'a>>;
+
+/// X API operations used by the MCP tools
+///
+/// The methods mirror the [`XClient`] methods of the same name.
+///
+/// ```rust
+/// use x_mcp_server::api::{ApiFuture, XApi};
+/// use x_mcp_server::types::User;
+///
+/// struct Fake;
+///
+/// impl XApi for Fake {
+///     fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+///         Box::pin(async move {
+///             Ok(Some(serde_json::from_value(serde_json::json!({
+///                 "id": "1",
+///                 "name": "Fake",
+///                 "username": username,
+///             }))?))
+///         })
+///     }
+/// }
+/// ```
+pub trait XApi: Send + Sync {
+    fn get_user_by_username<'a>(&'a self, _username: &'a str) -> ApiFuture<'a, Option<User>> {
+        unsupported("get_user_by_username")
+    }
+
+    fn get_user_by_id<'a>(&'a self, _user_id: &'a str) -> ApiFuture<'a, Option<User>> {
+        unsupported("get_user_by_id")
+    }
+
+    fn get_users_by_usernames<'a>(&'a self, _usernames: &'a [String]) -> ApiFuture<'a, UserLookup> {
+        unsupported("get_users_by_usernames")
+    }
+
+    fn get_pinned_tweet<'a>(&'a self, _username_or_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        unsupported("get_pinned_tweet")
+    }
+
+    fn search_users<'a>(&'a self, _query: &'a str, _count: u32) -> ApiFuture<'a, Vec<User>> {
+        unsupported("search_users")
+    }
+
+    fn search_tweets_with_includes(
+        &self,
+        _params: SearchTweetsParams,
+    ) -> ApiFuture<'_, (SearchPage, Includes)> {
+        unsupported("search_tweets")
+    }
+
+    fn get_tweet<'a>(&'a self, _tweet_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        unsupported("get_tweet")
+    }
+
+    fn get_tweet_with_media<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+    ) -> ApiFuture<'a, (Option<Tweet>, Vec<Media>)> {
+        unsupported("get_tweet")
+    }
+
+    fn get_tweets<'a>(&'a self, _tweet_ids: &'a [String]) -> ApiFuture<'a, TweetLookup> {
+        unsupported("get_tweets")
+    }
+
+    fn get_reply_context<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+        _max_replies: u32,
+    ) -> ApiFuture<'a, Option<ReplyContext>> {
+        unsupported("get_reply_context")
+    }
+
+    fn get_conversation_tree<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+        _max_depth: u32,
+        _max_tweets: u32,
+    ) -> ApiFuture<'a, Option<ConversationTree>> {
+        unsupported("get_conversation_tree")
+    }
+
+    fn get_conversation<'a>(
+        &'a self,
+        _conversation_id: &'a str,
+        _max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        unsupported("get_conversation")
+    }
+
+    fn get_quote_tweets_up_to<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        unsupported("get_quote_tweets")
+    }
+
+    fn get_bookmarks_up_to<'a>(
+        &'a self,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        unsupported("get_bookmarks")
+    }
+
+    fn get_list<'a>(&'a self, _list_id: &'a str) -> ApiFuture<'a, Option<TwitterList>> {
+        unsupported("get_list")
+    }
+
+    fn get_list_tweets_up_to<'a>(
+        &'a self,
+        _list_id: &'a str,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        unsupported("get_list_tweets")
+    }
+
+    fn get_owned_lists<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        unsupported("get_owned_lists")
+    }
+
+    fn get_list_memberships<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        unsupported("get_list_memberships")
+    }
+
+    fn get_followed_lists<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _limit: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        unsupported("get_followed_lists")
+    }
+
+    fn get_followers<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        unsupported("get_followers")
+    }
+
+    fn get_following<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        unsupported("get_following")
+    }
+
+    fn get_liking_users<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+        _max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        unsupported("get_liking_users")
+    }
+
+    fn get_retweeters<'a>(
+        &'a self,
+        _tweet_id: &'a str,
+        _max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        unsupported("get_retweeters")
+    }
+
+    fn get_user_tweets<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        unsupported("get_user_tweets")
+    }
+
+    fn get_user_tweets_between<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _start_time: DateTime<Utc>,
+        _end_time: DateTime<Utc>,
+        _max_pages: usize,
+        _pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        unsupported("get_user_tweets_between")
+    }
+
+    fn get_user_tweets_with_references<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Includes)> {
+        unsupported("get_user_tweets")
+    }
+
+    fn get_user_original_tweets<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: u32,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        unsupported("get_user_original_tweets")
+    }
+
+    fn get_user_media_tweets<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Vec<Media>)> {
+        unsupported("get_user_media_tweets")
+    }
+
+    fn get_trend_locations(&self) -> ApiFuture<'_, Vec<TrendLocation>> {
+        unsupported("get_trend_locations")
+    }
+
+    fn connect_filtered_stream(&self) -> ApiFuture<'_, TweetStream> {
+        unsupported("connect_filtered_stream")
+    }
+
+    fn get_stream_rules(&self) -> ApiFuture<'_, Vec<StreamRule>> {
+        unsupported("get_stream_rules")
+    }
+
+    fn find_woeid<'a>(&'a self, _place_name: &'a str) -> ApiFuture<'a, Option<TrendLocation>> {
+        unsupported("find_woeid")
+    }
+
+    fn get_trends(&self, _woeid: u32) -> ApiFuture<'_, Vec<Trend>> {
+        unsupported("get_trends")
+    }
+
+    fn get_rate_limit_status(&self) -> ApiFuture<'_, BTreeMap<String, RateLimit>> {
+        unsupported("get_rate_limit_status")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet<'a>(&'a self, _request: &'a PostTweetRequest) -> ApiFuture<'a, Tweet> {
+        unsupported("post_tweet")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet_once<'a>(
+        &'a self,
+        _request: &'a PostTweetRequest,
+        _key: &'a str,
+    ) -> ApiFuture<'a, (Tweet, bool)> {
+        unsupported("post_tweet")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet_once_with<'a>(
+        &'a self,
+        _draft: &'a PostTweetRequest,
+        _key: &'a str,
+        _request: ApiFuture<'a, PostTweetRequest>,
+    ) -> ApiFuture<'a, (Tweet, bool)> {
+        unsupported("post_tweet")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_thread_until<'a>(
+        &'a self,
+        _texts: &'a [String],
+        _resume_token: Option<&'a str>,
+        _cancel: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    ) -> ApiFuture<'a, ThreadResult> {
+        unsupported("post_thread")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn delete_tweet<'a>(&'a self, _tweet_id: &'a str) -> ApiFuture<'a, bool> {
+        unsupported("delete_tweet")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn upload_media<'a>(&'a self, _bytes: &'a [u8], _media_type: &'a str) -> ApiFuture<'a, String> {
+        unsupported("upload_media")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn follow_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, FollowStatus> {
+        unsupported("follow_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unfollow_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, FollowStatus> {
+        unsupported("unfollow_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn block_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, BlockStatus> {
+        unsupported("block_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unblock_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, BlockStatus> {
+        unsupported("unblock_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn mute_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, MuteStatus> {
+        unsupported("mute_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unmute_user<'a>(&'a self, _target_user_id: &'a str) -> ApiFuture<'a, MuteStatus> {
+        unsupported("unmute_user")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn bookmark_tweet<'a>(&'a self, _tweet_id: &'a str) -> ApiFuture<'a, BookmarkStatus> {
+        unsupported("bookmark_tweet")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn remove_bookmark<'a>(&'a self, _tweet_id: &'a str) -> ApiFuture<'a, BookmarkStatus> {
+        unsupported("remove_bookmark")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn set_reply_hidden<'a>(&'a self, _tweet_id: &'a str, _hidden: bool) -> ApiFuture<'a, bool> {
+        unsupported("set_reply_hidden")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn create_list<'a>(
+        &'a self,
+        _name: &'a str,
+        _description: Option<&'a str>,
+        _private: bool,
+    ) -> ApiFuture<'a, CreatedList> {
+        unsupported("create_list")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn add_list_member<'a>(&'a self, _list_id: &'a str, _user_id: &'a str) -> ApiFuture<'a, ListMemberStatus> {
+        unsupported("add_list_member")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn remove_list_member<'a>(&'a self, _list_id: &'a str, _user_id: &'a str) -> ApiFuture<'a, ListMemberStatus> {
+        unsupported("remove_list_member")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn add_stream_rule<'a>(&'a self, _value: &'a str, _tag: Option<&'a str>) -> ApiFuture<'a, StreamRule> {
+        unsupported("add_stream_rule")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn delete_stream_rules<'a>(&'a self, _rule_ids: &'a [String]) -> ApiFuture<'a, u64> {
+        unsupported("delete_stream_rules")
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn send_dm<'a>(&'a self, _recipient_id: &'a str, _text: &'a str) -> ApiFuture<'a, SentMessage> {
+        unsupported("send_dm")
+    }
+
+    /// State of the circuit breaker, closed for backends without one
+    fn circuit_status(&self) -> CircuitStatus {
+        CircuitStatus {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            retry_after_secs: None,
+        }
+    }
+
+    /// Requests currently in flight
+    fn in_flight(&self) -> usize {
+        0
+    }
+
+    /// Most requests allowed in flight at once
+    fn max_concurrency(&self) -> usize {
+        0
+    }
+}
+
+fn unsupported<'a, T>(operation: &str) -> ApiFuture<'a, T> {
+    let message = format!("{} is not supported by this backend", operation);
+    Box::pin(async move { Err(XError::Generic(message)) })
+}
+
+impl XApi for XClient {
+    fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(XClient::get_user_by_username(self, username))
+    }
+
+    fn get_user_by_id<'a>(&'a self, user_id: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(XClient::get_user_by_id(self, user_id))
+    }
+
+    fn get_users_by_usernames<'a>(&'a self, usernames: &'a [String]) -> ApiFuture<'a, UserLookup> {
+        Box::pin(XClient::get_users_by_usernames(self, usernames))
+    }
+
+    fn get_pinned_tweet<'a>(&'a self, username_or_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        Box::pin(XClient::get_pinned_tweet(self, username_or_id))
+    }
+
+    fn search_users<'a>(&'a self, query: &'a str, count: u32) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(XClient::search_users(self, query, count))
+    }
+
+    fn search_tweets_with_includes(
+        &self,
+        params: SearchTweetsParams,
+    ) -> ApiFuture<'_, (SearchPage, Includes)> {
+        Box::pin(XClient::search_tweets_with_includes(self, params))
+    }
+
+    fn get_tweet<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        Box::pin(XClient::get_tweet(self, tweet_id))
+    }
+
+    fn get_tweet_with_media<'a>(
+        &'a self,
+        tweet_id: &'a str,
+    ) -> ApiFuture<'a, (Option<Tweet>, Vec<Media>)> {
+        Box::pin(XClient::get_tweet_with_media(self, tweet_id))
+    }
+
+    fn get_tweets<'a>(&'a self, tweet_ids: &'a [String]) -> ApiFuture<'a, TweetLookup> {
+        Box::pin(XClient::get_tweets(self, tweet_ids))
+    }
+
+    fn get_reply_context<'a>(
+        &'a self,
+        tweet_id: &'a str,
+        max_replies: u32,
+    ) -> ApiFuture<'a, Option<ReplyContext>> {
+        Box::pin(XClient::get_reply_context(self, tweet_id, max_replies))
+    }
+
+    fn get_conversation_tree<'a>(
+        &'a self,
+        tweet_id: &'a str,
+        max_depth: u32,
+        max_tweets: u32,
+    ) -> ApiFuture<'a, Option<ConversationTree>> {
+        Box::pin(XClient::get_conversation_tree(
+            self, tweet_id, max_depth, max_tweets,
+        ))
+    }
+
+    fn get_conversation<'a>(
+        &'a self,
+        conversation_id: &'a str,
+        max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(XClient::get_conversation(
+            self,
+            conversation_id,
+            max_results,
+        ))
+    }
+
+    fn get_quote_tweets_up_to<'a>(
+        &'a self,
+        tweet_id: &'a str,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        Box::pin(XClient::get_quote_tweets_up_to(
+            self,
+            tweet_id,
+            limit,
+            pagination_token,
+        ))
+    }
+
+    fn get_bookmarks_up_to<'a>(
+        &'a self,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        Box::pin(XClient::get_bookmarks_up_to(self, limit, pagination_token))
+    }
+
+    fn get_list<'a>(&'a self, list_id: &'a str) -> ApiFuture<'a, Option<TwitterList>> {
+        Box::pin(XClient::get_list(self, list_id))
+    }
+
+    fn get_list_tweets_up_to<'a>(
+        &'a self,
+        list_id: &'a str,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        Box::pin(XClient::get_list_tweets_up_to(
+            self,
+            list_id,
+            limit,
+            pagination_token,
+        ))
+    }
+
+    fn get_owned_lists<'a>(
+        &'a self,
+        user_id: &'a str,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        Box::pin(XClient::get_owned_lists(
+            self,
+            user_id,
+            limit,
+            pagination_token,
+        ))
+    }
+
+    fn get_list_memberships<'a>(
+        &'a self,
+        user_id: &'a str,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        Box::pin(XClient::get_list_memberships(
+            self,
+            user_id,
+            limit,
+            pagination_token,
+        ))
+    }
+
+    fn get_followed_lists<'a>(
+        &'a self,
+        user_id: &'a str,
+        limit: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, ListPage> {
+        Box::pin(XClient::get_followed_lists(
+            self,
+            user_id,
+            limit,
+            pagination_token,
+        ))
+    }
+
+    fn get_followers<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(XClient::get_followers(self, user_id, max_results))
+    }
+
+    fn get_following<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(XClient::get_following(self, user_id, max_results))
+    }
+
+    fn get_liking_users<'a>(
+        &'a self,
+        tweet_id: &'a str,
+        max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(XClient::get_liking_users(self, tweet_id, max_results))
+    }
+
+    fn get_retweeters<'a>(
+        &'a self,
+        tweet_id: &'a str,
+        max_results: Option<u32>,
+    ) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(XClient::get_retweeters(self, tweet_id, max_results))
+    }
+
+    fn get_user_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(XClient::get_user_tweets(self, user_id, max_results, ids))
+    }
+
+    fn get_user_tweets_between<'a>(
+        &'a self,
+        user_id: &'a str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        max_pages: usize,
+        pagination_token: Option<&'a str>,
+    ) -> ApiFuture<'a, TweetPage> {
+        Box::pin(XClient::get_user_tweets_between(
+            self,
+            user_id,
+            start_time,
+            end_time,
+            max_pages,
+            pagination_token,
+        ))
+    }
+
+    fn get_user_tweets_with_references<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Includes)> {
+        Box::pin(XClient::get_user_tweets_with_references(
+            self,
+            user_id,
+            max_results,
+            ids,
+        ))
+    }
+
+    fn get_user_original_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: u32,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(XClient::get_user_original_tweets(
+            self,
+            user_id,
+            max_results,
+        ))
+    }
+
+    fn get_user_media_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Vec<Media>)> {
+        Box::pin(XClient::get_user_media_tweets(self, user_id, max_results, ids))
+    }
+
+    fn get_trend_locations(&self) -> ApiFuture<'_, Vec<TrendLocation>> {
+        Box::pin(XClient::get_trend_locations(self))
+    }
+
+    fn connect_filtered_stream(&self) -> ApiFuture<'_, TweetStream> {
+        Box::pin(XClient::connect_filtered_stream(self))
+    }
+
+    fn get_stream_rules(&self) -> ApiFuture<'_, Vec<StreamRule>> {
+        Box::pin(XClient::get_stream_rules(self))
+    }
+
+    fn find_woeid<'a>(&'a self, place_name: &'a str) -> ApiFuture<'a, Option<TrendLocation>> {
+        Box::pin(XClient::find_woeid(self, place_name))
+    }
+
+    fn get_trends(&self, woeid: u32) -> ApiFuture<'_, Vec<Trend>> {
+        Box::pin(XClient::get_trends(self, woeid))
+    }
+
+    fn get_rate_limit_status(&self) -> ApiFuture<'_, BTreeMap<String, RateLimit>> {
+        Box::pin(XClient::get_rate_limit_status(self))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet<'a>(&'a self, request: &'a PostTweetRequest) -> ApiFuture<'a, Tweet> {
+        Box::pin(XClient::post_tweet(self, request))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet_once<'a>(
+        &'a self,
+        request: &'a PostTweetRequest,
+        key: &'a str,
+    ) -> ApiFuture<'a, (Tweet, bool)> {
+        Box::pin(XClient::post_tweet_once(self, request, key))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet_once_with<'a>(
+        &'a self,
+        draft: &'a PostTweetRequest,
+        key: &'a str,
+        request: ApiFuture<'a, PostTweetRequest>,
+    ) -> ApiFuture<'a, (Tweet, bool)> {
+        Box::pin(XClient::post_tweet_once_with(self, draft, key, request))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_thread_until<'a>(
+        &'a self,
+        texts: &'a [String],
+        resume_token: Option<&'a str>,
+        cancel: Pin<Box<dyn Future<Output = ()> + Send + 'a>>,
+    ) -> ApiFuture<'a, ThreadResult> {
+        Box::pin(XClient::post_thread_until(
+            self,
+            texts,
+            resume_token,
+            cancel,
+        ))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn delete_tweet<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, bool> {
+        Box::pin(XClient::delete_tweet(self, tweet_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn upload_media<'a>(&'a self, bytes: &'a [u8], media_type: &'a str) -> ApiFuture<'a, String> {
+        Box::pin(XClient::upload_media(self, bytes, media_type))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn follow_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, FollowStatus> {
+        Box::pin(XClient::follow_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unfollow_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, FollowStatus> {
+        Box::pin(XClient::unfollow_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn block_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, BlockStatus> {
+        Box::pin(XClient::block_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unblock_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, BlockStatus> {
+        Box::pin(XClient::unblock_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn mute_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, MuteStatus> {
+        Box::pin(XClient::mute_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn unmute_user<'a>(&'a self, target_user_id: &'a str) -> ApiFuture<'a, MuteStatus> {
+        Box::pin(XClient::unmute_user(self, target_user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn bookmark_tweet<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, BookmarkStatus> {
+        Box::pin(XClient::bookmark_tweet(self, tweet_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn remove_bookmark<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, BookmarkStatus> {
+        Box::pin(XClient::remove_bookmark(self, tweet_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn set_reply_hidden<'a>(&'a self, tweet_id: &'a str, hidden: bool) -> ApiFuture<'a, bool> {
+        Box::pin(XClient::set_reply_hidden(self, tweet_id, hidden))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn create_list<'a>(
+        &'a self,
+        name: &'a str,
+        description: Option<&'a str>,
+        private: bool,
+    ) -> ApiFuture<'a, CreatedList> {
+        Box::pin(XClient::create_list(self, name, description, private))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn add_list_member<'a>(&'a self, list_id: &'a str, user_id: &'a str) -> ApiFuture<'a, ListMemberStatus> {
+        Box::pin(XClient::add_list_member(self, list_id, user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn remove_list_member<'a>(&'a self, list_id: &'a str, user_id: &'a str) -> ApiFuture<'a, ListMemberStatus> {
+        Box::pin(XClient::remove_list_member(self, list_id, user_id))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn add_stream_rule<'a>(&'a self, value: &'a str, tag: Option<&'a str>) -> ApiFuture<'a, StreamRule> {
+        Box::pin(XClient::add_stream_rule(self, value, tag))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn delete_stream_rules<'a>(&'a self, rule_ids: &'a [String]) -> ApiFuture<'a, u64> {
+        Box::pin(XClient::delete_stream_rules(self, rule_ids))
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn send_dm<'a>(&'a self, recipient_id: &'a str, text: &'a str) -> ApiFuture<'a, SentMessage> {
+        Box::pin(XClient::send_dm(self, recipient_id, text))
+    }
+
+    fn circuit_status(&self) -> CircuitStatus {
+        XClient::circuit_status(self)
+    }
+
+    fn in_flight(&self) -> usize {
+        XClient::in_flight(self)
+    }
+
+    fn max_concurrency(&self) -> usize {
+        XClient::max_concurrency(self)
+    }
+}
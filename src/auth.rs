@@ -0,0 +1,444 @@
+//! OAuth 1.0a request signing for user-context endpoints
+//!
+//! Posting needs user context, which X grants either through an OAuth 2.0
+//! user token passed as `X_BEARER_TOKEN` or through OAuth 1.0a consumer keys
+//! and access tokens. With [`OAuthCredentials`] set on the client, every
+//! request is signed as described in
+//! <https://developer.x.com/en/docs/authentication/oauth-1-0a/creating-a-signature>.
+//!
+//! Credentials reach the client through a [`CredentialProvider`], which is
+//! asked before every signed request. Implement it to read from a secrets
+//! manager, a file or a keychain; plain credentials are a provider that never
+//! changes, and [`EnvProvider`] reads the environment variables.
+
+use crate::error::{XError, XResult};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use rand::{distributions::Alphanumeric, Rng};
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+
+type HmacSha1 = Hmac<sha1::Sha1>;
+type HmacSha256 = Hmac<sha2::Sha256>;
+
+/// Characters OAuth leaves unescaped (RFC 3986 unreserved)
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Algorithm used to sign requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignatureMethod {
+    /// `HMAC-SHA1`, accepted everywhere
+    #[default]
+    HmacSha1,
+    /// `HMAC-SHA256`, for deployments where SHA1 is not allowed
+    HmacSha256,
+}
+
+impl SignatureMethod {
+    /// Value of the `oauth_signature_method` parameter
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HmacSha1 => "HMAC-SHA1",
+            Self::HmacSha256 => "HMAC-SHA256",
+        }
+    }
+}
+
+impl FromStr for SignatureMethod {
+    type Err = XError;
+
+    fn from_str(s: &str) -> XResult<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "HMAC-SHA1" => Ok(Self::HmacSha1),
+            "HMAC-SHA256" => Ok(Self::HmacSha256),
+            other => Err(XError::Config(format!(
+                "Unsupported OAuth signature method: {} (expected HMAC-SHA1 or HMAC-SHA256)",
+                other
+            ))),
+        }
+    }
+}
+
+/// OAuth 1.0a consumer and access token credentials
+#[derive(Clone)]
+pub struct OAuthCredentials {
+    pub consumer_key: String,
+    pub consumer_secret: String,
+    pub access_token: String,
+    pub access_token_secret: String,
+    pub signature_method: SignatureMethod,
+}
+
+impl std::fmt::Debug for OAuthCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OAuthCredentials")
+            .field("consumer_key", &self.consumer_key)
+            .field("access_token", &self.access_token)
+            .field("signature_method", &self.signature_method)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuthCredentials {
+    /// Create credentials that sign with HMAC-SHA1
+    pub fn new(
+        consumer_key: String,
+        consumer_secret: String,
+        access_token: String,
+        access_token_secret: String,
+    ) -> Self {
+        Self {
+            consumer_key,
+            consumer_secret,
+            access_token,
+            access_token_secret,
+            signature_method: SignatureMethod::default(),
+        }
+    }
+
+    /// Sign with `method` instead of HMAC-SHA1
+    pub fn with_signature_method(mut self, method: SignatureMethod) -> Self {
+        self.signature_method = method;
+        self
+    }
+
+    /// Read credentials from `X_CONSUMER_KEY`, `X_CONSUMER_SECRET`,
+    /// `X_ACCESS_TOKEN` and `X_ACCESS_TOKEN_SECRET`, plus the optional `X_OAUTH_SIGNATURE_METHOD`
+    ///
+    /// Returns `None` when none of the four are set, and an error when only
+    /// some are.
+    pub fn from_env() -> XResult<Option<Self>> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// [`from_env`](Self::from_env) reading variables through `lookup`
+    pub(crate) fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> XResult<Option<Self>> {
+        let names = [
+            "X_CONSUMER_KEY",
+            "X_CONSUMER_SECRET",
+            "X_ACCESS_TOKEN",
+            "X_ACCESS_TOKEN_SECRET",
+        ];
+        let values: Vec<Option<String>> = names
+            .iter()
+            .map(|name| lookup(name).filter(|value| !value.is_empty()))
+            .collect();
+
+        if values.iter().all(Option::is_none) {
+            return Ok(None);
+        }
+        let missing: Vec<&str> = names
+            .iter()
+            .zip(&values)
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| *name)
+            .collect();
+        if !missing.is_empty() {
+            return Err(XError::Config(format!(
+                "Incomplete OAuth 1.0a credentials, missing {}",
+                missing.join(", ")
+            )));
+        }
+
+        let mut values = values.into_iter().flatten();
+        let mut next = || values.next().unwrap_or_default();
+        let credentials = Self::new(next(), next(), next(), next());
+        Ok(Some(match lookup("X_OAUTH_SIGNATURE_METHOD") {
+            Some(method) if !method.is_empty() => {
+                credentials.with_signature_method(method.parse()?)
+            }
+            _ => credentials,
+        }))
+    }
+
+    /// `Authorization` header value for a request with a fresh nonce and
+    /// timestamp
+    ///
+    /// `params` are the query (or form body) parameters; JSON bodies are not
+    /// part of the signature.
+    pub fn authorization_header(
+        &self,
+        method: &str,
+        url: &str,
+        params: &[(&str, String)],
+    ) -> String {
+        let nonce: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        self.authorization_header_with(method, url, params, &nonce, &timestamp)
+    }
+
+    fn authorization_header_with(
+        &self,
+        method: &str,
+        url: &str,
+        params: &[(&str, String)],
+        nonce: &str,
+        timestamp: &str,
+    ) -> String {
+        let mut oauth_params = vec![
+            ("oauth_consumer_key", self.consumer_key.clone()),
+            ("oauth_nonce", nonce.to_string()),
+            (
+                "oauth_signature_method",
+                self.signature_method.as_str().to_string(),
+            ),
+            ("oauth_timestamp", timestamp.to_string()),
+            ("oauth_token", self.access_token.clone()),
+            ("oauth_version", "1.0".to_string()),
+        ];
+
+        let all_params: Vec<(&str, &str)> = oauth_params
+            .iter()
+            .chain(params)
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+        let signature = self.generate_signature(method, url, &all_params);
+        oauth_params.push(("oauth_signature", signature));
+
+        let fields: Vec<String> = oauth_params
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", encode(name), encode(value)))
+            .collect();
+        format!("OAuth {}", fields.join(", "))
+    }
+
+    /// Sign a request given all its `oauth_*` and request parameters
+    ///
+    /// Returns the base64 `oauth_signature`, computed with the configured
+    /// signature method.
+    pub fn generate_signature(&self, method: &str, url: &str, params: &[(&str, &str)]) -> String {
+        let mut encoded: Vec<(String, String)> = params
+            .iter()
+            .map(|(name, value)| (encode(name), encode(value)))
+            .collect();
+        encoded.sort();
+        let parameter_string = encoded
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let base_string = format!(
+            "{}&{}&{}",
+            method.to_ascii_uppercase(),
+            encode(url),
+            encode(&parameter_string)
+        );
+        let key = format!(
+            "{}&{}",
+            encode(&self.consumer_secret),
+            encode(&self.access_token_secret)
+        );
+
+        let digest = match self.signature_method {
+            SignatureMethod::HmacSha1 => sign::<HmacSha1>(&key, &base_string),
+            SignatureMethod::HmacSha256 => sign::<HmacSha256>(&key, &base_string),
+        };
+        STANDARD.encode(digest)
+    }
+}
+
+/// Future returned by [`CredentialProvider::credentials`]
+pub type CredentialsFuture<'a> =
+    Pin<Box<dyn Future<Output = XResult<OAuthCredentials>> + Send + 'a>>;
+
+/// Source of the OAuth 1.0a credentials requests are signed with
+///
+/// The client asks for credentials before each signed request, so a provider
+/// can rotate them; providers that fetch remotely should cache.
+///
+/// ```rust
+/// use x_mcp_server::auth::{CredentialProvider, CredentialsFuture, OAuthCredentials};
+///
+/// struct Vault;
+///
+/// impl CredentialProvider for Vault {
+///     fn credentials(&self) -> CredentialsFuture<'_> {
+///         Box::pin(async {
+///             // Look the secrets up here
+///             Ok(OAuthCredentials::new(
+///                 "key".into(),
+///                 "secret".into(),
+///                 "token".into(),
+///                 "token secret".into(),
+///             ))
+///         })
+///     }
+/// }
+/// ```
+pub trait CredentialProvider: Send + Sync {
+    /// Current credentials
+    fn credentials(&self) -> CredentialsFuture<'_>;
+}
+
+impl std::fmt::Debug for dyn CredentialProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CredentialProvider")
+    }
+}
+
+impl CredentialProvider for OAuthCredentials {
+    fn credentials(&self) -> CredentialsFuture<'_> {
+        Box::pin(async move { Ok(self.clone()) })
+    }
+}
+
+/// Provider reading the `X_CONSUMER_KEY`, `X_CONSUMER_SECRET`,
+/// `X_ACCESS_TOKEN`, `X_ACCESS_TOKEN_SECRET` and `X_OAUTH_SIGNATURE_METHOD`
+/// environment variables on every request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvProvider;
+
+impl CredentialProvider for EnvProvider {
+    fn credentials(&self) -> CredentialsFuture<'_> {
+        Box::pin(async {
+            OAuthCredentials::from_env()?.ok_or_else(|| {
+                XError::Auth("OAuth 1.0a credentials are not set in the environment".to_string())
+            })
+        })
+    }
+}
+
+fn sign<M: Mac + hmac::digest::KeyInit>(key: &str, message: &str) -> Vec<u8> {
+    let mut mac =
+        <M as Mac>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn encode(value: &str) -> String {
+    utf8_percent_encode(value, UNRESERVED).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from X's "Creating a signature" guide
+    const METHOD: &str = "POST";
+    const URL: &str = "https://api.twitter.com/1.1/statuses/update.json";
+
+    fn credentials() -> OAuthCredentials {
+        OAuthCredentials::new(
+            "xvz1evFS4wEEPTGEFPHBog".to_string(),
+            "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw".to_string(),
+            "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb".to_string(),
+            "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE".to_string(),
+        )
+    }
+
+    fn params(method: SignatureMethod) -> Vec<(&'static str, &'static str)> {
+        vec![
+            (
+                "status",
+                "Hello Ladies + Gentlemen, a signed OAuth request!",
+            ),
+            ("include_entities", "true"),
+            ("oauth_consumer_key", "xvz1evFS4wEEPTGEFPHBog"),
+            ("oauth_nonce", "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg"),
+            ("oauth_signature_method", method.as_str()),
+            ("oauth_timestamp", "1318622958"),
+            (
+                "oauth_token",
+                "370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb",
+            ),
+            ("oauth_version", "1.0"),
+        ]
+    }
+
+    #[test]
+    fn test_hmac_sha1_signature() {
+        let signature =
+            credentials().generate_signature(METHOD, URL, &params(SignatureMethod::HmacSha1));
+        assert_eq!(signature, "hCtSmYh+iHYCEqBWrE7C7hYmtUk=");
+    }
+
+    #[test]
+    fn test_hmac_sha256_signature() {
+        let signature = credentials()
+            .with_signature_method(SignatureMethod::HmacSha256)
+            .generate_signature(METHOD, URL, &params(SignatureMethod::HmacSha256));
+        assert_eq!(signature, "PLbq+OWUE2vwiOZeZBSR06GFvymUHoaBdCIHyD66IcM=");
+    }
+
+    #[test]
+    fn test_authorization_header() {
+        let credentials = credentials().with_signature_method(SignatureMethod::HmacSha256);
+        let header = credentials.authorization_header_with(
+            METHOD,
+            URL,
+            &[
+                (
+                    "status",
+                    "Hello Ladies + Gentlemen, a signed OAuth request!".to_string(),
+                ),
+                ("include_entities", "true".to_string()),
+            ],
+            "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+            "1318622958",
+        );
+        assert!(header.starts_with("OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\""));
+        assert!(header.contains("oauth_signature_method=\"HMAC-SHA256\""));
+        assert!(
+            header.contains("oauth_signature=\"PLbq%2BOWUE2vwiOZeZBSR06GFvymUHoaBdCIHyD66IcM%3D\"")
+        );
+    }
+
+    #[test]
+    fn test_authorization_header_exact() {
+        // The whole header for the guide's request, with the default HMAC-SHA1
+        let header = credentials().authorization_header_with(
+            METHOD,
+            URL,
+            &[
+                (
+                    "status",
+                    "Hello Ladies + Gentlemen, a signed OAuth request!".to_string(),
+                ),
+                ("include_entities", "true".to_string()),
+            ],
+            "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg",
+            "1318622958",
+        );
+        assert_eq!(
+            header,
+            "OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", \
+             oauth_nonce=\"kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg\", \
+             oauth_signature_method=\"HMAC-SHA1\", oauth_timestamp=\"1318622958\", \
+             oauth_token=\"370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb\", \
+             oauth_version=\"1.0\", oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_credentials_as_provider() {
+        let provider: Box<dyn CredentialProvider> = Box::new(credentials());
+        let provided = provider.credentials().await.unwrap();
+        assert_eq!(provided.consumer_key, "xvz1evFS4wEEPTGEFPHBog");
+        assert_eq!(provided.signature_method, SignatureMethod::HmacSha1);
+    }
+
+    #[test]
+    fn test_signature_method_parsing() {
+        assert_eq!(
+            "hmac-sha256".parse::<SignatureMethod>().unwrap(),
+            SignatureMethod::HmacSha256
+        );
+        assert_eq!(
+            "HMAC-SHA1".parse::<SignatureMethod>().unwrap(),
+            SignatureMethod::HmacSha1
+        );
+        assert!("PLAINTEXT".parse::<SignatureMethod>().is_err());
+        assert_eq!(SignatureMethod::default(), SignatureMethod::HmacSha1);
+    }
+}
@@ -0,0 +1,70 @@
+//! Capabilities the server advertises in the initialize handshake
+//!
+//! Every capability advertised is served and every one left out is refused
+//! with "method not found", so clients see the same feature set in the
+//! handshake as in the requests that follow. Tools are the only feature with
+//! content; `resources` and `prompts` list nothing, for embedders that add
+//! their own later or whose clients expect the methods to exist.
+
+use rmcp::model::{
+    LoggingLevel, PromptsCapability, ResourcesCapability, ServerCapabilities, ToolsCapability,
+};
+use serde_json::Map;
+
+/// Features advertised by [`XMcpServer`](crate::XMcpServer)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// `tools/list` and `tools/call`
+    pub tools: bool,
+    /// `resources/list` and `resources/templates/list`
+    pub resources: bool,
+    /// `prompts/list`
+    pub prompts: bool,
+    /// `logging/setLevel`, after which failed tool calls are also reported
+    /// as `notifications/message` warnings
+    pub logging: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            resources: false,
+            prompts: false,
+            logging: false,
+        }
+    }
+}
+
+impl From<Capabilities> for ServerCapabilities {
+    fn from(capabilities: Capabilities) -> Self {
+        // The tool, resource and prompt sets are static, so the server never
+        // promises change notifications
+        ServerCapabilities {
+            tools: capabilities.tools.then(ToolsCapability::default),
+            resources: capabilities.resources.then(ResourcesCapability::default),
+            prompts: capabilities.prompts.then(PromptsCapability::default),
+            logging: capabilities.logging.then(Map::new),
+            ..Default::default()
+        }
+    }
+}
+
+/// Whether a client that asked for `threshold` and above wants a message at
+/// `level`
+pub(crate) fn level_enabled(threshold: LoggingLevel, level: LoggingLevel) -> bool {
+    severity(level) >= severity(threshold)
+}
+
+fn severity(level: LoggingLevel) -> u8 {
+    match level {
+        LoggingLevel::Debug => 0,
+        LoggingLevel::Info => 1,
+        LoggingLevel::Notice => 2,
+        LoggingLevel::Warning => 3,
+        LoggingLevel::Error => 4,
+        LoggingLevel::Critical => 5,
+        LoggingLevel::Alert => 6,
+        LoggingLevel::Emergency => 7,
+    }
+}
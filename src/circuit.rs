@@ -0,0 +1,208 @@
+//! Circuit breaker that stops calling X during an outage
+//!
+//! After `failure_threshold` consecutive server errors (5xx) or transport
+//! failures the circuit opens and requests fail fast with
+//! [`XError::CircuitOpen`] instead of spending rate limit on a failing API.
+//! Once `cooldown` has passed, a single probe request is let through: success
+//! closes the circuit, failure opens it for another cooldown.
+
+use crate::error::{XError, XResult};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Thresholds of a [`CircuitBreaker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a probe is allowed
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Whether requests are flowing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Requests go through
+    Closed,
+    /// Requests fail fast until the cooldown ends
+    Open,
+    /// Cooldown over; the next request probes whether X has recovered
+    HalfOpen,
+}
+
+/// Snapshot of the breaker for status reporting
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CircuitStatus {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    /// Seconds until a probe is allowed, while open
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probe_started: Option<Instant>,
+}
+
+/// Consecutive-failure circuit breaker shared by clones of a client
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Create a closed breaker
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Thresholds of this breaker
+    pub fn config(&self) -> CircuitBreakerConfig {
+        self.config
+    }
+
+    /// Check whether a request may be sent, claiming the probe when half-open
+    pub fn check(&self) -> XResult<()> {
+        let mut inner = self.lock();
+        let Some(opened_at) = inner.opened_at else {
+            return Ok(());
+        };
+
+        let elapsed = opened_at.elapsed();
+        if elapsed < self.config.cooldown {
+            return Err(XError::CircuitOpen {
+                retry_after_secs: (self.config.cooldown - elapsed).as_secs().max(1),
+            });
+        }
+
+        // A probe whose request was dropped never reports back, so it
+        // expires after another cooldown
+        match inner.probe_started {
+            Some(started) if started.elapsed() < self.config.cooldown => Err(XError::CircuitOpen {
+                retry_after_secs: 1,
+            }),
+            _ => {
+                inner.probe_started = Some(Instant::now());
+                Ok(())
+            }
+        }
+    }
+
+    /// Record a request X answered without a server error
+    pub fn record_success(&self) {
+        *self.lock() = Inner::default();
+    }
+
+    /// Record a server error or transport failure
+    pub fn record_failure(&self) {
+        let mut inner = self.lock();
+        inner.consecutive_failures += 1;
+        inner.probe_started = None;
+        if inner.opened_at.is_some() || inner.consecutive_failures >= self.config.failure_threshold
+        {
+            if inner.opened_at.is_none() {
+                tracing::warn!(
+                    "X API failed {} times in a row, pausing requests for {:?}",
+                    inner.consecutive_failures,
+                    self.config.cooldown
+                );
+            }
+            inner.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Current state of the breaker
+    pub fn status(&self) -> CircuitStatus {
+        let inner = self.lock();
+        let (state, retry_after_secs) = match inner.opened_at {
+            None => (CircuitState::Closed, None),
+            Some(opened_at) => match self.config.cooldown.checked_sub(opened_at.elapsed()) {
+                Some(remaining) if !remaining.is_zero() => {
+                    (CircuitState::Open, Some(remaining.as_secs().max(1)))
+                }
+                _ => (CircuitState::HalfOpen, None),
+            },
+        };
+        CircuitStatus {
+            state,
+            consecutive_failures: inner.consecutive_failures,
+            retry_after_secs,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown,
+        })
+    }
+
+    #[test]
+    fn test_opens_after_threshold() {
+        let breaker = breaker(Duration::from_secs(60));
+        breaker.record_failure();
+        assert!(breaker.check().is_ok());
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.status().state, CircuitState::Closed);
+
+        breaker.record_failure();
+        assert_eq!(breaker.status().state, CircuitState::Open);
+        assert!(matches!(
+            breaker.check(),
+            Err(XError::CircuitOpen { retry_after_secs }) if retry_after_secs > 0
+        ));
+    }
+
+    #[test]
+    fn test_half_open_probe() {
+        let cooldown = Duration::from_millis(20);
+        let breaker = breaker(cooldown);
+        breaker.record_failure();
+        breaker.record_failure();
+        std::thread::sleep(cooldown);
+        assert_eq!(breaker.status().state, CircuitState::HalfOpen);
+
+        // One probe at a time; a failed probe reopens the circuit
+        assert!(breaker.check().is_ok());
+        assert!(breaker.check().is_err());
+        breaker.record_failure();
+        assert_eq!(breaker.status().state, CircuitState::Open);
+
+        std::thread::sleep(cooldown);
+        assert!(breaker.check().is_ok());
+        breaker.record_success();
+        assert_eq!(breaker.status().state, CircuitState::Closed);
+        assert_eq!(breaker.status().consecutive_failures, 0);
+    }
+}
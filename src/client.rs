@@ -1,230 +1,2660 @@
 //! X API client implementation using Bearer Token
 
+use crate::auth::{CredentialProvider, OAuthCredentials};
+use crate::circuit::{CircuitBreaker, CircuitBreakerConfig, CircuitStatus};
+use crate::config::{RetryConfig, RetryPolicy, ServerConfig, DEFAULT_BASE_URL, DEFAULT_TIMEOUT, DEFAULT_V1_BASE_URL};
 use crate::error::{XError, XResult};
-use crate::types::{SearchTweetsParams, Tweet, User, XResponse};
-use reqwest::Client;
+use crate::metrics::{endpoint_path, Metrics};
+use crate::redact;
+use crate::replay::{RecordedExchange, ReplayMode};
+use crate::stream::{TweetStream, STALL_TIMEOUT};
+use crate::types::{
+    AuthoredSearchPage, ConversationNode, ConversationTree, Includes, ListPage, Media, MissingUser, RateLimit, RateLimitStatus,
+    PlaceTrends, ReplyContext, SearchPage, SearchTweetsParams, StreamRule, Trend, TrendLocation, Tweet, TweetIdRange, TweetLookup, TweetPage,
+    TwitterList, User, UserLookup, UserPage, V1User, XApiError, XApiErrorResponse, XResponse,
+};
+#[cfg(feature = "write-ops")]
+use crate::idempotency::IdempotencyLog;
+#[cfg(feature = "write-ops")]
+use crate::types::{
+    BlockStatus, BookmarkStatus, CreatedList, DeletedTweet, FollowStatus, HiddenStatus,
+    ListMemberStatus, MuteStatus, PostTweetRequest, SentMessage, ThreadResult, TweetReply,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+#[cfg(feature = "write-ops")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OnceCell, Semaphore};
+
+/// User fields requested for every user lookup
+const USER_FIELDS: &str = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+
+/// List fields requested by the list endpoints
+const LIST_FIELDS: &str = "id,name,description,owner_id,private,follower_count,member_count,created_at";
+
+/// Media fields requested with the `attachments.media_keys` expansion
+pub const MEDIA_FIELDS: &str = "media_key,type,url,preview_image_url,width,height,duration_ms,alt_text";
+
+/// Most users the v1.1 user search returns per page
+const USER_SEARCH_MAX_COUNT: u32 = 20;
+
+/// Response headers X uses to flag a deprecated endpoint
+const DEPRECATION_HEADERS: [&str; 3] = ["deprecation", "sunset", "x-api-warn"];
+
+/// Lowest X API access tier that can use each endpoint, for explaining
+/// `client-not-enrolled` errors; endpoints not listed need Basic or Pro
+const ENDPOINT_TIERS: [(&str, &str); 16] = [
+    ("GET /2/lists/:id/tweets", "Basic"),
+    ("GET /2/tweets", "Basic"),
+    ("GET /2/tweets/:id", "Basic"),
+    ("GET /2/tweets/:id/quote_tweets", "Basic"),
+    ("GET /2/tweets/search/recent", "Basic"),
+    ("GET /2/users/:id", "Basic"),
+    ("GET /2/users/:id/followed_lists", "Basic"),
+    ("GET /2/users/:id/list_memberships", "Basic"),
+    ("GET /2/users/:id/owned_lists", "Basic"),
+    ("GET /2/users/:id/tweets", "Basic"),
+    ("GET /2/users/by/username/:username", "Basic"),
+    ("GET /2/tweets/search/all", "Pro"),
+    ("GET /2/tweets/counts/all", "Pro"),
+    ("GET /2/tweets/search/stream", "Pro"),
+    ("GET /2/tweets/search/stream/rules", "Pro"),
+    ("POST /2/tweets/search/stream/rules", "Pro"),
+];
+
+/// Tweet fields requested when assembling reply context
+const REPLY_CONTEXT_TWEET_FIELDS: &str =
+    "id,text,author_id,created_at,public_metrics,referenced_tweets,conversation_id,reply_settings";
+
+/// Tweet fields requested when listing the replies in a conversation
+const CONVERSATION_TWEET_FIELDS: &str =
+    "id,text,author_id,created_at,public_metrics,referenced_tweets,conversation_id,in_reply_to_user_id";
+
+/// Tweet fields of the tweets delivered by the filtered stream
+const STREAM_TWEET_FIELDS: &str = "id,text,author_id,created_at,public_metrics";
+
+/// Tweet fields requested when looking up tweets by ID
+const TWEET_LOOKUP_FIELDS: &str =
+    "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls";
+
+/// Most tweet IDs the batch lookup endpoint accepts per request
+const TWEET_LOOKUP_BATCH_SIZE: usize = 100;
+
+/// Most usernames the user lookup endpoint accepts per request
+const USER_LOOKUP_BATCH_SIZE: usize = 100;
+
+/// How long the list of trend locations is cached
+const TREND_LOCATIONS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Default cap on concurrent requests to X
+pub const DEFAULT_MAX_CONCURRENCY: usize = 8;
 
 /// X API client
+///
+/// Every request goes through one `reqwest::Client`, which keeps idle
+/// connections to X open for reuse and speaks HTTP/2 when the server offers
+/// it. Clones share the same connection pool and concurrency limit, as well as
+/// the circuit breaker, caches and idempotency log, so a clone behaves like
+/// the original when used from another task. [`XMcpServer`](crate::XMcpServer)
+/// keeps its client behind an `Arc` that every session and tool call shares.
 #[derive(Debug, Clone)]
 pub struct XClient {
     client: Client,
+    /// Time limit `client` was built with, for error messages
+    timeout: Duration,
+    /// `User-Agent` header sent with every request
+    user_agent: String,
+    bearer_token: String,
+    credentials: Option<Arc<dyn CredentialProvider>>,
+    base_url: String,
+    v1_base_url: String,
+    max_concurrency: usize,
+    request_slots: Arc<Semaphore>,
+    replay: Option<ReplayMode>,
+    rate_limit_fallback: bool,
+    trend_locations: Arc<Mutex<Option<CachedLocations>>>,
+    /// ID of the user the credentials act for, looked up once
+    authenticated_user_id: Arc<OnceCell<String>>,
+    deprecated_endpoints: Arc<std::sync::Mutex<HashSet<String>>>,
+    metrics: Option<Metrics>,
+    circuit: Arc<CircuitBreaker>,
+    retry: RetryPolicy,
+    #[cfg(feature = "write-ops")]
+    idempotency: Arc<IdempotencyLog>,
+}
+
+/// Trend locations and when they were fetched
+type CachedLocations = (Instant, Vec<TrendLocation>);
+
+tokio::task_local! {
+    /// JSON bodies of the X API responses received inside [`capture_raw_responses`]
+    static RAW_RESPONSES: RefCell<Vec<serde_json::Value>>;
+}
+
+/// Run `future`, also returning the unmodified JSON body of every successful
+/// X API response it received, in request order
+///
+/// Outside of this scope responses are only deserialized into their typed
+/// form. Cached data, such as trend locations, is not requested again and so
+/// does not appear.
+pub async fn capture_raw_responses<F: Future>(future: F) -> (F::Output, Vec<serde_json::Value>) {
+    RAW_RESPONSES
+        .scope(RefCell::new(Vec::new()), async {
+            let output = future.await;
+            let raw = RAW_RESPONSES.with(|responses| responses.take());
+            (output, raw)
+        })
+        .await
+}
+
+/// How far a multi-step operation, such as posting a thread, has got
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// Steps completed so far
+    pub completed: u32,
+    /// Total number of steps, when known
+    pub total: Option<u32>,
+    pub message: String,
+}
+
+tokio::task_local! {
+    /// Receiver of the progress made inside [`with_progress`]
+    static PROGRESS: Arc<dyn Fn(Progress) + Send + Sync>;
+}
+
+/// Run `future`, passing the progress of the multi-step client operations it
+/// runs to `report`
+///
+/// Outside of this scope no progress is reported.
+pub async fn with_progress<F: Future>(report: impl Fn(Progress) + Send + Sync + 'static, future: F) -> F::Output {
+    PROGRESS.scope(Arc::new(report), future).await
+}
+
+/// Report `completed` of `total` steps to the enclosing [`with_progress`]
+fn report_progress(completed: usize, total: usize, message: impl FnOnce() -> String) {
+    let _ = PROGRESS.try_with(|report| {
+        report(Progress {
+            completed: completed as u32,
+            total: Some(total as u32),
+            message: message(),
+        })
+    });
+}
+
+tokio::task_local! {
+    /// Set inside [`without_rate_limit_retries`]
+    static NO_RATE_LIMIT_RETRIES: ();
+}
+
+/// Run `future` with rate-limited requests failing at once, for callers with
+/// a fallback or retry loop of their own
+async fn without_rate_limit_retries<F: Future>(future: F) -> F::Output {
+    NO_RATE_LIMIT_RETRIES.scope((), future).await
+}
+
+/// Deserialize a response body, keeping the parsed JSON alongside
+fn parse_with_raw<T: DeserializeOwned>(body: &str) -> serde_json::Result<(T, serde_json::Value)> {
+    let raw: serde_json::Value = serde_json::from_str(body)?;
+    Ok((T::deserialize(&raw)?, raw))
+}
+
+/// Body of a request to the X API
+pub(crate) enum RequestBody {
+    Json(serde_json::Value),
+    /// `application/x-www-form-urlencoded` fields
+    #[cfg(feature = "write-ops")]
+    Form(Vec<(&'static str, String)>),
+}
+
+impl RequestBody {
+    /// The body as JSON, which is how recordings store and key it
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Json(value) => value.clone(),
+            #[cfg(feature = "write-ops")]
+            Self::Form(fields) => fields
+                .iter()
+                .map(|(name, value)| (name.to_string(), serde_json::Value::String(value.clone())))
+                .collect::<serde_json::Map<_, _>>()
+                .into(),
+        }
+    }
+
+    /// Form fields, which OAuth 1.0a signs along with the query
+    fn form_fields(&self) -> &[(&'static str, String)] {
+        match self {
+            Self::Json(_) => &[],
+            #[cfg(feature = "write-ops")]
+            Self::Form(fields) => fields,
+        }
+    }
+}
+
+/// Status and body of an HTTP response, live or replayed
+struct RawResponse {
+    status: u16,
+    body: String,
+    rate_limit: RateLimitHeaders,
+}
+
+/// X's `x-rate-limit-*` response headers
+#[derive(Debug, Default, Clone, Copy)]
+struct RateLimitHeaders {
+    limit: Option<u32>,
+    remaining: Option<u32>,
+    /// When the window resets, in Unix seconds
+    reset: Option<i64>,
+}
+
+impl RateLimitHeaders {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Self {
+        fn header<T: std::str::FromStr>(headers: &reqwest::header::HeaderMap, name: &str) -> Option<T> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        }
+        Self {
+            limit: header(headers, "x-rate-limit-limit"),
+            remaining: header(headers, "x-rate-limit-remaining"),
+            reset: header(headers, "x-rate-limit-reset"),
+        }
+    }
+}
+
+/// Configures an [`XClient`] before its connection pool is built, see
+/// [`XClient::builder`]
+#[derive(Debug, Clone)]
+pub struct XClientBuilder {
     bearer_token: String,
+    timeout: Duration,
+    retry: RetryPolicy,
     base_url: String,
+    v1_base_url: String,
+    user_agent: String,
+}
+
+impl Default for XClientBuilder {
+    fn default() -> Self {
+        Self {
+            bearer_token: String::new(),
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::default(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            v1_base_url: DEFAULT_V1_BASE_URL.to_string(),
+            user_agent: default_user_agent(),
+        }
+    }
+}
+
+impl XClientBuilder {
+    /// Bearer token sent with requests that are not OAuth 1.0a signed
+    pub fn bearer_token(mut self, bearer_token: impl Into<String>) -> Self {
+        self.bearer_token = bearer_token.into();
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout` (default: 30s)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Retry policy for rate-limited requests (default: 5 retries, waiting
+    /// at most 60s for each)
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// v2 API base URL (default: `https://api.twitter.com/2`)
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// v1.1 API base URL (default: `https://api.twitter.com/1.1`)
+    pub fn v1_base_url(mut self, v1_base_url: impl Into<String>) -> Self {
+        self.v1_base_url = v1_base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// `User-Agent` header sent with every request (default:
+    /// `x-mcp-server/<version>`)
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Build the client and its connection pool
+    pub fn build(self) -> XResult<XClient> {
+        let client = http_client(self.timeout)?;
+        Ok(self.with_http_client(client))
+    }
+
+    fn with_http_client(self, client: Client) -> XClient {
+        XClient {
+            client,
+            timeout: self.timeout,
+            user_agent: self.user_agent,
+            bearer_token: self.bearer_token,
+            credentials: None,
+            base_url: self.base_url,
+            v1_base_url: self.v1_base_url,
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            request_slots: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENCY)),
+            replay: None,
+            rate_limit_fallback: false,
+            trend_locations: Arc::new(Mutex::new(None)),
+            authenticated_user_id: Arc::new(OnceCell::new()),
+            deprecated_endpoints: Arc::new(std::sync::Mutex::new(HashSet::new())),
+            metrics: None,
+            circuit: Arc::new(CircuitBreaker::new(CircuitBreakerConfig::default())),
+            retry: self.retry,
+            #[cfg(feature = "write-ops")]
+            idempotency: Arc::new(IdempotencyLog::default()),
+        }
+    }
 }
 
 impl XClient {
     /// Create a new X API client
+    ///
+    /// # Panics
+    ///
+    /// Panics if the HTTP client cannot be built, like `reqwest::Client::new`,
+    /// which only happens when the TLS backend fails to initialize. Use
+    /// [`builder`](Self::builder) to get an error instead.
     pub fn new(bearer_token: String) -> Self {
-        Self {
-            client: Client::new(),
-            bearer_token,
-            base_url: "https://api.twitter.com/2".to_string(),
+        XClientBuilder::default()
+            .bearer_token(bearer_token)
+            .build()
+            .expect("HTTP client with only a timeout set should build")
+    }
+
+    /// Start configuring a client's timeout, retry policy, base URLs and
+    /// `User-Agent` in one place
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use x_mcp_server::XClient;
+    /// let client = XClient::builder()
+    ///     .bearer_token("token")
+    ///     .timeout(Duration::from_secs(10))
+    ///     .base_url("http://localhost:8080/2")
+    ///     .build()?;
+    /// # Ok::<(), x_mcp_server::XError>(())
+    /// ```
+    pub fn builder() -> XClientBuilder {
+        XClientBuilder::default()
+    }
+
+    /// Create a client from a validated [`ServerConfig`]
+    pub fn from_config(config: &ServerConfig) -> XResult<Self> {
+        let mut client = Self::builder()
+            .bearer_token(config.bearer_token.clone().unwrap_or_default())
+            .base_url(&config.base_url)
+            .v1_base_url(&config.v1_base_url)
+            .timeout(config.timeout)
+            .retry_policy(config.retry)
+            .build()?
+            .with_max_concurrency(config.max_concurrency)
+            .with_rate_limit_fallback(config.rate_limit_fallback);
+        if let Some(credentials) = &config.oauth {
+            client = client.with_oauth(credentials.clone());
+        }
+        if let Some(mode) = &config.replay {
+            client = client.with_replay(mode.clone());
+        }
+        #[cfg(feature = "write-ops")]
+        {
+            client = client.with_idempotency_log(match &config.idempotency_file {
+                Some(path) => IdempotencyLog::open(path, config.idempotency_ttl)?,
+                None => IdempotencyLog::in_memory(config.idempotency_ttl),
+            });
         }
+        Ok(client)
+    }
+
+    /// Send requests to `base_url` (v2) and `v1_base_url` (v1.1) instead of
+    /// api.twitter.com, e.g. a proxy or a mock server
+    pub fn with_base_urls(mut self, base_url: &str, v1_base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self.v1_base_url = v1_base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Send v2 requests to `base_url` instead of api.twitter.com, leaving
+    /// v1.1 requests alone
+    ///
+    /// OAuth 1.0a signatures are computed over the URL actually requested, so
+    /// they stay valid behind the override.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout` (default: 30s)
+    ///
+    /// A request that runs out of time fails with
+    /// `XError::Generic("request timed out after 30s")`.
+    ///
+    /// This replaces the connection pool; set the timeout with
+    /// [`builder`](Self::builder) to build the pool once.
+    pub fn with_timeout(mut self, timeout: Duration) -> XResult<Self> {
+        self.client = http_client(timeout)?;
+        self.timeout = timeout;
+        Ok(self)
+    }
+
+    /// Send `user_agent` as the `User-Agent` header instead of
+    /// `x-mcp-server/<version>`
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Replace the default retry policy for rate-limited requests (5
+    /// retries, waiting at most 60s for each)
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Same as [`with_retry_policy`](Self::with_retry_policy)
+    pub fn with_retry_config(self, retry: RetryConfig) -> Self {
+        self.with_retry_policy(retry)
+    }
+
+    /// Sign requests with OAuth 1.0a user credentials instead of sending the
+    /// bearer token
+    pub fn with_oauth(self, credentials: OAuthCredentials) -> Self {
+        self.with_credential_provider(credentials)
+    }
+
+    /// Sign requests with OAuth 1.0a credentials from `provider`, asked
+    /// before every request
+    pub fn with_credential_provider(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    /// Record every exchange to disk, or serve responses from recordings
+    /// instead of the network
+    pub fn with_replay(mut self, mode: ReplayMode) -> Self {
+        self.replay = Some(mode);
+        self
+    }
+
+    /// When the single-tweet endpoint is rate limited, have
+    /// [`get_tweet`](Self::get_tweet) retry through the batch lookup endpoint,
+    /// which has its own limit but returns fewer fields
+    pub fn with_rate_limit_fallback(mut self, enabled: bool) -> Self {
+        self.rate_limit_fallback = enabled;
+        self
+    }
+
+    /// Replace the default circuit breaker (5 consecutive failures, 30s
+    /// cooldown) with one using `config`
+    pub fn with_circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit = Arc::new(CircuitBreaker::new(config));
+        self
+    }
+
+    /// State of the circuit breaker guarding API requests
+    pub fn circuit_status(&self) -> CircuitStatus {
+        self.circuit.status()
+    }
+
+    /// Record the latency and outcome of every API request in `metrics`
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Limit how many requests may be in flight at once (at least 1);
+    /// further requests wait for a free slot
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        let max_concurrency = max_concurrency.max(1);
+        self.max_concurrency = max_concurrency;
+        self.request_slots = Arc::new(Semaphore::new(max_concurrency));
+        self
+    }
+
+    /// Maximum number of concurrent requests
+    pub fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    /// Number of requests currently in flight
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrency - self.request_slots.available_permits()
     }
 
     /// Create client from environment variables
+    ///
+    /// See [`ServerConfig::from_env`] for the variables read. Content
+    /// redaction is turned on when `X_MCP_REDACT_CONTENT` asks for it.
     pub fn from_env() -> XResult<Self> {
-        let bearer_token = std::env::var("X_BEARER_TOKEN")
-            .map_err(|_| XError::Config("X_BEARER_TOKEN not found".to_string()))?;
-        Ok(Self::new(bearer_token))
+        let config = ServerConfig::from_env()?;
+        if config.redact_content {
+            redact::set_enabled(true);
+        }
+        Self::from_config(&config)
     }
 
     /// Get user information by username
     pub async fn get_user_by_username(&self, username: &str) -> XResult<Option<User>> {
         let url = format!("{}/users/by/username/{}", self.base_url, username);
-        
-        let response = self.client
-            .get(&url)
-            .bearer_auth(&self.bearer_token)
-            .query(&[("user.fields", "id,name,username,description,public_metrics,profile_image_url,verified,created_at")])
-            .send()
-            .await?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(XError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
-        }
-
-        let api_response: XResponse<User> = response.json().await?;
-        
-        if let Some(errors) = api_response.errors {
-            if !errors.is_empty() {
-                return Err(XError::Api {
-                    status: 400,
-                    message: format!("API errors: {:?}", errors),
-                });
-            }
-        }
+        let query_params = [("user.fields", USER_FIELDS.to_string())];
 
+        let api_response: XResponse<User> = self.request_v2(Method::GET, &url, &query_params, None).await?;
         Ok(api_response.data)
     }
 
     /// Get user information by user ID
     pub async fn get_user_by_id(&self, user_id: &str) -> XResult<Option<User>> {
         let url = format!("{}/users/{}", self.base_url, user_id);
-        
-        let response = self.client
-            .get(&url)
-            .bearer_auth(&self.bearer_token)
-            .query(&[("user.fields", "id,name,username,description,public_metrics,profile_image_url,verified,created_at")])
-            .send()
-            .await?;
+        let query_params = [("user.fields", USER_FIELDS.to_string())];
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(XError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
-        }
+        let api_response: XResponse<User> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(api_response.data)
+    }
 
-        let api_response: XResponse<User> = response.json().await?;
-        
-        if let Some(errors) = api_response.errors {
-            if !errors.is_empty() {
-                return Err(XError::Api {
-                    status: 400,
-                    message: format!("API errors: {:?}", errors),
+    /// Get many users by username
+    ///
+    /// Usernames are deduplicated (ignoring case and any leading @) and
+    /// looked up 100 per request. Those X cannot return, and those that are
+    /// not valid handles, are listed in `not_found` with the reason from the
+    /// response's `errors` rather than failing the lookup.
+    pub async fn get_users_by_usernames(&self, usernames: &[String]) -> XResult<UserLookup> {
+        let url = format!("{}/users/by", self.base_url);
+        let mut lookup = UserLookup::default();
+
+        let mut names: Vec<&str> = Vec::new();
+        for name in usernames.iter().map(|name| name.trim().trim_start_matches('@')) {
+            if names.iter().any(|other| other.eq_ignore_ascii_case(name))
+                || lookup.not_found.iter().any(|missing| missing.username == name)
+            {
+                continue;
+            }
+            if crate::validate::is_username(name) {
+                names.push(name);
+            } else {
+                lookup.not_found.push(MissingUser {
+                    username: name.to_string(),
+                    reason: "Not a valid username".to_string(),
                 });
             }
         }
 
+        let mut found = Vec::new();
+        let mut errors = Vec::new();
+        for batch in names.chunks(USER_LOOKUP_BATCH_SIZE) {
+            let query_params = [
+                ("usernames", batch.join(",")),
+                ("user.fields", USER_FIELDS.to_string()),
+            ];
+
+            // Unknown and suspended usernames come back in `errors` next to
+            // the users that were found
+            let api_response: XResponse<Vec<User>> = self.make_request(Method::GET, &url, &query_params, None).await?;
+            found.extend(api_response.data.unwrap_or_default());
+            errors.extend(api_response.errors.unwrap_or_default());
+        }
+
+        for name in names {
+            match found.iter().position(|user| user.username.eq_ignore_ascii_case(name)) {
+                Some(index) => lookup.users.push(found.swap_remove(index)),
+                None => {
+                    let error = errors
+                        .iter()
+                        .find(|error| error.value.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(name)));
+                    lookup.not_found.push(MissingUser {
+                        username: name.to_string(),
+                        reason: error
+                            .map(|error| error.detail.clone().unwrap_or_else(|| error.title.clone()))
+                            .unwrap_or_else(|| "User not found".to_string()),
+                    });
+                }
+            }
+        }
+
+        Ok(lookup)
+    }
+
+    /// Get the user the credentials act for
+    ///
+    /// Needs user context: OAuth 1.0a credentials or an OAuth 2.0 user token.
+    pub async fn get_me(&self) -> XResult<Option<User>> {
+        let url = format!("{}/users/me", self.base_url);
+        let query_params = [("user.fields", USER_FIELDS.to_string())];
+
+        let api_response: XResponse<User> = self.request_v2(Method::GET, &url, &query_params, None).await?;
         Ok(api_response.data)
     }
 
+    /// ID of the user the credentials act for, as the endpoints acting on
+    /// their behalf need in the path
+    ///
+    /// Looked up with [`get_me`](Self::get_me) on first use and shared by
+    /// clones of this client afterwards.
+    pub async fn authenticated_user_id(&self) -> XResult<String> {
+        self.authenticated_user_id
+            .get_or_try_init(|| async {
+                match self.get_me().await? {
+                    Some(user) => Ok(user.id),
+                    None => Err(XError::Auth(
+                        "X returned no user for the credentials".to_string(),
+                    )),
+                }
+            })
+            .await
+            .cloned()
+    }
+
+    /// Get a user's pinned tweet, or `None` when they have not pinned one
+    ///
+    /// An all-digit identifier is taken as a user ID, anything else as a
+    /// username (a leading @ is ignored). The pinned tweet comes back with the
+    /// user through the `pinned_tweet_id` expansion, in a single request.
+    pub async fn get_pinned_tweet(&self, username_or_id: &str) -> XResult<Option<Tweet>> {
+        let identifier = username_or_id.trim().trim_start_matches('@');
+        let url = if !identifier.is_empty() && identifier.bytes().all(|b| b.is_ascii_digit()) {
+            format!("{}/users/{}", self.base_url, identifier)
+        } else {
+            format!("{}/users/by/username/{}", self.base_url, identifier)
+        };
+        let query_params = [
+            ("user.fields", format!("{},pinned_tweet_id", USER_FIELDS)),
+            ("expansions", "pinned_tweet_id".to_string()),
+            ("tweet.fields", "id,text,author_id,created_at,public_metrics,attachments".to_string()),
+        ];
+
+        let api_response: XResponse<User> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        let Some(pinned_id) = api_response.data.and_then(|user| user.pinned_tweet_id) else {
+            return Ok(None);
+        };
+
+        // A pinned tweet that has since been deleted is missing from `includes`
+        Ok(api_response
+            .includes
+            .and_then(|includes| includes.tweets)
+            .and_then(|tweets| tweets.into_iter().find(|tweet| tweet.id == pinned_id)))
+    }
+
+    /// Search users by name or keyword
+    ///
+    /// Uses the v1.1 `users/search` endpoint, as v2 has no user search on
+    /// standard access. It requires user context, i.e. OAuth 1.0a
+    /// credentials. At most 20 users are returned.
+    pub async fn search_users(&self, query: &str, count: u32) -> XResult<Vec<User>> {
+        let url = format!("{}/users/search.json", self.v1_base_url);
+        let query_params = [
+            ("q", query.to_string()),
+            ("count", count.clamp(1, USER_SEARCH_MAX_COUNT).to_string()),
+            ("include_entities", "false".to_string()),
+        ];
+
+        let users: Vec<V1User> = self.make_request(Method::GET, &url, &query_params, None).await?;
+        Ok(users.into_iter().map(User::from).collect())
+    }
+
     /// Search for tweets
-    pub async fn search_tweets(&self, params: SearchTweetsParams) -> XResult<Vec<Tweet>> {
+    ///
+    /// A `start_time` or `end_time` that is not an RFC 3339 time is refused
+    /// with `XError::Generic` without calling X.
+    pub async fn search_tweets(&self, params: SearchTweetsParams) -> XResult<SearchPage> {
+        let (page, _) = self.search_tweets_with_includes(params).await?;
+        Ok(page)
+    }
+
+    /// Search for tweets, pairing each with its author from `includes`
+    ///
+    /// The `author_id` expansion and tweet field are added to `params` when
+    /// missing, and authors get the usual user fields unless `user_fields` is
+    /// set.
+    pub async fn search_tweets_with_authors(&self, mut params: SearchTweetsParams) -> XResult<AuthoredSearchPage> {
+        let tweet_fields = params
+            .tweet_fields
+            .get_or_insert_with(|| vec!["id".to_string(), "text".to_string()]);
+        if !tweet_fields.iter().any(|field| field == "author_id") {
+            tweet_fields.push("author_id".to_string());
+        }
+        let expansions = params.expansions.get_or_insert_with(Vec::new);
+        if !expansions.iter().any(|expansion| expansion == "author_id") {
+            expansions.push("author_id".to_string());
+        }
+        params
+            .user_fields
+            .get_or_insert_with(|| USER_FIELDS.split(',').map(String::from).collect());
+
+        let (page, includes) = self.search_tweets_with_includes(params).await?;
+        let authors: HashMap<&str, &User> = includes
+            .users
+            .iter()
+            .flatten()
+            .map(|user| (user.id.as_str(), user))
+            .collect();
+        let tweets = page
+            .tweets
+            .into_iter()
+            .map(|tweet| {
+                let author = tweet
+                    .author_id
+                    .as_deref()
+                    .and_then(|id| authors.get(id))
+                    .map(|user| (*user).clone());
+                (tweet, author)
+            })
+            .collect();
+        Ok(AuthoredSearchPage {
+            tweets,
+            next_token: page.next_token,
+        })
+    }
+
+    /// Search for tweets, keeping the users, tweets and media the requested
+    /// expansions returned in `includes`
+    pub async fn search_tweets_with_includes(&self, params: SearchTweetsParams) -> XResult<(SearchPage, Includes)> {
         let url = format!("{}/tweets/search/recent", self.base_url);
-        
+
         let mut query_params = vec![("query", params.query)];
-        
+
         if let Some(max_results) = params.max_results {
             query_params.push(("max_results", max_results.to_string()));
         }
-        
+
+        if let Some(sort_order) = params.sort_order {
+            query_params.push(("sort_order", sort_order.as_str().to_string()));
+        }
+
+        if let Some(start_time) = params.start_time {
+            query_params.push(("start_time", search_time("start_time", &start_time)?));
+        }
+
+        if let Some(end_time) = params.end_time {
+            query_params.push(("end_time", search_time("end_time", &end_time)?));
+        }
+
+        push_id_range(
+            &mut query_params,
+            &TweetIdRange {
+                since_id: params.since_id,
+                until_id: params.until_id,
+            },
+        );
+
         if let Some(tweet_fields) = params.tweet_fields {
             query_params.push(("tweet.fields", tweet_fields.join(",")));
         }
-        
+
         if let Some(user_fields) = params.user_fields {
             query_params.push(("user.fields", user_fields.join(",")));
         }
-        
+
         if let Some(expansions) = params.expansions {
             query_params.push(("expansions", expansions.join(",")));
         }
 
-        let response = self.client
-            .get(&url)
-            .bearer_auth(&self.bearer_token)
-            .query(&query_params)
-            .send()
-            .await?;
+        if let Some(media_fields) = params.media_fields {
+            query_params.push(("media.fields", media_fields.join(",")));
+        }
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(XError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
+        if let Some(next_token) = params.next_token {
+            query_params.push(("next_token", next_token));
         }
 
-        let api_response: XResponse<Vec<Tweet>> = response.json().await?;
-        
-        if let Some(errors) = api_response.errors {
-            if !errors.is_empty() {
-                return Err(XError::Api {
-                    status: 400,
-                    message: format!("API errors: {:?}", errors),
-                });
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        let page = SearchPage {
+            next_token: api_response.next_token(),
+            tweets: api_response.data.unwrap_or_default(),
+        };
+        Ok((page, api_response.includes.unwrap_or_default()))
+    }
+
+    /// Connect to the filtered stream, which delivers the tweets matching
+    /// the app's [stream rules](Self::get_stream_rules) as they are posted
+    ///
+    /// Needs an app-only bearer token and Pro access. The connection is not
+    /// bound by the request timeout: it stays open until the stream is
+    /// dropped, or until [`STALL_TIMEOUT`] passes without even a keep-alive.
+    /// The stream cannot be recorded or replayed.
+    pub async fn connect_filtered_stream(&self) -> XResult<TweetStream> {
+        if self.replay.is_some() {
+            return Err(XError::Generic(
+                "the filtered stream cannot be recorded or replayed".to_string(),
+            ));
+        }
+        let url = format!("{}/tweets/search/stream", self.base_url);
+        let query_params = [("tweet.fields", STREAM_TWEET_FIELDS.to_string())];
+
+        self.circuit.check()?;
+        // The stream stays open indefinitely, so only connecting is timed
+        let client = Client::builder().connect_timeout(self.timeout).build()?;
+        let request = self
+            .authorize(client.get(&url), &Method::GET, &url, &query_params, None)
+            .await?;
+        let mut response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                self.circuit.record_failure();
+                return Err(e.into());
+            }
+        };
+        let status = response.status().as_u16();
+        if status < 500 {
+            self.circuit.record_success();
+        } else {
+            self.circuit.record_failure();
+        }
+        if !(200..300).contains(&status) {
+            let rate_limit = RateLimitHeaders::from_headers(response.headers());
+            let mut body = Vec::new();
+            while let Ok(Some(chunk)) = response.chunk().await {
+                body.extend_from_slice(&chunk);
+            }
+            let body = String::from_utf8_lossy(&body).into_owned();
+            if let Some(error) = response_error("GET", &url, &RawResponse { status, body, rate_limit }) {
+                return Err(error);
             }
         }
+        Ok(TweetStream::new(response, STALL_TIMEOUT))
+    }
+
+    /// Get the app's filtered stream rules
+    pub async fn get_stream_rules(&self) -> XResult<Vec<StreamRule>> {
+        let url = format!("{}/tweets/search/stream/rules", self.base_url);
 
+        let api_response: XResponse<Vec<StreamRule>> = self.request_v2(Method::GET, &url, &[], None).await?;
         Ok(api_response.data.unwrap_or_default())
     }
 
     /// Get a tweet by ID
     pub async fn get_tweet(&self, tweet_id: &str) -> XResult<Option<Tweet>> {
+        let (tweet, _) = self.get_tweet_with_media(tweet_id).await?;
+        Ok(tweet)
+    }
+
+    /// Get a tweet by ID along with the media attached to it
+    ///
+    /// Media is left out when the rate limit fallback had to be used.
+    pub async fn get_tweet_with_media(&self, tweet_id: &str) -> XResult<(Option<Tweet>, Vec<Media>)> {
         let url = format!("{}/tweets/{}", self.base_url, tweet_id);
-        
-        let response = self.client
-            .get(&url)
-            .bearer_auth(&self.bearer_token)
-            .query(&[
-                ("tweet.fields", "id,text,author_id,created_at,public_metrics,context_annotations,referenced_tweets"),
-                ("expansions", "author_id"),
-            ])
-            .send()
-            .await?;
+        let query_params = [
+            ("tweet.fields", "id,text,author_id,created_at,public_metrics,context_annotations,entities,referenced_tweets,attachments,edit_history_tweet_ids,edit_controls".to_string()),
+            ("expansions", "author_id,attachments.media_keys".to_string()),
+            ("media.fields", MEDIA_FIELDS.to_string()),
+        ];
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(XError::Api {
-                status: status.as_u16(),
-                message: error_text,
-            });
-        }
+        let lookup = self.request_v2::<Tweet>(Method::GET, &url, &query_params, None);
+        let lookup = if self.rate_limit_fallback {
+            without_rate_limit_retries(lookup).await
+        } else {
+            lookup.await
+        };
+        let error = match lookup {
+            Ok(api_response) => {
+                let media = api_response
+                    .includes
+                    .and_then(|includes| includes.media)
+                    .unwrap_or_default();
+                return Ok((api_response.data, media));
+            }
+            Err(e @ XError::RateLimited { .. }) if self.rate_limit_fallback => e,
+            Err(e) => return Err(e),
+        };
 
-        let api_response: XResponse<Tweet> = response.json().await?;
-        
-        if let Some(errors) = api_response.errors {
-            if !errors.is_empty() {
-                return Err(XError::Api {
-                    status: 400,
-                    message: format!("API errors: {:?}", errors),
-                });
+        tracing::warn!("Tweet lookup rate limited, falling back to the batch endpoint for {}", tweet_id);
+        match self.get_tweets(&[tweet_id.to_string()]).await {
+            Ok(lookup) => Ok((lookup.tweets.into_iter().next(), Vec::new())),
+            Err(fallback_error) => {
+                tracing::warn!("Batch fallback failed: {}", redact::error(&fallback_error));
+                Err(error)
             }
         }
-
-        Ok(api_response.data)
     }
 
-    /// Get user's recent tweets
-    pub async fn get_user_tweets(&self, user_id: &str, max_results: Option<u32>) -> XResult<Vec<Tweet>> {
-        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
-        
-        let mut query_params = vec![
-            ("tweet.fields", "id,text,author_id,created_at,public_metrics".to_string()),
-        ];
-        
-        if let Some(max) = max_results {
-            query_params.push(("max_results", max.to_string()));
+    /// Get many tweets by ID, with their authors
+    ///
+    /// IDs are deduplicated and looked up 100 per request with the
+    /// `author_id` expansion, so each author is fetched once however many of
+    /// the tweets they wrote. IDs X cannot return (malformed, deleted or
+    /// protected) are listed in `missing_ids` rather than failing the lookup.
+    pub async fn get_tweets(&self, tweet_ids: &[String]) -> XResult<TweetLookup> {
+        let url = format!("{}/tweets", self.base_url);
+        let mut lookup = TweetLookup::default();
+
+        let mut ids: Vec<&str> = Vec::new();
+        for id in tweet_ids.iter().map(|id| id.trim()) {
+            if ids.contains(&id) || lookup.missing_ids.iter().any(|missing| missing == id) {
+                continue;
+            }
+            if crate::validate::is_tweet_id(id) {
+                ids.push(id);
+            } else {
+                lookup.missing_ids.push(id.to_string());
+            }
         }
 
-        let response = self.client
-            .get(&url)
-            .bearer_auth(&self.bearer_token)
-            .query(&query_params)
-            .send()
-            .await?;
+        let mut found = Vec::new();
+        let batches = ids.len().div_ceil(TWEET_LOOKUP_BATCH_SIZE);
+        for (index, batch) in ids.chunks(TWEET_LOOKUP_BATCH_SIZE).enumerate() {
+            let query_params = [
+                ("ids", batch.join(",")),
+                ("tweet.fields", TWEET_LOOKUP_FIELDS.to_string()),
+                ("expansions", "author_id".to_string()),
+                ("user.fields", USER_FIELDS.to_string()),
+            ];
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_default();
-            return Err(XError::Api {
-                status: status.as_u16(),
-                message: error_text,
+            // Unavailable IDs come back in `errors` next to the tweets that
+            // were found, so they are not treated as a failure here
+            let api_response: XResponse<Vec<Tweet>> = self.make_request(Method::GET, &url, &query_params, None).await?;
+            found.extend(api_response.data.unwrap_or_default());
+            for user in api_response.includes.and_then(|includes| includes.users).unwrap_or_default() {
+                if !lookup.authors.iter().any(|author| author.id == user.id) {
+                    lookup.authors.push(user);
+                }
+            }
+            report_progress(index + 1, batches, || {
+                format!("Looked up batch {} of {}", index + 1, batches)
             });
         }
 
-        let api_response: XResponse<Vec<Tweet>> = response.json().await?;
-        
-        if let Some(errors) = api_response.errors {
-            if !errors.is_empty() {
-                return Err(XError::Api {
-                    status: 400,
-                    message: format!("API errors: {:?}", errors),
-                });
+        for id in ids {
+            match found.iter().position(|tweet| tweet.id == id) {
+                Some(index) => lookup.tweets.push(found.swap_remove(index)),
+                None => lookup.missing_ids.push(id.to_string()),
             }
         }
 
-        Ok(api_response.data.unwrap_or_default())
+        Ok(lookup)
+    }
+
+    /// Get a tweet with its author and the latest replies in its conversation
+    ///
+    /// The author comes back through the `author_id` expansion of the tweet
+    /// lookup and the replies from one conversation search, so the whole
+    /// context costs two requests. Recent search only covers the last 7 days,
+    /// so older conversations have no replies.
+    pub async fn get_reply_context(&self, tweet_id: &str, max_replies: u32) -> XResult<Option<ReplyContext>> {
+        let Some((tweet, author)) = self.get_tweet_with_author(tweet_id).await? else {
+            return Ok(None);
+        };
+
+        let replies = if max_replies == 0 {
+            Vec::new()
+        } else {
+            let conversation_id = tweet.conversation_id.as_deref().unwrap_or(&tweet.id);
+            let params = SearchTweetsParams {
+                query: format!("conversation_id:{} is:reply", conversation_id),
+                // Recent search accepts 10-100 results
+                max_results: Some(max_replies.clamp(10, 100)),
+                sort_order: None,
+                start_time: None,
+                end_time: None,
+                since_id: None,
+                until_id: None,
+                tweet_fields: Some(
+                    REPLY_CONTEXT_TWEET_FIELDS.split(',').map(String::from).collect(),
+                ),
+                user_fields: None,
+                expansions: None,
+                media_fields: None,
+                next_token: None,
+            };
+            let mut replies = self.search_tweets(params).await?.tweets;
+            replies.truncate(max_replies as usize);
+            replies
+        };
+
+        Ok(Some(ReplyContext { tweet, author, replies }))
+    }
+
+    /// Get the conversation a tweet belongs to as a reply tree
+    ///
+    /// The tweet is looked up to find its conversation, whose first tweet
+    /// becomes the root. The most recent `max_tweets` (1-100) replies come
+    /// from one conversation search and are linked to the tweets they reply
+    /// to; replies nested deeper than `max_depth` are left out. Recent search
+    /// only covers the last 7 days, so older replies are missing.
+    pub async fn get_conversation_tree(
+        &self,
+        tweet_id: &str,
+        max_depth: u32,
+        max_tweets: u32,
+    ) -> XResult<Option<ConversationTree>> {
+        let Some((tweet, author)) = self.get_tweet_with_author(tweet_id).await? else {
+            return Ok(None);
+        };
+        let mut authors: Vec<User> = author.into_iter().collect();
+        report_progress(1, 3, || "Fetched the tweet".to_string());
+
+        let conversation_id = tweet.conversation_id.clone().unwrap_or_else(|| tweet.id.clone());
+        let root = if conversation_id == tweet.id {
+            Some(tweet)
+        } else {
+            // The first tweet may have been deleted while the replies remain
+            self.get_tweet_with_author(&conversation_id).await?.map(|(root, author)| {
+                authors.extend(author);
+                root
+            })
+        };
+
+        report_progress(2, 3, || "Fetched the first tweet of the conversation".to_string());
+
+        let max_tweets = max_tweets.clamp(1, 100);
+        let params = SearchTweetsParams {
+            query: format!("conversation_id:{}", conversation_id),
+            // Recent search accepts 10-100 results
+            max_results: Some(max_tweets.max(10)),
+            sort_order: None,
+            start_time: None,
+            end_time: None,
+            since_id: None,
+            until_id: None,
+            tweet_fields: Some(REPLY_CONTEXT_TWEET_FIELDS.split(',').map(String::from).collect()),
+            user_fields: Some(USER_FIELDS.split(',').map(String::from).collect()),
+            expansions: Some(vec!["author_id".to_string()]),
+            media_fields: None,
+            next_token: None,
+        };
+        let (page, includes) = self.search_tweets_with_includes(params).await?;
+        let mut replies = page.tweets;
+        replies.retain(|reply| reply.id != conversation_id);
+        report_progress(3, 3, || format!("Fetched {} replies", replies.len()));
+        // A full page means there may be more replies than were fetched
+        let mut truncated = replies.len() >= max_tweets as usize;
+        replies.truncate(max_tweets as usize);
+
+        for user in includes.users.unwrap_or_default() {
+            if !authors.iter().any(|author| author.id == user.id) {
+                authors.push(user);
+            }
+        }
+
+        let beyond_search_window = root
+            .as_ref()
+            .and_then(|root| root.created_at.as_deref())
+            .and_then(|created_at| chrono::DateTime::parse_from_rfc3339(created_at).ok())
+            .is_some_and(|created_at| chrono::Utc::now() - created_at.to_utc() > chrono::Duration::days(7));
+
+        let mut root = build_conversation_tree(&conversation_id, root, replies, max_depth.max(1), &mut truncated);
+        let mut tweet_count = 0;
+        root.for_each_tweet_mut(&mut |_| tweet_count += 1);
+
+        Ok(Some(ConversationTree {
+            root,
+            authors,
+            tweet_count,
+            truncated,
+            beyond_search_window,
+        }))
+    }
+
+    /// Get the latest replies in a conversation, newest first
+    ///
+    /// `conversation_id` is the ID of the conversation's first tweet, which is
+    /// left out of the result. One recent search returns up to `max_results`
+    /// (1-100, default 10) replies with `in_reply_to_user_id` set. Recent
+    /// search only covers the last 7 days, so older replies are missing.
+    pub async fn get_conversation(&self, conversation_id: &str, max_results: Option<u32>) -> XResult<Vec<Tweet>> {
+        let max_results = max_results.unwrap_or(10).clamp(1, 100);
+        let params = SearchTweetsParams {
+            query: format!("conversation_id:{}", conversation_id),
+            // Recent search accepts 10-100 results
+            max_results: Some(max_results.max(10)),
+            sort_order: None,
+            start_time: None,
+            end_time: None,
+            since_id: None,
+            until_id: None,
+            tweet_fields: Some(CONVERSATION_TWEET_FIELDS.split(',').map(String::from).collect()),
+            user_fields: None,
+            expansions: None,
+            media_fields: None,
+            next_token: None,
+        };
+        let mut replies = self.search_tweets(params).await?.tweets;
+        replies.retain(|reply| reply.id != conversation_id);
+        replies.truncate(max_results as usize);
+        Ok(replies)
     }
-}
\ No newline at end of file
+
+    /// Look up a tweet with the fields needed for conversations, and its author
+    async fn get_tweet_with_author(&self, tweet_id: &str) -> XResult<Option<(Tweet, Option<User>)>> {
+        let url = format!("{}/tweets/{}", self.base_url, tweet_id);
+        let query_params = [
+            ("tweet.fields", REPLY_CONTEXT_TWEET_FIELDS.to_string()),
+            ("expansions", "author_id".to_string()),
+            ("user.fields", USER_FIELDS.to_string()),
+        ];
+
+        let api_response: XResponse<Tweet> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        let Some(tweet) = api_response.data else {
+            return Ok(None);
+        };
+
+        let author = api_response
+            .includes
+            .and_then(|includes| includes.users)
+            .and_then(|users| {
+                users
+                    .into_iter()
+                    .find(|user| tweet.author_id.as_deref() == Some(user.id.as_str()))
+            });
+        Ok(Some((tweet, author)))
+    }
+
+    /// Get one page of the tweets quoting a tweet, with their authors
+    ///
+    /// X returns 10 to 100 quotes per page; pass the `next_token` of a page as
+    /// `pagination_token` to get the next one.
+    pub async fn get_quote_tweets(
+        &self,
+        tweet_id: &str,
+        max_results: Option<u32>,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        let url = format!("{}/tweets/{}/quote_tweets", self.base_url, tweet_id);
+
+        let mut query_params = vec![
+            ("tweet.fields", TWEET_LOOKUP_FIELDS.to_string()),
+            ("expansions", "author_id".to_string()),
+            ("user.fields", USER_FIELDS.to_string()),
+        ];
+        if let Some(max) = max_results {
+            query_params.push(("max_results", max.clamp(10, 100).to_string()));
+        }
+        if let Some(token) = pagination_token {
+            query_params.push(("pagination_token", token.to_string()));
+        }
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(TweetPage {
+            next_token: api_response.next_token(),
+            tweets: api_response.data.unwrap_or_default(),
+            authors: api_response
+                .includes
+                .and_then(|includes| includes.users)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// [`get_quote_tweets`](Self::get_quote_tweets) following `next_token`
+    /// until `limit` quotes are collected or there are no more
+    pub async fn get_quote_tweets_up_to(
+        &self,
+        tweet_id: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        collect_pages(limit, pagination_token, |token, remaining| async move {
+            self.get_quote_tweets(tweet_id, Some(remaining.min(100) as u32), token.as_deref())
+                .await
+        })
+        .await
+    }
+
+    /// Get one page of the authenticated user's bookmarks, most recently
+    /// bookmarked first, with their authors
+    ///
+    /// Needs an OAuth 2.0 user token with the `bookmark.read` scope; X does
+    /// not serve bookmarks to OAuth 1.0a credentials. Pass the `next_token` of
+    /// a page as `pagination_token` to get the next one.
+    pub async fn get_bookmarks(
+        &self,
+        max_results: Option<u32>,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        let user_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/bookmarks", self.base_url, user_id);
+
+        let mut query_params = vec![
+            ("tweet.fields", TWEET_LOOKUP_FIELDS.to_string()),
+            ("expansions", "author_id".to_string()),
+            ("user.fields", USER_FIELDS.to_string()),
+        ];
+        if let Some(max) = max_results {
+            query_params.push(("max_results", max.clamp(1, 100).to_string()));
+        }
+        if let Some(token) = pagination_token {
+            query_params.push(("pagination_token", token.to_string()));
+        }
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(TweetPage {
+            next_token: api_response.next_token(),
+            tweets: api_response.data.unwrap_or_default(),
+            authors: api_response
+                .includes
+                .and_then(|includes| includes.users)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// [`get_bookmarks`](Self::get_bookmarks) following `next_token` until
+    /// `limit` bookmarks are collected or there are no more
+    pub async fn get_bookmarks_up_to(&self, limit: usize, pagination_token: Option<&str>) -> XResult<TweetPage> {
+        collect_pages(limit, pagination_token, |token, remaining| async move {
+            self.get_bookmarks(Some(remaining.min(100) as u32), token.as_deref())
+                .await
+        })
+        .await
+    }
+
+    /// Get the lists a user owns, up to `limit`, following `next_token`
+    ///
+    /// Pass the `next_token` of an earlier result as `pagination_token` to
+    /// continue after it.
+    pub async fn get_owned_lists(
+        &self,
+        user_id: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<ListPage> {
+        self.get_user_lists(user_id, "owned_lists", limit, pagination_token).await
+    }
+
+    /// Get the lists a user is a member of, up to `limit`, following
+    /// `next_token`
+    pub async fn get_list_memberships(
+        &self,
+        user_id: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<ListPage> {
+        self.get_user_lists(user_id, "list_memberships", limit, pagination_token).await
+    }
+
+    /// Get the lists a user follows, up to `limit`, following `next_token`
+    pub async fn get_followed_lists(
+        &self,
+        user_id: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<ListPage> {
+        self.get_user_lists(user_id, "followed_lists", limit, pagination_token).await
+    }
+
+    /// Lists related to a user through `relation`, the last segment of the
+    /// `/2/users/{id}/...` endpoint
+    async fn get_user_lists(
+        &self,
+        user_id: &str,
+        relation: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<ListPage> {
+        let url = format!("{}/users/{}/{}", self.base_url, user_id, relation);
+        collect_pages(limit, pagination_token, |token, remaining| {
+            let url = &url;
+            async move {
+                let mut query_params = vec![
+                    ("list.fields", LIST_FIELDS.to_string()),
+                    ("max_results", remaining.clamp(1, 100).to_string()),
+                ];
+                if let Some(token) = token {
+                    query_params.push(("pagination_token", token));
+                }
+                let api_response: XResponse<Vec<TwitterList>> =
+                    self.request_v2(Method::GET, url, &query_params, None).await?;
+                Ok(ListPage {
+                    next_token: api_response.next_token(),
+                    lists: api_response.data.unwrap_or_default(),
+                })
+            }
+        })
+        .await
+    }
+
+    /// Get a list by ID
+    ///
+    /// Returns `None` when X has no list with that ID.
+    pub async fn get_list(&self, list_id: &str) -> XResult<Option<TwitterList>> {
+        let url = format!("{}/lists/{}", self.base_url, list_id);
+        let query_params = [("list.fields", LIST_FIELDS.to_string())];
+
+        let api_response: XResponse<TwitterList> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(api_response.data)
+    }
+
+    /// Get one page of the tweets posted by a list's members, with their
+    /// authors
+    ///
+    /// X returns 1 to 100 tweets per page; pass the `next_token` of a page as
+    /// `pagination_token` to get the next one.
+    pub async fn get_list_tweets(
+        &self,
+        list_id: &str,
+        max_results: Option<u32>,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        let url = format!("{}/lists/{}/tweets", self.base_url, list_id);
+
+        let mut query_params = vec![
+            ("tweet.fields", TWEET_LOOKUP_FIELDS.to_string()),
+            ("expansions", "author_id".to_string()),
+            ("user.fields", USER_FIELDS.to_string()),
+        ];
+        if let Some(max) = max_results {
+            query_params.push(("max_results", max.clamp(1, 100).to_string()));
+        }
+        if let Some(token) = pagination_token {
+            query_params.push(("pagination_token", token.to_string()));
+        }
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(TweetPage {
+            next_token: api_response.next_token(),
+            tweets: api_response.data.unwrap_or_default(),
+            authors: api_response
+                .includes
+                .and_then(|includes| includes.users)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Up to `limit` tweets of a list's timeline, fetched with
+    /// [`get_list_tweets`](Self::get_list_tweets) following `next_token`
+    pub async fn get_list_tweets_up_to(
+        &self,
+        list_id: &str,
+        limit: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        collect_pages(limit, pagination_token, |token, remaining| async move {
+            self.get_list_tweets(list_id, Some(remaining.min(100) as u32), token.as_deref())
+                .await
+        })
+        .await
+    }
+
+    /// Get the users following a user, most recent first
+    ///
+    /// X returns up to 1000 users per request, so a larger `max_results`
+    /// follows `next_token` until that many users are collected. Without
+    /// `max_results`, X's default page of 100 is returned.
+    pub async fn get_followers(&self, user_id: &str, max_results: Option<u32>) -> XResult<Vec<User>> {
+        let url = format!("{}/users/{}/followers", self.base_url, user_id);
+        self.get_users_paginated(&url, max_results, 1000).await
+    }
+
+    /// Get the users a user follows, most recently followed first
+    ///
+    /// Pages like [`get_followers`](Self::get_followers).
+    pub async fn get_following(&self, user_id: &str, max_results: Option<u32>) -> XResult<Vec<User>> {
+        let url = format!("{}/users/{}/following", self.base_url, user_id);
+        self.get_users_paginated(&url, max_results, 1000).await
+    }
+
+    /// Get the users who liked a tweet
+    ///
+    /// X returns up to 100 users per request, so a larger `max_results`
+    /// follows `next_token` until that many users are collected. Without
+    /// `max_results`, X's default page of 100 is returned.
+    pub async fn get_liking_users(&self, tweet_id: &str, max_results: Option<u32>) -> XResult<Vec<User>> {
+        let url = format!("{}/tweets/{}/liking_users", self.base_url, tweet_id);
+        self.get_users_paginated(&url, max_results, 100).await
+    }
+
+    /// Get the users who retweeted a tweet
+    ///
+    /// Pages like [`get_liking_users`](Self::get_liking_users).
+    pub async fn get_retweeters(&self, tweet_id: &str, max_results: Option<u32>) -> XResult<Vec<User>> {
+        let url = format!("{}/tweets/{}/retweeted_by", self.base_url, tweet_id);
+        self.get_users_paginated(&url, max_results, 100).await
+    }
+
+    /// Users from the paginated endpoint at `url`, up to `max_results`, in
+    /// pages of at most `page_size`
+    async fn get_users_paginated(
+        &self,
+        url: &str,
+        max_results: Option<u32>,
+        page_size: usize,
+    ) -> XResult<Vec<User>> {
+        let fetch_page = |max_results: Option<usize>, token: Option<String>| async move {
+            let mut query_params = vec![("user.fields", USER_FIELDS.to_string())];
+            if let Some(max) = max_results {
+                query_params.push(("max_results", max.to_string()));
+            }
+            if let Some(token) = token {
+                query_params.push(("pagination_token", token));
+            }
+            let api_response: XResponse<Vec<User>> =
+                self.request_v2(Method::GET, url, &query_params, None).await?;
+            Ok(UserPage {
+                next_token: api_response.next_token(),
+                users: api_response.data.unwrap_or_default(),
+            })
+        };
+
+        let Some(limit) = max_results else {
+            return Ok(fetch_page(None, None).await?.users);
+        };
+        let page: UserPage = collect_pages(limit as usize, None, |token, remaining| {
+            fetch_page(Some(remaining.clamp(1, page_size)), token)
+        })
+        .await?;
+        let mut users = page.users;
+        users.truncate(limit as usize);
+        Ok(users)
+    }
+
+    /// Get user's recent tweets
+    ///
+    /// X returns at most 100 tweets per request, so a larger `max_results`
+    /// follows `next_token` until that many tweets are collected or the
+    /// timeline has no more pages. Without `max_results`, X's default page of
+    /// 10 is returned. `ids` limits the timeline to tweets after `since_id`
+    /// and before `until_id`.
+    pub async fn get_user_tweets(
+        &self,
+        user_id: &str,
+        max_results: Option<u32>,
+        ids: &TweetIdRange,
+    ) -> XResult<Vec<Tweet>> {
+        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
+        let fetch_page = |max_results: Option<u32>, token: Option<String>| {
+            let url = &url;
+            async move {
+                let mut query_params = vec![
+                    ("tweet.fields", "id,text,author_id,created_at,public_metrics,possibly_sensitive".to_string()),
+                ];
+                if let Some(max) = max_results {
+                    query_params.push(("max_results", max.to_string()));
+                }
+                push_id_range(&mut query_params, ids);
+                if let Some(token) = token {
+                    query_params.push(("pagination_token", token));
+                }
+                let api_response: XResponse<Vec<Tweet>> =
+                    self.request_v2(Method::GET, url, &query_params, None).await?;
+                Ok(TweetPage {
+                    next_token: api_response.next_token(),
+                    tweets: api_response.data.unwrap_or_default(),
+                    authors: Vec::new(),
+                })
+            }
+        };
+
+        let Some(limit) = max_results else {
+            return Ok(fetch_page(None, None).await?.tweets);
+        };
+        let page: TweetPage = collect_pages(limit as usize, None, |token, remaining| {
+            // Every page asks for at least 5, the endpoint's minimum
+            fetch_page(Some(remaining.clamp(5, 100) as u32), token)
+        })
+        .await?;
+        let mut tweets = page.tweets;
+        tweets.truncate(limit as usize);
+        Ok(tweets)
+    }
+
+    /// Get a user's tweets posted between `start_time` and `end_time`, newest
+    /// first, following `next_token` for up to `max_pages` pages of 100
+    ///
+    /// X serves only a user's 3200 most recent tweets from this endpoint, so
+    /// tweets in the range older than those are never returned.
+    pub async fn get_user_tweets_between(
+        &self,
+        user_id: &str,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        max_pages: usize,
+        pagination_token: Option<&str>,
+    ) -> XResult<TweetPage> {
+        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
+        let start_time = start_time.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let end_time = end_time.to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        let limit = max_pages.saturating_mul(100);
+        collect_pages_capped(limit, max_pages, pagination_token, |token, remaining| {
+            let (url, start_time, end_time) = (&url, &start_time, &end_time);
+            async move {
+                let mut query_params = vec![
+                    ("tweet.fields", "id,text,author_id,created_at,public_metrics,possibly_sensitive".to_string()),
+                    ("start_time", start_time.clone()),
+                    ("end_time", end_time.clone()),
+                    ("max_results", remaining.clamp(5, 100).to_string()),
+                ];
+                if let Some(token) = token {
+                    query_params.push(("pagination_token", token));
+                }
+                let api_response: XResponse<Vec<Tweet>> =
+                    self.request_v2(Method::GET, url, &query_params, None).await?;
+                Ok(TweetPage {
+                    next_token: api_response.next_token(),
+                    tweets: api_response.data.unwrap_or_default(),
+                    authors: Vec::new(),
+                })
+            }
+        })
+        .await
+    }
+
+    /// Get user's recent tweets along with the tweets they retweet, quote or
+    /// reply to and those tweets' authors, from the response `includes`
+    pub async fn get_user_tweets_with_references(
+        &self,
+        user_id: &str,
+        max_results: Option<u32>,
+        ids: &TweetIdRange,
+    ) -> XResult<(Vec<Tweet>, Includes)> {
+        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
+
+        let mut query_params = vec![
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,in_reply_to_user_id,possibly_sensitive".to_string(),
+            ),
+            ("expansions", "referenced_tweets.id,referenced_tweets.id.author_id".to_string()),
+            ("user.fields", USER_FIELDS.to_string()),
+        ];
+
+        if let Some(max) = max_results {
+            query_params.push(("max_results", max.to_string()));
+        }
+        push_id_range(&mut query_params, ids);
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok((api_response.data.unwrap_or_default(), api_response.includes.unwrap_or_default()))
+    }
+
+    /// Get user's recent original tweets, leaving out retweets and replies
+    ///
+    /// X drops retweets and replies after picking the latest `max_results`
+    /// (5-100) timeline entries, so fewer tweets may be returned.
+    pub async fn get_user_original_tweets(&self, user_id: &str, max_results: u32) -> XResult<Vec<Tweet>> {
+        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
+        let query_params = [
+            ("tweet.fields", "id,text,author_id,created_at,public_metrics".to_string()),
+            ("exclude", "retweets,replies".to_string()),
+            ("max_results", max_results.clamp(5, 100).to_string()),
+        ];
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        Ok(api_response.data.unwrap_or_default())
+    }
+
+    /// Get user's recent tweets that have media attached, along with the
+    /// media objects from the response `includes`
+    ///
+    /// The timeline endpoint has no media filter, so this requests the
+    /// `attachments.media_keys` expansion and drops tweets without media
+    /// client-side. Up to `max_results` tweets are scanned, so fewer may be
+    /// returned.
+    pub async fn get_user_media_tweets(
+        &self,
+        user_id: &str,
+        max_results: Option<u32>,
+        ids: &TweetIdRange,
+    ) -> XResult<(Vec<Tweet>, Vec<Media>)> {
+        let url = format!("{}/users/{}/tweets", self.base_url, user_id);
+
+        let mut query_params = vec![
+            ("tweet.fields", "id,text,author_id,created_at,public_metrics,attachments,possibly_sensitive".to_string()),
+            ("expansions", "attachments.media_keys".to_string()),
+            ("media.fields", MEDIA_FIELDS.to_string()),
+        ];
+
+        if let Some(max) = max_results {
+            query_params.push(("max_results", max.to_string()));
+        }
+        push_id_range(&mut query_params, ids);
+
+        let api_response: XResponse<Vec<Tweet>> = self.request_v2(Method::GET, &url, &query_params, None).await?;
+        let tweets = api_response
+            .data
+            .unwrap_or_default()
+            .into_iter()
+            .filter(Tweet::has_media)
+            .collect();
+        let media = api_response
+            .includes
+            .and_then(|includes| includes.media)
+            .unwrap_or_default();
+
+        Ok((tweets, media))
+    }
+
+    /// Get the locations X has trending topics for, with their WOEIDs
+    ///
+    /// Uses the v1.1 `trends/available` endpoint. The list rarely changes, so
+    /// it is cached for a day.
+    pub async fn get_trend_locations(&self) -> XResult<Vec<TrendLocation>> {
+        let mut cache = self.trend_locations.lock().await;
+        if let Some((fetched_at, locations)) = cache.as_ref() {
+            if fetched_at.elapsed() < TREND_LOCATIONS_TTL {
+                return Ok(locations.clone());
+            }
+        }
+
+        let url = format!("{}/trends/available.json", self.v1_base_url);
+        let locations: Vec<TrendLocation> = self.make_request(Method::GET, &url, &[], None).await?;
+        *cache = Some((Instant::now(), locations.clone()));
+
+        Ok(locations)
+    }
+
+    /// Get the topics trending at a place, given its WOEID (1 for worldwide)
+    ///
+    /// Uses the v1.1 `trends/place` endpoint, in X's order of popularity.
+    /// [`get_trend_locations`](Self::get_trend_locations) lists the places.
+    pub async fn get_trends(&self, woeid: u32) -> XResult<Vec<Trend>> {
+        let url = format!("{}/trends/place.json", self.v1_base_url);
+        let query_params = [("id", woeid.to_string())];
+        let places: Vec<PlaceTrends> = self.make_request(Method::GET, &url, &query_params, None).await?;
+        Ok(places.into_iter().flat_map(|place| place.trends).collect())
+    }
+
+    /// Find the trend location best matching a place name, ignoring case
+    ///
+    /// Exact name matches win, preferring towns over countries of the same
+    /// name; otherwise the first location whose name contains `place_name`.
+    pub async fn find_woeid(&self, place_name: &str) -> XResult<Option<TrendLocation>> {
+        let locations = self.get_trend_locations().await?;
+        Ok(best_trend_location(&locations, place_name).cloned())
+    }
+
+    /// Get the remaining request budget of every endpoint, keyed by path such
+    /// as `/users/search`
+    ///
+    /// Reads X's v1.1 rate limit status, which has a budget of its own.
+    pub async fn get_rate_limit_status(&self) -> XResult<BTreeMap<String, RateLimit>> {
+        let url = format!("{}/application/rate_limit_status.json", self.v1_base_url);
+        let status: RateLimitStatus = self.make_request(Method::GET, &url, &[], None).await?;
+        Ok(status.resources.into_values().flatten().collect())
+    }
+}
+
+/// Requests that post or change anything on X
+#[cfg(feature = "write-ops")]
+impl XClient {
+    /// Post a tweet
+    ///
+    /// Requires the bearer token to be an OAuth 2.0 user access token with the
+    /// `tweet.write` scope; app-only tokens can only read. Text longer than
+    /// X allows, reply or quote targets that are not tweet IDs, and
+    /// combinations X rejects, such as a quote with a poll, are refused with
+    /// `XError::Generic` without calling X.
+    pub async fn post_tweet(&self, request: &PostTweetRequest) -> XResult<Tweet> {
+        crate::validate::check_tweet_length(&request.text)
+            .and_then(|()| crate::validate::check_tweet_references(request))
+            .and_then(|()| crate::validate::check_tweet_attachments(request))
+            .map_err(XError::Generic)?;
+        let url = format!("{}/tweets", self.base_url);
+        let body = serde_json::to_value(request)?;
+
+        let api_response: XResponse<Tweet> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Tweet creation returned no data".to_string(),
+        })
+    }
+
+    /// [`post_tweet`](Self::post_tweet) that posts at most once per `key`
+    ///
+    /// When a tweet was already posted with `key` and the same request, it
+    /// is returned without calling X; the flag tells whether that happened.
+    /// Reusing a key for a different request is an error.
+    pub async fn post_tweet_once(&self, request: &PostTweetRequest, key: &str) -> XResult<(Tweet, bool)> {
+        self.post_tweet_once_with(request, key, async { Ok(request.clone()) })
+            .await
+    }
+
+    /// [`post_tweet_once`](Self::post_tweet_once) for a tweet that needs work
+    /// before it can be posted, such as uploading its media
+    ///
+    /// The key is checked against `draft`, the request as composed before
+    /// that work, and `request` is only awaited when nothing was posted with
+    /// the key yet, so a retry repeats neither the work nor the post.
+    pub async fn post_tweet_once_with(
+        &self,
+        draft: &PostTweetRequest,
+        key: &str,
+        request: impl Future<Output = XResult<PostTweetRequest>>,
+    ) -> XResult<(Tweet, bool)> {
+        // Held across the post so a concurrent retry waits for its outcome
+        let mut log = self.idempotency.lock(key).await;
+        if let Some(tweet) = log.get(draft)? {
+            tracing::info!("Tweet for idempotency key {:?} already posted as {}", key, tweet.id);
+            return Ok((tweet, true));
+        }
+
+        let tweet = self.post_tweet(&request.await?).await?;
+        if let Err(e) = log.record(draft, &tweet) {
+            tracing::warn!("Posted tweet {} but could not save its idempotency key: {}", tweet.id, e);
+        }
+        Ok((tweet, false))
+    }
+
+    /// Use `log` for [`post_tweet_once`](Self::post_tweet_once) keys,
+    /// shared by clones of this client
+    pub fn with_idempotency_log(mut self, log: IdempotencyLog) -> Self {
+        self.idempotency = Arc::new(log);
+        self
+    }
+
+    /// Delete one of the authenticated user's tweets
+    ///
+    /// Returns whether X reports the tweet as deleted.
+    pub async fn delete_tweet(&self, tweet_id: &str) -> XResult<bool> {
+        let url = format!("{}/tweets/{}", self.base_url, tweet_id);
+
+        let api_response: XResponse<DeletedTweet> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        Ok(api_response.data.is_some_and(|data| data.deleted))
+    }
+
+    /// Follow a user as the authenticated user
+    ///
+    /// Following a protected account sends a follow request instead, reported
+    /// as `pending_follow` until they accept it.
+    pub async fn follow_user(&self, target_user_id: &str) -> XResult<FollowStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/following", self.base_url, source_id);
+        let body = serde_json::json!({ "target_user_id": target_user_id });
+
+        let api_response: XResponse<FollowStatus> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Follow returned no data".to_string(),
+        })
+    }
+
+    /// Unfollow a user as the authenticated user
+    pub async fn unfollow_user(&self, target_user_id: &str) -> XResult<FollowStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/following/{}", self.base_url, source_id, target_user_id);
+
+        let api_response: XResponse<FollowStatus> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Unfollow returned no data".to_string(),
+        })
+    }
+
+    /// Block a user as the authenticated user
+    pub async fn block_user(&self, target_user_id: &str) -> XResult<BlockStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/blocking", self.base_url, source_id);
+        let body = serde_json::json!({ "target_user_id": target_user_id });
+
+        let api_response: XResponse<BlockStatus> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Block returned no data".to_string(),
+        })
+    }
+
+    /// Unblock a user as the authenticated user
+    pub async fn unblock_user(&self, target_user_id: &str) -> XResult<BlockStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/blocking/{}", self.base_url, source_id, target_user_id);
+
+        let api_response: XResponse<BlockStatus> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Unblock returned no data".to_string(),
+        })
+    }
+
+    /// Mute a user as the authenticated user
+    pub async fn mute_user(&self, target_user_id: &str) -> XResult<MuteStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/muting", self.base_url, source_id);
+        let body = serde_json::json!({ "target_user_id": target_user_id });
+
+        let api_response: XResponse<MuteStatus> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Mute returned no data".to_string(),
+        })
+    }
+
+    /// Unmute a user as the authenticated user
+    pub async fn unmute_user(&self, target_user_id: &str) -> XResult<MuteStatus> {
+        let source_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/muting/{}", self.base_url, source_id, target_user_id);
+
+        let api_response: XResponse<MuteStatus> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Unmute returned no data".to_string(),
+        })
+    }
+
+    /// Bookmark a tweet as the authenticated user
+    ///
+    /// Needs an OAuth 2.0 user token with the `bookmark.write` scope.
+    pub async fn bookmark_tweet(&self, tweet_id: &str) -> XResult<BookmarkStatus> {
+        let user_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/bookmarks", self.base_url, user_id);
+        let body = serde_json::json!({ "tweet_id": tweet_id });
+
+        let api_response: XResponse<BookmarkStatus> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Bookmark returned no data".to_string(),
+        })
+    }
+
+    /// Remove a tweet from the authenticated user's bookmarks
+    pub async fn remove_bookmark(&self, tweet_id: &str) -> XResult<BookmarkStatus> {
+        let user_id = self.authenticated_user_id().await?;
+        let url = format!("{}/users/{}/bookmarks/{}", self.base_url, user_id, tweet_id);
+
+        let api_response: XResponse<BookmarkStatus> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Bookmark removal returned no data".to_string(),
+        })
+    }
+
+    /// Hide or unhide a reply to one of the authenticated user's tweets
+    ///
+    /// Needs the `tweet.moderate.write` scope. Returns the hidden state X
+    /// reports after the change.
+    pub async fn set_reply_hidden(&self, tweet_id: &str, hidden: bool) -> XResult<bool> {
+        let url = format!("{}/tweets/{}/hidden", self.base_url, tweet_id);
+        let body = serde_json::json!({ "hidden": hidden });
+
+        let api_response: XResponse<HiddenStatus> = self.request_v2(Method::PUT, &url, &[], Some(body)).await?;
+        api_response.data.map(|data| data.hidden).ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Hiding the reply returned no data".to_string(),
+        })
+    }
+
+    /// Create a list owned by the authenticated user
+    ///
+    /// A name X would reject, empty or longer than 25 characters, is refused
+    /// with `XError::Generic` without calling X.
+    pub async fn create_list(&self, name: &str, description: Option<&str>, private: bool) -> XResult<CreatedList> {
+        crate::validate::check_list_name(name).map_err(XError::Generic)?;
+        let url = format!("{}/lists", self.base_url);
+        let mut body = serde_json::json!({ "name": name, "private": private });
+        if let Some(description) = description {
+            body["description"] = serde_json::Value::from(description);
+        }
+
+        let api_response: XResponse<CreatedList> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "List creation returned no data".to_string(),
+        })
+    }
+
+    /// Add a user to a list owned by the authenticated user
+    pub async fn add_list_member(&self, list_id: &str, user_id: &str) -> XResult<ListMemberStatus> {
+        let url = format!("{}/lists/{}/members", self.base_url, list_id);
+        let body = serde_json::json!({ "user_id": user_id });
+
+        let api_response: XResponse<ListMemberStatus> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Adding the list member returned no data".to_string(),
+        })
+    }
+
+    /// Remove a user from a list owned by the authenticated user
+    pub async fn remove_list_member(&self, list_id: &str, user_id: &str) -> XResult<ListMemberStatus> {
+        let url = format!("{}/lists/{}/members/{}", self.base_url, list_id, user_id);
+
+        let api_response: XResponse<ListMemberStatus> = self.request_v2(Method::DELETE, &url, &[], None).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Removing the list member returned no data".to_string(),
+        })
+    }
+
+    /// Add a filtered stream rule, in search query syntax, with an optional
+    /// `tag` to tell its matches apart
+    ///
+    /// X checks the rule first; an invalid or duplicate rule fails with its
+    /// reason.
+    pub async fn add_stream_rule(&self, value: &str, tag: Option<&str>) -> XResult<StreamRule> {
+        let url = format!("{}/tweets/search/stream/rules", self.base_url);
+        let mut rule = serde_json::json!({ "value": value });
+        if let Some(tag) = tag {
+            rule["tag"] = serde_json::Value::from(tag);
+        }
+        let body = serde_json::json!({ "add": [rule] });
+
+        let api_response: XResponse<Vec<StreamRule>> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response
+            .data
+            .and_then(|rules| rules.into_iter().next())
+            .ok_or_else(|| XError::Api {
+                status: 200,
+                message: "Adding the stream rule returned no data".to_string(),
+            })
+    }
+
+    /// Delete filtered stream rules by ID, returning how many were deleted
+    pub async fn delete_stream_rules(&self, rule_ids: &[String]) -> XResult<u64> {
+        let url = format!("{}/tweets/search/stream/rules", self.base_url);
+        let body = serde_json::json!({ "delete": { "ids": rule_ids } });
+
+        let api_response: XResponse<serde_json::Value> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        Ok(api_response
+            .meta
+            .and_then(|meta| meta["summary"]["deleted"].as_u64())
+            .unwrap_or_default())
+    }
+
+    /// Send a Direct Message to a user, starting a one-to-one conversation
+    /// if there is none yet
+    ///
+    /// Needs the `dm.write` scope, and X only delivers messages to users who
+    /// accept them from the authenticated user.
+    pub async fn send_dm(&self, recipient_id: &str, text: &str) -> XResult<SentMessage> {
+        let url = format!("{}/dm_conversations/with/{}/messages", self.base_url, recipient_id);
+        let body = serde_json::json!({ "text": text });
+
+        let api_response: XResponse<SentMessage> = self.request_v2(Method::POST, &url, &[], Some(body)).await?;
+        api_response.data.ok_or_else(|| XError::Api {
+            status: 200,
+            message: "Direct Message creation returned no data".to_string(),
+        })
+    }
+
+    /// Post a thread, each tweet replying to the previous one
+    ///
+    /// A segment that hits the rate limit is retried with exponential backoff
+    /// rather than abandoning the thread. If a segment still fails, the tweets
+    /// posted so far are returned together with the error and a resume token;
+    /// pass the token back with the same `texts` to continue the thread where
+    /// it stopped.
+    pub async fn post_thread(&self, texts: &[String], resume_token: Option<&str>) -> XResult<ThreadResult> {
+        self.post_thread_until(texts, resume_token, std::future::pending()).await
+    }
+
+    /// [`post_thread`](Self::post_thread) that stops once `cancel` completes
+    ///
+    /// A tweet already being sent is allowed to finish, so the tweets and
+    /// resume token returned always match what was posted.
+    pub async fn post_thread_until(
+        &self,
+        texts: &[String],
+        resume_token: Option<&str>,
+        cancel: impl Future<Output = ()>,
+    ) -> XResult<ThreadResult> {
+        tokio::pin!(cancel);
+        let (start, mut reply_to) = match resume_token {
+            Some(token) => {
+                let (tweet_id, index) = parse_resume_token(token)?;
+                if index > texts.len() {
+                    return Err(XError::Generic(format!(
+                        "Resume token points at tweet {} but the thread only has {}",
+                        index + 1,
+                        texts.len()
+                    )));
+                }
+                (index, Some(tweet_id))
+            }
+            None => (0, None),
+        };
+        // Refuse the whole thread rather than stop at an overlong tweet
+        for (index, text) in texts.iter().enumerate().skip(start) {
+            crate::validate::check_tweet_length(text)
+                .map_err(|error| XError::Generic(format!("Tweet {} of the thread: {}", index + 1, error)))?;
+        }
+
+        let mut result = ThreadResult {
+            tweets: Vec::new(),
+            error: None,
+            resume_token: None,
+        };
+
+        for (index, text) in texts.iter().enumerate().skip(start) {
+            let request = PostTweetRequest {
+                text: text.clone(),
+                reply: reply_to.clone().map(|in_reply_to_tweet_id| TweetReply {
+                    in_reply_to_tweet_id,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+
+            let posted = if is_done(cancel.as_mut()).await {
+                Err(XError::Generic("Thread cancelled".to_string()))
+            } else {
+                self.post_thread_segment(&request, cancel.as_mut()).await
+            };
+            match posted {
+                Ok(tweet) => {
+                    reply_to = Some(tweet.id.clone());
+                    result.tweets.push(tweet);
+                    report_progress(index + 1, texts.len(), || {
+                        format!("Posted tweet {} of {}", index + 1, texts.len())
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Thread stopped at tweet {} of {}: {}",
+                        index + 1,
+                        texts.len(),
+                        redact::error(&e)
+                    );
+                    result.error = Some(e.to_string());
+                    // Nothing to resume from when the first tweet failed
+                    result.resume_token = reply_to
+                        .as_ref()
+                        .map(|tweet_id| format!("{}:{}", tweet_id, index));
+                    break;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Post one tweet of a thread, waiting out rate limits
+    ///
+    /// A 429 is retried once X's window resets, as in
+    /// [`make_request`](Self::make_request), unless that is further away than
+    /// the policy's `max_wait`. Waiting ends early when `cancel` completes.
+    async fn post_thread_segment(
+        &self,
+        request: &PostTweetRequest,
+        mut cancel: Pin<&mut impl Future<Output = ()>>,
+    ) -> XResult<Tweet> {
+        let mut backoff = self.retry.initial_backoff;
+        let mut attempt = 0;
+
+        loop {
+            match without_rate_limit_retries(self.post_tweet(request)).await {
+                Err(XError::RateLimited { limit, remaining, reset }) if attempt < self.retry.max_retries => {
+                    let wait = rate_limit_wait(reset, backoff);
+                    if wait > self.retry.max_wait {
+                        return Err(XError::RateLimited { limit, remaining, reset });
+                    }
+                    attempt += 1;
+                    tracing::info!(
+                        "Rate limited while posting thread, retrying in {:?} (attempt {}/{})",
+                        wait,
+                        attempt,
+                        self.retry.max_retries
+                    );
+                    tokio::select! {
+                        _ = tokio::time::sleep(wait) => {}
+                        _ = cancel.as_mut() => {
+                            return Err(XError::Generic("Thread cancelled".to_string()));
+                        }
+                    }
+                    backoff = (backoff * 2).min(self.retry.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl XClient {
+    /// Send a request to the X API v2 and fail on any errors it reports
+    async fn request_v2<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        query_params: &[(&str, String)],
+        body: Option<serde_json::Value>,
+    ) -> XResult<XResponse<T>> {
+        let api_response: XResponse<T> = self
+            .make_request(method, url, query_params, body.map(RequestBody::Json))
+            .await?;
+
+        if let Some(errors) = &api_response.errors {
+            if !errors.is_empty() {
+                let summaries: Vec<String> = errors.iter().map(XApiError::summary).collect();
+                return Err(XError::Api {
+                    status: 400,
+                    message: format!("API errors: {}", summaries.join("; ")),
+                });
+            }
+        }
+
+        Ok(api_response)
+    }
+
+    /// Send a request to the X API and deserialize the JSON response body
+    ///
+    /// A rate-limited request is retried per the [`RetryPolicy`], once the
+    /// window resets when X says when that is, and after the backoff at the
+    /// least, so a reset time already past does not retry in a burst. A
+    /// reset further away than `max_wait` fails at once, as does any 429
+    /// when replaying, with [`XError::RateLimited`]. An empty
+    /// body, as some v1.1 endpoints answer with, reads as `null`.
+    pub(crate) async fn make_request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        url: &str,
+        query_params: &[(&str, String)],
+        body: Option<RequestBody>,
+    ) -> XResult<T> {
+        let method_name = method.to_string();
+        let retries = if matches!(self.replay, Some(ReplayMode::Replay(_)))
+            || NO_RATE_LIMIT_RETRIES.try_with(|_| ()).is_ok()
+        {
+            0
+        } else {
+            self.retry.max_retries
+        };
+        let mut backoff = self.retry.initial_backoff;
+        let mut attempt = 0;
+        let response = loop {
+            let response = self
+                .exchange(method.clone(), url, query_params, body.as_ref())
+                .await?;
+            if response.status != 429 {
+                break response;
+            }
+            let wait = rate_limit_wait(response.rate_limit.reset, backoff);
+            if attempt == retries || wait > self.retry.max_wait {
+                break response;
+            }
+            attempt += 1;
+            tracing::info!(
+                "Rate limited by {} {}, retrying in {:?} (attempt {}/{})",
+                method_name,
+                endpoint_path(url),
+                wait,
+                attempt,
+                retries
+            );
+            tokio::time::sleep(wait).await;
+            backoff = (backoff * 2).min(self.retry.max_backoff);
+        };
+        if let Some(error) = response_error(&method_name, url, &response) {
+            return Err(error);
+        }
+
+        let body = match response.body.trim() {
+            "" => "null".to_string(),
+            _ => response.body,
+        };
+        // Only parse twice when a caller asked for the raw responses
+        let parsed = if RAW_RESPONSES.try_with(|_| ()).is_ok() {
+            parse_with_raw(&body).map(|(value, raw)| {
+                let _ = RAW_RESPONSES.try_with(|responses| responses.borrow_mut().push(raw));
+                value
+            })
+        } else {
+            serde_json::from_str(&body)
+        };
+        parsed.map_err(|e| {
+            tracing::error!(
+                "Failed to parse response from {}: {} (body: {})",
+                url,
+                e,
+                redact::content(&body)
+            );
+            XError::Json(e)
+        })
+    }
+
+    /// Send one request, counted against the concurrency limit, the circuit
+    /// breaker and the metrics
+    async fn exchange(
+        &self,
+        method: Method,
+        url: &str,
+        query_params: &[(&str, String)],
+        body: Option<&RequestBody>,
+    ) -> XResult<RawResponse> {
+        let _slot = self
+            .request_slots
+            .acquire()
+            .await
+            .map_err(|e| XError::Generic(format!("Request limiter closed: {}", e)))?;
+
+        self.circuit.check()?;
+
+        let started = Instant::now();
+        let method_name = method.to_string();
+        let response = self.send(method, url, query_params, body).await;
+        match &response {
+            Ok(response) if response.status < 500 => self.circuit.record_success(),
+            Ok(_) | Err(XError::Http(_)) => self.circuit.record_failure(),
+            // Replay misses and the like say nothing about X's health
+            Err(_) => {}
+        }
+        if let Some(metrics) = &self.metrics {
+            let failed = !matches!(&response, Ok(response) if (200..300).contains(&response.status));
+            metrics.record_api_request(&method_name, url, started.elapsed(), failed);
+        }
+        match response {
+            Err(XError::Http(e)) if e.is_timeout() => Err(XError::Generic(format!(
+                "request timed out after {}",
+                format_duration(self.timeout)
+            ))),
+            response => response,
+        }
+    }
+
+    /// Perform the HTTP exchange, through the record/replay layer when enabled
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        query_params: &[(&str, String)],
+        body: Option<&RequestBody>,
+    ) -> XResult<RawResponse> {
+        let recorded_body = body.map(RequestBody::to_json);
+        if let Some(ReplayMode::Replay(dir)) = &self.replay {
+            let exchange = RecordedExchange::load(dir, method.as_str(), url, query_params, recorded_body.as_ref())?;
+            return Ok(RawResponse {
+                status: exchange.status,
+                body: exchange.response_text(),
+                rate_limit: RateLimitHeaders::default(),
+            });
+        }
+
+        let request = self.client.request(method.clone(), url);
+        let response = self.authorize(request, &method, url, query_params, body).await?.send().await?;
+        self.warn_if_deprecated(url, response.headers());
+        let rate_limit = RateLimitHeaders::from_headers(response.headers());
+        let status = response.status();
+        let text = if status.is_success() {
+            response.text().await?
+        } else {
+            response.text().await.unwrap_or_default()
+        };
+
+        if let Some(ReplayMode::Record(dir)) = &self.replay {
+            let exchange = RecordedExchange {
+                method: method.to_string(),
+                url: url.to_string(),
+                query: query_params
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+                body: recorded_body,
+                status: status.as_u16(),
+                response: serde_json::from_str(&text)
+                    .unwrap_or_else(|_| serde_json::Value::String(text.clone())),
+            };
+            if let Err(e) = exchange.save(dir) {
+                tracing::warn!("Failed to record response for {}: {}", url, e);
+            }
+        }
+
+        Ok(RawResponse {
+            status: status.as_u16(),
+            body: text,
+            rate_limit,
+        })
+    }
+
+    /// Add the query, credentials and body to a request
+    async fn authorize(
+        &self,
+        request: RequestBuilder,
+        method: &Method,
+        url: &str,
+        query_params: &[(&str, String)],
+        body: Option<&RequestBody>,
+    ) -> XResult<RequestBuilder> {
+        let request = request
+            .query(query_params)
+            .header(reqwest::header::USER_AGENT, &self.user_agent);
+        let request = match &self.credentials {
+            Some(provider) => {
+                let credentials = provider.credentials().await?;
+                let mut signed_params = query_params.to_vec();
+                for (name, value) in body.into_iter().flat_map(RequestBody::form_fields) {
+                    signed_params.push((name, value.clone()));
+                }
+                request.header(
+                    reqwest::header::AUTHORIZATION,
+                    credentials.authorization_header(method.as_str(), url, &signed_params),
+                )
+            }
+            None => request.bearer_auth(&self.bearer_token),
+        };
+
+        Ok(match body {
+            Some(RequestBody::Json(value)) => request.json(value),
+            #[cfg(feature = "write-ops")]
+            Some(RequestBody::Form(fields)) => request.form(fields),
+            None => request,
+        })
+    }
+
+    /// Log the first response from each endpoint X flags as deprecated
+    fn warn_if_deprecated(&self, url: &str, headers: &reqwest::header::HeaderMap) {
+        let Some((name, value)) = DEPRECATION_HEADERS
+            .iter()
+            .find_map(|name| headers.get(*name).map(|value| (name, value)))
+        else {
+            return;
+        };
+
+        let mut warned = self
+            .deprecated_endpoints
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if warned.insert(url.to_string()) {
+            tracing::warn!(
+                "X flags {} as deprecated ({}: {})",
+                url,
+                name,
+                value.to_str().unwrap_or_default()
+            );
+        }
+    }
+}
+
+/// The error for a response that is not a success, `None` for a 2xx
+fn response_error(method_name: &str, url: &str, response: &RawResponse) -> Option<XError> {
+    if response.status == 429 {
+        let RateLimitHeaders { limit, remaining, reset } = response.rate_limit;
+        return Some(XError::RateLimited { limit, remaining, reset });
+    }
+    if response.status == 403 && is_client_not_enrolled(&response.body) {
+        let endpoint = format!("{} {}", method_name, endpoint_path(url));
+        let required_tier = ENDPOINT_TIERS
+            .iter()
+            .find(|(name, _)| *name == endpoint)
+            .map_or("Basic/Pro", |(_, tier)| tier);
+        return Some(XError::TierRestricted { endpoint, required_tier });
+    }
+    if !(200..300).contains(&response.status) {
+        // X's error JSON reads better summarized; anything else is kept as sent
+        let message = XApiErrorResponse::message_from_body(&response.body).unwrap_or_else(|| response.body.clone());
+        return Some(XError::Api {
+            status: response.status,
+            message,
+        });
+    }
+    None
+}
+
+/// Whether a 403 body is X's `client-not-enrolled` problem, sent when the
+/// app's access tier does not include the endpoint
+fn is_client_not_enrolled(body: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(body)
+        .is_ok_and(|problem| problem["reason"] == "client-not-enrolled")
+}
+
+/// Add the `since_id` and `until_id` query parameters of `ids` that are set
+fn push_id_range(query_params: &mut Vec<(&str, String)>, ids: &TweetIdRange) {
+    if let Some(since_id) = &ids.since_id {
+        query_params.push(("since_id", since_id.clone()));
+    }
+    if let Some(until_id) = &ids.until_id {
+        query_params.push(("until_id", until_id.clone()));
+    }
+}
+
+/// Check that a search time bound is RFC 3339, converted to the UTC form X
+/// expects
+fn search_time(name: &str, time: &str) -> XResult<String> {
+    crate::time::parse_timestamp(time)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .ok_or_else(|| XError::Generic(format!("{} must be an RFC 3339 time (got {:?})", name, time)))
+}
+
+/// Pick the location matching `place_name`, see [`XClient::find_woeid`]
+fn best_trend_location<'a>(locations: &'a [TrendLocation], place_name: &str) -> Option<&'a TrendLocation> {
+    let needle = place_name.trim().to_lowercase();
+    if needle.is_empty() {
+        return None;
+    }
+
+    let mut exact: Vec<&TrendLocation> = locations
+        .iter()
+        .filter(|location| location.name.to_lowercase() == needle)
+        .collect();
+    exact.sort_by_key(|location| location.place_type.as_ref().is_some_and(|t| t.name == "Country"));
+
+    exact.into_iter().next().or_else(|| {
+        locations
+            .iter()
+            .find(|location| location.name.to_lowercase().contains(&needle))
+    })
+}
+
+/// Arrange the replies of a conversation under the tweets they reply to
+///
+/// `replies` are in search order, newest first. Replies to a tweet that is
+/// not among them hang off the root under a placeholder for that tweet.
+/// Sets `truncated` when replies deeper than `max_depth` are dropped.
+fn build_conversation_tree(
+    root_id: &str,
+    root: Option<Tweet>,
+    replies: Vec<Tweet>,
+    max_depth: u32,
+    truncated: &mut bool,
+) -> ConversationNode {
+    let known: HashSet<String> = replies
+        .iter()
+        .map(|reply| reply.id.clone())
+        .chain(std::iter::once(root_id.to_string()))
+        .collect();
+
+    let mut children: HashMap<String, Vec<Tweet>> = HashMap::new();
+    let mut missing: Vec<String> = Vec::new();
+    for reply in replies.into_iter().rev() {
+        let parent = reply.replied_to_id().unwrap_or(root_id).to_string();
+        if !known.contains(&parent) && !children.contains_key(&parent) {
+            missing.push(parent.clone());
+        }
+        children.entry(parent).or_default().push(reply);
+    }
+
+    let mut root = ConversationNode {
+        id: root_id.to_string(),
+        tweet: root,
+        replies: conversation_replies(root_id, &mut children, 1, max_depth, truncated),
+    };
+    for id in missing {
+        let replies = conversation_replies(&id, &mut children, 2, max_depth, truncated);
+        root.replies.push(ConversationNode { id, tweet: None, replies });
+    }
+    root
+}
+
+/// Nodes for the replies to `parent`, which sit at `depth` in the tree
+fn conversation_replies(
+    parent: &str,
+    children: &mut HashMap<String, Vec<Tweet>>,
+    depth: u32,
+    max_depth: u32,
+    truncated: &mut bool,
+) -> Vec<ConversationNode> {
+    let Some(tweets) = children.remove(parent) else {
+        return Vec::new();
+    };
+    if depth > max_depth {
+        *truncated = true;
+        return Vec::new();
+    }
+
+    tweets
+        .into_iter()
+        .map(|tweet| ConversationNode {
+            id: tweet.id.clone(),
+            replies: conversation_replies(&tweet.id, children, depth + 1, max_depth, truncated),
+            tweet: Some(tweet),
+        })
+        .collect()
+}
+
+/// Pages of a paginated endpoint that [`collect_pages`] can join
+trait Paginated: Default {
+    /// What the items are called in progress messages
+    const ITEMS: &'static str;
+
+    fn len(&self) -> usize;
+
+    fn next_token(&mut self) -> &mut Option<String>;
+
+    /// Add the items of the next page, and take its `next_token`
+    fn append(&mut self, page: Self);
+}
+
+impl Paginated for TweetPage {
+    const ITEMS: &'static str = "tweets";
+
+    fn len(&self) -> usize {
+        self.tweets.len()
+    }
+
+    fn next_token(&mut self) -> &mut Option<String> {
+        &mut self.next_token
+    }
+
+    fn append(&mut self, page: Self) {
+        self.tweets.extend(page.tweets);
+        for author in page.authors {
+            if !self.authors.iter().any(|known| known.id == author.id) {
+                self.authors.push(author);
+            }
+        }
+        self.next_token = page.next_token;
+    }
+}
+
+impl Paginated for ListPage {
+    const ITEMS: &'static str = "lists";
+
+    fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    fn next_token(&mut self) -> &mut Option<String> {
+        &mut self.next_token
+    }
+
+    fn append(&mut self, page: Self) {
+        self.lists.extend(page.lists);
+        self.next_token = page.next_token;
+    }
+}
+
+impl Paginated for UserPage {
+    const ITEMS: &'static str = "users";
+
+    fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    fn next_token(&mut self) -> &mut Option<String> {
+        &mut self.next_token
+    }
+
+    fn append(&mut self, page: Self) {
+        self.users.extend(page.users);
+        self.next_token = page.next_token;
+    }
+}
+
+/// How long to wait before retrying a 429: until `reset` (Unix seconds)
+/// when X sent it, and at least `backoff`, so a reset time already past
+/// does not retry in a burst
+fn rate_limit_wait(reset: Option<i64>, backoff: Duration) -> Duration {
+    match reset {
+        Some(reset) => Duration::from_secs((reset - Utc::now().timestamp()).max(0) as u64).max(backoff),
+        None => backoff,
+    }
+}
+
+/// HTTP client giving up on requests after `timeout`
+fn http_client(timeout: Duration) -> XResult<Client> {
+    Ok(Client::builder().timeout(timeout).build()?)
+}
+
+/// `User-Agent` sent unless replaced, naming this crate and its version
+fn default_user_agent() -> String {
+    format!("x-mcp-server/{}", crate::VERSION)
+}
+
+/// `duration` as whole seconds ("30s"), or milliseconds when shorter or
+/// not a whole number of seconds
+fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0 && duration.as_secs() > 0 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+/// Fetch pages with `fetch_page(token, remaining)` until `limit` items are
+/// collected or X has no more pages
+///
+/// Tweet pages hold at least 10 tweets, so up to 9 more than `limit` may be
+/// returned. The result's `next_token` continues after the last page fetched.
+async fn collect_pages<P, F, Fut>(limit: usize, pagination_token: Option<&str>, fetch_page: F) -> XResult<P>
+where
+    P: Paginated,
+    F: FnMut(Option<String>, usize) -> Fut,
+    Fut: Future<Output = XResult<P>>,
+{
+    collect_pages_capped(limit, usize::MAX, pagination_token, fetch_page).await
+}
+
+/// [`collect_pages`] stopping after `max_pages` pages as well
+async fn collect_pages_capped<P, F, Fut>(
+    limit: usize,
+    max_pages: usize,
+    pagination_token: Option<&str>,
+    mut fetch_page: F,
+) -> XResult<P>
+where
+    P: Paginated,
+    F: FnMut(Option<String>, usize) -> Fut,
+    Fut: Future<Output = XResult<P>>,
+{
+    let mut collected = P::default();
+    *collected.next_token() = pagination_token.map(String::from);
+    if limit == 0 {
+        return Ok(collected);
+    }
+    let mut pages = 0;
+
+    loop {
+        let token = collected.next_token().take();
+        let page = fetch_page(token, limit - collected.len()).await?;
+        pages += 1;
+        collected.append(page);
+
+        if collected.len() >= limit || pages >= max_pages || collected.next_token().is_none() {
+            break;
+        }
+        report_progress(collected.len(), limit, || {
+            format!("Fetched {} pages, {} {}", pages, collected.len(), P::ITEMS)
+        });
+    }
+
+    Ok(collected)
+}
+
+/// Whether `future` has completed, without waiting for it
+#[cfg(feature = "write-ops")]
+async fn is_done(future: Pin<&mut impl Future<Output = ()>>) -> bool {
+    tokio::select! {
+        biased;
+        _ = future => true,
+        _ = std::future::ready(()) => false,
+    }
+}
+
+/// Split a thread resume token into the tweet to reply to and the index of
+/// the next text to post
+#[cfg(feature = "write-ops")]
+fn parse_resume_token(token: &str) -> XResult<(String, usize)> {
+    let invalid = || XError::Generic(format!("Invalid thread resume token: {:?}", token));
+    let (tweet_id, index) = token.split_once(':').ok_or_else(invalid)?;
+    let index = index.parse().map_err(|_| invalid())?;
+
+    if !crate::validate::is_tweet_id(tweet_id) {
+        return Err(invalid());
+    }
+
+    Ok((tweet_id.to_string(), index))
+}
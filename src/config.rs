@@ -0,0 +1,423 @@
+//! Server configuration read from the environment
+//!
+//! [`ServerConfig::from_env`] is the one place that reads the server's
+//! environment variables. Everything except credentials has a default, and
+//! malformed values are reported by variable name instead of being ignored.
+//!
+//! | Variable | Default |
+//! |----------|---------|
+//! | `X_BEARER_TOKEN` | none; needed unless OAuth 1.0a or replay is set |
+//! | `X_CONSUMER_KEY`, `X_CONSUMER_SECRET`, `X_ACCESS_TOKEN`, `X_ACCESS_TOKEN_SECRET` | none |
+//! | `X_OAUTH_SIGNATURE_METHOD` | `HMAC-SHA1` |
+//! | `X_API_BASE_URL` | `https://api.twitter.com/2` |
+//! | `X_API_V1_BASE_URL` | `https://api.twitter.com/1.1` |
+//! | `X_MCP_TIMEOUT_SECS` | `30` |
+//! | `X_MCP_MAX_RETRIES` | `5` |
+//! | `X_MCP_RETRY_BACKOFF_MS` | `2000` |
+//! | `X_MCP_RETRY_MAX_WAIT_SECS` | `60` |
+//! | `X_MCP_MAX_CONCURRENCY` | `8` |
+//! | `X_MCP_READ_ONLY`, or its alias `X_READ_ONLY` | `false` |
+//! | `X_MCP_RATE_LIMIT_FALLBACK` | `false` |
+//! | `X_MCP_REDACT_CONTENT` | `false` |
+//! | `X_MCP_TIMEZONE` | `UTC` |
+//! | `X_MCP_COMPACT_OUTPUT` | `false` |
+//! | `X_MCP_CALL_BUDGET` | none; tool calls are not queued |
+//! | `X_MCP_CALL_WINDOW_SECS` | `900` |
+//! | `X_MCP_IDEMPOTENCY_FILE` | none; keys are kept in memory |
+//! | `X_MCP_IDEMPOTENCY_TTL_SECS` | `86400` |
+//! | `X_MCP_MEDIA_DIR` | none; `media_paths` are refused |
+//! | `X_MCP_RECORD_DIR`, `X_MCP_REPLAY_DIR` | none |
+//! | `X_MCP_HTTP_ADDR` | none; serve over stdio (needs the `http` feature) |
+//! | `RUST_LOG` | `x_mcp_server=info` |
+
+use crate::auth::OAuthCredentials;
+use crate::client::DEFAULT_MAX_CONCURRENCY;
+use crate::error::{XError, XResult};
+use crate::replay::ReplayMode;
+use crate::scheduler::{SchedulerConfig, DEFAULT_WINDOW};
+use crate::time::parse_utc_offset;
+use chrono::FixedOffset;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default X API v2 base URL
+pub const DEFAULT_BASE_URL: &str = "https://api.twitter.com/2";
+
+/// Default X API v1.1 base URL
+pub const DEFAULT_V1_BASE_URL: &str = "https://api.twitter.com/1.1";
+
+/// Default time limit for each X API request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default log filter when `RUST_LOG` is unset
+pub const DEFAULT_LOG_FILTER: &str = "x_mcp_server=info";
+
+/// How rate-limited requests are retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled on each further attempt, when X
+    /// does not say when the rate limit resets, or says it already has
+    pub initial_backoff: Duration,
+    /// Upper bound for the doubling backoff
+    pub max_backoff: Duration,
+    /// Longest wait for a rate limit to reset; one resetting later than
+    /// this is not waited out
+    pub max_wait: Duration,
+}
+
+/// The retry settings by the name [`XClient::with_retry_config`](crate::XClient::with_retry_config) uses
+pub type RetryConfig = RetryPolicy;
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(60),
+            max_wait: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Everything the server reads from its environment
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// App-only or OAuth 2.0 user token
+    pub bearer_token: Option<String>,
+    /// OAuth 1.0a user credentials, which take precedence over the bearer token
+    pub oauth: Option<OAuthCredentials>,
+    /// Base URL of the v2 API, e.g. a proxy or mock server
+    pub base_url: String,
+    /// Base URL of the v1.1 API
+    pub v1_base_url: String,
+    /// Time limit for each HTTP request
+    pub timeout: Duration,
+    pub retry: RetryPolicy,
+    /// Requests allowed in flight at once
+    pub max_concurrency: usize,
+    /// Refuse tools that post or change anything on X
+    pub read_only: bool,
+    /// Fall back to the batch lookup when `get_tweet` is rate limited
+    pub rate_limit_fallback: bool,
+    /// Keep tweet content out of log output
+    pub redact_content: bool,
+    /// Default timezone for readable tweet times in tool output
+    pub timezone: FixedOffset,
+    /// Emit tool results as compact rather than pretty-printed JSON text
+    pub compact_output: bool,
+    /// Session-wide budget of tool calls, queued by priority when used up
+    pub scheduler: Option<SchedulerConfig>,
+    /// File persisting the idempotency keys of posted tweets
+    pub idempotency_file: Option<PathBuf>,
+    /// How long an idempotency key is remembered
+    pub idempotency_ttl: Duration,
+    /// Directory `post_tweet` may upload `media_paths` from; without one
+    /// they are refused
+    pub media_dir: Option<PathBuf>,
+    pub replay: Option<ReplayMode>,
+    /// Address to serve MCP over HTTP on instead of stdio
+    pub http_addr: Option<SocketAddr>,
+    /// `tracing` filter directives for log output
+    pub log_filter: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bearer_token: None,
+            oauth: None,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            v1_base_url: DEFAULT_V1_BASE_URL.to_string(),
+            timeout: DEFAULT_TIMEOUT,
+            retry: RetryPolicy::default(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            read_only: false,
+            rate_limit_fallback: false,
+            redact_content: false,
+            timezone: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            compact_output: false,
+            scheduler: None,
+            idempotency_file: None,
+            idempotency_ttl: Duration::from_secs(24 * 60 * 60),
+            media_dir: None,
+            replay: None,
+            http_addr: None,
+            log_filter: DEFAULT_LOG_FILTER.to_string(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Read and validate the configuration from environment variables
+    pub fn from_env() -> XResult<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// [`from_env`](Self::from_env) reading variables through `lookup`
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> XResult<Self> {
+        let lookup = |name: &str| lookup(name).filter(|value| !value.trim().is_empty());
+        let defaults = Self::default();
+
+        let config = Self {
+            bearer_token: lookup("X_BEARER_TOKEN"),
+            oauth: OAuthCredentials::from_lookup(lookup)?,
+            base_url: url_var(&lookup, "X_API_BASE_URL")?.unwrap_or(defaults.base_url),
+            v1_base_url: url_var(&lookup, "X_API_V1_BASE_URL")?.unwrap_or(defaults.v1_base_url),
+            timeout: number_var(&lookup, "X_MCP_TIMEOUT_SECS")?
+                .map_or(defaults.timeout, Duration::from_secs),
+            retry: RetryPolicy {
+                max_retries: number_var(&lookup, "X_MCP_MAX_RETRIES")?
+                    .unwrap_or(defaults.retry.max_retries),
+                initial_backoff: number_var(&lookup, "X_MCP_RETRY_BACKOFF_MS")?
+                    .map_or(defaults.retry.initial_backoff, Duration::from_millis),
+                max_backoff: defaults.retry.max_backoff,
+                max_wait: number_var(&lookup, "X_MCP_RETRY_MAX_WAIT_SECS")?
+                    .map_or(defaults.retry.max_wait, Duration::from_secs),
+            },
+            max_concurrency: number_var(&lookup, "X_MCP_MAX_CONCURRENCY")?
+                .unwrap_or(defaults.max_concurrency),
+            // Either name turns read-only mode on, whatever the other says
+            read_only: bool_var(&lookup, "X_MCP_READ_ONLY")?.unwrap_or(defaults.read_only)
+                || bool_var(&lookup, "X_READ_ONLY")?.unwrap_or(defaults.read_only),
+            rate_limit_fallback: bool_var(&lookup, "X_MCP_RATE_LIMIT_FALLBACK")?
+                .unwrap_or(defaults.rate_limit_fallback),
+            redact_content: bool_var(&lookup, "X_MCP_REDACT_CONTENT")?
+                .unwrap_or(defaults.redact_content),
+            timezone: match lookup("X_MCP_TIMEZONE") {
+                Some(value) => parse_utc_offset(&value)
+                    .map_err(|e| XError::Config(format!("X_MCP_TIMEZONE: {}", e)))?,
+                None => defaults.timezone,
+            },
+            compact_output: bool_var(&lookup, "X_MCP_COMPACT_OUTPUT")?
+                .unwrap_or(defaults.compact_output),
+            scheduler: match number_var(&lookup, "X_MCP_CALL_BUDGET")? {
+                Some(budget) => Some(SchedulerConfig {
+                    budget,
+                    window: number_var(&lookup, "X_MCP_CALL_WINDOW_SECS")?
+                        .map_or(DEFAULT_WINDOW, Duration::from_secs),
+                }),
+                None => None,
+            },
+            idempotency_file: lookup("X_MCP_IDEMPOTENCY_FILE").map(PathBuf::from),
+            idempotency_ttl: number_var(&lookup, "X_MCP_IDEMPOTENCY_TTL_SECS")?
+                .map_or(defaults.idempotency_ttl, Duration::from_secs),
+            media_dir: lookup("X_MCP_MEDIA_DIR").map(PathBuf::from),
+            replay: ReplayMode::from_lookup(lookup)?,
+            http_addr: lookup("X_MCP_HTTP_ADDR")
+                .map(|value| {
+                    value.trim().parse().map_err(|_| {
+                        XError::Config(format!(
+                            "X_MCP_HTTP_ADDR must be an address like 127.0.0.1:8080, got {:?}",
+                            value
+                        ))
+                    })
+                })
+                .transpose()?,
+            log_filter: lookup("RUST_LOG").unwrap_or(defaults.log_filter),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check that the values make sense together
+    pub fn validate(&self) -> XResult<()> {
+        if self.timeout.is_zero() {
+            return Err(XError::Config(
+                "X_MCP_TIMEOUT_SECS must be greater than 0".to_string(),
+            ));
+        }
+        if self.retry.initial_backoff.is_zero() {
+            return Err(XError::Config(
+                "X_MCP_RETRY_BACKOFF_MS must be greater than 0".to_string(),
+            ));
+        }
+        if self.max_concurrency == 0 {
+            return Err(XError::Config(
+                "X_MCP_MAX_CONCURRENCY must be greater than 0".to_string(),
+            ));
+        }
+        if let Some(scheduler) = &self.scheduler {
+            if scheduler.budget == 0 || scheduler.window.is_zero() {
+                return Err(XError::Config(
+                    "X_MCP_CALL_BUDGET and X_MCP_CALL_WINDOW_SECS must be greater than 0"
+                        .to_string(),
+                ));
+            }
+        }
+        if let Some(dir) = &self.media_dir {
+            if !dir.is_dir() {
+                return Err(XError::Config(format!(
+                    "X_MCP_MEDIA_DIR {} is not a directory",
+                    dir.display()
+                )));
+            }
+        }
+        if cfg!(not(feature = "http")) && self.http_addr.is_some() {
+            return Err(XError::Config(
+                "X_MCP_HTTP_ADDR needs a server built with the http feature".to_string(),
+            ));
+        }
+        if let Err(e) = tracing_subscriber::EnvFilter::try_new(&self.log_filter) {
+            return Err(XError::Config(format!(
+                "Invalid RUST_LOG {:?}: {}",
+                self.log_filter, e
+            )));
+        }
+
+        let replaying = matches!(self.replay, Some(ReplayMode::Replay(_)));
+        if self.bearer_token.is_none() && self.oauth.is_none() && !replaying {
+            return Err(XError::Config(
+                "X_BEARER_TOKEN not found (or set the OAuth 1.0a variables X_CONSUMER_KEY, \
+                 X_CONSUMER_SECRET, X_ACCESS_TOKEN and X_ACCESS_TOKEN_SECRET)"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn number_var<T: FromStr>(
+    lookup: &impl Fn(&str) -> Option<String>,
+    name: &str,
+) -> XResult<Option<T>> {
+    lookup(name)
+        .map(|value| {
+            value.trim().parse().map_err(|_| {
+                XError::Config(format!(
+                    "{} must be a non-negative integer, got {:?}",
+                    name, value
+                ))
+            })
+        })
+        .transpose()
+}
+
+fn bool_var(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> XResult<Option<bool>> {
+    lookup(name)
+        .map(|value| match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => Ok(true),
+            "0" | "false" | "no" | "off" => Ok(false),
+            _ => Err(XError::Config(format!(
+                "{} must be true or false, got {:?}",
+                name, value
+            ))),
+        })
+        .transpose()
+}
+
+fn url_var(lookup: &impl Fn(&str) -> Option<String>, name: &str) -> XResult<Option<String>> {
+    lookup(name)
+        .map(|value| {
+            let value = value.trim().trim_end_matches('/');
+            if value.starts_with("https://") || value.starts_with("http://") {
+                Ok(value.to_string())
+            } else {
+                Err(XError::Config(format!(
+                    "{} must be an http(s) URL, got {:?}",
+                    name, value
+                )))
+            }
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn config(vars: &[(&str, &str)]) -> XResult<ServerConfig> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        ServerConfig::from_lookup(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = config(&[("X_BEARER_TOKEN", "token")]).unwrap();
+        assert_eq!(config.bearer_token.as_deref(), Some("token"));
+        assert_eq!(config.base_url, DEFAULT_BASE_URL);
+        assert_eq!(config.timeout, Duration::from_secs(30));
+        assert_eq!(config.retry, RetryPolicy::default());
+        assert!(!config.read_only);
+        assert!(config.replay.is_none());
+    }
+
+    #[test]
+    fn test_overrides() {
+        let config = config(&[
+            ("X_BEARER_TOKEN", "token"),
+            ("X_API_BASE_URL", "http://localhost:8080/2/"),
+            ("X_MCP_TIMEOUT_SECS", "5"),
+            ("X_MCP_MAX_RETRIES", "0"),
+            ("X_MCP_RETRY_MAX_WAIT_SECS", "900"),
+            ("X_MCP_READ_ONLY", "yes"),
+            ("X_MCP_RATE_LIMIT_FALLBACK", "TRUE"),
+            ("X_MCP_TIMEZONE", "+02:00"),
+            ("X_MCP_COMPACT_OUTPUT", "1"),
+            ("X_MCP_CALL_BUDGET", "300"),
+            ("X_MCP_MEDIA_DIR", "tests"),
+        ])
+        .unwrap();
+        assert_eq!(config.base_url, "http://localhost:8080/2");
+        assert_eq!(config.timeout, Duration::from_secs(5));
+        assert_eq!(config.retry.max_retries, 0);
+        assert_eq!(config.retry.max_wait, Duration::from_secs(900));
+        assert_eq!(config.retry.max_backoff, RetryPolicy::default().max_backoff);
+        assert!(config.read_only);
+        assert!(config.rate_limit_fallback);
+        assert_eq!(config.timezone.local_minus_utc(), 7200);
+        assert!(config.compact_output);
+        assert_eq!(config.media_dir, Some(PathBuf::from("tests")));
+        assert_eq!(
+            config.scheduler,
+            Some(SchedulerConfig {
+                budget: 300,
+                window: DEFAULT_WINDOW
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_only_alias() {
+        let read_only = |vars: &[(&str, &str)]| {
+            let mut vars = vars.to_vec();
+            vars.push(("X_BEARER_TOKEN", "token"));
+            config(&vars).unwrap().read_only
+        };
+        assert!(read_only(&[("X_READ_ONLY", "1")]));
+        assert!(read_only(&[("X_MCP_READ_ONLY", "0"), ("X_READ_ONLY", "1")]));
+        assert!(read_only(&[("X_MCP_READ_ONLY", "1"), ("X_READ_ONLY", "0")]));
+        assert!(!read_only(&[("X_READ_ONLY", "false")]));
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let error = |vars: &[(&str, &str)]| {
+            let mut vars = vars.to_vec();
+            vars.push(("X_BEARER_TOKEN", "token"));
+            config(&vars).unwrap_err().to_string()
+        };
+        assert!(error(&[("X_MCP_TIMEOUT_SECS", "0")]).contains("greater than 0"));
+        assert!(error(&[("X_MCP_TIMEOUT_SECS", "soon")]).contains("X_MCP_TIMEOUT_SECS"));
+        assert!(error(&[("X_MCP_READ_ONLY", "maybe")]).contains("true or false"));
+        assert!(error(&[("X_READ_ONLY", "maybe")]).contains("X_READ_ONLY"));
+        assert!(error(&[("X_API_BASE_URL", "api.x.com")]).contains("http(s) URL"));
+        assert!(error(&[("X_MCP_TIMEZONE", "Mars/Olympus")]).contains("X_MCP_TIMEZONE"));
+        assert!(error(&[("X_MCP_CALL_BUDGET", "0")]).contains("X_MCP_CALL_BUDGET"));
+        assert!(error(&[("X_MCP_HTTP_ADDR", "localhost")]).contains("X_MCP_HTTP_ADDR"));
+        assert!(error(&[("X_MCP_MEDIA_DIR", "Cargo.toml")]).contains("not a directory"));
+
+        assert!(config(&[])
+            .unwrap_err()
+            .to_string()
+            .contains("X_BEARER_TOKEN"));
+        assert!(config(&[("X_MCP_REPLAY_DIR", "fixtures")]).is_ok());
+    }
+}
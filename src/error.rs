@@ -1,5 +1,6 @@
 //! Error types for X MCP Server
 
+use chrono::{DateTime, SecondsFormat};
 use thiserror::Error;
 
 /// Result type alias for X operations
@@ -32,10 +33,40 @@ pub enum XError {
     #[error("Authentication error: {0}")]
     Auth(String),
 
+    /// The user declined (or X refused) an OAuth 2.0 authorization request
+    #[error("Authorization denied: {error}{}", .description.as_deref().map(|d| format!(" ({})", d)).unwrap_or_default())]
+    AuthDenied {
+        error: String,
+        description: Option<String>,
+    },
+
     /// API errors from X
     #[error("X API error: {status} - {message}")]
     Api { status: u16, message: String },
 
+    /// X answered 429, with its `x-rate-limit-*` headers when it sent them
+    #[error("X API rate limit exceeded{}", reset_suffix(*.reset))]
+    RateLimited {
+        /// Requests allowed per window
+        limit: Option<u32>,
+        /// Requests left in the current window
+        remaining: Option<u32>,
+        /// When the window resets, in Unix seconds
+        reset: Option<i64>,
+    },
+
+    /// The app's X API access tier does not include the endpoint
+    #[error("This operation requires X API {required_tier} access; your current tier can't use {endpoint}")]
+    TierRestricted {
+        /// Method and path, e.g. `GET /2/users/:id/tweets`
+        endpoint: String,
+        required_tier: &'static str,
+    },
+
+    /// Requests paused by the circuit breaker after repeated X failures
+    #[error("X API is failing, requests paused for {retry_after_secs}s (circuit breaker open)")]
+    CircuitOpen { retry_after_secs: u64 },
+
     /// Configuration errors
     #[error("Configuration error: {0}")]
     Config(String),
@@ -45,17 +76,21 @@ pub enum XError {
     Generic(String),
 }
 
+fn reset_suffix(reset: Option<i64>) -> String {
+    reset
+        .and_then(|reset| DateTime::from_timestamp(reset, 0))
+        .map(|reset| format!(", resets at {}", reset.to_rfc3339_opts(SecondsFormat::Secs, true)))
+        .unwrap_or_default()
+}
+
 impl From<anyhow::Error> for XError {
     fn from(err: anyhow::Error) -> Self {
         XError::Generic(err.to_string())
     }
 }
 
-impl<T> From<rmcp::service::ServerInitializeError<T>> for XError
-where
-    T: std::fmt::Display,
-{
-    fn from(err: rmcp::service::ServerInitializeError<T>) -> Self {
+impl From<rmcp::service::ServerInitializeError> for XError {
+    fn from(err: rmcp::service::ServerInitializeError) -> Self {
         XError::ServerInit(err.to_string())
     }
 }
@@ -0,0 +1,301 @@
+//! Idempotency keys for tweet creation
+//!
+//! X's create-tweet endpoint has no idempotency support, so a caller that
+//! retries after a timeout may post the same tweet twice. A post made with an
+//! idempotency key is recorded here once X accepts it; posting again with the
+//! same key returns the recorded tweet instead of calling X.
+//!
+//! Posts are serialized per key, so concurrent calls with the same key cannot
+//! both reach X while calls with other keys go ahead.
+//!
+//! With a file configured (`X_MCP_IDEMPOTENCY_FILE`) the log survives
+//! restarts. The file is JSON Lines, one [`IdempotencyRecord`] per posted
+//! key, appended as posts succeed. Records older than the TTL
+//! (`X_MCP_IDEMPOTENCY_TTL_SECS`, 24 hours by default) are ignored, and
+//! dropped from the file when it is next loaded. Without a file, keys are
+//! remembered for the life of the process.
+
+use crate::error::{XError, XResult};
+use crate::types::{PostTweetRequest, Tweet};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::OwnedMutexGuard;
+
+/// Default time a key is remembered
+pub const DEFAULT_IDEMPOTENCY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// One posted key, as stored on a line of the log file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    pub key: String,
+    /// Unix time in seconds when the tweet was posted
+    pub posted_at: u64,
+    /// SHA-256 of the create-tweet request body as composed before any media
+    /// upload, hex encoded
+    pub request_hash: String,
+    pub tweet: Tweet,
+}
+
+/// Keys of tweets already posted, optionally persisted to a file
+#[derive(Debug)]
+pub struct IdempotencyLog {
+    path: Option<PathBuf>,
+    ttl: Duration,
+    records: Mutex<HashMap<String, IdempotencyRecord>>,
+    /// Locks of the keys with a post in progress or waiting
+    in_progress: Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl Default for IdempotencyLog {
+    fn default() -> Self {
+        Self::in_memory(DEFAULT_IDEMPOTENCY_TTL)
+    }
+}
+
+impl IdempotencyLog {
+    /// Log kept only in memory
+    pub fn in_memory(ttl: Duration) -> Self {
+        Self {
+            path: None,
+            ttl,
+            records: Mutex::default(),
+            in_progress: Mutex::default(),
+        }
+    }
+
+    /// Log persisted to `path`, loading the records already there
+    ///
+    /// Malformed lines are skipped with a warning. Expired records are
+    /// dropped and the file rewritten without them.
+    pub fn open(path: impl Into<PathBuf>, ttl: Duration) -> XResult<Self> {
+        let path = path.into();
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(XError::Config(format!(
+                    "Cannot read idempotency log {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        };
+
+        let now = unix_now();
+        let mut records = HashMap::new();
+        let mut dropped = 0;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<IdempotencyRecord>(line) {
+                Ok(record) if !is_expired(&record, ttl, now) => {
+                    records.insert(record.key.clone(), record);
+                }
+                Ok(_) => dropped += 1,
+                Err(e) => {
+                    tracing::warn!("Skipping malformed idempotency log line: {}", e);
+                    dropped += 1;
+                }
+            }
+        }
+
+        if dropped > 0 {
+            let mut contents = String::new();
+            for record in records.values() {
+                let _ = writeln!(contents, "{}", serde_json::to_string(record)?);
+            }
+            std::fs::write(&path, contents)?;
+        }
+
+        Ok(Self {
+            path: Some(path),
+            ttl,
+            records: Mutex::new(records),
+            in_progress: Mutex::default(),
+        })
+    }
+
+    /// Time a key is remembered
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Lock `key` while a post with it is in progress, so concurrent calls
+    /// with the same key cannot both reach X
+    ///
+    /// Other keys are not held up.
+    pub async fn lock(&self, key: &str) -> IdempotencyGuard<'_> {
+        let lock = self
+            .in_progress
+            .lock()
+            .expect("idempotency lock map poisoned")
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+        IdempotencyGuard {
+            log: self,
+            key: key.to_string(),
+            _lock: lock.lock_owned().await,
+        }
+    }
+}
+
+/// Exclusive use of one key of an [`IdempotencyLog`]
+pub struct IdempotencyGuard<'a> {
+    log: &'a IdempotencyLog,
+    key: String,
+    _lock: OwnedMutexGuard<()>,
+}
+
+impl IdempotencyGuard<'_> {
+    /// The tweet already posted with the key, if it has not expired
+    ///
+    /// Fails when the key was used for a different request.
+    pub fn get(&self, request: &PostTweetRequest) -> XResult<Option<Tweet>> {
+        let records = self.log.records.lock().expect("idempotency log poisoned");
+        let Some(record) = records.get(&self.key) else {
+            return Ok(None);
+        };
+        if is_expired(record, self.log.ttl, unix_now()) {
+            return Ok(None);
+        }
+        if record.request_hash != request_hash(request)? {
+            return Err(XError::Generic(format!(
+                "Idempotency key {:?} was already used for a different tweet",
+                self.key
+            )));
+        }
+        Ok(Some(record.tweet.clone()))
+    }
+
+    /// Remember that `tweet` was posted for `request` under the key
+    ///
+    /// The record is kept in memory even when writing the file fails.
+    pub fn record(&mut self, request: &PostTweetRequest, tweet: &Tweet) -> XResult<()> {
+        let record = IdempotencyRecord {
+            key: self.key.clone(),
+            posted_at: unix_now(),
+            request_hash: request_hash(request)?,
+            tweet: tweet.clone(),
+        };
+        let line = serde_json::to_string(&record)?;
+        self.log
+            .records
+            .lock()
+            .expect("idempotency log poisoned")
+            .insert(record.key.clone(), record);
+
+        if let Some(path) = &self.log.path {
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IdempotencyGuard<'_> {
+    fn drop(&mut self) {
+        // Forget the key's lock unless another call waits for it: the map
+        // and this guard are then its only holders, and a new caller would
+        // have to clone it from the map, which is locked here
+        let mut in_progress = self.log.in_progress.lock().expect("idempotency lock map poisoned");
+        if in_progress.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 2) {
+            in_progress.remove(&self.key);
+        }
+    }
+}
+
+fn is_expired(record: &IdempotencyRecord, ttl: Duration, now: u64) -> bool {
+    now.saturating_sub(record.posted_at) >= ttl.as_secs()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+fn request_hash(request: &PostTweetRequest) -> XResult<String> {
+    let body = serde_json::to_vec(request)?;
+    let mut hash = String::with_capacity(64);
+    for byte in Sha256::digest(&body) {
+        let _ = write!(hash, "{:02x}", byte);
+    }
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(text: &str) -> PostTweetRequest {
+        PostTweetRequest {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn tweet(id: &str) -> Tweet {
+        serde_json::from_value(serde_json::json!({ "id": id, "text": "hello" })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_log_persists_and_expires() {
+        let path =
+            std::env::temp_dir().join(format!("x-mcp-idempotency-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let log = IdempotencyLog::open(&path, DEFAULT_IDEMPOTENCY_TTL).unwrap();
+        let mut guard = log.lock("a").await;
+        assert!(guard.get(&request("hello")).unwrap().is_none());
+        guard.record(&request("hello"), &tweet("1")).unwrap();
+        drop(guard);
+
+        let reopened = IdempotencyLog::open(&path, DEFAULT_IDEMPOTENCY_TTL).unwrap();
+        let guard = reopened.lock("a").await;
+        assert_eq!(guard.get(&request("hello")).unwrap().unwrap().id, "1");
+        assert!(guard.get(&request("different")).is_err());
+        drop(guard);
+
+        // Expired records are dropped from the file
+        let expired = IdempotencyLog::open(&path, Duration::ZERO).unwrap();
+        assert!(expired
+            .lock("a")
+            .await
+            .get(&request("hello"))
+            .unwrap()
+            .is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_per_key() {
+        let log = IdempotencyLog::default();
+        let guard = log.lock("a").await;
+
+        // Another key is not held up
+        let other = tokio::time::timeout(Duration::from_secs(1), log.lock("b")).await;
+        assert!(other.is_ok());
+        drop(other);
+
+        // The same key waits until the post in progress is done
+        let mut waiting = std::pin::pin!(log.lock("a"));
+        assert!(tokio::time::timeout(Duration::from_millis(50), &mut waiting)
+            .await
+            .is_err());
+        drop(guard);
+        drop(tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap());
+
+        // Keys are forgotten once nothing holds them
+        assert!(log.in_progress.lock().unwrap().is_empty());
+    }
+}
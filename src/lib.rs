@@ -25,12 +25,33 @@
 //! }
 //! ```
 
+pub mod api;
+pub mod auth;
+pub mod capabilities;
+pub mod circuit;
 pub mod client;
+pub mod config;
 pub mod error;
+#[cfg(feature = "write-ops")]
+pub mod idempotency;
+#[cfg(feature = "write-ops")]
+pub mod media;
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod pkce;
+pub mod redact;
+pub mod replay;
+pub mod scheduler;
 pub mod server;
+pub mod stream;
+pub mod time;
+pub mod transport;
 pub mod types;
+pub mod validate;
 
-pub use client::XClient;
+pub use client::{XClient, XClientBuilder};
+pub use config::ServerConfig;
 pub use error::{XError, XResult};
 pub use server::XMcpServer;
 
@@ -2,36 +2,52 @@
 
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use x_mcp_server::{XMcpServer, XResult};
+use x_mcp_server::{redact, ServerConfig, XMcpServer, XResult};
 
 #[tokio::main]
 async fn main() -> XResult<()> {
+    // Load environment variables from .env file if it exists
+    dotenvy::dotenv().ok();
+
+    // Logging is configured from here too, so errors can only go to stderr
+    let config = ServerConfig::from_env().inspect_err(|e| {
+        eprintln!("Invalid configuration: {}", e);
+    })?;
+    redact::set_enabled(config.redact_content);
+
     // Initialize logging
+    let mut filter = tracing_subscriber::EnvFilter::new(&config.log_filter);
+    if redact::is_enabled() {
+        // The MCP SDK logs raw protocol messages, tool arguments included, at debug level
+        filter = filter.add_directive("rmcp=info".parse().expect("valid directive"));
+    }
+    // Logs go to stderr, as stdout carries the JSON-RPC messages over stdio
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "x_mcp_server=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
         .init();
 
-    // Load environment variables from .env file if it exists
-    dotenvy::dotenv().ok();
-
     tracing::info!("Starting X MCP Server v{}", x_mcp_server::VERSION);
 
     // Create the server
-    let server = XMcpServer::from_env().map_err(|e| {
-        tracing::error!("Failed to create server: {}", e);
-        e
+    let server = XMcpServer::from_config(&config).inspect_err(|e| {
+        tracing::error!("Failed to create server: {}", redact::error(e));
     })?;
 
-    tracing::info!("Server started, listening for MCP requests...");
-
     // Run the server
-    server.run_stdio().await.map_err(|e| {
-        tracing::error!("Server error: {}", e);
-        e
+    let result = match config.http_addr {
+        #[cfg(feature = "http")]
+        Some(addr) => {
+            tracing::info!("Server started, listening for MCP requests on http://{}", addr);
+            server.run_http(addr).await
+        }
+        _ => {
+            tracing::info!("Server started, listening for MCP requests...");
+            server.run_stdio().await
+        }
+    };
+    result.inspect_err(|e| {
+        tracing::error!("Server error: {}", redact::error(e));
     })?;
 
     Ok(())
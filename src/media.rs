@@ -0,0 +1,193 @@
+//! Media uploads for attaching to tweets
+//!
+//! Media go through X's v1.1 chunked upload endpoint: `INIT` announces the
+//! size and type, `APPEND` sends the bytes in segments and `FINALIZE`
+//! completes the upload. Videos and GIFs are then processed asynchronously,
+//! so [`XClient::upload_media`] polls `STATUS` until X is done with them.
+//! Uploading needs OAuth 1.0a user credentials; a bearer token is refused.
+
+use crate::client::{RequestBody, XClient};
+use crate::error::{XError, XResult};
+use reqwest::Method;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// X's media upload endpoint
+pub const UPLOAD_URL: &str = "https://upload.twitter.com/1.1/media/upload.json";
+
+/// Bytes sent per `APPEND`, well under X's 5 MB segment limit once base64
+/// encoded
+const SEGMENT_SIZE: usize = 1024 * 1024;
+
+/// Most `STATUS` checks while X processes an upload
+const MAX_STATUS_CHECKS: u32 = 60;
+
+/// Media types X accepts, by file extension
+const MEDIA_TYPES: [(&str, &str); 7] = [
+    ("gif", "image/gif"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("mov", "video/quicktime"),
+    ("mp4", "video/mp4"),
+    ("png", "image/png"),
+    ("webp", "image/webp"),
+];
+
+#[derive(Debug, Deserialize)]
+struct UploadResponse {
+    media_id_string: String,
+    #[serde(default)]
+    processing_info: Option<ProcessingInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProcessingInfo {
+    /// `pending`, `in_progress`, `succeeded` or `failed`
+    state: String,
+    #[serde(default)]
+    check_after_secs: Option<u64>,
+    #[serde(default)]
+    error: Option<ProcessingError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProcessingError {
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Media type of a file, from its extension
+pub fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    MEDIA_TYPES
+        .iter()
+        .find(|(known, _)| *known == extension)
+        .map(|(_, media_type)| *media_type)
+}
+
+/// Upload category X processes a media type under
+fn media_category(media_type: &str) -> &'static str {
+    if media_type == "image/gif" {
+        "tweet_gif"
+    } else if media_type.starts_with("video/") {
+        "tweet_video"
+    } else {
+        "tweet_image"
+    }
+}
+
+impl XClient {
+    /// Upload media of the given type and return the media ID to attach to a
+    /// tweet
+    pub async fn upload_media(&self, bytes: &[u8], media_type: &str) -> XResult<String> {
+        use base64::{engine::general_purpose::STANDARD, Engine as _};
+
+        if bytes.is_empty() {
+            return Err(XError::Generic("Cannot upload empty media".to_string()));
+        }
+
+        let init: UploadResponse = self
+            .upload_command(vec![
+                ("command", "INIT".to_string()),
+                ("total_bytes", bytes.len().to_string()),
+                ("media_type", media_type.to_string()),
+                ("media_category", media_category(media_type).to_string()),
+            ])
+            .await?;
+        let media_id = init.media_id_string;
+
+        for (index, segment) in bytes.chunks(SEGMENT_SIZE).enumerate() {
+            let _: IgnoredAny = self
+                .upload_command(vec![
+                    ("command", "APPEND".to_string()),
+                    ("media_id", media_id.clone()),
+                    ("segment_index", index.to_string()),
+                    ("media_data", STANDARD.encode(segment)),
+                ])
+                .await?;
+        }
+
+        let mut upload: UploadResponse = self
+            .upload_command(vec![
+                ("command", "FINALIZE".to_string()),
+                ("media_id", media_id.clone()),
+            ])
+            .await?;
+
+        let mut checks = 0;
+        while let Some(info) = upload.processing_info {
+            match info.state.as_str() {
+                "succeeded" => break,
+                "failed" => {
+                    let reason = info
+                        .error
+                        .and_then(|error| error.message)
+                        .unwrap_or_else(|| "no reason given".to_string());
+                    return Err(XError::Generic(format!(
+                        "X could not process media {}: {}",
+                        media_id, reason
+                    )));
+                }
+                _ if checks == MAX_STATUS_CHECKS => {
+                    return Err(XError::Generic(format!(
+                        "X is still processing media {} after {} checks",
+                        media_id, MAX_STATUS_CHECKS
+                    )));
+                }
+                _ => {}
+            }
+            checks += 1;
+            let wait = info.check_after_secs.unwrap_or(1).max(1);
+            tracing::debug!(
+                "Media {} is {}, checking again in {}s",
+                media_id,
+                info.state,
+                wait
+            );
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+
+            let query = [
+                ("command", "STATUS".to_string()),
+                ("media_id", media_id.clone()),
+            ];
+            upload = self
+                .make_request(Method::GET, UPLOAD_URL, &query, None)
+                .await?;
+        }
+
+        Ok(media_id)
+    }
+
+    /// Send one command of the chunked upload as a form
+    async fn upload_command<T: serde::de::DeserializeOwned>(
+        &self,
+        fields: Vec<(&'static str, String)>,
+    ) -> XResult<T> {
+        self.make_request(
+            Method::POST,
+            UPLOAD_URL,
+            &[],
+            Some(RequestBody::Form(fields)),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_media_type() {
+        assert_eq!(media_type(Path::new("cat.PNG")), Some("image/png"));
+        assert_eq!(media_type(Path::new("/tmp/clip.mp4")), Some("video/mp4"));
+        assert_eq!(media_type(Path::new("notes.txt")), None);
+        assert_eq!(media_type(Path::new("README")), None);
+
+        assert_eq!(media_category("image/gif"), "tweet_gif");
+        assert_eq!(media_category("video/quicktime"), "tweet_video");
+        assert_eq!(media_category("image/webp"), "tweet_image");
+    }
+}
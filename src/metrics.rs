@@ -0,0 +1,252 @@
+//! Call counts and latencies for tools and X API endpoints
+//!
+//! Counters are plain atomics; [`Metrics::render`] exports them in the
+//! Prometheus text format. API endpoints are labelled by method and path
+//! with IDs and usernames replaced by placeholders, so the number of series
+//! stays bounded.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Shared metrics registry; clones record into the same counters
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    tools: Arc<Mutex<BTreeMap<String, Arc<Series>>>>,
+    endpoints: Arc<Mutex<BTreeMap<String, Arc<Series>>>>,
+}
+
+/// Counters for one tool or endpoint
+#[derive(Debug, Default)]
+struct Series {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    /// Calls per bucket, not cumulative; the last entry is +Inf
+    buckets: [AtomicU64; BUCKETS.len() + 1],
+    sum_micros: AtomicU64,
+}
+
+impl Series {
+    fn record(&self, elapsed: Duration, failed: bool) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        let seconds = elapsed.as_secs_f64();
+        let bucket = BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(BUCKETS.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Metrics {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record one MCP tool call
+    pub fn record_tool_call(&self, tool: &str, elapsed: Duration, failed: bool) {
+        series(&self.tools, tool).record(elapsed, failed);
+    }
+
+    /// Record one request to the X API
+    ///
+    /// `failed` covers both transport errors and non-2xx responses.
+    pub fn record_api_request(&self, method: &str, url: &str, elapsed: Duration, failed: bool) {
+        let endpoint = format!("{} {}", method, endpoint_path(url));
+        series(&self.endpoints, &endpoint).record(elapsed, failed);
+    }
+
+    /// Export every series in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        render_family(
+            &mut out,
+            &self.tools,
+            "x_mcp_tool",
+            "tool",
+            "calls",
+            "MCP tool calls",
+        );
+        render_family(
+            &mut out,
+            &self.endpoints,
+            "x_mcp_api",
+            "endpoint",
+            "requests",
+            "X API requests",
+        );
+        out
+    }
+}
+
+fn series(map: &Mutex<BTreeMap<String, Arc<Series>>>, name: &str) -> Arc<Series> {
+    let mut map = map.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    map.entry(name.to_string()).or_default().clone()
+}
+
+fn render_family(
+    out: &mut String,
+    map: &Mutex<BTreeMap<String, Arc<Series>>>,
+    prefix: &str,
+    label: &str,
+    unit: &str,
+    help: &str,
+) {
+    let map = map.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if map.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(out, "# HELP {}_{}_total {}", prefix, unit, help);
+    let _ = writeln!(out, "# TYPE {}_{}_total counter", prefix, unit);
+    for (name, series) in map.iter() {
+        let _ = writeln!(
+            out,
+            "{}_{}_total{{{}=\"{}\"}} {}",
+            prefix,
+            unit,
+            label,
+            escape(name),
+            series.calls.load(Ordering::Relaxed)
+        );
+    }
+
+    let _ = writeln!(out, "# HELP {}_errors_total Failed {}", prefix, help);
+    let _ = writeln!(out, "# TYPE {}_errors_total counter", prefix);
+    for (name, series) in map.iter() {
+        let _ = writeln!(
+            out,
+            "{}_errors_total{{{}=\"{}\"}} {}",
+            prefix,
+            label,
+            escape(name),
+            series.errors.load(Ordering::Relaxed)
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP {}_duration_seconds Latency of {}",
+        prefix, help
+    );
+    let _ = writeln!(out, "# TYPE {}_duration_seconds histogram", prefix);
+    for (name, series) in map.iter() {
+        let name = escape(name);
+        let mut cumulative = 0;
+        for (index, count) in series.buckets.iter().enumerate() {
+            cumulative += count.load(Ordering::Relaxed);
+            let bound = BUCKETS
+                .get(index)
+                .map_or("+Inf".to_string(), |bound| bound.to_string());
+            let _ = writeln!(
+                out,
+                "{}_duration_seconds_bucket{{{}=\"{}\",le=\"{}\"}} {}",
+                prefix, label, name, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{}_duration_seconds_sum{{{}=\"{}\"}} {}",
+            prefix,
+            label,
+            name,
+            series.sum_micros.load(Ordering::Relaxed) as f64 / 1e6
+        );
+        let _ = writeln!(
+            out,
+            "{}_duration_seconds_count{{{}=\"{}\"}} {}",
+            prefix, label, name, cumulative
+        );
+    }
+}
+
+/// Path of `url` with IDs and usernames replaced, e.g. `/2/tweets/:id`
+pub(crate) fn endpoint_path(url: &str) -> String {
+    let path = url.split_once("://").map_or(url, |(_, rest)| {
+        rest.find('/').map_or("/", |index| &rest[index..])
+    });
+    let path = path.split('?').next().unwrap_or_default();
+
+    // The first segment is the API version ("2", "1.1")
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        let segment = if segments.len() < 2 {
+            segment
+        } else if segments.last() == Some(&"username") {
+            ":username"
+        } else if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+            ":id"
+        } else {
+            segment
+        };
+        segments.push(segment);
+    }
+    segments.join("/")
+}
+
+/// Escape a Prometheus label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_path() {
+        assert_eq!(
+            endpoint_path("https://api.twitter.com/2/tweets/1234567890"),
+            "/2/tweets/:id"
+        );
+        assert_eq!(
+            endpoint_path("https://api.twitter.com/2/users/12/tweets"),
+            "/2/users/:id/tweets"
+        );
+        assert_eq!(
+            endpoint_path("https://api.twitter.com/2/users/by/username/jack"),
+            "/2/users/by/username/:username"
+        );
+        assert_eq!(
+            endpoint_path("https://api.twitter.com/1.1/trends/available.json"),
+            "/1.1/trends/available.json"
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        assert_eq!(metrics.render(), "");
+
+        metrics.record_tool_call("get_user", Duration::from_millis(30), false);
+        metrics.record_tool_call("get_user", Duration::from_millis(700), true);
+        let text = metrics.render();
+
+        assert!(text.contains("# TYPE x_mcp_tool_calls_total counter"));
+        assert!(text.contains("x_mcp_tool_calls_total{tool=\"get_user\"} 2"));
+        assert!(text.contains("x_mcp_tool_errors_total{tool=\"get_user\"} 1"));
+        assert!(
+            text.contains("x_mcp_tool_duration_seconds_bucket{tool=\"get_user\",le=\"0.05\"} 1")
+        );
+        assert!(text.contains("x_mcp_tool_duration_seconds_bucket{tool=\"get_user\",le=\"1\"} 2"));
+        assert!(
+            text.contains("x_mcp_tool_duration_seconds_bucket{tool=\"get_user\",le=\"+Inf\"} 2")
+        );
+        assert!(text.contains("x_mcp_tool_duration_seconds_sum{tool=\"get_user\"} 0.73"));
+        assert!(!text.contains("x_mcp_api"));
+    }
+}
@@ -0,0 +1,408 @@
+//! Offline X API backend for testing code built on this crate
+//!
+//! [`MockXClient`] implements [`XApi`] over users and tweets held in memory,
+//! seeded with the fixtures in this module, so tool wiring can be exercised
+//! without credentials or network access:
+//!
+//! ```
+//! use x_mcp_server::{mock::MockXClient, XMcpServer};
+//!
+//! let server = XMcpServer::from_api(MockXClient::new());
+//! ```
+//!
+//! The mock covers user and tweet lookups, search, user timelines and
+//! posting; see [`MockXClient`] for the exact methods. The rest, such as
+//! followers, likes, lists, bookmarks, trends and the filtered stream, fail
+//! with "not supported by this backend", as with any other [`XApi`]
+//! implementation. Implement [`XApi`] yourself to fake those.
+
+use crate::api::{ApiFuture, XApi};
+#[cfg(feature = "write-ops")]
+use crate::error::XError;
+use crate::types::{
+    Includes, Media, MissingUser, SearchPage, SearchTweetsParams, Tweet, TweetIdRange, TweetLookup,
+    TweetMetrics, User, UserLookup, UserMetrics,
+};
+#[cfg(feature = "write-ops")]
+use crate::types::PostTweetRequest;
+#[cfg(feature = "write-ops")]
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// ID of the [`user`] fixture
+pub const USER_ID: &str = "2244994945";
+
+/// ID of the [`tweet`] fixture
+pub const TWEET_ID: &str = "1445880548472328192";
+
+/// The `XDevelopers` account
+pub fn user() -> User {
+    User {
+        id: USER_ID.to_string(),
+        name: "Developers".to_string(),
+        username: "XDevelopers".to_string(),
+        description: Some("The voice of the X Dev team and your official source for updates.".to_string()),
+        public_metrics: Some(UserMetrics {
+            followers_count: 570_842,
+            following_count: 2_048,
+            tweet_count: 14_052,
+            listed_count: 1_672,
+        }),
+        profile_image_url: None,
+        verified: Some(true),
+        created_at: Some("2013-12-14T04:35:55.000Z".to_string()),
+        pinned_tweet_id: None,
+    }
+}
+
+/// A tweet by the [`user`] fixture
+pub fn tweet() -> Tweet {
+    fixture_tweet(
+        TWEET_ID,
+        "Introducing a new era for the X Developer Platform! 📣 The X API v2 is here",
+        "2021-10-07T00:00:00.000Z",
+    )
+}
+
+/// The result of searching for "rust": two tweets by the [`user`] fixture,
+/// newest first
+pub fn search_page() -> SearchPage {
+    SearchPage {
+        tweets: vec![
+            fixture_tweet(
+                "1445880548472328194",
+                "Which Rust crates do you use with the X API? Tell us below",
+                "2021-10-07T02:00:00.000Z",
+            ),
+            fixture_tweet(
+                "1445880548472328193",
+                "Rust client libraries for the X API, maintained by the community 🦀",
+                "2021-10-07T01:00:00.000Z",
+            ),
+        ],
+        next_token: None,
+    }
+}
+
+fn fixture_tweet(id: &str, text: &str, created_at: &str) -> Tweet {
+    let mut tweet = new_tweet(id, text, USER_ID);
+    tweet.created_at = Some(created_at.to_string());
+    tweet.public_metrics = Some(TweetMetrics {
+        retweet_count: 12,
+        like_count: 104,
+        reply_count: 7,
+        quote_count: 3,
+    });
+    tweet
+}
+
+fn new_tweet(id: &str, text: &str, author_id: &str) -> Tweet {
+    Tweet {
+        id: id.to_string(),
+        text: text.to_string(),
+        author_id: Some(author_id.to_string()),
+        created_at: None,
+        public_metrics: None,
+        context_annotations: None,
+        referenced_tweets: None,
+        attachments: None,
+        entities: None,
+        in_reply_to_user_id: None,
+        conversation_id: Some(id.to_string()),
+        reply_settings: Some("everyone".to_string()),
+        possibly_sensitive: Some(false),
+        edit_history_tweet_ids: Some(vec![id.to_string()]),
+        edit_controls: None,
+        created_at_relative: None,
+        created_at_local: None,
+    }
+}
+
+/// In-memory [`XApi`] backend serving the fixtures in this module
+///
+/// Only these methods are answered, from the stored users and tweets:
+///
+/// - users: `get_user_by_username`, `get_user_by_id`,
+///   `get_users_by_usernames`, `search_users`
+/// - tweets: `get_tweet`, `get_tweet_with_media`, `get_tweets`,
+///   `get_pinned_tweet`, `search_tweets_with_includes` (tweets containing
+///   every word of the query)
+/// - timelines: `get_user_tweets`, `get_user_tweets_with_references`,
+///   `get_user_original_tweets`, `get_user_media_tweets`; the since/until
+///   IDs are ignored and no media objects are returned
+/// - with `write-ops`: `post_tweet` and `delete_tweet`. Posted tweets are
+///   stored, authored by the [`user`] fixture, and can be inspected with
+///   [`posted_tweets`](Self::posted_tweets).
+///
+/// Every other [`XApi`] method fails with "not supported by this backend".
+///
+/// Clones share the stored tweets, so a test can keep a clone to inspect
+/// what a server it handed the mock to has done.
+#[derive(Debug, Clone)]
+pub struct MockXClient {
+    users: Vec<User>,
+    /// Oldest first
+    tweets: Arc<Mutex<Vec<Tweet>>>,
+    #[cfg(feature = "write-ops")]
+    posted: Arc<Mutex<Vec<PostTweetRequest>>>,
+    #[cfg(feature = "write-ops")]
+    next_id: Arc<AtomicU64>,
+}
+
+impl Default for MockXClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockXClient {
+    /// A mock holding the [`user`], [`tweet`] and [`search_page`] fixtures
+    pub fn new() -> Self {
+        let mut tweets = vec![tweet()];
+        tweets.extend(search_page().tweets.into_iter().rev());
+        Self::empty().with_user(user()).with_tweets(tweets)
+    }
+
+    /// A mock with no users or tweets
+    pub fn empty() -> Self {
+        Self {
+            users: Vec::new(),
+            tweets: Arc::default(),
+            #[cfg(feature = "write-ops")]
+            posted: Arc::default(),
+            #[cfg(feature = "write-ops")]
+            next_id: Arc::new(AtomicU64::new(1_900_000_000_000_000_000)),
+        }
+    }
+
+    /// Also serve `user`
+    pub fn with_user(mut self, user: User) -> Self {
+        self.users.push(user);
+        self
+    }
+
+    /// Also serve `tweets`, given oldest first
+    pub fn with_tweets(self, tweets: impl IntoIterator<Item = Tweet>) -> Self {
+        self.tweets.lock().unwrap().extend(tweets);
+        self
+    }
+
+    /// Requests of the tweets posted so far, oldest first
+    #[cfg(feature = "write-ops")]
+    pub fn posted_tweets(&self) -> Vec<PostTweetRequest> {
+        self.posted.lock().unwrap().clone()
+    }
+
+    fn user_by_username(&self, username: &str) -> Option<User> {
+        self.users
+            .iter()
+            .find(|user| user.username.eq_ignore_ascii_case(username))
+            .cloned()
+    }
+
+    fn tweet_by_id(&self, tweet_id: &str) -> Option<Tweet> {
+        self.tweets.lock().unwrap().iter().find(|tweet| tweet.id == tweet_id).cloned()
+    }
+
+    /// Authors of `tweets` that the mock knows, each listed once
+    fn authors(&self, tweets: &[Tweet]) -> Vec<User> {
+        self.users
+            .iter()
+            .filter(|user| tweets.iter().any(|tweet| tweet.author_id.as_deref() == Some(user.id.as_str())))
+            .cloned()
+            .collect()
+    }
+
+    /// Tweets by `user_id`, newest first
+    fn timeline(&self, user_id: &str, max_results: Option<u32>) -> Vec<Tweet> {
+        self.timeline_matching(user_id, max_results, |_| true)
+    }
+
+    /// Tweets by `user_id` that `keep` accepts, newest first
+    fn timeline_matching(
+        &self,
+        user_id: &str,
+        max_results: Option<u32>,
+        keep: impl Fn(&Tweet) -> bool,
+    ) -> Vec<Tweet> {
+        let tweets = self.tweets.lock().unwrap();
+        tweets
+            .iter()
+            .rev()
+            .filter(|tweet| tweet.author_id.as_deref() == Some(user_id) && keep(tweet))
+            .take(max_results.unwrap_or(10) as usize)
+            .cloned()
+            .collect()
+    }
+}
+
+impl XApi for MockXClient {
+    fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(async move { Ok(self.user_by_username(username)) })
+    }
+
+    fn get_user_by_id<'a>(&'a self, user_id: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(async move { Ok(self.users.iter().find(|user| user.id == user_id).cloned()) })
+    }
+
+    fn get_users_by_usernames<'a>(&'a self, usernames: &'a [String]) -> ApiFuture<'a, UserLookup> {
+        Box::pin(async move {
+            let mut lookup = UserLookup::default();
+            for username in usernames {
+                match self.user_by_username(username) {
+                    Some(user) => lookup.users.push(user),
+                    None => lookup.not_found.push(MissingUser {
+                        username: username.clone(),
+                        reason: format!("Could not find user with usernames: [{}].", username),
+                    }),
+                }
+            }
+            Ok(lookup)
+        })
+    }
+
+    fn search_users<'a>(&'a self, query: &'a str, count: u32) -> ApiFuture<'a, Vec<User>> {
+        Box::pin(async move {
+            let query = query.to_lowercase();
+            Ok(self
+                .users
+                .iter()
+                .filter(|user| {
+                    user.username.to_lowercase().contains(&query) || user.name.to_lowercase().contains(&query)
+                })
+                .take(count as usize)
+                .cloned()
+                .collect())
+        })
+    }
+
+    fn search_tweets_with_includes(&self, params: SearchTweetsParams) -> ApiFuture<'_, (SearchPage, Includes)> {
+        Box::pin(async move {
+            let words: Vec<String> = params.query.split_whitespace().map(str::to_lowercase).collect();
+            let tweets: Vec<Tweet> = {
+                let tweets = self.tweets.lock().unwrap();
+                tweets
+                    .iter()
+                    .rev()
+                    .filter(|tweet| {
+                        let text = tweet.text.to_lowercase();
+                        words.iter().all(|word| text.contains(word.as_str()))
+                    })
+                    .take(params.max_results.unwrap_or(10) as usize)
+                    .cloned()
+                    .collect()
+            };
+            let includes = Includes {
+                users: Some(self.authors(&tweets)),
+                ..Includes::default()
+            };
+            Ok((SearchPage { tweets, next_token: None }, includes))
+        })
+    }
+
+    fn get_tweet<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        Box::pin(async move { Ok(self.tweet_by_id(tweet_id)) })
+    }
+
+    fn get_tweet_with_media<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, (Option<Tweet>, Vec<Media>)> {
+        Box::pin(async move { Ok((self.tweet_by_id(tweet_id), Vec::new())) })
+    }
+
+    fn get_tweets<'a>(&'a self, tweet_ids: &'a [String]) -> ApiFuture<'a, TweetLookup> {
+        Box::pin(async move {
+            let mut lookup = TweetLookup::default();
+            for tweet_id in tweet_ids {
+                match self.tweet_by_id(tweet_id) {
+                    Some(tweet) => lookup.tweets.push(tweet),
+                    None => lookup.missing_ids.push(tweet_id.clone()),
+                }
+            }
+            lookup.authors = self.authors(&lookup.tweets);
+            Ok(lookup)
+        })
+    }
+
+    fn get_user_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(async move { Ok(self.timeline(user_id, max_results)) })
+    }
+
+    fn get_user_tweets_with_references<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Includes)> {
+        Box::pin(async move { Ok((self.timeline(user_id, max_results), Includes::default())) })
+    }
+
+    fn get_user_original_tweets<'a>(&'a self, user_id: &'a str, max_results: u32) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(async move {
+            Ok(self.timeline_matching(user_id, Some(max_results), |tweet| {
+                tweet.referenced_tweets.as_ref().is_none_or(Vec::is_empty)
+            }))
+        })
+    }
+
+    fn get_user_media_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, (Vec<Tweet>, Vec<Media>)> {
+        Box::pin(async move {
+            let tweets = self.timeline_matching(user_id, max_results, |tweet| {
+                tweet
+                    .attachments
+                    .as_ref()
+                    .and_then(|attachments| attachments.media_keys.as_ref())
+                    .is_some_and(|keys| !keys.is_empty())
+            });
+            Ok((tweets, Vec::new()))
+        })
+    }
+
+    fn get_pinned_tweet<'a>(&'a self, username_or_id: &'a str) -> ApiFuture<'a, Option<Tweet>> {
+        Box::pin(async move {
+            let username_or_id = username_or_id.trim_start_matches('@');
+            let user = self
+                .users
+                .iter()
+                .find(|user| user.id == username_or_id || user.username.eq_ignore_ascii_case(username_or_id));
+            Ok(user
+                .and_then(|user| user.pinned_tweet_id.as_deref())
+                .and_then(|tweet_id| self.tweet_by_id(tweet_id)))
+        })
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn post_tweet<'a>(&'a self, request: &'a PostTweetRequest) -> ApiFuture<'a, Tweet> {
+        Box::pin(async move {
+            let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+            let tweet = new_tweet(&id, &request.text, USER_ID);
+            self.tweets.lock().unwrap().push(tweet.clone());
+            self.posted.lock().unwrap().push(request.clone());
+            Ok(tweet)
+        })
+    }
+
+    #[cfg(feature = "write-ops")]
+    fn delete_tweet<'a>(&'a self, tweet_id: &'a str) -> ApiFuture<'a, bool> {
+        Box::pin(async move {
+            let mut tweets = self.tweets.lock().unwrap();
+            let count = tweets.len();
+            tweets.retain(|tweet| tweet.id != tweet_id);
+            if tweets.len() == count {
+                return Err(XError::Api {
+                    status: 404,
+                    message: format!("Could not find tweet with id: [{}].", tweet_id),
+                });
+            }
+            Ok(true)
+        })
+    }
+}
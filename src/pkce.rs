@@ -0,0 +1,207 @@
+//! OAuth 2.0 authorization code flow with PKCE
+//!
+//! Obtaining an OAuth 2.0 user token takes a browser round trip: the user
+//! opens the [`authorization_url`](PkceSession::authorization_url), approves
+//! the app, and X redirects back with a `code` to exchange for the token. A
+//! [`PkceSession`] holds the `state` and code verifier generated for one such
+//! attempt and checks the redirect against them, as described in
+//! <https://developer.x.com/en/docs/authentication/oauth-2-0/authorization-code>.
+
+use crate::error::{XError, XResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use sha2::{Digest, Sha256};
+
+/// Page the user is sent to for approval
+pub const AUTHORIZE_URL: &str = "https://twitter.com/i/oauth2/authorize";
+
+/// State and code verifier of one authorization attempt
+#[derive(Clone)]
+pub struct PkceSession {
+    state: String,
+    code_verifier: String,
+}
+
+impl std::fmt::Debug for PkceSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PkceSession")
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for PkceSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PkceSession {
+    /// Start an attempt with a random state and code verifier
+    pub fn new() -> Self {
+        Self {
+            state: random_string(32),
+            code_verifier: random_string(64),
+        }
+    }
+
+    /// The `state` the callback must echo back
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// The verifier sent with the token request
+    pub fn code_verifier(&self) -> &str {
+        &self.code_verifier
+    }
+
+    /// `S256` challenge derived from the code verifier
+    pub fn code_challenge(&self) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(self.code_verifier.as_bytes()))
+    }
+
+    /// URL that asks the user to authorize `client_id` for `scopes`
+    pub fn authorization_url(&self, client_id: &str, redirect_uri: &str, scopes: &[&str]) -> Url {
+        let mut url = Url::parse(AUTHORIZE_URL).expect("authorize URL is valid");
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &scopes.join(" "))
+            .append_pair("state", &self.state)
+            .append_pair("code_challenge", &self.code_challenge())
+            .append_pair("code_challenge_method", "S256");
+        url
+    }
+
+    /// Authorization code from the redirect X sent the user back with
+    ///
+    /// `callback` is the full redirect URL or just its query string. The
+    /// `state` must match this session's, otherwise the redirect may have been
+    /// forged and an [`XError::Auth`] is returned. When the user declined,
+    /// X sends `error` instead of `code` and this returns
+    /// [`XError::AuthDenied`].
+    pub fn parse_callback(&self, callback: &str) -> XResult<String> {
+        let callback = callback.trim();
+        let url = match Url::parse(callback) {
+            Ok(url) => url,
+            Err(_) => {
+                let query = callback.trim_start_matches('?');
+                Url::parse(&format!("http://localhost/?{}", query))
+                    .map_err(|e| XError::Auth(format!("Malformed OAuth callback: {}", e)))?
+            }
+        };
+
+        let mut code = None;
+        let mut state = None;
+        let mut error = None;
+        let mut description = None;
+        for (name, value) in url.query_pairs() {
+            match name.as_ref() {
+                "code" => code = Some(value.into_owned()),
+                "state" => state = Some(value.into_owned()),
+                "error" => error = Some(value.into_owned()),
+                "error_description" => description = Some(value.into_owned()),
+                _ => {}
+            }
+        }
+
+        match state {
+            Some(state) if state == self.state => {}
+            Some(_) => {
+                return Err(XError::Auth(
+                    "OAuth callback state does not match the authorization request".to_string(),
+                ))
+            }
+            None => {
+                return Err(XError::Auth(
+                    "OAuth callback is missing the state parameter".to_string(),
+                ))
+            }
+        }
+        if let Some(error) = error {
+            return Err(XError::AuthDenied { error, description });
+        }
+        code.filter(|code| !code.is_empty()).ok_or_else(|| {
+            XError::Auth("OAuth callback has neither a code nor an error".to_string())
+        })
+    }
+}
+
+fn random_string(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> PkceSession {
+        PkceSession {
+            state: "abc123".to_string(),
+            // Example verifier from RFC 7636, appendix B
+            code_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_code_challenge_and_url() {
+        let session = session();
+        assert_eq!(
+            session.code_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let url = session.authorization_url("client", "http://localhost:8080/cb", &["tweet.read"]);
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert!(query.contains(&("state".to_string(), "abc123".to_string())));
+        assert!(query.contains(&("code_challenge_method".to_string(), "S256".to_string())));
+        assert!(query.contains(&(
+            "redirect_uri".to_string(),
+            "http://localhost:8080/cb".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_parse_callback() {
+        let session = session();
+        assert_eq!(
+            session
+                .parse_callback("http://localhost:8080/cb?state=abc123&code=xyz")
+                .unwrap(),
+            "xyz"
+        );
+        assert_eq!(
+            session.parse_callback("?code=xyz&state=abc123").unwrap(),
+            "xyz"
+        );
+
+        match session
+            .parse_callback("state=abc123&error=access_denied&error_description=User+declined")
+        {
+            Err(XError::AuthDenied { error, description }) => {
+                assert_eq!(error, "access_denied");
+                assert_eq!(description.as_deref(), Some("User declined"));
+            }
+            other => panic!("expected AuthDenied, got {:?}", other),
+        }
+
+        assert!(matches!(
+            session.parse_callback("code=xyz&state=forged"),
+            Err(XError::Auth(_))
+        ));
+        assert!(matches!(
+            session.parse_callback("code=xyz"),
+            Err(XError::Auth(_))
+        ));
+        assert!(matches!(
+            session.parse_callback("state=abc123"),
+            Err(XError::Auth(_))
+        ));
+    }
+}
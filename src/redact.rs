@@ -0,0 +1,73 @@
+//! Keeping tweet content out of log output
+//!
+//! With `X_MCP_REDACT_CONTENT=1`, log sites that could include tweet text or
+//! raw API response bodies write `[redacted]` in their place. The setting is
+//! process-wide, like the `tracing` subscriber it protects; the binary turns
+//! it on with [`set_enabled`] from `ServerConfig::redact_content`.
+
+use crate::error::XError;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Placeholder written instead of redacted content
+pub const REDACTED: &str = "[redacted]";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turn content redaction on or off for the whole process
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether content is currently redacted from logs
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// `text` for a log line, or the placeholder when redacting
+pub fn content(text: &str) -> &str {
+    if is_enabled() {
+        REDACTED
+    } else {
+        text
+    }
+}
+
+/// An error message for a log line
+///
+/// API errors carry the raw response body and HTTP errors the request URL
+/// (with search queries), so both lose those details when redacting.
+pub fn error(err: &XError) -> String {
+    if !is_enabled() {
+        return err.to_string();
+    }
+    match err {
+        XError::Api { status, .. } => format!("X API error: {} - {}", status, REDACTED),
+        XError::Http(_) => format!("HTTP request failed: {}", REDACTED),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let api_error = XError::Api {
+            status: 403,
+            message: "{\"detail\":\"my secret tweet\"}".to_string(),
+        };
+
+        set_enabled(true);
+        assert_eq!(content("my secret tweet"), REDACTED);
+        assert_eq!(error(&api_error), "X API error: 403 - [redacted]");
+        assert_eq!(
+            error(&XError::Config("missing token".to_string())),
+            "Configuration error: missing token"
+        );
+
+        set_enabled(false);
+        assert_eq!(content("my tweet"), "my tweet");
+        assert!(error(&api_error).contains("my secret tweet"));
+    }
+}
@@ -0,0 +1,144 @@
+//! Record and replay of X API exchanges for offline, deterministic runs
+//!
+//! With `X_MCP_RECORD_DIR` set, every request the client makes is sent to X
+//! as usual and the response is saved to that directory. With
+//! `X_MCP_REPLAY_DIR` set, responses are served from the directory and the
+//! network is never touched, so the server runs without credentials.
+//!
+//! Each exchange is stored as `<key>.json`, where the key is a stable hash of
+//! the method, URL, query parameters and body. Credentials are not part of the
+//! key and are never written to disk.
+
+use crate::error::{XError, XResult};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Whether the client records live exchanges or replays saved ones
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// Send requests to X and save each exchange in the directory
+    Record(PathBuf),
+    /// Serve responses from the directory without network access
+    Replay(PathBuf),
+}
+
+impl ReplayMode {
+    /// Read the mode from `X_MCP_RECORD_DIR` / `X_MCP_REPLAY_DIR`
+    pub fn from_env() -> XResult<Option<Self>> {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// [`from_env`](Self::from_env) reading variables through `lookup`
+    pub(crate) fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> XResult<Option<Self>> {
+        let record = lookup("X_MCP_RECORD_DIR").filter(|dir| !dir.is_empty());
+        let replay = lookup("X_MCP_REPLAY_DIR").filter(|dir| !dir.is_empty());
+
+        match (record, replay) {
+            (Some(_), Some(_)) => Err(XError::Config(
+                "X_MCP_RECORD_DIR and X_MCP_REPLAY_DIR cannot both be set".to_string(),
+            )),
+            (Some(dir), None) => Ok(Some(Self::Record(dir.into()))),
+            (None, Some(dir)) => Ok(Some(Self::Replay(dir.into()))),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Directory holding the recorded exchanges
+    pub fn dir(&self) -> &Path {
+        match self {
+            Self::Record(dir) | Self::Replay(dir) => dir,
+        }
+    }
+}
+
+/// A request and the response X gave to it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub method: String,
+    pub url: String,
+    pub query: Vec<(String, String)>,
+    pub body: Option<serde_json::Value>,
+    pub status: u16,
+    /// Response body, as JSON when it parses and as a string otherwise
+    pub response: serde_json::Value,
+}
+
+impl RecordedExchange {
+    /// Raw response body text
+    pub fn response_text(&self) -> String {
+        match &self.response {
+            serde_json::Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Key identifying the request this exchange answers
+    pub fn key(&self) -> String {
+        let query: Vec<(&str, String)> = self
+            .query
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.clone()))
+            .collect();
+        request_key(&self.method, &self.url, &query, self.body.as_ref())
+    }
+
+    /// Save the exchange into `dir`
+    pub fn save(&self, dir: &Path) -> XResult<()> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.json", self.key()));
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Load the exchange recorded for a request from `dir`
+    pub fn load(
+        dir: &Path,
+        method: &str,
+        url: &str,
+        query: &[(&str, String)],
+        body: Option<&serde_json::Value>,
+    ) -> XResult<Self> {
+        let path = dir.join(format!("{}.json", request_key(method, url, query, body)));
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            XError::Config(format!(
+                "No recorded response for {} {} (expected {}): {}",
+                method,
+                url,
+                path.display(),
+                e
+            ))
+        })?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Stable key for a request: FNV-1a over its method, URL, query and body
+pub fn request_key(
+    method: &str,
+    url: &str,
+    query: &[(&str, String)],
+    body: Option<&serde_json::Value>,
+) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = OFFSET_BASIS;
+    let mut feed = |bytes: &[u8]| {
+        for byte in bytes.iter().chain([0u8].iter()) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    };
+
+    feed(method.to_ascii_uppercase().as_bytes());
+    feed(url.as_bytes());
+    for (name, value) in query {
+        feed(name.as_bytes());
+        feed(value.as_bytes());
+    }
+    if let Some(body) = body {
+        feed(body.to_string().as_bytes());
+    }
+
+    format!("{:016x}", hash)
+}
@@ -0,0 +1,239 @@
+//! Session-wide scheduling of tool calls under a rate-limit budget
+//!
+//! X counts requests against 15-minute windows, whichever tool makes them.
+//! Left alone, a burst of background searches can use up the window and make
+//! the user's next `post_tweet` fail with a 429. A [`Scheduler`] admits at
+//! most `budget` tool calls per `window`; further calls wait for a slot, and
+//! when one frees up the waiting call with the highest [`Priority`] goes
+//! first, in arrival order within a priority.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Length of X's rate limit windows
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+/// Urgency of a tool call waiting for the budget
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Background work that can wait behind everything else
+    Low,
+    Normal,
+    /// Calls a user is waiting on, such as posting
+    High,
+}
+
+impl Priority {
+    /// Parse `low`, `normal` or `high`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Some(Self::Low),
+            "normal" => Some(Self::Normal),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+/// Budget of a [`Scheduler`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedulerConfig {
+    /// Tool calls admitted per window
+    pub budget: u32,
+    pub window: Duration,
+}
+
+/// Snapshot of the scheduler for status reporting
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SchedulerStatus {
+    pub budget: u32,
+    pub window_secs: u64,
+    /// Calls admitted in the current window
+    pub used: u32,
+    /// Calls waiting for a slot
+    pub waiting: u32,
+}
+
+/// Place in the queue: highest priority first, then oldest ticket
+type Ticket = (Reverse<Priority>, u64);
+
+#[derive(Debug, Default)]
+struct State {
+    /// When each call of the current window was admitted, oldest first
+    admitted: VecDeque<Instant>,
+    waiting: BTreeSet<Ticket>,
+    next_ticket: u64,
+}
+
+impl State {
+    fn expire(&mut self, window: Duration, now: Instant) {
+        while self
+            .admitted
+            .front()
+            .is_some_and(|admitted| now.duration_since(*admitted) >= window)
+        {
+            self.admitted.pop_front();
+        }
+    }
+}
+
+/// Queue admitting tool calls under a shared budget
+#[derive(Debug)]
+pub struct Scheduler {
+    config: SchedulerConfig,
+    state: Mutex<State>,
+    /// Woken whenever the head of the queue may have changed
+    changed: Notify,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::default()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Wait until a call of `priority` may go ahead, and count it against the
+    /// budget
+    ///
+    /// Dropping the future gives up the place in the queue.
+    pub async fn acquire(&self, priority: Priority) {
+        let ticket = {
+            let mut state = self.lock();
+            let ticket = (Reverse(priority), state.next_ticket);
+            state.next_ticket += 1;
+            state.waiting.insert(ticket);
+            ticket
+        };
+        let mut queued = Queued {
+            scheduler: self,
+            ticket: Some(ticket),
+        };
+
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            let wait = {
+                let mut state = self.lock();
+                let now = Instant::now();
+                state.expire(self.config.window, now);
+                if (state.admitted.len() as u32) < self.config.budget {
+                    if state.waiting.first() == Some(&ticket) {
+                        state.waiting.remove(&ticket);
+                        state.admitted.push_back(now);
+                        queued.ticket = None;
+                        drop(state);
+                        // Let the next call in line check for a slot
+                        self.changed.notify_waiters();
+                        return;
+                    }
+                    None
+                } else {
+                    state
+                        .admitted
+                        .front()
+                        .map(|oldest| *oldest + self.config.window - now)
+                }
+            };
+
+            match wait {
+                Some(wait) => {
+                    tracing::debug!("Call budget used up, waiting {:?} for a slot", wait);
+                    tokio::select! {
+                        _ = changed => {}
+                        _ = tokio::time::sleep(wait) => {}
+                    }
+                }
+                None => changed.await,
+            }
+        }
+    }
+
+    /// Budget use in the current window
+    pub fn status(&self) -> SchedulerStatus {
+        let mut state = self.lock();
+        state.expire(self.config.window, Instant::now());
+        SchedulerStatus {
+            budget: self.config.budget,
+            window_secs: self.config.window.as_secs(),
+            used: state.admitted.len() as u32,
+            waiting: state.waiting.len() as u32,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Removes an abandoned ticket from the queue
+struct Queued<'a> {
+    scheduler: &'a Scheduler,
+    ticket: Option<Ticket>,
+}
+
+impl Drop for Queued<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            self.scheduler.lock().waiting.remove(&ticket);
+            self.scheduler.changed.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_priority_order() {
+        let scheduler = Arc::new(Scheduler::new(SchedulerConfig {
+            budget: 1,
+            window: Duration::from_millis(200),
+        }));
+        scheduler.acquire(Priority::Normal).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut calls = Vec::new();
+        for priority in [Priority::Low, Priority::Normal, Priority::High] {
+            let (scheduler, order) = (scheduler.clone(), order.clone());
+            calls.push(tokio::spawn(async move {
+                scheduler.acquire(priority).await;
+                order.lock().unwrap().push(priority);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(scheduler.status().used, 1);
+        assert_eq!(scheduler.status().waiting, 3);
+        assert!(order.lock().unwrap().is_empty());
+
+        for call in calls {
+            call.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            [Priority::High, Priority::Normal, Priority::Low]
+        );
+
+        // A dropped waiter leaves the queue
+        let waiting =
+            tokio::time::timeout(Duration::from_millis(10), scheduler.acquire(Priority::High))
+                .await;
+        assert!(waiting.is_err());
+        assert_eq!(scheduler.status().waiting, 0);
+    }
+}
@@ -1,22 +1,130 @@
 //! MCP Server implementation for X API using RMCP SDK
 
-use crate::client::XClient;
+use crate::api::XApi;
+use crate::circuit::CircuitStatus;
+use crate::client::{capture_raw_responses, with_progress, Progress, XClient, MEDIA_FIELDS};
+use crate::config::ServerConfig;
+#[cfg(feature = "write-ops")]
+use crate::error::XError;
 use crate::error::XResult;
-use crate::types::SearchTweetsParams;
+use crate::metrics::Metrics;
+use crate::redact;
+use crate::time::{parse_timestamp, parse_utc_offset};
+use crate::capabilities::{level_enabled, Capabilities};
+use crate::scheduler::{Priority, Scheduler, SchedulerConfig, SchedulerStatus};
+use crate::stream::StreamBuffer;
+use crate::transport::{LineTransport, RetryingReader};
+use crate::types::{
+    ConversationTree, Includes, Media, MissingUser, PostTweetRequest, RateLimit, ReplyContext, SearchTweetsParams,
+    SortOrder, StreamRule, Trend, TrendLocation, Tweet, TweetIdRange, TweetMedia, TweetMetrics, TweetPoll, TweetReply, TwitterList,
+    User,
+};
+use crate::validate::{
+    is_tweet_id, parse_tweet_id, reply_settings_value, validate_tweet, TweetValidation, MAX_TWEET_LENGTH,
+};
+#[cfg(feature = "write-ops")]
+use crate::validate::{
+    check_list_name, check_reply_settings, check_tweet_attachments, check_tweet_length,
+    check_tweet_references,
+};
 use rmcp::{
-    model::ErrorData as McpError, RoleServer, ServerHandler,
+    model::ErrorData as McpError, ServerHandler,
     handler::server::{
         router::{tool::ToolRouter},
-        tool::Parameters,
+        tool::{cached_schema_for_type, ToolCallContext},
+        wrapper::Parameters,
     },
     model::*,
     service::RequestContext,
-    tool, tool_handler, tool_router,
-    ServiceExt, transport::stdio,
+    tool, tool_router,
+    Peer, RoleServer, ServiceExt, transport::stdio,
 };
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
-use std::future::Future;
+use std::collections::BTreeMap;
+#[cfg(feature = "http")]
+use std::net::SocketAddr;
+#[cfg(feature = "write-ops")]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+#[cfg(feature = "http")]
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Default instructions sent to clients in the initialize response
+pub const DEFAULT_INSTRUCTIONS: &str = "This server provides tools for the X (Twitter) API. \
+Usernames are given without the leading @. Recent search only covers roughly the last 7 days. \
+The X API enforces per-endpoint rate limits (e.g. 180 searches per 15 minutes), so prefer \
+a single call with a larger max_results over many small calls. Anything posted through X \
+is public, so confirm content with the user before publishing.";
+
+/// Tools that post or change something on X, left out in read-only mode
+pub const WRITE_TOOLS: [&str; 18] = [
+    "post_tweet",
+    "post_thread",
+    "delete_tweet",
+    "follow_user",
+    "unfollow_user",
+    "send_dm",
+    "bookmark_tweet",
+    "unbookmark_tweet",
+    "create_list",
+    "add_list_member",
+    "remove_list_member",
+    "hide_reply",
+    "block_user",
+    "unblock_user",
+    "mute_user",
+    "unmute_user",
+    "add_stream_rule",
+    "delete_stream_rules",
+];
+
+/// Tools that watch for cancellation themselves and stop at a safe point,
+/// reporting what they completed, rather than being dropped mid-request
+const GRACEFULLY_CANCELLED_TOOLS: [&str; 1] = ["post_thread"];
+
+/// How many of a user's most recent tweets X serves from the timeline
+/// endpoint
+const TIMELINE_DEPTH: u64 = 3200;
+
+/// Tools that make no X API requests, so never wait for the call budget
+const UNSCHEDULED_TOOLS: [&str; 2] = ["status", "preview_tweet"];
+
+/// Output options of the tools that fetch users or tweets
+///
+/// Flattened into those tools' arguments, so clients pass them as top-level
+/// fields. The tweet options are ignored by tools returning only users or
+/// lists.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct OutputOptions {
+    /// Decode HTML entities such as &amp; in tweet text (default: true)
+    #[serde(default = "default_true")]
+    pub decode_html: bool,
+    /// Add created_at_relative ("3 hours ago") and created_at_local to each tweet
+    #[serde(default)]
+    pub human_times: bool,
+    /// UTC offset for created_at_local, e.g. "+02:00" (default: the server's
+    /// X_MCP_TIMEZONE, or UTC). Implies human_times
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Also return the unmodified X API responses under "raw"
+    #[serde(default)]
+    pub raw: bool,
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            decode_html: true,
+            human_times: false,
+            timezone: None,
+            raw: false,
+        }
+    }
+}
 
 /// Tool arguments for getting user information
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
@@ -26,24 +134,72 @@ pub struct GetUserArgs {
     /// Whether the identifier is a user ID (true) or username (false)
     #[serde(default)]
     pub is_user_id: bool,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
 
 
+/// Tool arguments for searching users
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SearchUsersArgs {
+    /// Name or keyword to look for, e.g. "rust lang"
+    pub query: String,
+    /// Maximum number of users (default: 10, max: 20)
+    #[serde(default = "default_max_results")]
+    pub count: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
+
 /// Tool arguments for searching tweets
 #[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct SearchTweetsArgs {
     /// Search query
     pub query: String,
-    /// Maximum number of results (default: 10, max: 100)
+    /// Maximum number of results (default: 10, min: 1, max: 100)
     #[serde(default = "default_max_results")]
+    #[schemars(range(min = 1, max = 100))]
     pub max_results: u32,
-    /// Include user information in results
+    /// "recency" (newest first, the default) or "relevancy" (X's ranking)
+    #[serde(default)]
+    pub sort_order: Option<SortOrder>,
+    /// Only match tweets created at or after this time, RFC 3339 (e.g.
+    /// "2024-05-01T00:00:00Z"); recent search covers the last 7 days
+    #[serde(default)]
+    pub start_time: Option<String>,
+    /// Only match tweets created before this time, RFC 3339
+    #[serde(default)]
+    pub end_time: Option<String>,
+    /// Only return tweets newer than this tweet ID, e.g. the newest one
+    /// from an earlier call
+    #[serde(default)]
+    pub since_id: Option<String>,
+    /// Only return tweets older than this tweet ID
+    #[serde(default)]
+    pub until_id: Option<String>,
+    /// Attach each tweet's author (ID, name and username) as "author"
     #[serde(default)]
     pub include_users: bool,
     /// Include tweet metrics
     #[serde(default)]
     pub include_metrics: bool,
+    /// Include reply relationships: the user each reply is directed at and
+    /// the tweets each result replies to, quotes or retweets
+    #[serde(default)]
+    pub include_references: bool,
+    /// Drop tweets X marks as possibly sensitive
+    #[serde(default)]
+    pub exclude_sensitive: bool,
+    /// Drop tweets whose text contains any of these terms (case-insensitive)
+    #[serde(default)]
+    pub blocklist: Option<Vec<String>>,
+    /// next_token from a previous search with the same query, to fetch the
+    /// page after it
+    #[serde(default)]
+    pub next_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
 /// Tool arguments for getting a specific tweet
@@ -51,6 +207,8 @@ pub struct SearchTweetsArgs {
 pub struct GetTweetArgs {
     /// The tweet ID
     pub tweet_id: String,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
 /// Tool arguments for getting user's tweets
@@ -61,270 +219,3618 @@ pub struct GetUserTweetsArgs {
     /// Whether the identifier is a user ID (true) or username (false)
     #[serde(default)]
     pub is_user_id: bool,
-    /// Maximum number of tweets to retrieve (default: 10)
+    /// Maximum number of tweets to retrieve (default: 10). Over 100 takes
+    /// several requests, up to X's 3200 most recent tweets; media_only and
+    /// include_references stay capped at 100
     #[serde(default = "default_max_results")]
     pub max_results: u32,
+    /// Only return tweets with images or video attached. Filtering happens
+    /// after fetching, so fewer than max_results tweets may be returned
+    #[serde(default)]
+    pub media_only: bool,
+    /// Include the tweets each tweet retweets, quotes or replies to, with
+    /// their authors, under "referenced". Ignored with media_only
+    #[serde(default)]
+    pub include_references: bool,
+    /// Only return tweets newer than this tweet ID, e.g. the newest one
+    /// from an earlier call
+    #[serde(default)]
+    pub since_id: Option<String>,
+    /// Only return tweets older than this tweet ID
+    #[serde(default)]
+    pub until_id: Option<String>,
+    /// Drop tweets X marks as possibly sensitive
+    #[serde(default)]
+    pub exclude_sensitive: bool,
+    /// Drop tweets whose text contains any of these terms (case-insensitive)
+    #[serde(default)]
+    pub blocklist: Option<Vec<String>>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
-fn default_max_results() -> u32 {
-    10
+/// Tool arguments for getting a user's tweets in a date range
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetUserTweetsRangeArgs {
+    /// Username (with or without @) or numeric user ID
+    pub identifier: String,
+    /// Start of the range, RFC 3339, e.g. "2024-05-01T00:00:00Z"
+    pub start_time: String,
+    /// End of the range, RFC 3339 (default: now)
+    #[serde(default)]
+    pub end_time: Option<String>,
+    /// Pages of up to 100 tweets to fetch at most (default: 10, max: 32)
+    #[serde(default = "default_max_pages")]
+    pub max_pages: u32,
+    /// next_token from an earlier call, to continue where it stopped
+    #[serde(default)]
+    pub pagination_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
-/// X MCP Server
-#[derive(Clone)]
-pub struct XMcpServer {
-    client: XClient,
-    tool_router: ToolRouter<XMcpServer>,
+/// Tool arguments for getting a user's pinned tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetPinnedTweetArgs {
+    /// Username (with or without @) or numeric user ID
+    pub identifier: String,
+    #[serde(flatten)]
+    pub output: OutputOptions,
 }
 
-#[tool_router]
-impl XMcpServer {
-    /// Create a new X MCP Server
-    pub fn new(client: XClient) -> Self {
-        Self {
-            client,
-            tool_router: Self::tool_router(),
-        }
-    }
+/// Tool arguments for hydrating tweet IDs
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HydrateTweetsArgs {
+    /// IDs of the tweets to fetch; duplicates are ignored
+    pub tweet_ids: Vec<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Create server from environment variables
-    pub fn from_env() -> XResult<Self> {
-        let client = XClient::from_env()?;
-        Ok(Self::new(client))
-    }
+/// Tool arguments for fetching a conversation tree
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetConversationTreeArgs {
+    /// ID of any tweet in the conversation
+    pub tweet_id: String,
+    /// Deepest level of replies to include (default: 10, max: 50)
+    #[serde(default = "default_max_depth")]
+    pub max_depth: u32,
+    /// Maximum number of replies to fetch, newest first (default: 100, max: 100)
+    #[serde(default = "default_max_tweets")]
+    pub max_tweets: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Run the server with stdio transport
-    pub async fn run_stdio(self) -> XResult<()> {
-        let service = self.serve(stdio()).await?;
-        service.waiting().await?;
-        Ok(())
-    }
+/// Tool arguments for fetching quote tweets
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetQuoteTweetsArgs {
+    /// Quoted tweet, as an ID or a tweet URL
+    pub tweet_id: String,
+    /// Maximum number of quotes to return; more than 100 are fetched over
+    /// several pages (default: 10, max: 500)
+    #[serde(default = "default_max_results")]
+    pub max_results: u32,
+    /// next_token from an earlier call, to continue where it stopped
+    #[serde(default)]
+    pub pagination_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Get user information by username or user ID
-    #[tool(description = "Get user information by username or user ID")]
-    async fn get_user(
-        &self,
-        Parameters(args): Parameters<GetUserArgs>,
-    ) -> Result<CallToolResult, McpError> {
-        let user = if args.is_user_id {
-            self.client.get_user_by_id(&args.identifier).await
-        } else {
-            self.client.get_user_by_username(&args.identifier).await
-        };
-
-        match user {
-            Ok(Some(user)) => {
-                let result = json!({
-                    "success": true,
-                    "user": user
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Ok(None) => {
-                let result = json!({
-                    "success": false,
-                    "error": "User not found"
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Err(e) => {
-                let result = json!({
-                    "success": false,
-                    "error": format!("Error: {}", e)
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-        }
-    }
+/// Tool arguments for fetching the authenticated user's bookmarks
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetBookmarksArgs {
+    /// Maximum number of bookmarks to return; more than 100 are fetched over
+    /// several pages (default: 10, max: 500)
+    #[serde(default = "default_max_results")]
+    pub max_results: u32,
+    /// next_token from an earlier call, to continue where it stopped
+    #[serde(default)]
+    pub pagination_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
+/// Tool arguments for fetching the replies under a tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetRepliesArgs {
+    /// First tweet of the conversation, as an ID or a tweet URL
+    pub tweet_id: String,
+    /// Maximum number of replies to return, newest first (default: 10, max: 100)
+    #[serde(default = "default_max_results")]
+    pub max_results: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
+/// Tool arguments for looking up a list
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetListArgs {
+    /// ID of the list
+    pub list_id: String,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Search for tweets
-    #[tool(description = "Search for tweets")]
-    async fn search_tweets(
-        &self,
-        Parameters(args): Parameters<SearchTweetsArgs>,
-    ) -> Result<CallToolResult, McpError> {
-        let mut tweet_fields = vec![
-            "id".to_string(),
-            "text".to_string(),
-            "author_id".to_string(),
-            "created_at".to_string(),
-        ];
+/// Tool arguments for fetching a list's timeline
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetListTweetsArgs {
+    /// ID of the list
+    pub list_id: String,
+    /// Maximum number of tweets to return; more than 100 are fetched over
+    /// several pages (default: 10, max: 500)
+    #[serde(default = "default_max_results")]
+    pub max_results: u32,
+    /// next_token from an earlier call, to continue where it stopped
+    #[serde(default)]
+    pub pagination_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-        let mut user_fields = vec![];
-        let mut expansions = vec![];
+/// Tool arguments for looking up several users at once
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetUsersArgs {
+    /// Usernames (with or without @) to look up; duplicates are ignored
+    pub usernames: Vec<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-        if args.include_metrics {
-            tweet_fields.push("public_metrics".to_string());
-        }
+/// Tool arguments for listing a user's followers or followed accounts
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetUserConnectionsArgs {
+    /// Username or user ID
+    pub identifier: String,
+    /// Whether the identifier is a user ID (true) or username (false)
+    #[serde(default)]
+    pub is_user_id: bool,
+    /// Maximum number of users to return (default: 100, max: 5000). Over
+    /// 1000 takes several requests
+    #[serde(default = "default_max_users")]
+    pub max_results: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-        if args.include_users {
-            user_fields.extend(vec![
-                "id".to_string(),
-                "name".to_string(),
-                "username".to_string(),
-            ]);
-            expansions.push("author_id".to_string());
-        }
+/// Tool arguments for listing the users who liked or retweeted a tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetTweetEngagementArgs {
+    /// Tweet, as an ID or a tweet URL
+    pub tweet_id: String,
+    /// Maximum number of users to return (default: 100, max: 1000). Over
+    /// 100 takes several requests
+    #[serde(default = "default_max_users")]
+    pub max_results: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-        let search_params = SearchTweetsParams {
-            query: args.query,
-            max_results: Some(args.max_results.min(100)), // API limit
-            tweet_fields: Some(tweet_fields),
-            user_fields: if user_fields.is_empty() { None } else { Some(user_fields) },
-            expansions: if expansions.is_empty() { None } else { Some(expansions) },
-        };
+/// Tool arguments for listing a user's lists
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetUserListsArgs {
+    /// Username (with or without @) or numeric user ID
+    pub identifier: String,
+    /// Maximum number of lists to return; more than 100 are fetched over
+    /// several pages (default: 100, max: 500)
+    #[serde(default = "default_max_lists")]
+    pub max_results: u32,
+    /// next_token from an earlier call, to continue where it stopped
+    #[serde(default)]
+    pub pagination_token: Option<String>,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-        match self.client.search_tweets(search_params).await {
-            Ok(tweets) => {
-                let result = json!({
-                    "success": true,
-                    "tweets": tweets,
-                    "count": tweets.len()
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Err(e) => {
-                let result = json!({
-                    "success": false,
-                    "error": format!("Error: {}", e)
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-        }
-    }
+/// Tool arguments for gathering reply context
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ComposeReplyArgs {
+    /// ID of the tweet to reply to
+    pub tweet_id: String,
+    /// Maximum number of recent replies to include (default: 10, max: 100, 0 to skip)
+    #[serde(default = "default_max_results")]
+    pub max_replies: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Get a specific tweet by ID
-    #[tool(description = "Get a specific tweet by ID")]
-    async fn get_tweet(
-        &self,
-        Parameters(args): Parameters<GetTweetArgs>,
-    ) -> Result<CallToolResult, McpError> {
-        match self.client.get_tweet(&args.tweet_id).await {
-            Ok(Some(tweet)) => {
-                let result = json!({
-                    "success": true,
-                    "tweet": tweet
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Ok(None) => {
-                let result = json!({
-                    "success": false,
-                    "error": "Tweet not found"
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Err(e) => {
-                let result = json!({
-                    "success": false,
-                    "error": format!("Error: {}", e)
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-        }
-    }
+/// Tool arguments for summarizing an account
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ProfileReportArgs {
+    /// Username, with or without @
+    pub username: String,
+    /// Number of latest timeline entries to sample, of which retweets and
+    /// replies are left out (default: 20, 5-100)
+    #[serde(default = "default_report_tweets")]
+    pub max_tweets: u32,
+    #[serde(flatten)]
+    pub output: OutputOptions,
+}
 
-    /// Get user's recent tweets
-    #[tool(description = "Get user's recent tweets")]
-    async fn get_user_tweets(
-        &self,
-        Parameters(args): Parameters<GetUserTweetsArgs>,
-    ) -> Result<CallToolResult, McpError> {
-        // First, get the user to get their ID if we have a username
-        let user_id = if args.is_user_id {
-            args.identifier.clone()
-        } else {
-            match self.client.get_user_by_username(&args.identifier).await {
-                Ok(Some(user)) => user.id,
-                Ok(None) => {
-                    let result = json!({
-                        "success": false,
-                        "error": "User not found"
-                    });
-                    return Ok(CallToolResult::success(vec![Content::text(
-                        serde_json::to_string_pretty(&result).unwrap_or_default(),
-                    )]));
+/// Tool arguments for composing a tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PostTweetArgs {
+    /// The text content of the tweet
+    pub text: String,
+    /// Tweet to reply to, as an ID or a tweet URL
+    #[serde(default)]
+    pub reply_to: Option<String>,
+    /// Tweet to quote, as an ID or a tweet URL
+    #[serde(default)]
+    pub quote_tweet_id: Option<String>,
+    /// IDs of already uploaded media to attach (up to 4)
+    #[serde(default)]
+    pub media_ids: Vec<String>,
+    /// Paths of image or video files in the server's media directory to
+    /// upload and attach, after any media_ids (up to 4 media in all). Not
+    /// checked by preview_tweet
+    #[serde(default)]
+    pub media_paths: Vec<String>,
+    /// IDs of users to tag in the attached media (up to 10)
+    #[serde(default)]
+    pub tagged_user_ids: Vec<String>,
+    /// Card to attach instead of media, a poll or a quote
+    #[serde(default)]
+    pub card_uri: Option<String>,
+    /// Link that opens a Direct Message conversation with you
+    #[serde(default)]
+    pub direct_message_deep_link: Option<String>,
+    /// Choices of a poll to attach (2 to 4, up to 25 characters each)
+    #[serde(default)]
+    pub poll_options: Option<Vec<String>>,
+    /// How long the poll stays open, 5 to 10080 minutes (default: 1440, one day)
+    #[serde(default)]
+    pub poll_duration_minutes: Option<u32>,
+    /// Who can reply: "everyone" (default), "following", "mentioned_users",
+    /// "subscribers" or "verified"
+    #[serde(default)]
+    pub reply_settings: Option<String>,
+    /// Any unique string making the call safe to retry: repeating it with the
+    /// same key returns the tweet posted the first time instead of posting again
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Poll duration when `poll_options` are given without `poll_duration_minutes`
+const DEFAULT_POLL_DURATION_MINUTES: u32 = 24 * 60;
+
+impl PostTweetArgs {
+    /// Build the create-tweet request body for these arguments
+    ///
+    /// Tweet URLs are turned into IDs; values that are neither are kept as
+    /// given so validation can report them.
+    pub fn to_request(&self) -> PostTweetRequest {
+        let tweet_id = |input: &String| parse_tweet_id(input).unwrap_or_else(|| input.clone());
+        PostTweetRequest {
+            text: self.text.clone(),
+            reply: self.reply_to.as_ref().map(|input| TweetReply {
+                in_reply_to_tweet_id: tweet_id(input),
+                ..Default::default()
+            }),
+            quote_tweet_id: self.quote_tweet_id.as_ref().map(tweet_id),
+            media: (!self.media_ids.is_empty() || !self.tagged_user_ids.is_empty()).then(|| {
+                TweetMedia {
+                    media_ids: self.media_ids.clone(),
+                    tagged_user_ids: (!self.tagged_user_ids.is_empty())
+                        .then(|| self.tagged_user_ids.clone()),
                 }
-                Err(e) => {
-                    let result = json!({
-                        "success": false,
-                        "error": format!("Error: {}", e)
-                    });
-                    return Ok(CallToolResult::success(vec![Content::text(
-                        serde_json::to_string_pretty(&result).unwrap_or_default(),
-                    )]));
+            }),
+            poll: (self.poll_options.is_some() || self.poll_duration_minutes.is_some()).then(|| {
+                TweetPoll {
+                    options: self.poll_options.clone().unwrap_or_default(),
+                    duration_minutes: self.poll_duration_minutes.unwrap_or(DEFAULT_POLL_DURATION_MINUTES),
                 }
-            }
-        };
-
-        match self
-            .client
-            .get_user_tweets(&user_id, Some(args.max_results.min(100)))
-            .await
-        {
-            Ok(tweets) => {
-                let result = json!({
-                    "success": true,
-                    "tweets": tweets,
-                    "count": tweets.len(),
-                    "user_id": user_id
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
-            Err(e) => {
-                let result = json!({
-                    "success": false,
-                    "error": format!("Error: {}", e)
-                });
-                Ok(CallToolResult::success(vec![Content::text(
-                    serde_json::to_string_pretty(&result).unwrap_or_default(),
-                )]))
-            }
+            }),
+            card_uri: self.card_uri.clone(),
+            direct_message_deep_link: self.direct_message_deep_link.clone(),
+            reply_settings: self.reply_settings.as_deref().and_then(reply_settings_value),
+            ..Default::default()
         }
     }
 }
 
-#[tool_handler]
-impl ServerHandler for XMcpServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            protocol_version: ProtocolVersion::V_2024_11_05,
-            capabilities: ServerCapabilities::builder()
-                .enable_tools()
-                .build(),
-            server_info: Implementation {
-                name: "x-mcp-server".to_string(),
-                version: crate::VERSION.to_string(),
-            },
-            instructions: Some("This server provides X (Twitter) API tools for read-only operations. Available tools: get_user, search_tweets, get_tweet, get_user_tweets.".to_string()),
-        }
-    }
+/// Tool arguments for listing trend locations
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListTrendLocationsArgs {
+    /// Place name to look for, e.g. "Tokyo" or "Brazil" (case-insensitive).
+    /// Omit to list every location
+    #[serde(default)]
+    pub query: Option<String>,
+}
 
-    async fn initialize(
-        &self,
-        _request: InitializeRequestParam,
-        _context: RequestContext<RoleServer>,
-    ) -> Result<InitializeResult, McpError> {
-        Ok(self.get_info())
+/// Tool arguments for fetching the trends at a place
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct GetTrendsArgs {
+    /// WOEID of the place, from list_trend_locations (default: 1, worldwide)
+    #[serde(default = "default_woeid")]
+    pub woeid: u32,
+}
+
+/// Tool arguments for polling the filtered stream
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PollStreamArgs {
+    /// Most tweets to return; the rest stay buffered for the next poll
+    /// (default: 100, max: 1000)
+    #[serde(default = "default_max_users")]
+    pub max_results: u32,
+    /// Decode HTML entities such as &amp; in tweet text (default: true)
+    #[serde(default = "default_true")]
+    pub decode_html: bool,
+}
+
+/// Tool arguments for adding a filtered stream rule
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct AddStreamRuleArgs {
+    /// The rule, in search query syntax, e.g. "from:rustlang has:links"
+    pub value: String,
+    /// Label for the rule
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+/// Tool arguments for deleting filtered stream rules
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeleteStreamRulesArgs {
+    /// IDs of the rules to delete, from get_stream_rules
+    pub rule_ids: Vec<String>,
+}
+
+/// Tool arguments for the server status
+#[derive(Debug, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StatusArgs {
+    /// Also fetch the remaining request budget of every X endpoint. Takes one
+    /// API request, which has a rate limit of its own
+    #[serde(default)]
+    pub include_rate_limits: bool,
+}
+
+/// Tool arguments for posting a thread
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PostThreadArgs {
+    /// Text of each tweet in the thread, in order
+    pub texts: Vec<String>,
+    /// Resume token from a previous partially posted attempt with the same texts
+    #[serde(default)]
+    pub resume_token: Option<String>,
+}
+
+/// Tool arguments for deleting a tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeleteTweetArgs {
+    /// Tweet to delete, as an ID or a tweet URL
+    pub tweet_id: String,
+    /// Text the tweet is expected to have; it is not deleted when the text differs
+    #[serde(default)]
+    pub expected_text: Option<String>,
+    /// Fetch the tweet before deleting it and return its text. Set to false to
+    /// skip the extra request (always fetched when expected_text is given)
+    #[serde(default = "default_true")]
+    pub fetch_first: bool,
+}
+
+/// Tool arguments naming the user to act on
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserTargetArgs {
+    /// Username (without @) or user ID
+    pub identifier: String,
+    /// Whether the identifier is a user ID (true) or username (false)
+    #[serde(default)]
+    pub is_user_id: bool,
+}
+
+/// Tool arguments for bookmarking or unbookmarking a tweet
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BookmarkArgs {
+    /// Tweet to bookmark or unbookmark, as an ID or a tweet URL
+    pub tweet_id: String,
+}
+
+/// Tool arguments for hiding or unhiding a reply
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HideReplyArgs {
+    /// Reply to one of your tweets, as an ID or a tweet URL
+    pub tweet_id: String,
+    /// Hide the reply (true) or show it again (false) (default: true)
+    #[serde(default = "default_true")]
+    pub hidden: bool,
+}
+
+/// Tool arguments for creating a list
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CreateListArgs {
+    /// Name of the list, 1 to 25 characters
+    pub name: String,
+    /// Description shown on the list
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Whether only you can see the list (default: false)
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// Tool arguments for adding a user to a list or removing them from it
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListMemberArgs {
+    /// ID of a list you own
+    pub list_id: String,
+    /// Username (without @) or user ID of the member
+    pub identifier: String,
+    /// Whether the identifier is a user ID (true) or username (false)
+    #[serde(default)]
+    pub is_user_id: bool,
+}
+
+/// Tool arguments for sending a Direct Message
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SendDmArgs {
+    /// Recipient's username (without @) or user ID
+    pub identifier: String,
+    /// Whether the identifier is a user ID (true) or username (false)
+    #[serde(default)]
+    pub is_user_id: bool,
+    /// Text of the message
+    pub text: String,
+}
+
+fn default_max_results() -> u32 {
+    10
+}
+
+fn default_max_lists() -> u32 {
+    100
+}
+
+fn default_woeid() -> u32 {
+    1
+}
+
+fn default_max_users() -> u32 {
+    100
+}
+
+fn default_max_pages() -> u32 {
+    10
+}
+
+fn default_max_depth() -> u32 {
+    10
+}
+
+fn default_max_tweets() -> u32 {
+    100
+}
+
+fn default_report_tweets() -> u32 {
+    20
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Safe-mode filtering asked for by a tool call's `exclude_sensitive` and
+/// `blocklist` arguments
+struct ContentFilter {
+    exclude_sensitive: bool,
+    /// Lowercased, non-empty terms
+    blocklist: Vec<String>,
+}
+
+impl ContentFilter {
+    /// The filter, or `None` when the arguments ask for no filtering
+    fn new(exclude_sensitive: bool, blocklist: Option<&[String]>) -> Option<Self> {
+        let blocklist: Vec<String> = blocklist
+            .unwrap_or_default()
+            .iter()
+            .map(|term| term.trim().to_lowercase())
+            .filter(|term| !term.is_empty())
+            .collect();
+        (exclude_sensitive || !blocklist.is_empty()).then_some(Self {
+            exclude_sensitive,
+            blocklist,
+        })
+    }
+
+    fn blocks(&self, tweet: &Tweet) -> bool {
+        if self.exclude_sensitive && tweet.possibly_sensitive == Some(true) {
+            return true;
+        }
+        let text = tweet.text.to_lowercase();
+        self.blocklist.iter().any(|term| text.contains(term))
+    }
+
+    /// Drop the blocked tweets, returning how many were dropped
+    ///
+    /// Runs on finished views, after text decoding, so terms match the text
+    /// as returned.
+    fn apply(filter: Option<&Self>, tweets: &mut Vec<TweetView>) -> Option<usize> {
+        let filter = filter?;
+        let before = tweets.len();
+        tweets.retain(|view| !filter.blocks(&view.tweet));
+        Some(before - tweets.len())
+    }
+}
+
+/// How the fetching tools present tweets
+struct TweetFormat {
+    decode_html: bool,
+    /// Timezone of the readable times, when they were asked for
+    timezone: Option<FixedOffset>,
+    now: DateTime<Utc>,
+}
+
+impl TweetFormat {
+    /// Apply the format to each tweet
+    fn apply<'a>(&self, tweets: impl IntoIterator<Item = &'a mut Tweet>) {
+        for tweet in tweets {
+            self.apply_one(tweet);
+        }
+    }
+
+    fn apply_one(&self, tweet: &mut Tweet) {
+        if self.decode_html {
+            tweet.text = tweet.decoded_text();
+        }
+        if let Some(timezone) = self.timezone {
+            tweet.add_readable_times(timezone, self.now);
+        }
+    }
+}
+
+/// The `since_id` and `until_id` arguments of a tool call, checked to be
+/// tweet IDs
+fn tweet_id_range(since_id: Option<String>, until_id: Option<String>) -> Result<TweetIdRange, String> {
+    for (name, id) in [("since_id", &since_id), ("until_id", &until_id)] {
+        if let Some(id) = id.as_deref().filter(|id| !is_tweet_id(id)) {
+            return Err(format!("Error: {} must be a tweet ID (got {:?})", name, id));
+        }
+    }
+    Ok(TweetIdRange { since_id, until_id })
+}
+
+/// Run a tool body, attaching the X API responses it received when `raw` is set
+async fn with_raw<T>(
+    raw: bool,
+    body: impl std::future::Future<Output = ToolOutput<T>>,
+) -> ToolOutput<T> {
+    if !raw {
+        return body.await;
+    }
+    let (mut output, responses) = capture_raw_responses(body).await;
+    output.raw = Some(responses);
+    output
+}
+
+/// Reporter that forwards client progress to the MCP client as
+/// `notifications/progress` under `token`
+///
+/// The returned task ends once the reporter is dropped and every
+/// notification has been sent.
+fn progress_forwarder(
+    token: ProgressToken,
+    peer: Peer<RoleServer>,
+) -> (impl Fn(Progress) + Send + Sync + 'static, JoinHandle<()>) {
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<Progress>();
+    let forwarder = tokio::spawn(async move {
+        while let Some(progress) = receiver.recv().await {
+            let param = ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress: progress.completed.into(),
+                total: progress.total.map(f64::from),
+                message: Some(progress.message),
+            };
+            if let Err(e) = peer.notify_progress(param).await {
+                tracing::debug!("Failed to send progress notification: {}", e);
+                break;
+            }
+        }
+    });
+    let report = move |progress| {
+        let _ = sender.send(progress);
+    };
+    (report, forwarder)
+}
+
+/// Envelope shared by every tool result: `{ "success": true, ...data }` on
+/// success or `{ "success": false, "error": "..." }` on failure
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ToolOutput<T> {
+    /// Whether the operation succeeded
+    pub success: bool,
+    /// Tool-specific payload, present on success
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    /// Error message, present on failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Unmodified X API responses, in request order, when the tool was
+    /// called with `raw: true`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<Vec<serde_json::Value>>,
+}
+
+impl<T> ToolOutput<T> {
+    /// Create a successful output
+    pub fn ok(data: T) -> Self {
+        Self {
+            success: true,
+            data: Some(data),
+            error: None,
+            raw: None,
+        }
+    }
+
+    /// Create a failed output
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: None,
+            error: Some(message.into()),
+            raw: None,
+        }
+    }
+
+    /// Create a failed output that still carries the work done before the failure
+    pub fn partial(data: T, message: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            data: Some(data),
+            error: Some(message.into()),
+            raw: None,
+        }
+    }
+}
+
+impl<T: Serialize> From<ToolOutput<T>> for CallToolResult {
+    /// Emit the output both as pretty-printed text, for clients without
+    /// structured output support, and as `structuredContent`; failed outputs
+    /// set `isError`
+    fn from(output: ToolOutput<T>) -> Self {
+        let value = serde_json::to_value(&output).unwrap_or_default();
+        let text = serde_json::to_string_pretty(&value).unwrap_or_default();
+        let content = vec![Content::text(text)];
+        let mut result = if output.success {
+            CallToolResult::success(content)
+        } else {
+            CallToolResult::error(content)
+        };
+        result.structured_content = Some(value);
+        result
+    }
+}
+
+/// Priority a tool call waits for the call budget with: the request's
+/// `_meta.priority` when valid, otherwise high for posting tools
+fn call_priority(name: &str, meta: &Meta) -> Priority {
+    let requested = meta.get("priority").and_then(|value| value.as_str());
+    if let Some(requested) = requested {
+        match Priority::parse(requested) {
+            Some(priority) => return priority,
+            None => tracing::warn!("Ignoring unknown call priority {:?}", requested),
+        }
+    }
+    if WRITE_TOOLS.contains(&name) {
+        Priority::High
+    } else {
+        Priority::Normal
+    }
+}
+
+/// Span covering a tool call, with the tool name and argument names
+fn tool_call_span(request: &CallToolRequestParam) -> tracing::Span {
+    // Argument names only: values can hold tweet text or other content
+    let arg_keys = request
+        .arguments
+        .iter()
+        .flat_map(|arguments| arguments.keys())
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(",");
+    tracing::info_span!("tool_call", tool = %request.name, args = %arg_keys)
+}
+
+/// The error a failed tool call reported, from its output envelope or the
+/// protocol error
+fn tool_error(result: &Result<CallToolResult, McpError>) -> serde_json::Value {
+    match result {
+        Ok(result) => result
+            .structured_content
+            .as_ref()
+            .and_then(|content| content.get("error"))
+            .cloned()
+            .unwrap_or_default(),
+        Err(e) => serde_json::Value::String(e.message.to_string()),
+    }
+}
+
+/// Replace the pretty-printed text content of a result with compact JSON
+fn compact_text(mut result: CallToolResult) -> CallToolResult {
+    if let Some(value) = &result.structured_content {
+        let text = serde_json::to_string(value).unwrap_or_default();
+        result.content = vec![Content::text(text)];
+    }
+    result
+}
+
+/// Output of the `get_user` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserData {
+    pub user: User,
+}
+
+/// Output of the `status` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StatusData {
+    /// Server version
+    pub version: String,
+    /// Circuit breaker guarding X API requests
+    pub circuit: CircuitStatus,
+    /// X API requests currently in flight
+    pub in_flight: usize,
+    pub max_concurrency: usize,
+    /// Request budget per endpoint path, when include_rate_limits was set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limits: Option<BTreeMap<String, RateLimit>>,
+    /// Why the rate limits could not be fetched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limits_error: Option<String>,
+    /// Use of the session's call budget, when one is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduler: Option<SchedulerStatus>,
+}
+
+/// Output of the `search_users` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UsersData {
+    pub users: Vec<User>,
+    pub count: usize,
+}
+
+/// Output of the `get_tweet` and `post_tweet` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TweetData {
+    pub tweet: TweetView,
+    /// Set by `post_tweet` when an earlier call with the same idempotency_key
+    /// already posted the tweet
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub already_posted: bool,
+}
+
+/// Output of the `get_pinned_tweet` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct PinnedTweetData {
+    /// The pinned tweet, or null when the user has none
+    pub pinned_tweet: Option<Tweet>,
+}
+
+/// Output of the `search_tweets` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TweetsData {
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    /// Tweets dropped by `exclude_sensitive` or `blocklist`; present when
+    /// either was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered: Option<usize>,
+    /// Pass as next_token with the same query to fetch more results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+}
+
+/// Output of the `post_thread` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ThreadData {
+    /// Tweets posted by this call, in thread order
+    pub tweets: Vec<Tweet>,
+    pub count: usize,
+    /// Present when the thread stopped early; pass it back with the same
+    /// texts to continue
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_token: Option<String>,
+}
+
+/// Output of the `delete_tweet` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeleteTweetData {
+    pub tweet_id: String,
+    pub deleted: bool,
+    /// Text of the tweet before it was deleted, when it was fetched first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub was_text: Option<String>,
+}
+
+/// Output of the `bookmark_tweet` and `unbookmark_tweet` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BookmarkData {
+    pub tweet_id: String,
+    /// Whether the tweet is now bookmarked
+    pub bookmarked: bool,
+}
+
+/// Output of the `hide_reply` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HideReplyData {
+    pub tweet_id: String,
+    /// Whether the reply is now hidden, as reported by X
+    pub hidden: bool,
+}
+
+/// Output of the `create_list` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct CreatedListData {
+    /// ID of the new list
+    pub list_id: String,
+    pub name: String,
+}
+
+/// Output of the `add_list_member` and `remove_list_member` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListMemberData {
+    pub list_id: String,
+    /// ID the identifier resolved to
+    pub user_id: String,
+    /// Whether the user is now a member of the list
+    pub is_member: bool,
+}
+
+/// Output of the `send_dm` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SentDmData {
+    /// ID the identifier resolved to
+    pub recipient_id: String,
+    /// Conversation the message was added to
+    pub dm_conversation_id: String,
+    /// ID of the message event
+    pub dm_event_id: String,
+}
+
+/// Output of the `follow_user` and `unfollow_user` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FollowData {
+    /// ID the identifier resolved to
+    pub user_id: String,
+    /// Whether you now follow the user
+    pub following: bool,
+    /// Set when the user is protected and has yet to accept the follow request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_follow: Option<bool>,
+}
+
+/// Output of the `block_user` and `unblock_user` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BlockData {
+    /// ID the identifier resolved to
+    pub user_id: String,
+    /// Whether you now block the user
+    pub blocking: bool,
+}
+
+/// Output of the `mute_user` and `unmute_user` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct MuteData {
+    /// ID the identifier resolved to
+    pub user_id: String,
+    /// Whether you now mute the user
+    pub muting: bool,
+}
+
+/// Output of the `compose_reply` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ComposeReplyData {
+    pub original_tweet: Tweet,
+    /// Author of the original tweet, when X returned it
+    pub author: Option<User>,
+    /// Latest replies in the conversation, newest first
+    pub recent_replies: Vec<Tweet>,
+    pub suggested_reply_settings: SuggestedReplySettings,
+}
+
+/// Output of the `profile_report` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ProfileReportData {
+    pub user: User,
+    /// Latest original tweets, newest first
+    pub recent_tweets: Vec<Tweet>,
+    /// The sampled tweet with the most likes, retweets, replies and quotes
+    pub top_tweet: Option<Tweet>,
+    pub stats: ProfileStats,
+}
+
+/// Engagement averaged over the tweets of a `profile_report`
+#[derive(Debug, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ProfileStats {
+    pub sampled_tweets: usize,
+    pub average_likes: f64,
+    pub average_retweets: f64,
+    pub average_replies: f64,
+    pub average_quotes: f64,
+}
+
+impl ProfileReportData {
+    fn new(user: User, recent_tweets: Vec<Tweet>) -> Self {
+        let metrics: Vec<TweetMetrics> = recent_tweets
+            .iter()
+            .map(|tweet| tweet.public_metrics.clone().unwrap_or_default())
+            .collect();
+        let average = |count: fn(&TweetMetrics) -> u64| {
+            if metrics.is_empty() {
+                0.0
+            } else {
+                metrics.iter().map(count).sum::<u64>() as f64 / metrics.len() as f64
+            }
+        };
+        let stats = ProfileStats {
+            sampled_tweets: metrics.len(),
+            average_likes: average(|m| m.like_count),
+            average_retweets: average(|m| m.retweet_count),
+            average_replies: average(|m| m.reply_count),
+            average_quotes: average(|m| m.quote_count),
+        };
+
+        // Ties go to the newest tweet
+        let top_tweet = recent_tweets
+            .iter()
+            .zip(&metrics)
+            .rev()
+            .max_by_key(|(_, m)| m.like_count + m.retweet_count + m.reply_count + m.quote_count)
+            .map(|(tweet, _)| tweet.clone());
+
+        Self {
+            user,
+            recent_tweets,
+            top_tweet,
+            stats,
+        }
+    }
+}
+
+/// How to post a reply to a tweet with `post_tweet`
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SuggestedReplySettings {
+    /// Value for the `reply_to` argument of `post_tweet`
+    pub reply_to: String,
+    /// Handle of the author to address, e.g. "@jack"
+    pub mention: Option<String>,
+    /// Who the original author allows to reply (`everyone` unless restricted);
+    /// replies from others are rejected by X
+    pub reply_settings: String,
+    /// Maximum weighted length of the reply
+    pub max_length: usize,
+}
+
+impl ComposeReplyData {
+    fn new(context: ReplyContext) -> Self {
+        let suggested_reply_settings = SuggestedReplySettings {
+            reply_to: context.tweet.id.clone(),
+            mention: context
+                .author
+                .as_ref()
+                .map(|author| format!("@{}", author.username)),
+            reply_settings: context
+                .tweet
+                .reply_settings
+                .clone()
+                .unwrap_or_else(|| "everyone".to_string()),
+            max_length: MAX_TWEET_LENGTH,
+        };
+        Self {
+            original_tweet: context.tweet,
+            author: context.author,
+            recent_replies: context.replies,
+            suggested_reply_settings,
+        }
+    }
+}
+
+/// Output of the `list_trend_locations` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TrendLocationsData {
+    /// Location best matching the query, if one was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_match: Option<TrendLocation>,
+    pub locations: Vec<TrendLocation>,
+    pub count: usize,
+}
+
+/// Output of the `get_trends` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TrendsData {
+    pub woeid: u32,
+    /// Trending topics, most popular first
+    pub trends: Vec<Trend>,
+    pub count: usize,
+}
+
+/// Output of the `poll_stream` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StreamData {
+    /// Tweets received since the previous poll, oldest first
+    pub tweets: Vec<Tweet>,
+    pub count: usize,
+    /// Whether the stream is connected; the first poll only starts connecting
+    pub connected: bool,
+    /// Tweets dropped since the previous poll because too many were waiting
+    pub dropped: usize,
+    /// Why the stream disconnected since the previous poll; the next poll
+    /// reconnects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Output of the `get_stream_rules` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StreamRulesData {
+    pub rules: Vec<StreamRule>,
+    pub count: usize,
+}
+
+/// Output of the `add_stream_rule` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StreamRuleData {
+    pub rule: StreamRule,
+}
+
+/// Output of the `delete_stream_rules` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct DeletedStreamRulesData {
+    /// How many rules X deleted
+    pub deleted: u64,
+}
+
+/// A tweet with related objects joined in from the response `includes`
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TweetView {
+    #[serde(flatten)]
+    pub tweet: Tweet,
+    /// Author of the tweet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<User>,
+    /// Media attached to the tweet
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<Media>,
+    /// User the tweet replies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_reply_to_user: Option<User>,
+    /// Tweets listed in `referenced_tweets` (replied to, quoted or retweeted),
+    /// with their authors when those were expanded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub referenced: Vec<TweetView>,
+    /// Position in X's result order, starting at 1. Not a relevance score
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<usize>,
+}
+
+impl TweetView {
+    /// Attach the media referenced by the tweet's media keys
+    fn with_media(mut self, media: &[Media]) -> Self {
+        self.media = self
+            .tweet
+            .media_keys()
+            .filter_map(|key| media.iter().find(|m| m.media_key == key))
+            .cloned()
+            .collect();
+        self
+    }
+
+    /// Attach the tweet's author from `users`
+    fn with_author(mut self, users: &[User]) -> Self {
+        self.author = self
+            .tweet
+            .author_id
+            .as_ref()
+            .and_then(|id| users.iter().find(|user| &user.id == id))
+            .cloned();
+        self
+    }
+
+    /// Attach the replied-to user and the referenced tweets found in
+    /// `includes`, each with its author if `includes` has it
+    fn with_references(mut self, includes: &Includes) -> Self {
+        let users = includes.users.as_deref().unwrap_or_default();
+        self.in_reply_to_user = self.tweet.in_reply_to_user_id.as_ref().and_then(|id| {
+            includes
+                .users
+                .iter()
+                .flatten()
+                .find(|user| &user.id == id)
+                .cloned()
+        });
+        self.referenced = self
+            .tweet
+            .referenced_tweets
+            .iter()
+            .flatten()
+            .filter_map(|reference| {
+                includes
+                    .tweets
+                    .iter()
+                    .flatten()
+                    .find(|t| t.id == reference.id)
+            })
+            .map(|tweet| TweetView::from(tweet.clone()).with_author(users))
+            .collect();
+        self
+    }
+}
+
+impl From<Tweet> for TweetView {
+    fn from(tweet: Tweet) -> Self {
+        Self {
+            tweet,
+            author: None,
+            media: Vec::new(),
+            in_reply_to_user: None,
+            referenced: Vec::new(),
+            rank: None,
+        }
+    }
+}
+
+/// Output of the `get_quote_tweets` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct QuoteTweetsData {
+    /// Tweets quoting the requested tweet, with their authors
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    /// Pass as pagination_token to fetch more quotes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+}
+
+/// Output of the `get_bookmarks` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct BookmarksData {
+    /// Bookmarked tweets, most recently bookmarked first, with their authors
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    /// Pass as pagination_token to fetch more bookmarks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+}
+
+/// Output of the `get_replies` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct RepliesData {
+    pub conversation_id: String,
+    /// Replies from the last 7 days, newest first; `in_reply_to_user_id`
+    /// names the user each one answers
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+}
+
+/// Output of the `get_list` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListData {
+    pub list: TwitterList,
+}
+
+/// Output of the `get_list_tweets` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct ListTweetsData {
+    /// Tweets by the list's members, newest first, with their authors
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    /// Pass as pagination_token to fetch more tweets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+}
+
+/// Output of the `get_owned_lists`, `get_list_memberships` and
+/// `get_followed_lists` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserListsData {
+    /// ID the identifier resolved to
+    pub user_id: String,
+    pub lists: Vec<TwitterList>,
+    pub count: usize,
+    /// Pass as pagination_token to fetch more lists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+}
+
+/// Output of the `get_users` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UsersLookupData {
+    /// Users found, in the order their usernames were given
+    pub users: Vec<User>,
+    pub count: usize,
+    /// Usernames that could not be looked up, with the reason
+    pub not_found: Vec<MissingUser>,
+}
+
+/// Output of the `get_followers` and `get_following` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserConnectionsData {
+    /// ID the identifier resolved to
+    pub user_id: String,
+    pub users: Vec<User>,
+    pub count: usize,
+}
+
+/// Output of the `get_liking_users` and `get_retweeters` tools
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct TweetEngagementData {
+    pub tweet_id: String,
+    pub users: Vec<User>,
+    pub count: usize,
+}
+
+/// How the users returned by [`XMcpServer::tweet_engagement`] engaged with the tweet
+#[derive(Debug, Clone, Copy)]
+enum Engagement {
+    Likes,
+    Retweets,
+}
+
+/// How the users returned by [`XMcpServer::user_connections`] relate to the user
+#[derive(Debug, Clone, Copy)]
+enum Connection {
+    Followers,
+    Following,
+}
+
+/// How the lists returned by [`XMcpServer::user_lists`] relate to the user
+#[derive(Debug, Clone, Copy)]
+enum ListRelation {
+    Owned,
+    Member,
+    Followed,
+}
+
+/// Output of the `hydrate_tweets` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct HydratedTweetsData {
+    /// Tweets found, each with its author
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    /// Requested IDs that are invalid, deleted or not visible
+    pub missing_ids: Vec<String>,
+}
+
+/// Output of the `get_user_tweets` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserTweetsData {
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    pub user_id: String,
+    /// Tweets dropped by `exclude_sensitive` or `blocklist`; present when
+    /// either was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filtered: Option<usize>,
+}
+
+/// Output of the `get_user_tweets_range` tool
+#[derive(Debug, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct UserTweetsRangeData {
+    /// Tweets in the range, newest first
+    pub tweets: Vec<TweetView>,
+    pub count: usize,
+    pub user_id: String,
+    /// Requested range
+    pub start_time: String,
+    pub end_time: String,
+    /// Creation times of the oldest and newest tweets returned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covered_start: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covered_end: Option<String>,
+    /// Whether every tweet X has in the range was fetched
+    pub complete: bool,
+    /// Pass as pagination_token to continue after the page cap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_token: Option<String>,
+    /// Why the result may not cover the whole range
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+}
+
+/// X MCP Server
+#[derive(Clone)]
+pub struct XMcpServer {
+    client: Arc<dyn XApi>,
+    metrics: Metrics,
+    instructions: String,
+    timezone: FixedOffset,
+    compact_output: bool,
+    scheduler: Option<Arc<Scheduler>>,
+    capabilities: Capabilities,
+    /// Lowest level of log messages the client asked for with
+    /// `logging/setLevel`
+    log_level: Arc<std::sync::Mutex<Option<LoggingLevel>>>,
+    /// Tweets from the filtered stream, shared by every session
+    stream: StreamBuffer,
+    /// Directory `media_paths` are uploaded from
+    #[cfg(feature = "write-ops")]
+    media_dir: Option<PathBuf>,
+    tool_router: ToolRouter<XMcpServer>,
+}
+
+#[tool_router]
+impl XMcpServer {
+    /// Create a new X MCP Server
+    pub fn new(client: XClient) -> Self {
+        let metrics = Metrics::new();
+        Self::build(Arc::new(client.with_metrics(metrics.clone())), metrics)
+    }
+
+    /// Create a server whose tools call `api` instead of an [`XClient`]
+    ///
+    /// Only tool metrics are recorded; X API request metrics come from the
+    /// client.
+    pub fn from_api(api: impl XApi + 'static) -> Self {
+        Self::build(Arc::new(api), Metrics::new())
+    }
+
+    fn build(client: Arc<dyn XApi>, metrics: Metrics) -> Self {
+        Self {
+            client,
+            metrics,
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            timezone: FixedOffset::east_opt(0).expect("zero offset is valid"),
+            compact_output: false,
+            scheduler: None,
+            capabilities: Capabilities::default(),
+            log_level: Arc::default(),
+            stream: StreamBuffer::default(),
+            #[cfg(feature = "write-ops")]
+            media_dir: None,
+            tool_router: Self::tools(),
+        }
+    }
+
+    /// Routes of every tool compiled in
+    fn tools() -> ToolRouter<Self> {
+        #[allow(unused_mut)]
+        let mut router = Self::tool_router();
+        #[cfg(feature = "write-ops")]
+        router.merge(Self::write_tool_router());
+        router
+    }
+
+    /// Override the instructions sent to clients in the initialize response
+    pub fn with_instructions(mut self, instructions: String) -> Self {
+        self.instructions = instructions;
+        self
+    }
+
+    /// Default timezone of the readable tweet times (`human_times`)
+    pub fn with_timezone(mut self, timezone: FixedOffset) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Resolve a username (with or without @) or numeric user ID to an ID
+    async fn resolve_user_id(&self, identifier: &str) -> Result<String, String> {
+        let identifier = identifier.trim().trim_start_matches('@');
+        if !identifier.is_empty() && identifier.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(identifier.to_string());
+        }
+        match self.client.get_user_by_username(identifier).await {
+            Ok(Some(user)) => Ok(user.id),
+            Ok(None) => Err("User not found".to_string()),
+            Err(e) => Err(format!("Error: {}", e)),
+        }
+    }
+
+    /// ID of the user named by a tool's `identifier` and `is_user_id`
+    /// arguments, looking usernames up
+    async fn target_user_id(&self, identifier: &str, is_user_id: bool) -> Result<String, String> {
+        let identifier = identifier.trim();
+        if is_user_id {
+            return Ok(identifier.to_string());
+        }
+        match self
+            .client
+            .get_user_by_username(identifier.trim_start_matches('@'))
+            .await
+        {
+            Ok(Some(user)) => Ok(user.id),
+            Ok(None) => Err("User not found".to_string()),
+            Err(e) => Err(format!("Error: {}", e)),
+        }
+    }
+
+    /// Body of the tools listing a user's followers or followed accounts
+    async fn user_connections(
+        &self,
+        args: GetUserConnectionsArgs,
+        connection: Connection,
+    ) -> ToolOutput<UserConnectionsData> {
+        with_raw(args.output.raw, async {
+            let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+                Ok(user_id) => user_id,
+                Err(e) => return ToolOutput::error(e),
+            };
+            let limit = Some(args.max_results.clamp(1, 5000));
+            let users = match connection {
+                Connection::Followers => self.client.get_followers(&user_id, limit).await,
+                Connection::Following => self.client.get_following(&user_id, limit).await,
+            };
+            match users {
+                Ok(users) => ToolOutput::ok(UserConnectionsData {
+                    user_id,
+                    count: users.len(),
+                    users,
+                }),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await
+    }
+
+    /// Body of the tools listing the users who liked or retweeted a tweet
+    async fn tweet_engagement(
+        &self,
+        args: GetTweetEngagementArgs,
+        engagement: Engagement,
+    ) -> ToolOutput<TweetEngagementData> {
+        with_raw(args.output.raw, async {
+            let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+                return ToolOutput::error(format!(
+                    "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                    args.tweet_id
+                ));
+            };
+            let limit = Some(args.max_results.clamp(1, 1000));
+            let users = match engagement {
+                Engagement::Likes => self.client.get_liking_users(&tweet_id, limit).await,
+                Engagement::Retweets => self.client.get_retweeters(&tweet_id, limit).await,
+            };
+            match users {
+                Ok(users) => ToolOutput::ok(TweetEngagementData {
+                    tweet_id,
+                    count: users.len(),
+                    users,
+                }),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await
+    }
+
+    /// Body of the tools listing a user's lists
+    async fn user_lists(&self, args: GetUserListsArgs, relation: ListRelation) -> ToolOutput<UserListsData> {
+        with_raw(args.output.raw, async {
+            let user_id = match self.resolve_user_id(&args.identifier).await {
+                Ok(user_id) => user_id,
+                Err(e) => return ToolOutput::error(e),
+            };
+            let limit = args.max_results.clamp(1, 500) as usize;
+            let token = args.pagination_token.as_deref();
+            let page = match relation {
+                ListRelation::Owned => self.client.get_owned_lists(&user_id, limit, token).await,
+                ListRelation::Member => self.client.get_list_memberships(&user_id, limit, token).await,
+                ListRelation::Followed => self.client.get_followed_lists(&user_id, limit, token).await,
+            };
+            match page {
+                Ok(page) => ToolOutput::ok(UserListsData {
+                    user_id,
+                    count: page.lists.len(),
+                    lists: page.lists,
+                    next_token: page.next_token,
+                }),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await
+    }
+
+    /// Emit the text content of tool results as compact JSON
+    ///
+    /// Pretty-printed text is easier to read in logs but costs model context;
+    /// `structuredContent` is the same either way.
+    pub fn with_compact_output(mut self, compact_output: bool) -> Self {
+        self.compact_output = compact_output;
+        self
+    }
+
+    /// Choose the capabilities advertised in the initialize response
+    ///
+    /// Requests for a capability left out are answered with "method not
+    /// found".
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Report a failed tool call to a client that enabled logging
+    async fn log_tool_failure(
+        &self,
+        peer: &Peer<RoleServer>,
+        name: &str,
+        result: &Result<CallToolResult, McpError>,
+    ) {
+        let threshold = *self.log_level.lock().unwrap_or_else(|e| e.into_inner());
+        let level = LoggingLevel::Warning;
+        match threshold {
+            Some(threshold) if level_enabled(threshold, level) => {}
+            _ => return,
+        }
+        let error = tool_error(result);
+        let _ = peer
+            .notify_logging_message(LoggingMessageNotificationParam {
+                level,
+                logger: Some("x-mcp-server".to_string()),
+                data: serde_json::json!({ "tool": name, "error": error }),
+            })
+            .await;
+    }
+
+    /// Log the outcome of a tool call in its span, failures at warn level
+    fn log_tool_outcome(
+        span: &tracing::Span,
+        elapsed: std::time::Duration,
+        failed: bool,
+        result: &Result<CallToolResult, McpError>,
+    ) {
+        let elapsed_ms = elapsed.as_millis() as u64;
+        if failed {
+            let error = match tool_error(result) {
+                serde_json::Value::String(error) => error,
+                other => other.to_string(),
+            };
+            tracing::warn!(
+                parent: span,
+                elapsed_ms,
+                success = false,
+                error = redact::content(&error),
+                "Tool call failed"
+            );
+        } else {
+            tracing::info!(parent: span, elapsed_ms, success = true, "Tool call finished");
+        }
+    }
+
+    /// Queue tool calls once `config.budget` calls were made in a window
+    ///
+    /// Posting tools default to [`Priority::High`] and everything else to
+    /// [`Priority::Normal`]; a client can pass `"priority"` in the request
+    /// `_meta` to override it, e.g. `"low"` for background polling.
+    pub fn with_scheduler(mut self, config: SchedulerConfig) -> Self {
+        self.scheduler = Some(Arc::new(Scheduler::new(config)));
+        self
+    }
+
+    /// Tweet presentation for a tool call's output options
+    fn tweet_format(&self, options: &OutputOptions) -> Result<TweetFormat, String> {
+        let timezone = match &options.timezone {
+            Some(timezone) => Some(parse_utc_offset(timezone)?),
+            None => options.human_times.then_some(self.timezone),
+        };
+        Ok(TweetFormat {
+            decode_html: options.decode_html,
+            timezone,
+            now: Utc::now(),
+        })
+    }
+
+    /// Call counts and latencies of tools and X API requests
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Tool and X API metrics in the Prometheus text format
+    pub fn metrics_text(&self) -> String {
+        self.metrics.render()
+    }
+
+    /// Leave out the tools that post or change anything on X, so they are
+    /// neither listed nor callable; calls to them fail with "server is in
+    /// read-only mode" without reaching X
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.tool_router = Self::tools();
+        if read_only {
+            for name in WRITE_TOOLS {
+                self.tool_router.remove_route(name);
+            }
+        }
+        self
+    }
+
+    /// [`with_read_only(true)`](Self::with_read_only)
+    pub fn read_only(self) -> Self {
+        self.with_read_only(true)
+    }
+
+    /// Let `post_tweet` upload `media_paths` from `dir`
+    ///
+    /// Relative paths are resolved against `dir`, and paths that lead outside
+    /// it, through `..` or a symlink, are refused. Without a media directory
+    /// `media_paths` are refused altogether, since any client could otherwise
+    /// publish any image or video file the server can read.
+    #[cfg(feature = "write-ops")]
+    pub fn with_media_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.media_dir = Some(dir.into());
+        self
+    }
+
+    /// Create server from a validated [`ServerConfig`]
+    pub fn from_config(config: &ServerConfig) -> XResult<Self> {
+        if config.redact_content {
+            redact::set_enabled(true);
+        }
+        let client = XClient::from_config(config)?;
+        let server = Self::new(client)
+            .with_read_only(config.read_only)
+            .with_timezone(config.timezone)
+            .with_compact_output(config.compact_output);
+        #[cfg(feature = "write-ops")]
+        let server = match &config.media_dir {
+            Some(dir) => server.with_media_dir(dir),
+            None => server,
+        };
+        Ok(match config.scheduler {
+            Some(scheduler) => server.with_scheduler(scheduler),
+            None => server,
+        })
+    }
+
+    /// Create server from environment variables
+    pub fn from_env() -> XResult<Self> {
+        Self::from_config(&ServerConfig::from_env()?)
+    }
+
+    /// Run the server with stdio transport
+    ///
+    /// Interrupted reads from stdin are retried rather than ending the session,
+    /// and so are malformed messages, which get a JSON-RPC error response.
+    /// Requests are handled concurrently, each response written as soon as it
+    /// is ready, so a slow call does not hold up the ones after it.
+    pub async fn run_stdio(self) -> XResult<()> {
+        let (stdin, stdout) = stdio();
+        let service = self.serve(LineTransport::new(RetryingReader::new(stdin), stdout)).await?;
+        service.waiting().await?;
+        Ok(())
+    }
+
+    /// Run the server as an HTTP service on `addr`
+    ///
+    /// See [`serve_http`](Self::serve_http).
+    #[cfg(feature = "http")]
+    pub async fn run_http(self, addr: SocketAddr) -> XResult<()> {
+        let listener = TcpListener::bind(addr).await?;
+        self.serve_http(listener).await
+    }
+
+    /// Serve MCP's streamable HTTP transport to the connections `listener`
+    /// accepts, until the listener fails for good
+    ///
+    /// Accept errors for a single connection are skipped, and ones caused by
+    /// running out of resources, such as file descriptors, are retried after
+    /// a short pause rather than taking every session down.
+    ///
+    /// Clients POST JSON-RPC messages to any path and get the responses as
+    /// Server-Sent Events. Each client that initializes gets its own session,
+    /// named by the `Mcp-Session-Id` header, with its own log level; the X
+    /// client, metrics and call budget are shared by all sessions. `GET
+    /// /metrics` returns [`metrics_text`](Self::metrics_text) for Prometheus
+    /// to scrape.
+    #[cfg(feature = "http")]
+    pub async fn serve_http(self, listener: TcpListener) -> XResult<()> {
+        use http_body_util::{BodyExt, Full};
+        use hyper::body::{Bytes, Incoming};
+        use hyper_util::rt::TokioIo;
+        use rmcp::transport::streamable_http_server::{
+            session::local::LocalSessionManager, StreamableHttpServerConfig, StreamableHttpService,
+        };
+
+        let metrics = self.metrics.clone();
+        let service = StreamableHttpService::new(
+            move || {
+                let mut session = self.clone();
+                session.log_level = Arc::default();
+                Ok(session)
+            },
+            Arc::new(LocalSessionManager::default()),
+            StreamableHttpServerConfig::default(),
+        );
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => match accept_retry_delay(&e) {
+                    Some(delay) => {
+                        tracing::warn!("Failed to accept an HTTP connection: {}", e);
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    None => return Err(e.into()),
+                },
+            };
+            let (service, metrics) = (service.clone(), metrics.clone());
+            let service = hyper::service::service_fn(move |request: hyper::Request<Incoming>| {
+                let (service, metrics) = (service.clone(), metrics.clone());
+                async move {
+                    if request.method() == hyper::Method::GET && request.uri().path() == "/metrics" {
+                        let response = hyper::Response::builder()
+                            .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+                            .body(Full::new(Bytes::from(metrics.render())).boxed())
+                            .expect("valid response");
+                        return Ok::<_, std::convert::Infallible>(response);
+                    }
+                    Ok(service.handle(request).await)
+                }
+            });
+            tokio::spawn(async move {
+                let connection = hyper::server::conn::http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service);
+                if let Err(e) = connection.await {
+                    tracing::debug!("HTTP connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Get user information by username or user ID
+    #[tool(
+        description = "Get user information by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<UserData>>()
+    )]
+    async fn get_user(
+        &self,
+        Parameters(args): Parameters<GetUserArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let user = if args.is_user_id {
+                self.client.get_user_by_id(&args.identifier).await
+            } else {
+                self.client.get_user_by_username(&args.identifier).await
+            };
+
+            match user {
+                Ok(Some(user)) => ToolOutput::ok(UserData { user }),
+                Ok(None) => ToolOutput::error("User not found"),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get several users by username
+    #[tool(
+        description = "Get user information for several usernames at once (looked up 100 per request). Usernames that cannot be found are listed in not_found with the reason",
+        output_schema = cached_schema_for_type::<ToolOutput<UsersLookupData>>()
+    )]
+    async fn get_users(
+        &self,
+        Parameters(args): Parameters<GetUsersArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            match self.client.get_users_by_usernames(&args.usernames).await {
+                Ok(lookup) => ToolOutput::ok(UsersLookupData {
+                    count: lookup.users.len(),
+                    users: lookup.users,
+                    not_found: lookup.not_found,
+                }),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Search users by name or keyword
+    #[tool(
+        description = "Find accounts by name or keyword when the exact username is unknown. Needs OAuth 1.0a user credentials; returns at most 20 users",
+        output_schema = cached_schema_for_type::<ToolOutput<UsersData>>()
+    )]
+    async fn search_users(
+        &self,
+        Parameters(args): Parameters<SearchUsersArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            match self.client.search_users(&args.query, args.count).await {
+                Ok(users) => ToolOutput::ok(UsersData {
+                    count: users.len(),
+                    users,
+                }),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Search for tweets
+    #[tool(
+        description = "Search for tweets",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetsData>>()
+    )]
+    async fn search_tweets(
+        &self,
+        Parameters(args): Parameters<SearchTweetsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut tweet_fields = vec![
+            "id".to_string(),
+            "text".to_string(),
+            "author_id".to_string(),
+            "created_at".to_string(),
+        ];
+
+        let mut user_fields = vec![];
+        let mut expansions = vec![];
+
+        if args.include_metrics {
+            tweet_fields.push("public_metrics".to_string());
+        }
+
+        if args.exclude_sensitive {
+            tweet_fields.push("possibly_sensitive".to_string());
+        }
+
+        if args.include_users || args.include_references {
+            user_fields.extend(vec![
+                "id".to_string(),
+                "name".to_string(),
+                "username".to_string(),
+            ]);
+        }
+
+        if args.include_users {
+            expansions.push("author_id".to_string());
+        }
+
+        tweet_fields.push("entities".to_string());
+
+        // Media is joined onto the tweets that have it
+        tweet_fields.push("attachments".to_string());
+        expansions.push("attachments.media_keys".to_string());
+
+        if args.include_references {
+            tweet_fields.push("in_reply_to_user_id".to_string());
+            tweet_fields.push("referenced_tweets".to_string());
+            expansions.push("in_reply_to_user_id".to_string());
+            expansions.push("referenced_tweets.id".to_string());
+        }
+
+        let times = [("start_time", args.start_time.as_deref()), ("end_time", args.end_time.as_deref())];
+        let mut range = Vec::new();
+        for (name, time) in times {
+            let Some(time) = time else { continue };
+            match parse_timestamp(time) {
+                Some(time) => range.push(time),
+                None => {
+                    return Ok(ToolOutput::<TweetsData>::error(format!(
+                        "Error: {} must be an RFC 3339 time (got {:?})",
+                        name, time
+                    ))
+                    .into())
+                }
+            }
+        }
+        if let [start_time, end_time] = range[..] {
+            if start_time >= end_time {
+                return Ok(ToolOutput::<TweetsData>::error("Error: start_time must be before end_time").into());
+            }
+        }
+        let ids = match tweet_id_range(args.since_id, args.until_id) {
+            Ok(ids) => ids,
+            Err(e) => return Ok(ToolOutput::<TweetsData>::error(e).into()),
+        };
+
+        // Recent search accepts 10-100 results, so fewer are asked for as 10
+        // and the rest dropped
+        let max_results = args.max_results.clamp(1, 100);
+        let search_params = SearchTweetsParams {
+            query: args.query,
+            max_results: Some(max_results.max(10)),
+            sort_order: args.sort_order,
+            start_time: args.start_time,
+            end_time: args.end_time,
+            since_id: ids.since_id,
+            until_id: ids.until_id,
+            tweet_fields: Some(tweet_fields),
+            user_fields: if user_fields.is_empty() { None } else { Some(user_fields) },
+            expansions: Some(expansions),
+            media_fields: Some(MEDIA_FIELDS.split(',').map(String::from).collect()),
+            next_token: args.next_token,
+        };
+
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let content_filter = ContentFilter::new(args.exclude_sensitive, args.blocklist.as_deref());
+            match self.client.search_tweets_with_includes(search_params).await {
+                Ok((page, includes)) => {
+                    let media = includes.media.as_deref().unwrap_or_default();
+                    let users = includes.users.as_deref().unwrap_or_default();
+                    let mut tweets: Vec<TweetView> = page
+                        .tweets
+                        .into_iter()
+                        .take(max_results as usize)
+                        .enumerate()
+                        .map(|(index, tweet)| {
+                            let mut view = TweetView::from(tweet).with_media(media);
+                            if args.include_users {
+                                view = view.with_author(users);
+                            }
+                            view.rank = Some(index + 1);
+                            if args.include_references {
+                                view.with_references(&includes)
+                            } else {
+                                view
+                            }
+                        })
+                        .collect();
+                    tweet_format.apply(tweets.iter_mut().flat_map(|view| {
+                        std::iter::once(&mut view.tweet).chain(view.referenced.iter_mut().map(|r| &mut r.tweet))
+                    }));
+                    let filtered = ContentFilter::apply(content_filter.as_ref(), &mut tweets);
+                    ToolOutput::ok(TweetsData {
+                        count: tweets.len(),
+                        tweets,
+                        filtered,
+                        next_token: page.next_token,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get a specific tweet by ID
+    #[tool(
+        description = "Get a specific tweet by ID",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetData>>()
+    )]
+    async fn get_tweet(
+        &self,
+        Parameters(args): Parameters<GetTweetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            match self.client.get_tweet_with_media(&args.tweet_id).await {
+                Ok((Some(tweet), media)) => {
+                    let mut tweet = TweetView::from(tweet).with_media(&media);
+                    tweet_format.apply_one(&mut tweet.tweet);
+                    ToolOutput::ok(TweetData {
+                        tweet,
+                        already_posted: false,
+                    })
+                }
+                Ok((None, _)) => ToolOutput::error("Tweet not found"),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get user's recent tweets
+    #[tool(
+        description = "Get user's recent tweets",
+        output_schema = cached_schema_for_type::<ToolOutput<UserTweetsData>>()
+    )]
+    async fn get_user_tweets(
+        &self,
+        Parameters(args): Parameters<GetUserTweetsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let ids = match tweet_id_range(args.since_id.clone(), args.until_id.clone()) {
+                Ok(ids) => ids,
+                Err(e) => return ToolOutput::error(e),
+            };
+            let content_filter = ContentFilter::new(args.exclude_sensitive, args.blocklist.as_deref());
+            // First, get the user to get their ID if we have a username
+            let user_id = if args.is_user_id {
+                args.identifier.clone()
+            } else {
+                match self.client.get_user_by_username(&args.identifier).await {
+                    Ok(Some(user)) => user.id,
+                    Ok(None) => return ToolOutput::error("User not found"),
+                    Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+                }
+            };
+
+            // Only the plain timeline follows pages past X's 100-tweet limit
+            let max_results = Some(args.max_results.min(100));
+            let tweets = if args.media_only {
+                self.client
+                    .get_user_media_tweets(&user_id, max_results, &ids)
+                    .await
+                    .map(|(tweets, media)| {
+                        tweets
+                            .into_iter()
+                            .map(|tweet| TweetView::from(tweet).with_media(&media))
+                            .collect::<Vec<_>>()
+                    })
+            } else if args.include_references {
+                self.client
+                    .get_user_tweets_with_references(&user_id, max_results, &ids)
+                    .await
+                    .map(|(tweets, includes)| {
+                        tweets
+                            .into_iter()
+                            .map(|tweet| TweetView::from(tweet).with_references(&includes))
+                            .collect()
+                    })
+            } else {
+                self.client
+                    .get_user_tweets(&user_id, Some(args.max_results), &ids)
+                    .await
+                    .map(|tweets| tweets.into_iter().map(TweetView::from).collect())
+            };
+
+            match tweets {
+                Ok(mut tweets) => {
+                    tweet_format.apply(tweets.iter_mut().flat_map(|view| {
+                        std::iter::once(&mut view.tweet).chain(view.referenced.iter_mut().map(|r| &mut r.tweet))
+                    }));
+                    let filtered = ContentFilter::apply(content_filter.as_ref(), &mut tweets);
+                    ToolOutput::ok(UserTweetsData {
+                        count: tweets.len(),
+                        tweets,
+                        user_id,
+                        filtered,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get a user's tweets in a date range
+    #[tool(
+        description = "Get everything a user posted between start_time and end_time (RFC 3339), following pages until the range is exhausted or max_pages is hit. Returns the range actually covered; X only serves a user's 3200 most recent tweets, so older parts of the range may be missing (a warning says so)",
+        output_schema = cached_schema_for_type::<ToolOutput<UserTweetsRangeData>>()
+    )]
+    async fn get_user_tweets_range(
+        &self,
+        Parameters(args): Parameters<GetUserTweetsRangeArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let Some(start_time) = parse_timestamp(&args.start_time) else {
+                return ToolOutput::error(format!("Error: start_time must be an RFC 3339 time (got {:?})", args.start_time));
+            };
+            let end_time = match args.end_time.as_deref() {
+                Some(end_time) => match parse_timestamp(end_time) {
+                    Some(end_time) => end_time,
+                    None => return ToolOutput::error(format!("Error: end_time must be an RFC 3339 time (got {:?})", end_time)),
+                },
+                None => Utc::now(),
+            };
+            if start_time >= end_time {
+                return ToolOutput::error("Error: start_time must be before end_time");
+            }
+
+            let identifier = args.identifier.trim().trim_start_matches('@');
+            let user = if !identifier.is_empty() && identifier.bytes().all(|b| b.is_ascii_digit()) {
+                self.client.get_user_by_id(identifier).await
+            } else {
+                self.client.get_user_by_username(identifier).await
+            };
+            let user = match user {
+                Ok(Some(user)) => user,
+                Ok(None) => return ToolOutput::error("User not found"),
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+
+            let max_pages = args.max_pages.clamp(1, 32) as usize;
+            let page = match self
+                .client
+                .get_user_tweets_between(&user.id, start_time, end_time, max_pages, args.pagination_token.as_deref())
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+
+            let covered_end = page.tweets.first().and_then(|tweet| tweet.created_at.clone());
+            let covered_start = page.tweets.last().and_then(|tweet| tweet.created_at.clone());
+            let complete = page.next_token.is_none();
+            let tweet_count = user.public_metrics.as_ref().map_or(0, |metrics| metrics.tweet_count);
+            let reaches_start = covered_start
+                .as_deref()
+                .and_then(parse_timestamp)
+                .is_some_and(|covered| covered - start_time < chrono::Duration::days(1));
+            let warning = if !complete {
+                Some(format!(
+                    "Stopped after {} pages; pass next_token as pagination_token to continue",
+                    max_pages
+                ))
+            } else if tweet_count > TIMELINE_DEPTH && !reaches_start {
+                Some(format!(
+                    "X only serves a user's {} most recent tweets and @{} has posted {}; tweets before {} may be missing",
+                    TIMELINE_DEPTH,
+                    user.username,
+                    tweet_count,
+                    covered_start.as_deref().unwrap_or("the end of the range")
+                ))
+            } else {
+                None
+            };
+
+            let mut tweets: Vec<TweetView> = page.tweets.into_iter().map(TweetView::from).collect();
+            tweet_format.apply(tweets.iter_mut().map(|view| &mut view.tweet));
+            ToolOutput::ok(UserTweetsRangeData {
+                count: tweets.len(),
+                tweets,
+                user_id: user.id,
+                start_time: start_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                end_time: end_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                covered_start,
+                covered_end,
+                complete,
+                next_token: page.next_token,
+                warning,
+            })
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get a user's pinned tweet
+    #[tool(
+        description = "Get the tweet a user has pinned to their profile, by username or user ID. Returns a null pinned_tweet when nothing is pinned",
+        output_schema = cached_schema_for_type::<ToolOutput<PinnedTweetData>>()
+    )]
+    async fn get_pinned_tweet(
+        &self,
+        Parameters(args): Parameters<GetPinnedTweetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            match self.client.get_pinned_tweet(&args.identifier).await {
+                Ok(mut pinned_tweet) => {
+                    tweet_format.apply(&mut pinned_tweet);
+                    ToolOutput::ok(PinnedTweetData { pinned_tweet })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get a list's details
+    #[tool(
+        description = "Get a list by ID: its name, description, owner, whether it is private, and its member and follower counts",
+        output_schema = cached_schema_for_type::<ToolOutput<ListData>>()
+    )]
+    async fn get_list(&self, Parameters(args): Parameters<GetListArgs>) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let list_id = args.list_id.trim();
+            if list_id.is_empty() || !list_id.bytes().all(|b| b.is_ascii_digit()) {
+                return ToolOutput::error(format!("Error: list_id must be a numeric list ID (got {:?})", args.list_id));
+            }
+
+            match self.client.get_list(list_id).await {
+                Ok(Some(list)) => ToolOutput::ok(ListData { list }),
+                Ok(None) => ToolOutput::error("List not found"),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get the tweets posted by a list's members
+    #[tool(
+        description = "Get the timeline of a list: recent tweets posted by its members, newest first, with their authors. Up to 500 tweets are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<ListTweetsData>>()
+    )]
+    async fn get_list_tweets(
+        &self,
+        Parameters(args): Parameters<GetListTweetsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let list_id = args.list_id.trim();
+            if list_id.is_empty() || !list_id.bytes().all(|b| b.is_ascii_digit()) {
+                return ToolOutput::error(format!("Error: list_id must be a numeric list ID (got {:?})", args.list_id));
+            }
+
+            let limit = args.max_results.clamp(1, 500) as usize;
+            match self
+                .client
+                .get_list_tweets_up_to(list_id, limit, args.pagination_token.as_deref())
+                .await
+            {
+                Ok(page) => {
+                    let mut tweets: Vec<TweetView> = page
+                        .tweets
+                        .into_iter()
+                        .map(|tweet| TweetView::from(tweet).with_author(&page.authors))
+                        .collect();
+                    tweet_format.apply(tweets.iter_mut().map(|view| &mut view.tweet));
+                    ToolOutput::ok(ListTweetsData {
+                        count: tweets.len(),
+                        tweets,
+                        next_token: page.next_token,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get the users following a user
+    #[tool(
+        description = "Get the users following a user, most recent first, by username or user ID. Up to 5000 users are fetched across pages",
+        output_schema = cached_schema_for_type::<ToolOutput<UserConnectionsData>>()
+    )]
+    async fn get_followers(
+        &self,
+        Parameters(args): Parameters<GetUserConnectionsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.user_connections(args, Connection::Followers).await.into())
+    }
+
+    /// Get the users a user follows
+    #[tool(
+        description = "Get the users a user follows, most recently followed first, by username or user ID. Up to 5000 users are fetched across pages",
+        output_schema = cached_schema_for_type::<ToolOutput<UserConnectionsData>>()
+    )]
+    async fn get_following(
+        &self,
+        Parameters(args): Parameters<GetUserConnectionsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.user_connections(args, Connection::Following).await.into())
+    }
+
+    /// Get the users who liked a tweet
+    #[tool(
+        description = "Get the users who liked a tweet (tweet_id takes an ID or URL). Up to 1000 users are fetched across pages",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetEngagementData>>()
+    )]
+    async fn get_liking_users(
+        &self,
+        Parameters(args): Parameters<GetTweetEngagementArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.tweet_engagement(args, Engagement::Likes).await.into())
+    }
+
+    /// Get the users who retweeted a tweet
+    #[tool(
+        description = "Get the users who retweeted a tweet (tweet_id takes an ID or URL). Up to 1000 users are fetched across pages",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetEngagementData>>()
+    )]
+    async fn get_retweeters(
+        &self,
+        Parameters(args): Parameters<GetTweetEngagementArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.tweet_engagement(args, Engagement::Retweets).await.into())
+    }
+
+    /// Get the lists a user owns
+    #[tool(
+        description = "Get the lists a user owns, by username or user ID. Up to 500 lists are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<UserListsData>>()
+    )]
+    async fn get_owned_lists(
+        &self,
+        Parameters(args): Parameters<GetUserListsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.user_lists(args, ListRelation::Owned).await.into())
+    }
+
+    /// Get the lists a user has been added to
+    #[tool(
+        description = "Get the lists a user has been added to as a member, by username or user ID. Up to 500 lists are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<UserListsData>>()
+    )]
+    async fn get_list_memberships(
+        &self,
+        Parameters(args): Parameters<GetUserListsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.user_lists(args, ListRelation::Member).await.into())
+    }
+
+    /// Get the lists a user follows
+    #[tool(
+        description = "Get the lists a user follows, by username or user ID. Up to 500 lists are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<UserListsData>>()
+    )]
+    async fn get_followed_lists(
+        &self,
+        Parameters(args): Parameters<GetUserListsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(self.user_lists(args, ListRelation::Followed).await.into())
+    }
+
+    /// Fetch tweets by ID with their authors
+    #[tool(
+        description = "Fetch full tweets with their authors from a list of tweet IDs (any number, looked up 100 per request). IDs that cannot be fetched are listed in missing_ids",
+        output_schema = cached_schema_for_type::<ToolOutput<HydratedTweetsData>>()
+    )]
+    async fn hydrate_tweets(
+        &self,
+        Parameters(args): Parameters<HydrateTweetsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            match self.client.get_tweets(&args.tweet_ids).await {
+                Ok(lookup) => {
+                    let mut tweets: Vec<TweetView> = lookup
+                        .tweets
+                        .into_iter()
+                        .map(|tweet| TweetView::from(tweet).with_author(&lookup.authors))
+                        .collect();
+                    tweet_format.apply(tweets.iter_mut().map(|view| &mut view.tweet));
+                    ToolOutput::ok(HydratedTweetsData {
+                        count: tweets.len(),
+                        tweets,
+                        missing_ids: lookup.missing_ids,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get the tweets quoting a tweet
+    #[tool(
+        description = "Get tweets that quote a tweet (tweet_id takes an ID or URL), newest first, with their authors. Up to 500 quotes are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<QuoteTweetsData>>()
+    )]
+    async fn get_quote_tweets(
+        &self,
+        Parameters(args): Parameters<GetQuoteTweetsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+                return ToolOutput::error(format!(
+                    "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                    args.tweet_id
+                ));
+            };
+
+            let limit = args.max_results.clamp(1, 500) as usize;
+            match self
+                .client
+                .get_quote_tweets_up_to(&tweet_id, limit, args.pagination_token.as_deref())
+                .await
+            {
+                Ok(page) => {
+                    let mut tweets: Vec<TweetView> = page
+                        .tweets
+                        .into_iter()
+                        .map(|tweet| TweetView::from(tweet).with_author(&page.authors))
+                        .collect();
+                    tweet_format.apply(tweets.iter_mut().map(|view| &mut view.tweet));
+                    ToolOutput::ok(QuoteTweetsData {
+                        count: tweets.len(),
+                        tweets,
+                        next_token: page.next_token,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get the authenticated user's bookmarks
+    #[tool(
+        description = "Get your bookmarked tweets, most recently bookmarked first, with their authors. Needs an OAuth 2.0 user token with the bookmark.read scope. Up to 500 bookmarks are fetched across pages; pass next_token back as pagination_token for more",
+        output_schema = cached_schema_for_type::<ToolOutput<BookmarksData>>()
+    )]
+    async fn get_bookmarks(
+        &self,
+        Parameters(args): Parameters<GetBookmarksArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let limit = args.max_results.clamp(1, 500) as usize;
+            match self
+                .client
+                .get_bookmarks_up_to(limit, args.pagination_token.as_deref())
+                .await
+            {
+                Ok(page) => {
+                    let mut tweets: Vec<TweetView> = page
+                        .tweets
+                        .into_iter()
+                        .map(|tweet| TweetView::from(tweet).with_author(&page.authors))
+                        .collect();
+                    tweet_format.apply(tweets.iter_mut().map(|view| &mut view.tweet));
+                    ToolOutput::ok(BookmarksData {
+                        count: tweets.len(),
+                        tweets,
+                        next_token: page.next_token,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get the replies in the conversation a tweet started
+    #[tool(
+        description = "Get the latest replies in the conversation a tweet started (tweet_id takes an ID or URL of the first tweet), newest first. Each reply carries in_reply_to_user_id. Only replies from the last 7 days are found",
+        output_schema = cached_schema_for_type::<ToolOutput<RepliesData>>()
+    )]
+    async fn get_replies(
+        &self,
+        Parameters(args): Parameters<GetRepliesArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+                return ToolOutput::error(format!(
+                    "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                    args.tweet_id
+                ));
+            };
+
+            match self
+                .client
+                .get_conversation(&tweet_id, Some(args.max_results.clamp(1, 100)))
+                .await
+            {
+                Ok(mut replies) => {
+                    tweet_format.apply(replies.iter_mut());
+                    let tweets: Vec<TweetView> = replies.into_iter().map(TweetView::from).collect();
+                    ToolOutput::ok(RepliesData {
+                        conversation_id: tweet_id,
+                        count: tweets.len(),
+                        tweets,
+                    })
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Get a conversation as a tree of replies
+    #[tool(
+        description = "Get the discussion a tweet belongs to as a nested tree: the conversation's first tweet with each reply placed under the tweet it answers. Replies to deleted tweets sit under a placeholder with a null tweet. Only replies from the last 7 days are found",
+        output_schema = cached_schema_for_type::<ToolOutput<ConversationTree>>()
+    )]
+    async fn get_conversation_tree(
+        &self,
+        Parameters(args): Parameters<GetConversationTreeArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            match self
+                .client
+                .get_conversation_tree(&args.tweet_id, args.max_depth.min(50), args.max_tweets)
+                .await
+            {
+                Ok(Some(mut tree)) => {
+                    tree.root.for_each_tweet_mut(&mut |tweet| tweet_format.apply_one(tweet));
+                    ToolOutput::ok(tree)
+                }
+                Ok(None) => ToolOutput::error("Tweet not found"),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Gather the context for replying to a tweet
+    #[tool(
+        description = "Gather everything needed to draft a reply to a tweet in one call: the tweet, its author, recent replies in the conversation and settings for post_tweet. Does not post anything",
+        output_schema = cached_schema_for_type::<ToolOutput<ComposeReplyData>>()
+    )]
+    async fn compose_reply(
+        &self,
+        Parameters(args): Parameters<ComposeReplyArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            match self
+                .client
+                .get_reply_context(&args.tweet_id, args.max_replies.min(100))
+                .await
+            {
+                Ok(Some(mut context)) => {
+                    tweet_format.apply(std::iter::once(&mut context.tweet).chain(&mut context.replies));
+                    ToolOutput::ok(ComposeReplyData::new(context))
+                }
+                Ok(None) => ToolOutput::error("Tweet not found"),
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// Summarize an account from its profile and latest tweets
+    #[tool(
+        description = "Summarize an account in one call: the user's profile, their latest original tweets (no retweets or replies), their top tweet by engagement among those, and average likes, retweets, replies and quotes. Uses two API requests",
+        output_schema = cached_schema_for_type::<ToolOutput<ProfileReportData>>()
+    )]
+    async fn profile_report(
+        &self,
+        Parameters(args): Parameters<ProfileReportArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = with_raw(args.output.raw, async {
+            let tweet_format = match self.tweet_format(&args.output) {
+                Ok(tweet_format) => tweet_format,
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+            let username = args.username.trim().trim_start_matches('@');
+            let user = match self.client.get_user_by_username(username).await {
+                Ok(Some(user)) => user,
+                Ok(None) => return ToolOutput::error("User not found"),
+                Err(e) => return ToolOutput::error(format!("Error: {}", e)),
+            };
+
+            match self
+                .client
+                .get_user_original_tweets(&user.id, args.max_tweets.clamp(5, 100))
+                .await
+            {
+                Ok(mut tweets) => {
+                    tweet_format.apply(tweets.iter_mut());
+                    ToolOutput::ok(ProfileReportData::new(user, tweets))
+                }
+                Err(e) => ToolOutput::error(format!("Error: {}", e)),
+            }
+        })
+        .await;
+        Ok(output.into())
+    }
+
+    /// List locations with trending topics and their WOEIDs
+    #[tool(
+        description = "List places X reports trending topics for, with the WOEID needed to fetch their trends. Pass a query such as \"Tokyo\" to find a place by name",
+        output_schema = cached_schema_for_type::<ToolOutput<TrendLocationsData>>()
+    )]
+    async fn list_trend_locations(
+        &self,
+        Parameters(args): Parameters<ListTrendLocationsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let locations = match self.client.get_trend_locations().await {
+            Ok(locations) => locations,
+            Err(e) => {
+                return Ok(ToolOutput::<TrendLocationsData>::error(format!("Error: {}", e)).into())
+            }
+        };
+
+        let data = match args.query.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            Some(query) => {
+                let best_match = self.client.find_woeid(query).await.ok().flatten();
+                let needle = query.to_lowercase();
+                let locations: Vec<TrendLocation> = locations
+                    .into_iter()
+                    .filter(|location| {
+                        location.name.to_lowercase().contains(&needle)
+                            || location.country.to_lowercase().contains(&needle)
+                    })
+                    .collect();
+                TrendLocationsData {
+                    best_match,
+                    count: locations.len(),
+                    locations,
+                }
+            }
+            None => TrendLocationsData {
+                best_match: None,
+                count: locations.len(),
+                locations,
+            },
+        };
+        Ok(ToolOutput::ok(data).into())
+    }
+
+    /// Get the trending topics at a place
+    #[tool(
+        description = "Get the topics trending at a place, most popular first, with their tweet volume when X counts it. Takes the place's WOEID from list_trend_locations (default: 1, worldwide)",
+        output_schema = cached_schema_for_type::<ToolOutput<TrendsData>>()
+    )]
+    async fn get_trends(
+        &self,
+        Parameters(args): Parameters<GetTrendsArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = match self.client.get_trends(args.woeid).await {
+            Ok(trends) => ToolOutput::ok(TrendsData {
+                woeid: args.woeid,
+                count: trends.len(),
+                trends,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Drain the tweets received from the filtered stream
+    #[tool(
+        description = "Get the tweets the filtered stream delivered since the last poll, oldest first. The first call connects to the stream in the background, so poll again for tweets; the stream keeps running between polls and reconnects on the next poll after a disconnect. Which tweets arrive is set by the stream rules (get_stream_rules, add_stream_rule). Needs X API Pro access",
+        output_schema = cached_schema_for_type::<ToolOutput<StreamData>>()
+    )]
+    async fn poll_stream(
+        &self,
+        Parameters(args): Parameters<PollStreamArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let tweet_format = match self.tweet_format(&OutputOptions {
+            decode_html: args.decode_html,
+            ..OutputOptions::default()
+        }) {
+            Ok(tweet_format) => tweet_format,
+            Err(e) => return Ok(ToolOutput::<StreamData>::error(format!("Error: {}", e)).into()),
+        };
+        self.stream.start(Arc::clone(&self.client));
+        let mut poll = self.stream.drain(args.max_results.clamp(1, 1000) as usize);
+        tweet_format.apply(poll.tweets.iter_mut());
+        Ok(ToolOutput::ok(StreamData {
+            count: poll.tweets.len(),
+            tweets: poll.tweets,
+            connected: poll.connected,
+            dropped: poll.dropped,
+            error: poll.error,
+        })
+        .into())
+    }
+
+    /// List the filtered stream rules
+    #[tool(
+        description = "List the rules choosing which tweets the filtered stream (poll_stream) delivers, with their IDs and tags",
+        output_schema = cached_schema_for_type::<ToolOutput<StreamRulesData>>()
+    )]
+    async fn get_stream_rules(&self) -> Result<CallToolResult, McpError> {
+        let output = match self.client.get_stream_rules().await {
+            Ok(rules) => ToolOutput::ok(StreamRulesData {
+                count: rules.len(),
+                rules,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Report the server's health
+    #[tool(
+        description = "Report server health: whether X API requests are paused by the circuit breaker after repeated X failures (and for how long), and how many requests are in flight. With include_rate_limits, also the remaining requests and reset time of every X endpoint, for planning bursts of calls",
+        output_schema = cached_schema_for_type::<ToolOutput<StatusData>>()
+    )]
+    async fn status(
+        &self,
+        Parameters(args): Parameters<StatusArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let (rate_limits, rate_limits_error) = if args.include_rate_limits {
+            match self.client.get_rate_limit_status().await {
+                Ok(limits) => (Some(limits), None),
+                Err(e) => (None, Some(e.to_string())),
+            }
+        } else {
+            (None, None)
+        };
+
+        Ok(ToolOutput::ok(StatusData {
+            version: crate::VERSION.to_string(),
+            circuit: self.client.circuit_status(),
+            in_flight: self.client.in_flight(),
+            max_concurrency: self.client.max_concurrency(),
+            rate_limits,
+            rate_limits_error,
+            scheduler: self.scheduler.as_ref().map(|scheduler| scheduler.status()),
+        })
+        .into())
+    }
+
+    /// Validate a tweet without posting it
+    #[tool(
+        description = "Check a tweet draft without posting it. Takes the same arguments as post_tweet and returns whether X would accept it, its weighted length, remaining characters and warnings",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetValidation>>()
+    )]
+    async fn preview_tweet(
+        &self,
+        Parameters(args): Parameters<PostTweetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        Ok(ToolOutput::ok(validate_tweet(&args.to_request())).into())
+    }
+}
+
+/// Tools that post or change anything on X, listed in [`WRITE_TOOLS`]
+#[cfg(feature = "write-ops")]
+#[tool_router(router = write_tool_router)]
+impl XMcpServer {
+    /// Post a new tweet
+    #[tool(
+        description = "Post a new tweet, optionally as a reply or quote (reply_to and quote_tweet_id take a tweet ID or URL), with media uploaded from media_paths. Tweets are public; confirm the content first (see preview_tweet)",
+        output_schema = cached_schema_for_type::<ToolOutput<TweetData>>()
+    )]
+    async fn post_tweet(
+        &self,
+        Parameters(args): Parameters<PostTweetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let request = args.to_request();
+        // Check the request with a placeholder per file, so nothing is
+        // uploaded for a tweet that X would refuse
+        let mut checked = request.clone();
+        if !args.media_paths.is_empty() {
+            checked
+                .media
+                .get_or_insert_with(Default::default)
+                .media_ids
+                .extend(args.media_paths.iter().map(|_| "0".to_string()));
+        }
+        if let Err(error) = check_tweet_length(&checked.text)
+            .and_then(|()| check_tweet_references(&checked))
+            .and_then(|()| check_tweet_attachments(&checked))
+        {
+            return Ok(ToolOutput::<TweetData>::error(format!("Error: {}", error)).into());
+        }
+        if let Some(warning) = check_reply_settings(&checked) {
+            tracing::warn!("{}", warning);
+        }
+
+        // An idempotency key is checked against the request with the file
+        // paths in place of their media IDs, as a retry would upload the
+        // files under new IDs
+        let mut draft = request.clone();
+        if !args.media_paths.is_empty() {
+            draft
+                .media
+                .get_or_insert_with(Default::default)
+                .media_ids
+                .extend(args.media_paths.iter().cloned());
+        }
+        let upload = async {
+            let mut request = request;
+            if !args.media_paths.is_empty() {
+                let media_ids = self.upload_media_files(&args.media_paths).await.map_err(XError::Generic)?;
+                request
+                    .media
+                    .get_or_insert_with(Default::default)
+                    .media_ids
+                    .extend(media_ids);
+            }
+            Ok(request)
+        };
+        let posted = match args.idempotency_key.as_deref().map(str::trim) {
+            Some(key) if !key.is_empty() => {
+                self.client
+                    .post_tweet_once_with(&draft, key, Box::pin(upload))
+                    .await
+            }
+            _ => match upload.await {
+                Ok(request) => self.client.post_tweet(&request).await.map(|tweet| (tweet, false)),
+                Err(e) => Err(e),
+            },
+        };
+        let output = match posted {
+            Ok((tweet, already_posted)) => ToolOutput::ok(TweetData {
+                tweet: tweet.into(),
+                already_posted,
+            }),
+            // Already reads "Error: ..."
+            Err(e @ XError::Generic(_)) => ToolOutput::error(e.to_string()),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Upload local media files, returning their media IDs in order
+    ///
+    /// Only files in the media directory are read (see
+    /// [`with_media_dir`](Self::with_media_dir)). Every file is read before the first upload, so an unreadable one
+    /// leaves nothing uploaded.
+    async fn upload_media_files(&self, paths: &[String]) -> Result<Vec<String>, String> {
+        let dir = self
+            .media_dir
+            .as_ref()
+            .ok_or("media_paths are disabled; the server needs X_MCP_MEDIA_DIR set")?;
+        let dir = tokio::fs::canonicalize(dir)
+            .await
+            .map_err(|e| format!("cannot read the media directory: {}", e))?;
+        let mut files = Vec::with_capacity(paths.len());
+        for path in paths {
+            let media_type = crate::media::media_type(std::path::Path::new(path))
+                .ok_or_else(|| format!("{} is not a GIF, JPEG, PNG, WebP, MP4 or MOV file", path))?;
+            let file = tokio::fs::canonicalize(dir.join(path))
+                .await
+                .map_err(|e| format!("cannot read {}: {}", path, e))?;
+            if !file.starts_with(&dir) {
+                return Err(format!("{} is outside the media directory", path));
+            }
+            let bytes = tokio::fs::read(&file)
+                .await
+                .map_err(|e| format!("cannot read {}: {}", path, e))?;
+            files.push((media_type, bytes));
+        }
+
+        let mut media_ids = Vec::with_capacity(files.len());
+        for ((media_type, bytes), path) in files.iter().zip(paths) {
+            let media_id = self
+                .client
+                .upload_media(bytes, media_type)
+                .await
+                .map_err(|e| format!("uploading {} failed: {}", path, e))?;
+            media_ids.push(media_id);
+        }
+        Ok(media_ids)
+    }
+
+    /// Post a thread of tweets
+    #[tool(
+        description = "Post a thread: each text becomes a tweet replying to the previous one. Rate limits between tweets are waited out, up to the server's configured maximum wait. If posting stops partway, the result includes the tweets posted so far and a resume_token; call again with the same texts and the token to continue",
+        output_schema = cached_schema_for_type::<ToolOutput<ThreadData>>()
+    )]
+    async fn post_thread(
+        &self,
+        Parameters(args): Parameters<PostThreadArgs>,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let output = match self
+            .client
+            .post_thread_until(&args.texts, args.resume_token.as_deref(), Box::pin(context.ct.cancelled()))
+            .await
+        {
+            Ok(result) => {
+                let data = ThreadData {
+                    count: result.tweets.len(),
+                    tweets: result.tweets,
+                    resume_token: result.resume_token,
+                };
+                match result.error {
+                    Some(error) => ToolOutput::partial(data, format!("Error: {}", error)),
+                    None => ToolOutput::ok(data),
+                }
+            }
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Delete a tweet, checking what it says first
+    #[tool(
+        description = "Delete one of your tweets (tweet_id takes an ID or URL). By default the tweet is fetched first and its text returned as was_text; pass expected_text to refuse the deletion when the tweet says something else",
+        output_schema = cached_schema_for_type::<ToolOutput<DeleteTweetData>>()
+    )]
+    async fn delete_tweet(
+        &self,
+        Parameters(args): Parameters<DeleteTweetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+            return Ok(ToolOutput::<DeleteTweetData>::error(format!(
+                "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                args.tweet_id
+            ))
+            .into());
+        };
+
+        let was_text = if args.fetch_first || args.expected_text.is_some() {
+            let tweet = match self.client.get_tweet(&tweet_id).await {
+                Ok(Some(tweet)) => tweet,
+                Ok(None) => return Ok(ToolOutput::<DeleteTweetData>::error("Tweet not found").into()),
+                Err(e) => {
+                    return Ok(ToolOutput::<DeleteTweetData>::error(format!("Error: {}", e)).into())
+                }
+            };
+            let text = tweet.decoded_text();
+            if let Some(expected) = &args.expected_text {
+                if expected.trim() != text.trim() && expected.trim() != tweet.text.trim() {
+                    return Ok(ToolOutput::<DeleteTweetData>::error(format!(
+                        "Error: Tweet {} does not have the expected text, so it was not deleted. Its text is {:?}",
+                        tweet_id, text
+                    ))
+                    .into());
+                }
+            }
+            Some(text)
+        } else {
+            None
+        };
+
+        let output = match self.client.delete_tweet(&tweet_id).await {
+            Ok(deleted) => ToolOutput::ok(DeleteTweetData {
+                tweet_id,
+                deleted,
+                was_text,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Follow a user
+    #[tool(
+        description = "Follow a user by username or user ID. Following a protected account sends a follow request, reported as pending_follow",
+        output_schema = cached_schema_for_type::<ToolOutput<FollowData>>()
+    )]
+    async fn follow_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<FollowData>::error(e).into()),
+        };
+        let output = match self.client.follow_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(FollowData {
+                user_id,
+                following: status.following,
+                pending_follow: status.pending_follow,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Unfollow a user
+    #[tool(
+        description = "Unfollow a user by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<FollowData>>()
+    )]
+    async fn unfollow_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<FollowData>::error(e).into()),
+        };
+        let output = match self.client.unfollow_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(FollowData {
+                user_id,
+                following: status.following,
+                pending_follow: status.pending_follow,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Block a user
+    #[tool(
+        description = "Block a user by username or user ID. Blocked users cannot follow you or see your tweets while logged in",
+        output_schema = cached_schema_for_type::<ToolOutput<BlockData>>()
+    )]
+    async fn block_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<BlockData>::error(e).into()),
+        };
+        let output = match self.client.block_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(BlockData {
+                user_id,
+                blocking: status.blocking,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Unblock a user
+    #[tool(
+        description = "Unblock a user by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<BlockData>>()
+    )]
+    async fn unblock_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<BlockData>::error(e).into()),
+        };
+        let output = match self.client.unblock_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(BlockData {
+                user_id,
+                blocking: status.blocking,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Mute a user
+    #[tool(
+        description = "Mute a user by username or user ID, hiding their tweets from your timeline without them being told",
+        output_schema = cached_schema_for_type::<ToolOutput<MuteData>>()
+    )]
+    async fn mute_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<MuteData>::error(e).into()),
+        };
+        let output = match self.client.mute_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(MuteData {
+                user_id,
+                muting: status.muting,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Unmute a user
+    #[tool(
+        description = "Unmute a user by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<MuteData>>()
+    )]
+    async fn unmute_user(
+        &self,
+        Parameters(args): Parameters<UserTargetArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<MuteData>::error(e).into()),
+        };
+        let output = match self.client.unmute_user(&user_id).await {
+            Ok(status) => ToolOutput::ok(MuteData {
+                user_id,
+                muting: status.muting,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Bookmark a tweet
+    #[tool(
+        description = "Bookmark a tweet (tweet_id takes an ID or URL). Needs an OAuth 2.0 user token with the bookmark.write scope",
+        output_schema = cached_schema_for_type::<ToolOutput<BookmarkData>>()
+    )]
+    async fn bookmark_tweet(
+        &self,
+        Parameters(args): Parameters<BookmarkArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+            return Ok(ToolOutput::<BookmarkData>::error(format!(
+                "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                args.tweet_id
+            ))
+            .into());
+        };
+        let output = match self.client.bookmark_tweet(&tweet_id).await {
+            Ok(status) => ToolOutput::ok(BookmarkData {
+                tweet_id,
+                bookmarked: status.bookmarked,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Remove a tweet from the bookmarks
+    #[tool(
+        description = "Remove a tweet from your bookmarks (tweet_id takes an ID or URL). Needs an OAuth 2.0 user token with the bookmark.write scope",
+        output_schema = cached_schema_for_type::<ToolOutput<BookmarkData>>()
+    )]
+    async fn unbookmark_tweet(
+        &self,
+        Parameters(args): Parameters<BookmarkArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+            return Ok(ToolOutput::<BookmarkData>::error(format!(
+                "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                args.tweet_id
+            ))
+            .into());
+        };
+        let output = match self.client.remove_bookmark(&tweet_id).await {
+            Ok(status) => ToolOutput::ok(BookmarkData {
+                tweet_id,
+                bookmarked: status.bookmarked,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Hide or unhide a reply
+    #[tool(
+        description = "Hide a reply to one of your tweets from the conversation, or show it again with hidden=false (tweet_id takes an ID or URL). Returns the hidden state X reports",
+        output_schema = cached_schema_for_type::<ToolOutput<HideReplyData>>()
+    )]
+    async fn hide_reply(
+        &self,
+        Parameters(args): Parameters<HideReplyArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let Some(tweet_id) = parse_tweet_id(&args.tweet_id) else {
+            return Ok(ToolOutput::<HideReplyData>::error(format!(
+                "Error: tweet_id must be a tweet ID or tweet URL (got {:?})",
+                args.tweet_id
+            ))
+            .into());
+        };
+        let output = match self.client.set_reply_hidden(&tweet_id, args.hidden).await {
+            Ok(hidden) => ToolOutput::ok(HideReplyData { tweet_id, hidden }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Create a list
+    #[tool(
+        description = "Create a list owned by you, with a name of 1 to 25 characters and an optional description. Lists are public unless private is true. Returns the new list_id",
+        output_schema = cached_schema_for_type::<ToolOutput<CreatedListData>>()
+    )]
+    async fn create_list(
+        &self,
+        Parameters(args): Parameters<CreateListArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if let Err(error) = check_list_name(&args.name) {
+            return Ok(ToolOutput::<CreatedListData>::error(format!("Error: {}", error)).into());
+        }
+        let output = match self
+            .client
+            .create_list(&args.name, args.description.as_deref(), args.private)
+            .await
+        {
+            Ok(list) => ToolOutput::ok(CreatedListData {
+                list_id: list.id,
+                name: list.name,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Add a user to a list
+    #[tool(
+        description = "Add a user to a list you own, by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<ListMemberData>>()
+    )]
+    async fn add_list_member(
+        &self,
+        Parameters(args): Parameters<ListMemberArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let list_id = args.list_id.trim();
+        if list_id.is_empty() || !list_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(ToolOutput::<ListMemberData>::error(format!(
+                "Error: list_id must be a numeric list ID (got {:?})",
+                args.list_id
+            ))
+            .into());
+        }
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<ListMemberData>::error(e).into()),
+        };
+        let output = match self.client.add_list_member(list_id, &user_id).await {
+            Ok(status) => ToolOutput::ok(ListMemberData {
+                list_id: list_id.to_string(),
+                user_id,
+                is_member: status.is_member,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Remove a user from a list
+    #[tool(
+        description = "Remove a user from a list you own, by username or user ID",
+        output_schema = cached_schema_for_type::<ToolOutput<ListMemberData>>()
+    )]
+    async fn remove_list_member(
+        &self,
+        Parameters(args): Parameters<ListMemberArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let list_id = args.list_id.trim();
+        if list_id.is_empty() || !list_id.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(ToolOutput::<ListMemberData>::error(format!(
+                "Error: list_id must be a numeric list ID (got {:?})",
+                args.list_id
+            ))
+            .into());
+        }
+        let user_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(user_id) => user_id,
+            Err(e) => return Ok(ToolOutput::<ListMemberData>::error(e).into()),
+        };
+        let output = match self.client.remove_list_member(list_id, &user_id).await {
+            Ok(status) => ToolOutput::ok(ListMemberData {
+                list_id: list_id.to_string(),
+                user_id,
+                is_member: status.is_member,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Add a filtered stream rule
+    #[tool(
+        description = "Add a rule to the filtered stream, in search query syntax (e.g. \"from:rustlang has:links\"), with an optional tag. Tweets matching any rule arrive through poll_stream. X rejects invalid and duplicate rules",
+        output_schema = cached_schema_for_type::<ToolOutput<StreamRuleData>>()
+    )]
+    async fn add_stream_rule(
+        &self,
+        Parameters(args): Parameters<AddStreamRuleArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if args.value.trim().is_empty() {
+            return Ok(ToolOutput::<StreamRuleData>::error("Error: value must not be empty").into());
+        }
+        let output = match self.client.add_stream_rule(&args.value, args.tag.as_deref()).await {
+            Ok(rule) => ToolOutput::ok(StreamRuleData { rule }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Delete filtered stream rules
+    #[tool(
+        description = "Delete filtered stream rules by ID (from get_stream_rules)",
+        output_schema = cached_schema_for_type::<ToolOutput<DeletedStreamRulesData>>()
+    )]
+    async fn delete_stream_rules(
+        &self,
+        Parameters(args): Parameters<DeleteStreamRulesArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if args.rule_ids.is_empty() {
+            return Ok(ToolOutput::<DeletedStreamRulesData>::error("Error: rule_ids must not be empty").into());
+        }
+        let output = match self.client.delete_stream_rules(&args.rule_ids).await {
+            Ok(deleted) => ToolOutput::ok(DeletedStreamRulesData { deleted }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+
+    /// Send a Direct Message
+    #[tool(
+        description = "Send a Direct Message to a user, by username or user ID. Needs an app with Direct Message access and a token with the dm.write scope; the recipient must accept messages from you. Messages are private but cannot be unsent; confirm the text first",
+        output_schema = cached_schema_for_type::<ToolOutput<SentDmData>>()
+    )]
+    async fn send_dm(
+        &self,
+        Parameters(args): Parameters<SendDmArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        if args.text.trim().is_empty() {
+            return Ok(ToolOutput::<SentDmData>::error("Message text is empty").into());
+        }
+        let recipient_id = match self.target_user_id(&args.identifier, args.is_user_id).await {
+            Ok(recipient_id) => recipient_id,
+            Err(e) => return Ok(ToolOutput::<SentDmData>::error(e).into()),
+        };
+        let output = match self.client.send_dm(&recipient_id, &args.text).await {
+            Ok(message) => ToolOutput::ok(SentDmData {
+                recipient_id,
+                dm_conversation_id: message.dm_conversation_id,
+                dm_event_id: message.dm_event_id,
+            }),
+            Err(e) => ToolOutput::error(format!("Error: {}", e)),
+        };
+        Ok(output.into())
+    }
+}
+
+impl ServerHandler for XMcpServer {
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        if !self.capabilities.tools {
+            return Err(McpError::method_not_found::<CallToolRequestMethod>());
+        }
+        // Write tools left out by read-only mode or a read-only build are
+        // refused plainly rather than reported as unknown
+        if WRITE_TOOLS.contains(&request.name.as_ref()) && !self.tool_router.has_route(&request.name) {
+            return Ok(ToolOutput::<()>::error("server is in read-only mode").into());
+        }
+        let name = request.name.clone();
+        let started = Instant::now();
+        let peer = context.peer.clone();
+        let span = tool_call_span(&request);
+
+        // The SDK cancels this token on `notifications/cancelled` for the
+        // request; dropping the tool future stops any further X API calls
+        let cancelled = context.ct.clone();
+        let progress = context
+            .meta
+            .get_progress_token()
+            .map(|token| progress_forwarder(token, context.peer.clone()));
+        let priority = call_priority(&name, &context.meta);
+        let queued = async {
+            match &self.scheduler {
+                Some(scheduler) if !UNSCHEDULED_TOOLS.contains(&name.as_ref()) => {
+                    scheduler.acquire(priority).await
+                }
+                _ => {}
+            }
+        };
+        let cancelled_output = || {
+            tracing::info!("Tool call {} cancelled by the client", name);
+            Ok(ToolOutput::<()>::error("Cancelled by the client").into())
+        };
+        let call = self.tool_router.call(ToolCallContext::new(self, request, context));
+        let call = async {
+            // Waiting for the call budget can always be cancelled
+            tokio::select! {
+                _ = queued => {}
+                _ = cancelled.cancelled() => return cancelled_output(),
+            }
+            if GRACEFULLY_CANCELLED_TOOLS.contains(&name.as_ref()) {
+                return call.await;
+            }
+            tokio::select! {
+                result = call => result,
+                _ = cancelled.cancelled() => cancelled_output(),
+            }
+        };
+
+        // Progress is only reported when the client asked for it with a token
+        let result = async {
+            match progress {
+                Some((report, forwarder)) => {
+                    let result = with_progress(report, call).await;
+                    // Send every notification before the result
+                    let _ = forwarder.await;
+                    result
+                }
+                None => call.await,
+            }
+        }
+        .instrument(span.clone())
+        .await;
+
+        // Tools report failures in the output envelope rather than as errors
+        let failed = match &result {
+            Ok(result) => {
+                result.is_error == Some(true)
+                    || result
+                        .structured_content
+                        .as_ref()
+                        .and_then(|content| content.get("success"))
+                        == Some(&serde_json::Value::Bool(false))
+            }
+            Err(_) => true,
+        };
+        self.metrics.record_tool_call(&name, started.elapsed(), failed);
+        Self::log_tool_outcome(&span, started.elapsed(), failed, &result);
+        if failed {
+            self.log_tool_failure(&peer, &name, &result).await;
+        }
+        match result {
+            Ok(result) if self.compact_output => Ok(compact_text(result)),
+            result => result,
+        }
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        if !self.capabilities.tools {
+            return Err(McpError::method_not_found::<ListToolsRequestMethod>());
+        }
+        Ok(ListToolsResult::with_all_items(self.tool_router.list_all()))
+    }
+
+    async fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourcesResult, McpError> {
+        if !self.capabilities.resources {
+            return Err(McpError::method_not_found::<ListResourcesRequestMethod>());
+        }
+        Ok(ListResourcesResult::default())
+    }
+
+    async fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListResourceTemplatesResult, McpError> {
+        if !self.capabilities.resources {
+            return Err(McpError::method_not_found::<ListResourceTemplatesRequestMethod>());
+        }
+        Ok(ListResourceTemplatesResult::default())
+    }
+
+    async fn list_prompts(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListPromptsResult, McpError> {
+        if !self.capabilities.prompts {
+            return Err(McpError::method_not_found::<ListPromptsRequestMethod>());
+        }
+        Ok(ListPromptsResult::default())
+    }
+
+    async fn set_level(
+        &self,
+        request: SetLevelRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        if !self.capabilities.logging {
+            return Err(McpError::method_not_found::<SetLevelRequestMethod>());
+        }
+        *self.log_level.lock().unwrap_or_else(|e| e.into_inner()) = Some(request.level);
+        Ok(())
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::V_2025_06_18,
+            capabilities: self.capabilities.into(),
+            server_info: Implementation {
+                name: "x-mcp-server".to_string(),
+                title: Some("X (Twitter) MCP Server".to_string()),
+                version: crate::VERSION.to_string(),
+                icons: None,
+                website_url: None,
+            },
+            instructions: Some(self.instructions.clone()),
+        }
+    }
+}
+
+/// How long to wait before accepting again after `error`, or `None` when
+/// the listener itself is broken
+///
+/// Errors about the one connection being accepted are retried at once. The
+/// listener being invalid ends serving. Anything else, such as running out
+/// of file descriptors (EMFILE, ENFILE) or buffers (ENOBUFS), is likely to
+/// clear up once some connections close.
+#[cfg(feature = "http")]
+fn accept_retry_delay(error: &std::io::Error) -> Option<std::time::Duration> {
+    use std::io::ErrorKind;
+    use std::time::Duration;
+    match error.kind() {
+        ErrorKind::ConnectionAborted
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionRefused
+        | ErrorKind::Interrupted
+        | ErrorKind::WouldBlock => Some(Duration::ZERO),
+        ErrorKind::InvalidInput | ErrorKind::NotConnected | ErrorKind::Unsupported => None,
+        _ => Some(Duration::from_millis(100)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_accept_retry_delay() {
+        use std::io::{Error, ErrorKind};
+        use std::time::Duration;
+        let delay = |error: Error| accept_retry_delay(&error);
+        assert_eq!(delay(ErrorKind::ConnectionAborted.into()), Some(Duration::ZERO));
+        // EMFILE: too many open files
+        assert_eq!(delay(Error::from_raw_os_error(24)), Some(Duration::from_millis(100)));
+        assert_eq!(delay(ErrorKind::OutOfMemory.into()), Some(Duration::from_millis(100)));
+        assert_eq!(delay(ErrorKind::InvalidInput.into()), None);
+    }
+
+    #[test]
+    fn test_read_only_removes_write_tools() {
+        // Without the write-ops feature the write tools are never registered
+        let compiled_in = cfg!(feature = "write-ops");
+        let server = XMcpServer::new(XClient::new(String::new()));
+        assert!(WRITE_TOOLS
+            .iter()
+            .all(|name| server.tool_router.has_route(name) == compiled_in));
+
+        let server = server.with_read_only(true);
+        assert!(!server.tool_router.has_route("post_tweet"));
+        assert!(!server.tool_router.has_route("post_thread"));
+        assert!(server.tool_router.has_route("preview_tweet"));
+
+        let server = server.with_read_only(false);
+        assert_eq!(server.tool_router.has_route("post_tweet"), compiled_in);
+    }
+
+    #[test]
+    fn test_tweet_view_with_references() {
+        let tweet: Tweet = serde_json::from_value(json!({
+            "id": "2",
+            "text": "@jack agreed",
+            "in_reply_to_user_id": "7",
+            "referenced_tweets": [{ "type": "replied_to", "id": "1" }]
+        }))
+        .unwrap();
+        let includes: Includes = serde_json::from_value(json!({
+            "users": [{ "id": "7", "name": "Jack", "username": "jack" }],
+            "tweets": [{ "id": "1", "text": "hello" }, { "id": "9", "text": "other" }]
+        }))
+        .unwrap();
+
+        let view = TweetView::from(tweet).with_references(&includes);
+        assert_eq!(view.in_reply_to_user.unwrap().username, "jack");
+        assert_eq!(view.referenced.len(), 1);
+        assert_eq!(view.referenced[0].tweet.text, "hello");
+
+        let plain = serde_json::to_value(TweetView::from(view.tweet)).unwrap();
+        assert!(plain.get("in_reply_to_user").is_none());
+        assert!(plain.get("referenced").is_none());
+    }
+
+    /// Log output collected in memory
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tool_outcome_logging() {
+        let buffer = LogBuffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tool_call_span(&CallToolRequestParam {
+                name: "get_user".into(),
+                arguments: json!({ "identifier": "jack", "raw": true }).as_object().cloned(),
+            });
+            let ok: Result<CallToolResult, McpError> = Ok(ToolOutput::ok(json!({})).into());
+            XMcpServer::log_tool_outcome(&span, std::time::Duration::from_millis(42), false, &ok);
+            let failed: Result<CallToolResult, McpError> =
+                Ok(ToolOutput::<()>::error("User not found").into());
+            XMcpServer::log_tool_outcome(&span, std::time::Duration::from_millis(7), true, &failed);
+        });
+
+        let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<&str> = logs.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", logs);
+        assert!(!logs.contains("jack"), "argument values are left out: {}", logs);
+        assert!(lines[0].contains(" INFO tool_call{tool=get_user args=identifier,raw}"), "{}", logs);
+        assert!(lines[0].contains("elapsed_ms=42 success=true"), "{}", logs);
+        assert!(lines[1].contains(" WARN tool_call{"), "{}", logs);
+        assert!(lines[1].contains(r#"elapsed_ms=7 success=false error="User not found""#), "{}", logs);
     }
 }
@@ -0,0 +1,200 @@
+//! Real-time tweets from X's filtered stream
+//!
+//! [`TweetStream`] reads the long-lived `GET /2/tweets/search/stream`
+//! response one line at a time. MCP tool calls are request/response, so the
+//! server keeps a [`StreamBuffer`] that a background task fills from the
+//! stream and the `poll_stream` tool drains.
+
+use crate::api::XApi;
+use crate::error::{XError, XResult};
+use crate::types::{Tweet, XApiError, XResponse};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long the stream may stay silent before it counts as dropped; X sends
+/// a keep-alive newline every 20 seconds
+pub const STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Most tweets a [`StreamBuffer`] holds; older ones are dropped first
+pub const BUFFER_CAPACITY: usize = 1000;
+
+/// Tweets arriving on the filtered stream, see
+/// [`XClient::connect_filtered_stream`](crate::XClient::connect_filtered_stream)
+#[derive(Debug)]
+pub struct TweetStream {
+    response: reqwest::Response,
+    /// Bytes received after the last complete line
+    pending: Vec<u8>,
+    stall_timeout: Duration,
+    closed: bool,
+}
+
+impl TweetStream {
+    pub(crate) fn new(response: reqwest::Response, stall_timeout: Duration) -> Self {
+        Self {
+            response,
+            pending: Vec::new(),
+            stall_timeout,
+            closed: false,
+        }
+    }
+
+    /// The next tweet, or `None` once the stream has ended
+    ///
+    /// Keep-alive newlines are skipped. A message X sends in place of a
+    /// tweet, such as an `operational-disconnect` error, comes back as
+    /// `XError::Api` and the stream goes on. A lost connection, or
+    /// [`STALL_TIMEOUT`] without even a keep-alive, is returned as an error
+    /// and ends the stream.
+    pub async fn next(&mut self) -> Option<XResult<Tweet>> {
+        loop {
+            if let Some(end) = self.pending.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.pending.drain(..=end).collect();
+                match parse_line(&line) {
+                    Some(message) => return Some(message),
+                    None => continue,
+                }
+            }
+            if self.closed {
+                let rest = std::mem::take(&mut self.pending);
+                return parse_line(&rest);
+            }
+            match tokio::time::timeout(self.stall_timeout, self.response.chunk()).await {
+                Ok(Ok(Some(chunk))) => self.pending.extend_from_slice(&chunk),
+                Ok(Ok(None)) => self.closed = true,
+                Ok(Err(e)) => {
+                    self.closed = true;
+                    self.pending.clear();
+                    return Some(Err(e.into()));
+                }
+                Err(_) => {
+                    self.closed = true;
+                    self.pending.clear();
+                    return Some(Err(XError::Generic(format!(
+                        "filtered stream stalled: nothing received for {}s",
+                        self.stall_timeout.as_secs()
+                    ))));
+                }
+            }
+        }
+    }
+}
+
+/// The tweet or error on one line of the stream, `None` for a keep-alive
+fn parse_line(line: &[u8]) -> Option<XResult<Tweet>> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let message: XResponse<Tweet> = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(Err(e.into())),
+    };
+    Some(match (message.data, message.errors) {
+        (Some(tweet), _) => Ok(tweet),
+        (None, errors) => {
+            let summaries: Vec<String> = errors.iter().flatten().map(XApiError::summary).collect();
+            Err(XError::Api {
+                status: 200,
+                message: format!("Stream error: {}", summaries.join("; ")),
+            })
+        }
+    })
+}
+
+/// Tweets drained from a [`StreamBuffer`]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct StreamPoll {
+    /// Tweets received since the previous poll, oldest first
+    pub tweets: Vec<Tweet>,
+    /// Whether the stream is connected
+    pub connected: bool,
+    /// Tweets dropped since the previous poll because the buffer was full
+    pub dropped: usize,
+    /// Why the stream last disconnected, if it did since the previous poll
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct BufferState {
+    tweets: VecDeque<Tweet>,
+    connected: bool,
+    /// Whether a task is connecting or reading the stream
+    running: bool,
+    dropped: usize,
+    error: Option<String>,
+}
+
+/// Tweets received from the filtered stream, waiting to be polled
+///
+/// Clones share the buffer and the connection behind it, so every session
+/// of a server reads from one stream, as X allows one connection per app.
+#[derive(Debug, Clone, Default)]
+pub struct StreamBuffer {
+    state: Arc<Mutex<BufferState>>,
+}
+
+impl StreamBuffer {
+    /// Connect to the stream in the background unless it is already
+    /// connected or connecting
+    ///
+    /// The task keeps up to [`BUFFER_CAPACITY`] tweets and stops when the
+    /// stream ends or fails, leaving the reason for the next [`drain`](Self::drain);
+    /// the next call to `start` reconnects.
+    pub fn start(&self, api: Arc<dyn XApi>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.running {
+                return;
+            }
+            state.running = true;
+        }
+        let state = Arc::clone(&self.state);
+        tokio::spawn(async move {
+            let error = match api.connect_filtered_stream().await {
+                Ok(mut stream) => {
+                    state.lock().unwrap().connected = true;
+                    loop {
+                        match stream.next().await {
+                            Some(Ok(tweet)) => {
+                                let mut state = state.lock().unwrap();
+                                if state.tweets.len() == BUFFER_CAPACITY {
+                                    state.tweets.pop_front();
+                                    state.dropped += 1;
+                                }
+                                state.tweets.push_back(tweet);
+                            }
+                            Some(Err(e @ XError::Api { .. })) => {
+                                tracing::warn!("Filtered stream: {}", e);
+                            }
+                            Some(Err(e)) => break Some(e.to_string()),
+                            None => break Some("stream closed by X".to_string()),
+                        }
+                    }
+                }
+                Err(e) => Some(e.to_string()),
+            };
+            let mut state = state.lock().unwrap();
+            state.connected = false;
+            state.running = false;
+            state.error = error;
+        });
+    }
+
+    /// Take up to `max` of the buffered tweets, oldest first
+    pub fn drain(&self, max: usize) -> StreamPoll {
+        let mut state = self.state.lock().unwrap();
+        let count = max.min(state.tweets.len());
+        StreamPoll {
+            tweets: state.tweets.drain(..count).collect(),
+            connected: state.connected,
+            dropped: std::mem::take(&mut state.dropped),
+            error: state.error.take(),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Human-readable tweet timestamps
+//!
+//! X reports `created_at` as an ISO 8601 UTC timestamp. These helpers add a
+//! relative form ("3 hours ago") and the absolute time in a configured
+//! timezone. Timezones are fixed UTC offsets such as `+02:00` or `UTC-5`;
+//! named zones like `Europe/Paris` would need the tz database, which this
+//! crate does not ship.
+
+use chrono::{DateTime, FixedOffset, Utc};
+
+/// Parse a timezone given as `UTC`, `Z` or an offset such as `+02:00`,
+/// `-0530`, `+9` or `UTC+2`
+pub fn parse_utc_offset(input: &str) -> Result<FixedOffset, String> {
+    let trimmed = input.trim();
+    let upper = trimmed.to_ascii_uppercase();
+    let offset = ["UTC", "GMT"]
+        .iter()
+        .find_map(|prefix| upper.strip_prefix(prefix))
+        .unwrap_or(&upper);
+    if offset.is_empty() || offset == "Z" {
+        return Ok(FixedOffset::east_opt(0).expect("zero offset is valid"));
+    }
+
+    let invalid = || {
+        format!(
+            "Invalid timezone {:?}: expected UTC or an offset such as +02:00",
+            input
+        )
+    };
+    let (sign, digits) = match offset.as_bytes()[0] {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return Err(invalid()),
+    };
+    // Splitting below indexes bytes, which is only safe on ASCII
+    if !digits.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return Err(invalid());
+    }
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if digits.len() > 2 => digits.split_at(digits.len() - 2),
+        None => (digits, "0"),
+    };
+    let valid_part =
+        |part: &str| (1..=2).contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit());
+    if !valid_part(hours) || !valid_part(minutes) {
+        return Err(invalid());
+    }
+
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 14 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+/// Parse an X API timestamp such as `2024-05-01T12:03:00.000Z`
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// How long before `now` the time was, e.g. `3 hours ago`
+///
+/// Times in the future read `in 5 minutes`. Months are counted as 30 days
+/// and years as 365.
+pub fn relative_time(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - time).num_seconds();
+    let elapsed = seconds.unsigned_abs();
+    if elapsed < 45 {
+        return "just now".to_string();
+    }
+
+    const UNITS: [(u64, &str); 5] = [
+        (60, "minute"),
+        (3600, "hour"),
+        (86_400, "day"),
+        (30 * 86_400, "month"),
+        (365 * 86_400, "year"),
+    ];
+    let (size, unit) = UNITS
+        .iter()
+        .rev()
+        .find(|(size, _)| elapsed >= *size)
+        .copied()
+        .unwrap_or(UNITS[0]);
+    let count = elapsed / size;
+    let plural = if count == 1 { "" } else { "s" };
+
+    if seconds < 0 {
+        format!("in {} {}{}", count, unit, plural)
+    } else {
+        format!("{} {}{} ago", count, unit, plural)
+    }
+}
+
+/// The time in `timezone`, e.g. `Wed 2024-05-01 14:03 UTC+02:00`
+pub fn local_time(time: DateTime<Utc>, timezone: FixedOffset) -> String {
+    let local = time.with_timezone(&timezone);
+    if timezone.local_minus_utc() == 0 {
+        local.format("%a %Y-%m-%d %H:%M UTC").to_string()
+    } else {
+        local.format("%a %Y-%m-%d %H:%M UTC%:z").to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_utc_offset() {
+        let offset = |input| parse_utc_offset(input).map(|tz| tz.local_minus_utc());
+        assert_eq!(offset("UTC"), Ok(0));
+        assert_eq!(offset(" z "), Ok(0));
+        assert_eq!(offset("+02:00"), Ok(7200));
+        assert_eq!(offset("-0530"), Ok(-19_800));
+        assert_eq!(offset("UTC+9"), Ok(32_400));
+        assert_eq!(offset("gmt-3"), Ok(-10_800));
+
+        assert!(offset("Europe/Paris").is_err());
+        assert!(offset("+15:00").is_err());
+        assert!(offset("+02:75").is_err());
+        assert!(offset("+").is_err());
+        assert!(offset("+é1").is_err());
+        assert!(offset("-1é30").is_err());
+    }
+
+    #[test]
+    fn test_relative_and_local_time() {
+        let now = parse_timestamp("2024-05-01T12:00:00.000Z").unwrap();
+        let ago = |timestamp| relative_time(parse_timestamp(timestamp).unwrap(), now);
+        assert_eq!(ago("2024-05-01T11:59:50Z"), "just now");
+        assert_eq!(ago("2024-05-01T11:59:00Z"), "1 minute ago");
+        assert_eq!(ago("2024-05-01T09:00:00Z"), "3 hours ago");
+        assert_eq!(ago("2024-04-29T12:00:00Z"), "2 days ago");
+        assert_eq!(ago("2023-01-01T00:00:00Z"), "1 year ago");
+        assert_eq!(ago("2024-05-01T12:05:00Z"), "in 5 minutes");
+
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(local_time(now, utc), "Wed 2024-05-01 12:00 UTC");
+        let tokyo = parse_utc_offset("+09:00").unwrap();
+        assert_eq!(local_time(now, tokyo), "Wed 2024-05-01 21:00 UTC+09:00");
+    }
+}
@@ -0,0 +1,296 @@
+//! Stdio transport helpers
+//!
+//! The MCP session ends on the first error reading stdin. Some errors are
+//! transient, though: a read interrupted by a signal (`Interrupted`), as
+//! happens under process supervisors, or a descriptor momentarily left
+//! non-blocking (`WouldBlock`). [`RetryingReader`] retries those and passes
+//! every other error through.
+//!
+//! A line that is not a valid JSON-RPC message should not end the session
+//! either. [`LineTransport`] answers it with the error JSON-RPC 2.0 defines
+//! and keeps reading.
+
+use rmcp::model::ErrorCode;
+use rmcp::service::{RxJsonRpcMessage, TxJsonRpcMessage};
+use rmcp::transport::Transport;
+use rmcp::RoleServer;
+use serde_json::Value;
+use std::future::Future;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines, ReadBuf};
+use tokio::sync::Mutex;
+use tokio::time::Sleep;
+
+/// Wait before reading again after a read would block
+const WOULD_BLOCK_RETRY: Duration = Duration::from_millis(10);
+
+/// Reader that retries reads failing with a transient error
+///
+/// A read that would block is retried after [`WOULD_BLOCK_RETRY`] rather
+/// than at once, so an empty non-blocking descriptor does not spin.
+#[derive(Debug)]
+pub struct RetryingReader<R> {
+    inner: R,
+    /// Timer to wait out before the next read, after one would block
+    retry: Option<Pin<Box<Sleep>>>,
+    /// Whether the last read would block, so a streak is logged once
+    blocked: bool,
+}
+
+impl<R> RetryingReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            retry: None,
+            blocked: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for RetryingReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        loop {
+            if let Some(retry) = self.retry.as_mut() {
+                ready!(retry.as_mut().poll(cx));
+                self.retry = None;
+            }
+            match Pin::new(&mut self.inner).poll_read(cx, buf) {
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::Interrupted => {
+                    tracing::warn!("Read from stdin interrupted, retrying");
+                }
+                Poll::Ready(Err(e)) if e.kind() == ErrorKind::WouldBlock => {
+                    if !self.blocked {
+                        tracing::warn!("Read from stdin would block, retrying");
+                        self.blocked = true;
+                    }
+                    self.retry = Some(Box::pin(tokio::time::sleep(WOULD_BLOCK_RETRY)));
+                }
+                Poll::Pending => return Poll::Pending,
+                result => {
+                    self.blocked = false;
+                    return result;
+                }
+            }
+        }
+    }
+}
+
+/// Newline-delimited JSON-RPC transport for the server side of a session
+///
+/// Unlike rmcp's own stdio transport, which ends the session on a line it
+/// cannot parse, this one replies and carries on: `-32700 Parse error` for a
+/// line that is not JSON, and `-32600 Invalid Request` for JSON that is not a
+/// JSON-RPC 2.0 request, with the request's `id`, or `null` when it has none.
+/// Notifications and responses it cannot parse get no reply, as JSON-RPC
+/// forbids answering them, and are only logged.
+pub struct LineTransport<R, W> {
+    lines: Lines<BufReader<R>>,
+    write: Arc<Mutex<W>>,
+}
+
+impl<R: AsyncRead, W: AsyncWrite> LineTransport<R, W> {
+    pub fn new(read: R, write: W) -> Self {
+        Self {
+            lines: BufReader::new(read).lines(),
+            write: Arc::new(Mutex::new(write)),
+        }
+    }
+}
+
+impl<R, W> Transport<RoleServer> for LineTransport<R, W>
+where
+    R: AsyncRead + Send + Unpin + 'static,
+    W: AsyncWrite + Send + Unpin + 'static,
+{
+    type Error = std::io::Error;
+
+    fn send(
+        &mut self,
+        item: TxJsonRpcMessage<RoleServer>,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send + 'static {
+        let write = self.write.clone();
+        async move { write_message(&write, &item).await }
+    }
+
+    async fn receive(&mut self) -> Option<RxJsonRpcMessage<RoleServer>> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return None,
+                Err(e) => {
+                    tracing::error!("Error reading from stdin: {}", e);
+                    return None;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_message(&line) {
+                Ok(message) => return Some(message),
+                Err(Some(reply)) => {
+                    if let Err(e) = write_message(&self.write, &reply).await {
+                        tracing::error!("Error writing to stdout: {}", e);
+                        return None;
+                    }
+                }
+                Err(None) => {}
+            }
+        }
+    }
+
+    async fn close(&mut self) -> Result<(), Self::Error> {
+        self.write.lock().await.shutdown().await
+    }
+}
+
+/// Write `message` as one line
+async fn write_message<W, T>(write: &Mutex<W>, message: &T) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: serde::Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    let mut write = write.lock().await;
+    write.write_all(&line).await?;
+    write.flush().await
+}
+
+/// Parse one line into a message, or into the error response to send back
+/// instead (`None` when the line must go unanswered)
+fn parse_message(line: &str) -> Result<RxJsonRpcMessage<RoleServer>, Option<Value>> {
+    let value: Value = serde_json::from_str(line).map_err(|e| {
+        tracing::warn!("Received a line that is not JSON: {}", e);
+        Some(error_response(Value::Null, ErrorCode::PARSE_ERROR, "Parse error".to_string()))
+    })?;
+
+    // Only strings and numbers identify a request; anything else is answered
+    // with a null id
+    let id = value
+        .get("id")
+        .filter(|id| id.is_string() || id.is_number())
+        .cloned();
+    let is_request = value.get("method").is_some() && id.is_some();
+    if value.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        tracing::warn!("Received a message without \"jsonrpc\": \"2.0\"");
+        return Err(Some(error_response(
+            id.unwrap_or(Value::Null),
+            ErrorCode::INVALID_REQUEST,
+            "Invalid Request: \"jsonrpc\" must be \"2.0\"".to_string(),
+        )));
+    }
+    let method = value.get("method").and_then(Value::as_str).map(str::to_string);
+    let is_notification = value.get("method").is_some() && value.get("id").is_none();
+    let is_response = value.get("method").is_none()
+        && (value.get("result").is_some() || value.get("error").is_some());
+
+    serde_json::from_value(value).map_err(|e| {
+        if is_request || !(is_notification || is_response) {
+            tracing::warn!("Received an invalid request: {}", e);
+            // serde only says no message variant matched, so name the method
+            let message = match method {
+                Some(method) => format!("Invalid Request: unknown method {:?} or invalid params", method),
+                None => "Invalid Request".to_string(),
+            };
+            Some(error_response(id.unwrap_or(Value::Null), ErrorCode::INVALID_REQUEST, message))
+        } else {
+            tracing::debug!("Ignoring a message that could not be parsed: {}", e);
+            None
+        }
+    })
+}
+
+/// JSON-RPC error response with `id`
+fn error_response(id: Value, code: ErrorCode, message: String) -> Value {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code.0, "message": message },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    /// Reader whose reads always would block, counting them
+    struct AlwaysBlocking(Arc<std::sync::atomic::AtomicUsize>);
+
+    impl AsyncRead for AlwaysBlocking {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            _buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Poll::Ready(Err(ErrorKind::WouldBlock.into()))
+        }
+    }
+
+    /// Reader returning scripted errors and chunks in order
+    struct Scripted(VecDeque<std::io::Result<&'static str>>);
+
+    impl AsyncRead for Scripted {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Poll::Ready(match self.0.pop_front() {
+                Some(Ok(chunk)) => {
+                    buf.put_slice(chunk.as_bytes());
+                    Ok(())
+                }
+                Some(Err(e)) => Err(e),
+                None => Ok(()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let reader = Scripted(VecDeque::from([
+            Err(ErrorKind::Interrupted.into()),
+            Ok("{\"jsonrpc\":"),
+            Err(ErrorKind::WouldBlock.into()),
+            Ok("\"2.0\"}\n"),
+        ]));
+        let mut lines = BufReader::new(RetryingReader::new(reader)).lines();
+        assert_eq!(
+            lines.next_line().await.unwrap().as_deref(),
+            Some("{\"jsonrpc\":\"2.0\"}")
+        );
+        assert_eq!(lines.next_line().await.unwrap(), None);
+
+        let reader = Scripted(VecDeque::from([Err(ErrorKind::BrokenPipe.into())]));
+        let mut lines = BufReader::new(RetryingReader::new(reader)).lines();
+        assert_eq!(
+            lines.next_line().await.unwrap_err().kind(),
+            ErrorKind::BrokenPipe
+        );
+    }
+
+    #[tokio::test]
+    async fn test_would_block_waits_between_reads() {
+        let reads = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let mut lines = BufReader::new(RetryingReader::new(AlwaysBlocking(Arc::clone(&reads)))).lines();
+        let waited = tokio::time::timeout(Duration::from_millis(100), lines.next_line()).await;
+        assert!(waited.is_err());
+        // One read per 10ms retry, not a busy loop
+        let reads = reads.load(std::sync::atomic::Ordering::Relaxed);
+        assert!((2..=15).contains(&reads), "{} reads", reads);
+    }
+}
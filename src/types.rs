@@ -1,9 +1,13 @@
 //! Type definitions for X API responses
 
+use crate::time;
+use chrono::{DateTime, FixedOffset, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// User information from X API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct User {
     pub id: String,
     pub name: String,
@@ -13,10 +17,66 @@ pub struct User {
     pub profile_image_url: Option<String>,
     pub verified: Option<bool>,
     pub created_at: Option<String>,
+    pub pinned_tweet_id: Option<String>,
+}
+
+/// User object as returned by the v1.1 API, converted to [`User`] on arrival
+#[derive(Debug, Clone, Deserialize)]
+pub struct V1User {
+    pub id_str: String,
+    pub name: String,
+    pub screen_name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub followers_count: u64,
+    #[serde(default)]
+    pub friends_count: u64,
+    #[serde(default)]
+    pub statuses_count: u64,
+    #[serde(default)]
+    pub listed_count: u64,
+    pub profile_image_url_https: Option<String>,
+    pub verified: Option<bool>,
+    /// e.g. "Wed Oct 10 20:19:24 +0000 2018"
+    pub created_at: Option<String>,
+}
+
+impl From<V1User> for User {
+    fn from(user: V1User) -> Self {
+        // v2 reports RFC 3339 timestamps; keep the original text if it doesn't parse
+        let created_at = user.created_at.map(|created_at| {
+            chrono::DateTime::parse_from_str(&created_at, "%a %b %d %H:%M:%S %z %Y")
+                .map(|time| {
+                    time.with_timezone(&chrono::Utc)
+                        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                })
+                .unwrap_or(created_at)
+        });
+
+        User {
+            id: user.id_str,
+            name: user.name,
+            username: user.screen_name,
+            description: user.description.filter(|description| !description.is_empty()),
+            public_metrics: Some(UserMetrics {
+                followers_count: user.followers_count,
+                following_count: user.friends_count,
+                tweet_count: user.statuses_count,
+                listed_count: user.listed_count,
+            }),
+            profile_image_url: user.profile_image_url_https,
+            verified: user.verified,
+            created_at,
+            pinned_tweet_id: None,
+        }
+    }
 }
 
 /// User metrics (followers, following, etc.)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Counts X leaves out of a partial `public_metrics` object default to 0.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct UserMetrics {
     pub followers_count: u64,
     pub following_count: u64,
@@ -25,7 +85,7 @@ pub struct UserMetrics {
 }
 
 /// Tweet information from X API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Tweet {
     pub id: String,
     pub text: String,
@@ -34,10 +94,199 @@ pub struct Tweet {
     pub public_metrics: Option<TweetMetrics>,
     pub context_annotations: Option<Vec<ContextAnnotation>>,
     pub referenced_tweets: Option<Vec<ReferencedTweet>>,
+    pub attachments: Option<Attachments>,
+    /// Links, mentions, hashtags and cashtags X found in the text
+    pub entities: Option<Entities>,
+    /// ID of the author of the tweet this one replies to
+    pub in_reply_to_user_id: Option<String>,
+    /// ID of the tweet that started the conversation this tweet belongs to
+    pub conversation_id: Option<String>,
+    /// Who can reply: `everyone`, `mentionedUsers`, `following`, ...
+    pub reply_settings: Option<String>,
+    /// Whether X flags the tweet's links or media as possibly sensitive
+    pub possibly_sensitive: Option<bool>,
+    /// IDs of every version of the tweet, oldest first
+    pub edit_history_tweet_ids: Option<Vec<String>>,
+    /// Whether and until when the tweet can still be edited
+    pub edit_controls: Option<EditControls>,
+    /// `created_at` relative to the request, e.g. "3 hours ago"; only set
+    /// when readable times were asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_relative: Option<String>,
+    /// `created_at` in the requested timezone, e.g. "Wed 2024-05-01 14:03 UTC+02:00"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at_local: Option<String>,
+}
+
+impl Tweet {
+    /// Tweet text with the HTML entities X escapes (`&amp;`, `&lt;`, `&gt;`,
+    /// plus quotes) turned back into literal characters
+    ///
+    /// Decoding is a single pass, so `&amp;lt;` becomes `&lt;`, not `<`.
+    pub fn decoded_text(&self) -> String {
+        decode_html_entities(&self.text)
+    }
+
+    /// Fill in `created_at_relative` and `created_at_local` from `created_at`
+    pub fn add_readable_times(&mut self, timezone: FixedOffset, now: DateTime<Utc>) {
+        let Some(created_at) = self.created_at.as_deref().and_then(time::parse_timestamp) else {
+            return;
+        };
+        self.created_at_relative = Some(time::relative_time(created_at, now));
+        self.created_at_local = Some(time::local_time(created_at, timezone));
+    }
+
+    /// Whether the tweet was edited, i.e. has earlier versions
+    ///
+    /// The earlier versions can be fetched by the IDs in
+    /// `edit_history_tweet_ids`.
+    pub fn is_edited(&self) -> bool {
+        self.edit_history_tweet_ids
+            .as_ref()
+            .is_some_and(|ids| ids.len() > 1)
+    }
+
+    /// Whether the tweet has any media attached
+    pub fn has_media(&self) -> bool {
+        self.media_keys().next().is_some()
+    }
+
+    /// Keys of the media attached to the tweet
+    pub fn media_keys(&self) -> impl Iterator<Item = &str> {
+        self.attachments
+            .iter()
+            .flat_map(|attachments| attachments.media_keys.iter().flatten())
+            .map(String::as_str)
+    }
+
+    /// ID of the tweet this one replies to
+    pub fn replied_to_id(&self) -> Option<&str> {
+        self.referenced_tweets
+            .iter()
+            .flatten()
+            .find(|reference| reference.tweet_type == "replied_to")
+            .map(|reference| reference.id.as_str())
+    }
+}
+
+fn decode_html_entities(text: &str) -> String {
+    const ENTITIES: [(&str, char); 6] = [
+        ("&amp;", '&'),
+        ("&lt;", '<'),
+        ("&gt;", '>'),
+        ("&quot;", '"'),
+        ("&#39;", '\''),
+        ("&apos;", '\''),
+    ];
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(index) = rest.find('&') {
+        decoded.push_str(&rest[..index]);
+        rest = &rest[index..];
+
+        match ENTITIES.iter().find(|(entity, _)| rest.starts_with(entity)) {
+            Some((entity, c)) => {
+                decoded.push(*c);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Edit state of a tweet
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EditControls {
+    pub edits_remaining: Option<u32>,
+    pub is_edit_eligible: Option<bool>,
+    /// When the edit window closes, as an ISO 8601 timestamp
+    pub editable_until: Option<String>,
+}
+
+/// Entities X parsed out of a tweet's text
+///
+/// `start` and `end` are code point offsets into the text, end exclusive. X
+/// leaves out the arrays a tweet has nothing for.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Entities {
+    pub urls: Option<Vec<UrlEntity>>,
+    pub mentions: Option<Vec<MentionEntity>>,
+    pub hashtags: Option<Vec<TagEntity>>,
+    pub cashtags: Option<Vec<TagEntity>>,
+}
+
+/// Link in a tweet's text
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UrlEntity {
+    pub start: usize,
+    pub end: usize,
+    /// Shortened t.co link as it appears in the text
+    pub url: String,
+    pub expanded_url: Option<String>,
+    pub display_url: Option<String>,
+    /// Final destination after following redirects, when X resolved it
+    pub unwound_url: Option<String>,
+    /// Page title, when X fetched it
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+/// @-mention in a tweet's text
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MentionEntity {
+    pub start: usize,
+    pub end: usize,
+    /// Username, without the @
+    pub username: String,
+    pub id: Option<String>,
+}
+
+/// Hashtag or cashtag in a tweet's text
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagEntity {
+    pub start: usize,
+    pub end: usize,
+    /// Tag without the leading # or $
+    pub tag: String,
+}
+
+/// Attachments of a tweet
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Attachments {
+    pub media_keys: Option<Vec<String>>,
+}
+
+/// Media (photo, video or GIF) attached to a tweet
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Media {
+    pub media_key: String,
+    #[serde(rename = "type", default)]
+    pub media_type: String,
+    /// Image URL, for photos
+    pub url: Option<String>,
+    /// Still image, for videos and GIFs
+    pub preview_image_url: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Length of a video
+    pub duration_ms: Option<u64>,
+    /// Description of the image, when the author provided one
+    pub alt_text: Option<String>,
 }
 
 /// Tweet metrics (likes, retweets, etc.)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Counts X leaves out of a partial `public_metrics` object default to 0.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
 pub struct TweetMetrics {
     pub retweet_count: u64,
     pub like_count: u64,
@@ -46,30 +295,32 @@ pub struct TweetMetrics {
 }
 
 /// Context annotation for tweets
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ContextAnnotation {
     pub domain: Domain,
     pub entity: Entity,
 }
 
 /// Domain information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Domain {
     pub id: String,
+    #[serde(default)]
     pub name: String,
     pub description: Option<String>,
 }
 
 /// Entity information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Entity {
     pub id: String,
+    #[serde(default)]
     pub name: String,
     pub description: Option<String>,
 }
 
 /// Referenced tweet information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ReferencedTweet {
     #[serde(rename = "type")]
     pub tweet_type: String,
@@ -77,7 +328,7 @@ pub struct ReferencedTweet {
 }
 
 /// Response wrapper for X API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct XResponse<T> {
     pub data: Option<T>,
     pub includes: Option<Includes>,
@@ -85,16 +336,29 @@ pub struct XResponse<T> {
     pub meta: Option<serde_json::Value>,
 }
 
+impl<T> XResponse<T> {
+    /// Pagination token for the next page, from `meta.next_token`
+    pub fn next_token(&self) -> Option<String> {
+        self.meta
+            .as_ref()?
+            .get("next_token")?
+            .as_str()
+            .map(String::from)
+    }
+}
+
 /// Includes section for API responses
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct Includes {
     pub users: Option<Vec<User>>,
     pub tweets: Option<Vec<Tweet>>,
+    pub media: Option<Vec<Media>>,
 }
 
 /// X API error response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct XApiError {
+    #[serde(default)]
     pub title: String,
     pub detail: Option<String>,
     pub resource_type: Option<String>,
@@ -102,16 +366,487 @@ pub struct XApiError {
     pub value: Option<String>,
     #[serde(rename = "type")]
     pub error_type: Option<String>,
+    /// Description of the error in v1.1 responses and v2 parameter errors,
+    /// which have no `title`
+    pub message: Option<String>,
+}
+
+impl XApiError {
+    /// Readable description, e.g. "Not Found Error: Could not find tweet with id: [1]."
+    pub fn summary(&self) -> String {
+        let detail = self.detail.as_deref().or(self.message.as_deref());
+        problem_summary(Some(&self.title), detail).unwrap_or_default()
+    }
+}
+
+/// Body of a failed X API request
+///
+/// v2 answers with a problem (`title`, `detail`, `type`), sometimes listing
+/// each failure in `errors`; v1.1 only has `errors`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct XApiErrorResponse {
+    pub title: Option<String>,
+    pub detail: Option<String>,
+    #[serde(rename = "type")]
+    pub error_type: Option<String>,
+    pub errors: Option<Vec<XApiError>>,
+}
+
+impl XApiErrorResponse {
+    /// Readable message for an error body, or `None` when it is not an X
+    /// error object or says nothing
+    ///
+    /// `{"title": "Invalid Request", "detail": "One or more parameters to your
+    /// request was invalid.", "errors": [{"message": "The `query` query
+    /// parameter can not be empty"}]}` reads "Invalid Request: One or more
+    /// parameters to your request was invalid. (The `query` query parameter
+    /// can not be empty)".
+    pub fn message_from_body(body: &str) -> Option<String> {
+        serde_json::from_str::<Self>(body).ok()?.message()
+    }
+
+    /// Readable message, see [`message_from_body`](Self::message_from_body)
+    pub fn message(&self) -> Option<String> {
+        let problem = problem_summary(self.title.as_deref(), self.detail.as_deref());
+        let mut errors: Vec<String> = Vec::new();
+        for error in self.errors.iter().flatten() {
+            let summary = error.summary();
+            if !summary.is_empty() && problem.as_ref() != Some(&summary) && !errors.contains(&summary) {
+                errors.push(summary);
+            }
+        }
+        match (problem, errors.is_empty()) {
+            (Some(problem), true) => Some(problem),
+            (Some(problem), false) => Some(format!("{} ({})", problem, errors.join("; "))),
+            (None, false) => Some(errors.join("; ")),
+            (None, true) => None,
+        }
+    }
+}
+
+/// "title: detail", or whichever of the two is set when the other is empty
+/// or repeats it
+fn problem_summary(title: Option<&str>, detail: Option<&str>) -> Option<String> {
+    let title = title.map(str::trim).filter(|title| !title.is_empty());
+    let detail = detail.map(str::trim).filter(|detail| !detail.is_empty());
+    match (title, detail) {
+        (Some(title), Some(detail)) if detail.starts_with(title) => Some(detail.to_string()),
+        (Some(title), Some(detail)) => Some(format!("{}: {}", title, detail)),
+        (Some(text), None) | (None, Some(text)) => Some(text.to_string()),
+        (None, None) => None,
+    }
+}
+
+
+
+/// A place X reports trending topics for
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TrendLocation {
+    pub name: String,
+    /// Yahoo! Where On Earth ID used to request the place's trends
+    pub woeid: u32,
+    #[serde(default)]
+    pub country: String,
+    #[serde(rename = "countryCode")]
+    pub country_code: Option<String>,
+    #[serde(rename = "placeType")]
+    pub place_type: Option<PlaceType>,
+}
+
+/// A topic trending at a place
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Trend {
+    pub name: String,
+    /// Search results for the trend on X
+    pub url: String,
+    /// Tweets about the trend in the last 24 hours, when X counts them
+    pub tweet_volume: Option<u64>,
+}
+
+/// One place in the response of `GET /1.1/trends/place.json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlaceTrends {
+    #[serde(default)]
+    pub trends: Vec<Trend>,
+}
+
+/// Kind of a trend location (town, country, supername, ...)
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PlaceType {
+    pub code: u32,
+    pub name: String,
+}
+
+/// Request budget of one endpoint in the current rate-limit window
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RateLimit {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Unix time in seconds when the window resets
+    pub reset: u64,
+}
+
+/// Response of `GET /1.1/application/rate_limit_status.json`, with budgets
+/// grouped by resource family (`users`, `statuses`, ...) and then endpoint
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitStatus {
+    #[serde(default)]
+    pub resources: HashMap<String, HashMap<String, RateLimit>>,
 }
 
+/// Order of search results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Newest first
+    Recency,
+    /// X's own relevance ranking; X does not expose the scores
+    Relevancy,
+}
 
+impl SortOrder {
+    /// Value of the `sort_order` query parameter
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SortOrder::Recency => "recency",
+            SortOrder::Relevancy => "relevancy",
+        }
+    }
+}
 
 /// Search tweets request parameters
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchTweetsParams {
     pub query: String,
     pub max_results: Option<u32>,
+    /// Result order; X returns the newest first when unset
+    pub sort_order: Option<SortOrder>,
+    /// Oldest creation time to match, RFC 3339
+    pub start_time: Option<String>,
+    /// Creation time the results must be older than, RFC 3339
+    pub end_time: Option<String>,
+    /// Only match tweets newer than this tweet ID
+    pub since_id: Option<String>,
+    /// Only match tweets older than this tweet ID
+    pub until_id: Option<String>,
     pub tweet_fields: Option<Vec<String>>,
     pub user_fields: Option<Vec<String>>,
     pub expansions: Option<Vec<String>>,
+    pub media_fields: Option<Vec<String>>,
+    /// Token of the page to fetch, from a previous [`SearchPage`]
+    pub next_token: Option<String>,
+}
+
+/// Tweet IDs bounding a timeline, for fetching only what is new since
+/// an earlier call
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetIdRange {
+    /// Only return tweets newer than this tweet ID
+    pub since_id: Option<String>,
+    /// Only return tweets older than this tweet ID
+    pub until_id: Option<String>,
+}
+
+/// One page of recent search results
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct SearchPage {
+    pub tweets: Vec<Tweet>,
+    /// Token for the page after this one, when there is one
+    pub next_token: Option<String>,
+}
+
+/// One page of recent search results, each tweet paired with its author
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuthoredSearchPage {
+    /// Tweets with their author, or `None` when X did not include one
+    pub tweets: Vec<(Tweet, Option<User>)>,
+    /// Token for the page after this one, when there is one
+    pub next_token: Option<String>,
+}
+
+/// Request body for creating a tweet
+///
+/// Covers the documented `POST /2/tweets` body; see
+/// [`check_tweet_attachments`](crate::validate::check_tweet_attachments)
+/// for the combinations X rejects.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct PostTweetRequest {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<TweetReply>,
+    /// ID of the tweet to quote
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quote_tweet_id: Option<String>,
+    /// Uploaded media to attach
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media: Option<TweetMedia>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll: Option<TweetPoll>,
+    /// Card to attach, such as a website or app card from the Ads API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub card_uri: Option<String>,
+    /// Link that opens a Direct Message conversation with the author
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub direct_message_deep_link: Option<String>,
+    /// Who can reply: `following`, `mentionedUsers`, `subscribers` or
+    /// `verified`; everyone when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_settings: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geo: Option<TweetGeo>,
+    /// Community to post the tweet in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub community_id: Option<String>,
+    /// Only show the tweet to the author's super followers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub for_super_followers_only: Option<bool>,
+    /// Keep the tweet off timelines, for promoted-only tweets
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nullcast: Option<bool>,
+}
+
+/// Reply settings for a new tweet
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetReply {
+    pub in_reply_to_tweet_id: String,
+    /// Users mentioned in the conversation to leave out of the reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_reply_user_ids: Option<Vec<String>>,
+}
+
+/// Media attached to a new tweet
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetMedia {
+    /// IDs of uploaded media, 1 to 4
+    pub media_ids: Vec<String>,
+    /// Users tagged in the media, up to 10
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tagged_user_ids: Option<Vec<String>>,
+}
+
+/// Poll attached to a new tweet
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetPoll {
+    /// 2 to 4 choices of up to 25 characters
+    pub options: Vec<String>,
+    /// How long the poll is open, 5 to 10080 minutes (7 days)
+    pub duration_minutes: u32,
+}
+
+/// Place a new tweet is tagged with
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetGeo {
+    pub place_id: String,
+}
+
+/// Response data of the delete-tweet endpoint
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DeletedTweet {
+    pub deleted: bool,
+}
+
+/// Response data of the bookmark endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BookmarkStatus {
+    /// Whether the tweet is now bookmarked
+    pub bookmarked: bool,
+}
+
+/// Response data of the endpoint hiding or unhiding a reply
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HiddenStatus {
+    /// Whether the reply is now hidden
+    pub hidden: bool,
+}
+
+/// Response data of the endpoint creating a list
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CreatedList {
+    pub id: String,
+    pub name: String,
+}
+
+/// Response data of the list member endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ListMemberStatus {
+    /// Whether the user is now a member of the list
+    pub is_member: bool,
+}
+
+/// Response data of the endpoint sending a Direct Message
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SentMessage {
+    /// Conversation the message was added to
+    pub dm_conversation_id: String,
+    /// ID of the message event
+    pub dm_event_id: String,
+}
+
+/// Response data of the follow and unfollow endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FollowStatus {
+    /// Whether the authenticated user now follows the target
+    pub following: bool,
+    /// Set when the target is protected, so the follow waits for their approval
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending_follow: Option<bool>,
+}
+
+/// Response data of the block and unblock endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BlockStatus {
+    /// Whether the authenticated user now blocks the target
+    pub blocking: bool,
+}
+
+/// Response data of the mute and unmute endpoints
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MuteStatus {
+    /// Whether the authenticated user now mutes the target
+    pub muting: bool,
+}
+
+/// A tweet together with the context needed to reply to it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ReplyContext {
+    pub tweet: Tweet,
+    /// Author of the tweet, when X returned it
+    pub author: Option<User>,
+    /// Most recent replies in the tweet's conversation, newest first
+    pub replies: Vec<Tweet>,
+}
+
+/// Tweets looked up by ID, with their authors
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetLookup {
+    /// Tweets found, in the order their IDs were requested
+    pub tweets: Vec<Tweet>,
+    /// Authors of the tweets found, each listed once
+    pub authors: Vec<User>,
+    /// Requested IDs that are invalid, deleted or not visible
+    pub missing_ids: Vec<String>,
+}
+
+/// One or more pages of a paginated tweet list, with the tweets' authors
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TweetPage {
+    /// Tweets in the order X returned them
+    pub tweets: Vec<Tweet>,
+    /// Authors of the tweets, each listed once
+    pub authors: Vec<User>,
+    /// Token for the page after these, when there is one
+    pub next_token: Option<String>,
+}
+
+/// Users looked up by username
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UserLookup {
+    /// Users found, in the order their usernames were requested
+    pub users: Vec<User>,
+    /// Requested usernames that are invalid, suspended or do not exist
+    pub not_found: Vec<MissingUser>,
+}
+
+/// A username a lookup could not return
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct MissingUser {
+    pub username: String,
+    /// Why, as X reported it
+    pub reason: String,
+}
+
+/// One or more pages of a paginated list of users
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UserPage {
+    /// Users in the order X returned them
+    pub users: Vec<User>,
+    /// Token for the page after these, when there is one
+    pub next_token: Option<String>,
+}
+
+/// A rule choosing the tweets the filtered stream delivers
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StreamRule {
+    pub id: String,
+    /// The rule, in search query syntax, e.g. "from:rustlang has:links"
+    pub value: String,
+    /// Label given when the rule was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// An X list
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TwitterList {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// User ID of the list's owner
+    pub owner_id: Option<String>,
+    pub private: Option<bool>,
+    pub follower_count: Option<u64>,
+    pub member_count: Option<u64>,
+    pub created_at: Option<String>,
+}
+
+/// One or more pages of a paginated list of lists
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ListPage {
+    /// Lists in the order X returned them
+    pub lists: Vec<TwitterList>,
+    /// Token for the page after these, when there is one
+    pub next_token: Option<String>,
+}
+
+/// A tweet in a conversation tree, with the replies to it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationNode {
+    /// Tweet ID, set even when the tweet itself is missing
+    pub id: String,
+    /// The tweet, or `None` when it is deleted, not visible or older than
+    /// the search window; its replies are kept either way
+    pub tweet: Option<Tweet>,
+    /// Direct replies, oldest first
+    pub replies: Vec<ConversationNode>,
+}
+
+impl ConversationNode {
+    /// Apply `f` to every tweet in this subtree
+    pub fn for_each_tweet_mut(&mut self, f: &mut impl FnMut(&mut Tweet)) {
+        if let Some(tweet) = &mut self.tweet {
+            f(tweet);
+        }
+        for reply in &mut self.replies {
+            reply.for_each_tweet_mut(f);
+        }
+    }
+}
+
+/// A conversation arranged by who replied to whom
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConversationTree {
+    /// The tweet that started the conversation. Replies whose parent is
+    /// missing hang off it under a placeholder node for that parent
+    pub root: ConversationNode,
+    /// Authors of the tweets in the tree, each listed once
+    pub authors: Vec<User>,
+    /// Number of tweets in the tree, not counting missing ones
+    pub tweet_count: usize,
+    /// Whether replies may have been left out to stay within the depth and
+    /// size caps
+    pub truncated: bool,
+    /// Whether the conversation started over 7 days ago, so replies older
+    /// than recent search covers are missing
+    pub beyond_search_window: bool,
+}
+
+/// Outcome of posting a thread, which may have stopped partway
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ThreadResult {
+    /// Tweets posted by this call, in thread order
+    pub tweets: Vec<Tweet>,
+    /// Why the thread stopped early, if it did
+    pub error: Option<String>,
+    /// Token to pass back with the same texts to continue the thread
+    pub resume_token: Option<String>,
 }
@@ -0,0 +1,746 @@
+//! Client-side validation of tweets and lists before they are posted
+//!
+//! Character counting follows X's twitter-text (v3) rules: code points in the
+//! Latin and general punctuation ranges weigh 1, everything else (CJK, emoji,
+//! ...) weighs 2, a flag or other emoji sequence counts as one emoji, and every
+//! URL counts as 23 characters because X wraps it in a t.co link. URLs are
+//! found inside words too, as in `(https://x.com)`, and bare domains such as
+//! `example.com/path` count as URLs.
+
+use crate::types::PostTweetRequest;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Maximum weighted length of a tweet
+pub const MAX_TWEET_LENGTH: usize = 280;
+
+/// Weighted length of any URL once shortened to t.co
+pub const URL_LENGTH: usize = 23;
+
+/// Most media attachments per tweet
+pub const MAX_MEDIA: usize = 4;
+
+/// Most users tagged in a tweet's media
+pub const MAX_TAGGED_USERS: usize = 10;
+
+/// Fewest and most choices in a poll
+pub const MIN_POLL_OPTIONS: usize = 2;
+pub const MAX_POLL_OPTIONS: usize = 4;
+
+/// Longest poll choice, in characters
+pub const MAX_POLL_OPTION_LENGTH: usize = 25;
+
+/// How long a poll may stay open, in minutes
+pub const POLL_DURATION_MINUTES: std::ops::RangeInclusive<u32> = 5..=10080;
+
+/// Longest list name, in characters
+pub const MAX_LIST_NAME_LENGTH: usize = 25;
+
+/// `reply_settings` values X accepts on a new tweet; leaving it unset lets
+/// everyone reply
+pub const REPLY_SETTINGS: [&str; 4] = ["following", "mentionedUsers", "subscribers", "verified"];
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// Top-level domains whose domains X links without a scheme; country codes
+/// are linked only when followed by a path
+const GENERIC_TLDS: [&str; 21] = [
+    "com", "net", "org", "edu", "gov", "mil", "int", "info", "biz", "name", "pro", "app",
+    "dev", "xyz", "blog", "news", "online", "site", "tech", "shop", "store",
+];
+
+/// Code point ranges that count as a single character
+const SINGLE_WEIGHT_RANGES: [(u32, u32); 4] = [
+    (0x0000, 0x10FF),
+    (0x2000, 0x200D),
+    (0x2010, 0x201F),
+    (0x2032, 0x2037),
+];
+
+/// Result of validating a tweet draft
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TweetValidation {
+    /// Whether X should accept the tweet
+    pub valid: bool,
+    /// Length of the text as counted by X
+    pub weighted_length: usize,
+    /// Characters left before the limit (negative when over)
+    pub remaining: i64,
+    /// Problems and notable transformations, e.g. link shortening
+    pub warnings: Vec<String>,
+}
+
+/// Count the length of `text` the way X does
+pub fn tweet_weighted_length(text: &str) -> usize {
+    let (length, _) = weighted_length_and_urls(text);
+    length
+}
+
+/// Validate a tweet request body without sending it
+pub fn validate_tweet(request: &PostTweetRequest) -> TweetValidation {
+    let (weighted_length, urls) = weighted_length_and_urls(&request.text);
+    let remaining = MAX_TWEET_LENGTH as i64 - weighted_length as i64;
+    let mut valid = true;
+    let mut warnings = Vec::new();
+
+    if request.text.trim().is_empty() {
+        valid = false;
+        warnings.push("tweet text is empty".to_string());
+    }
+
+    if let Err(error) = check_tweet_length(&request.text) {
+        valid = false;
+        warnings.push(error);
+    }
+
+    if urls > 0 {
+        warnings.push(format!(
+            "contains {} link(s) that will be shortened to {} characters each",
+            urls, URL_LENGTH
+        ));
+    }
+
+    for check in [check_tweet_references, check_tweet_attachments] {
+        if let Err(error) = check(request) {
+            valid = false;
+            warnings.push(error);
+        }
+    }
+
+    warnings.extend(check_reply_settings(request));
+
+    TweetValidation {
+        valid,
+        weighted_length,
+        remaining,
+        warnings,
+    }
+}
+
+/// Check that `text` fits in a tweet, counted the way X does
+pub fn check_tweet_length(text: &str) -> Result<(), String> {
+    let length = tweet_weighted_length(text);
+    if length > MAX_TWEET_LENGTH {
+        return Err(format!(
+            "tweet exceeds {} characters by {} (got {})",
+            MAX_TWEET_LENGTH,
+            length - MAX_TWEET_LENGTH,
+            length
+        ));
+    }
+    Ok(())
+}
+
+/// Check that the reply and quote targets of a request are tweet IDs
+pub fn check_tweet_references(request: &PostTweetRequest) -> Result<(), String> {
+    let references = [
+        (
+            "reply_to",
+            request
+                .reply
+                .as_ref()
+                .map(|reply| &reply.in_reply_to_tweet_id),
+        ),
+        ("quote_tweet_id", request.quote_tweet_id.as_ref()),
+    ];
+
+    for (name, id) in references {
+        if let Some(id) = id.filter(|id| !is_tweet_id(id)) {
+            return Err(format!(
+                "{} must be a tweet ID or tweet URL (got {:?})",
+                name, id
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Check the media, poll, card and link of a request against X's limits
+///
+/// A card cannot be combined with media, a poll, a quote or a Direct Message
+/// link, and a poll cannot be combined with media or a quote.
+pub fn check_tweet_attachments(request: &PostTweetRequest) -> Result<(), String> {
+    if request.card_uri.is_some() {
+        let others = [
+            ("media", request.media.is_some()),
+            ("a poll", request.poll.is_some()),
+            ("a quote tweet", request.quote_tweet_id.is_some()),
+            (
+                "a direct message link",
+                request.direct_message_deep_link.is_some(),
+            ),
+        ];
+        if let Some((name, _)) = others.iter().find(|(_, present)| *present) {
+            return Err(format!("card_uri cannot be combined with {}", name));
+        }
+    }
+
+    if let Some(poll) = &request.poll {
+        if request.media.is_some() {
+            return Err("a poll cannot be combined with media".to_string());
+        }
+        if request.quote_tweet_id.is_some() {
+            return Err("a poll cannot be combined with a quote tweet".to_string());
+        }
+        if !(MIN_POLL_OPTIONS..=MAX_POLL_OPTIONS).contains(&poll.options.len()) {
+            return Err(format!(
+                "a poll needs {} to {} options (got {})",
+                MIN_POLL_OPTIONS,
+                MAX_POLL_OPTIONS,
+                poll.options.len()
+            ));
+        }
+        if let Some(option) = poll.options.iter().find(|option| {
+            let length = option.chars().count();
+            length == 0 || length > MAX_POLL_OPTION_LENGTH
+        }) {
+            return Err(format!(
+                "poll options must be 1 to {} characters (got {:?})",
+                MAX_POLL_OPTION_LENGTH, option
+            ));
+        }
+        if !POLL_DURATION_MINUTES.contains(&poll.duration_minutes) {
+            return Err(format!(
+                "poll duration must be {} to {} minutes (got {})",
+                POLL_DURATION_MINUTES.start(),
+                POLL_DURATION_MINUTES.end(),
+                poll.duration_minutes
+            ));
+        }
+    }
+
+    if let Some(media) = &request.media {
+        if media.media_ids.is_empty() {
+            return Err("tagged_user_ids need media_ids to tag users in".to_string());
+        }
+        if media.media_ids.len() > MAX_MEDIA {
+            return Err(format!(
+                "at most {} media can be attached (got {})",
+                MAX_MEDIA,
+                media.media_ids.len()
+            ));
+        }
+        if let Some(id) = media.media_ids.iter().find(|id| !is_tweet_id(id)) {
+            return Err(format!("media_ids must be numeric IDs (got {:?})", id));
+        }
+        let tagged = media.tagged_user_ids.as_ref().map_or(0, Vec::len);
+        if tagged > MAX_TAGGED_USERS {
+            return Err(format!(
+                "at most {} users can be tagged (got {})",
+                MAX_TAGGED_USERS, tagged
+            ));
+        }
+    }
+
+    if let Some(link) = &request.direct_message_deep_link {
+        if !link.starts_with("https://") {
+            return Err(format!(
+                "direct_message_deep_link must be an https URL (got {:?})",
+                link
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Warning for a `reply_settings` value X is not known to accept
+///
+/// Unknown values are still sent, so settings X adds later keep working.
+pub fn check_reply_settings(request: &PostTweetRequest) -> Option<String> {
+    let value = request.reply_settings.as_deref()?;
+    (!REPLY_SETTINGS.contains(&value)).then(|| {
+        format!(
+            "reply_settings {:?} is not one of {}; X may reject it",
+            value,
+            REPLY_SETTINGS.join(", ")
+        )
+    })
+}
+
+/// X's `reply_settings` value for a tool's option, `None` for `everyone`
+///
+/// Known options match ignoring case and `_`, so `mentioned_users` gives
+/// `mentionedUsers`. Anything else is passed through unchanged.
+pub fn reply_settings_value(option: &str) -> Option<String> {
+    let option = option.trim();
+    let normalized = option.to_ascii_lowercase().replace('_', "");
+    if normalized.is_empty() || normalized == "everyone" {
+        return None;
+    }
+    let known = REPLY_SETTINGS
+        .iter()
+        .find(|value| value.to_ascii_lowercase() == normalized);
+    Some(known.map_or(option, |value| *value).to_string())
+}
+
+/// Check that `name` can name a list: 1 to 25 characters, not all blank
+pub fn check_list_name(name: &str) -> Result<(), String> {
+    let length = name.chars().count();
+    if name.trim().is_empty() || length > MAX_LIST_NAME_LENGTH {
+        return Err(format!(
+            "list name must be 1 to {} characters (got {})",
+            MAX_LIST_NAME_LENGTH, length
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `id` looks like a tweet (snowflake) ID
+pub fn is_tweet_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 20 && id.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Whether `username` is a valid X handle: 1 to 15 letters, digits or
+/// underscores, without the @
+pub fn is_username(username: &str) -> bool {
+    !username.is_empty()
+        && username.len() <= 15
+        && username.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Extract the tweet ID from a raw ID or a tweet URL such as
+/// `https://x.com/jack/status/20?s=20` or `twitter.com/i/web/status/20`
+pub fn parse_tweet_id(input: &str) -> Option<String> {
+    let input = input.trim();
+    if is_tweet_id(input) {
+        return Some(input.to_string());
+    }
+
+    let without_scheme = input
+        .strip_prefix("https://")
+        .or_else(|| input.strip_prefix("http://"))
+        .unwrap_or(input);
+    let (host, path) = without_scheme.split_once('/')?;
+    let host = host.to_ascii_lowercase();
+    let known_host = ["x.com", "twitter.com"]
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)));
+    if !known_host {
+        return None;
+    }
+
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let mut segments = path.split('/');
+    segments.find(|segment| matches!(*segment, "status" | "statuses"))?;
+    segments
+        .next()
+        .filter(|id| is_tweet_id(id))
+        .map(str::to_string)
+}
+
+/// Weighted length of `text` and the number of URLs it contains
+fn weighted_length_and_urls(text: &str) -> (usize, usize) {
+    let mut counter = LengthCounter::default();
+    for word in split_keeping_whitespace(text) {
+        let mut counted = 0;
+        for url in find_urls(word) {
+            counter.add_text(&word[counted..url.start]);
+            counter.add_url();
+            counted = url.end;
+        }
+        counter.add_text(&word[counted..]);
+    }
+    (counter.length, counter.urls)
+}
+
+/// Running weighted length, carrying emoji sequences across pieces of text
+#[derive(Default)]
+struct LengthCounter {
+    length: usize,
+    urls: usize,
+    in_emoji: bool,
+    joining: bool,
+    /// The last character was a regional indicator starting a flag
+    in_flag: bool,
+}
+
+impl LengthCounter {
+    fn add_text(&mut self, text: &str) {
+        for c in text.chars() {
+            // A pair of regional indicators is one flag, which counts once
+            if self.in_flag && is_regional_indicator(c) {
+                self.in_flag = false;
+                continue;
+            }
+            // Modifiers and zero-width-joined emoji are folded into the
+            // sequence they extend, which counts once
+            if self.in_emoji && is_emoji_modifier(c) {
+                self.joining = c == ZERO_WIDTH_JOINER;
+                self.in_flag = false;
+                continue;
+            }
+            if self.joining && is_emoji(c) {
+                self.joining = false;
+                continue;
+            }
+            self.joining = false;
+            self.length += char_weight(c);
+            self.in_emoji = is_emoji(c);
+            self.in_flag = is_regional_indicator(c);
+        }
+    }
+
+    fn add_url(&mut self) {
+        self.length += URL_LENGTH;
+        self.urls += 1;
+        self.in_emoji = false;
+        self.joining = false;
+        self.in_flag = false;
+    }
+}
+
+/// Split `text` into alternating runs of words and whitespace
+fn split_keeping_whitespace(text: &str) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    let mut in_whitespace = None;
+
+    for (index, c) in text.char_indices() {
+        let whitespace = c.is_whitespace();
+        if in_whitespace.is_some_and(|w| w != whitespace) {
+            pieces.push(&text[start..index]);
+            start = index;
+        }
+        in_whitespace = Some(whitespace);
+    }
+    if start < text.len() {
+        pieces.push(&text[start..]);
+    }
+
+    pieces
+}
+
+/// Byte ranges of the URLs X would link in `word`, which has no whitespace
+fn find_urls(word: &str) -> Vec<Range<usize>> {
+    let mut urls = Vec::new();
+    let mut previous = None;
+    let mut skip_to = 0;
+    for (start, c) in word.char_indices() {
+        if start >= skip_to && !previous.is_some_and(joins_url) {
+            if let Some(length) = url_length(&word[start..]) {
+                urls.push(start..start + length);
+                skip_to = start + length;
+            }
+        }
+        previous = Some(c);
+    }
+    urls
+}
+
+/// Characters a URL cannot directly follow, as they make it part of a word,
+/// an email address or a longer domain
+fn joins_url(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '@' | '.' | '-' | '_' | '/' | '#' | '$')
+}
+
+/// Length in bytes of the URL at the start of `text`, if there is one
+///
+/// A URL with an http(s) scheme needs only a host. Without one, the domain
+/// must end in a generic top-level domain, or in a country code followed by
+/// a path, as with X's own linking. Trailing punctuation and unbalanced
+/// closing brackets are left out.
+fn url_length(text: &str) -> Option<usize> {
+    let lower = text.get(..8).unwrap_or(text).to_ascii_lowercase();
+    let scheme = ["https://", "http://"]
+        .into_iter()
+        .find(|scheme| lower.starts_with(scheme))
+        .map_or(0, str::len);
+
+    let rest = &text[scheme..];
+    let host = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '.'))
+        .map_or(rest, |end| &rest[..end])
+        .trim_end_matches('.');
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels
+        .iter()
+        .any(|label| label.is_empty() || label.starts_with('-') || label.ends_with('-'))
+    {
+        return None;
+    }
+    let mut end = scheme + host.len();
+    let has_path = text[end..].starts_with('/');
+    if scheme == 0 {
+        let tld = labels.last().map(|label| label.to_ascii_lowercase()).unwrap_or_default();
+        let known = GENERIC_TLDS.contains(&tld.as_str())
+            || (tld.len() == 2 && tld.bytes().all(|b| b.is_ascii_alphabetic()) && has_path);
+        if labels.len() < 2 || !known {
+            return None;
+        }
+    }
+
+    // Port, then path, query or fragment up to the end of the word
+    if let Some(port) = text[end..].strip_prefix(':') {
+        let digits = port.bytes().take_while(u8::is_ascii_digit).count();
+        if digits > 0 {
+            end += 1 + digits;
+        }
+    }
+    if text[end..].starts_with(['/', '?', '#']) {
+        let mut url = text;
+        // Leave out trailing punctuation and brackets closing outside the URL
+        while let Some(last) = url.chars().next_back().filter(|_| url.len() > end) {
+            let trailing = matches!(last, '.' | ',' | ';' | ':' | '!' | '?' | '\'' | '"')
+                || (last == ')' && url.matches(')').count() > url.matches('(').count())
+                || (last == ']' && url.matches(']').count() > url.matches('[').count());
+            if !trailing {
+                break;
+            }
+            url = &url[..url.len() - last.len_utf8()];
+        }
+        end = url.len();
+    }
+    Some(end)
+}
+
+fn char_weight(c: char) -> usize {
+    let code = c as u32;
+    if SINGLE_WEIGHT_RANGES
+        .iter()
+        .any(|&(start, end)| (start..=end).contains(&code))
+    {
+        1
+    } else {
+        2
+    }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F000..=0x1FAFF | 0x2600..=0x27BF)
+}
+
+/// Joiner, variation selector 16 or skin tone modifier
+fn is_emoji_modifier(c: char) -> bool {
+    c == ZERO_WIDTH_JOINER || matches!(c as u32, 0xFE0F | 0x1F3FB..=0x1F3FF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{TweetMedia, TweetPoll, TweetReply};
+
+    fn request(text: &str) -> PostTweetRequest {
+        PostTweetRequest {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_weighted_length() {
+        assert_eq!(tweet_weighted_length("hello"), 5);
+        assert_eq!(tweet_weighted_length("日本語"), 6);
+        assert_eq!(
+            tweet_weighted_length("see https://example.com/a/very/long/path"),
+            4 + URL_LENGTH
+        );
+        assert_eq!(tweet_weighted_length("👍"), 2);
+        assert_eq!(tweet_weighted_length("👍🏽"), 2);
+        assert_eq!(tweet_weighted_length("👍👍"), 4);
+        assert_eq!(tweet_weighted_length("👨\u{200D}👩\u{200D}👧"), 2);
+    }
+
+    #[test]
+    fn test_urls_inside_words() {
+        // Bare domains are links, with or without a path
+        assert_eq!(tweet_weighted_length("example.com/a/very/long/path"), URL_LENGTH);
+        assert_eq!(tweet_weighted_length("see example.com"), 4 + URL_LENGTH);
+        assert_eq!(tweet_weighted_length("docs.rs/serde"), URL_LENGTH);
+        // Country codes only with a path, so file names are not links
+        assert_eq!(tweet_weighted_length("README.md"), 9);
+        assert_eq!(tweet_weighted_length("node.js"), 7);
+        assert_eq!(tweet_weighted_length("e.g. 3.14"), 9);
+        assert_eq!(tweet_weighted_length("me@example.com"), 14);
+
+        // Surrounding punctuation is not part of the URL
+        assert_eq!(tweet_weighted_length("(https://x.com)"), 1 + URL_LENGTH + 1);
+        assert_eq!(tweet_weighted_length("https://x.com."), URL_LENGTH + 1);
+        assert_eq!(tweet_weighted_length("see x.com/a, then"), 4 + URL_LENGTH + 6);
+        assert_eq!(tweet_weighted_length("(x.com/wiki/Rust_(language))"), 1 + URL_LENGTH + 1);
+        assert_eq!(tweet_weighted_length("link:https://example.com/a?b=c!"), 5 + URL_LENGTH + 1);
+        assert_eq!(weighted_length_and_urls("a.com,b.com").1, 2);
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(tweet_weighted_length("🇺🇸"), 2);
+        assert_eq!(tweet_weighted_length("🇺🇸🇬🇧"), 4);
+        assert_eq!(tweet_weighted_length("🇺"), 2);
+        assert_eq!(tweet_weighted_length("🇺🇸 x.com"), 3 + URL_LENGTH);
+    }
+
+    #[test]
+    fn test_tweet_length() {
+        assert!(check_tweet_length(&"a".repeat(MAX_TWEET_LENGTH)).is_ok());
+        assert_eq!(
+            check_tweet_length(&"a".repeat(MAX_TWEET_LENGTH + 1)).unwrap_err(),
+            "tweet exceeds 280 characters by 1 (got 281)"
+        );
+
+        // CJK characters and emoji count twice
+        assert!(check_tweet_length(&"語".repeat(140)).is_ok());
+        assert!(check_tweet_length(&"語".repeat(141)).is_err());
+        assert!(check_tweet_length(&"👍🏽".repeat(140)).is_ok());
+        assert!(check_tweet_length(&format!("{}a", "👍".repeat(140))).is_err());
+
+        // Links count as t.co links, however long they are
+        let link = format!("https://example.com/{} ", "x".repeat(100));
+        let text = link.repeat(11);
+        assert!(text.chars().count() > MAX_TWEET_LENGTH);
+        assert!(check_tweet_length(&text).is_ok());
+        assert!(check_tweet_length(&link.repeat(12)).is_err());
+    }
+
+    #[test]
+    fn test_list_name() {
+        assert!(check_list_name("Rustaceans").is_ok());
+        assert!(check_list_name(&"語".repeat(MAX_LIST_NAME_LENGTH)).is_ok());
+        assert_eq!(
+            check_list_name(&"a".repeat(MAX_LIST_NAME_LENGTH + 1)).unwrap_err(),
+            "list name must be 1 to 25 characters (got 26)"
+        );
+        assert!(check_list_name("").is_err());
+        assert!(check_list_name("   ").is_err());
+    }
+
+    #[test]
+    fn test_reply_settings() {
+        let cases = [
+            ("everyone", None),
+            ("following", Some("following")),
+            ("mentioned_users", Some("mentionedUsers")),
+            ("mentionedUsers", Some("mentionedUsers")),
+            ("subscribers", Some("subscribers")),
+            ("Verified", Some("verified")),
+        ];
+        for (option, expected) in cases {
+            let value = reply_settings_value(option);
+            assert_eq!(value.as_deref(), expected, "{}", option);
+
+            let request = PostTweetRequest {
+                reply_settings: value,
+                ..request("hello")
+            };
+            assert!(check_reply_settings(&request).is_none(), "{}", option);
+            assert!(validate_tweet(&request).warnings.is_empty(), "{}", option);
+        }
+
+        // Values X adds later are passed through with a warning
+        let request = PostTweetRequest {
+            reply_settings: reply_settings_value("communityMembers"),
+            ..request("hello")
+        };
+        assert_eq!(request.reply_settings.as_deref(), Some("communityMembers"));
+        let validation = validate_tweet(&request);
+        assert!(validation.valid);
+        assert!(validation.warnings[0].contains("communityMembers"));
+    }
+
+    #[test]
+    fn test_parse_tweet_id() {
+        assert_eq!(
+            parse_tweet_id(" 1234567890 ").as_deref(),
+            Some("1234567890")
+        );
+        assert_eq!(
+            parse_tweet_id("https://x.com/jack/status/20?s=20").as_deref(),
+            Some("20")
+        );
+        assert_eq!(
+            parse_tweet_id("https://mobile.twitter.com/jack/status/20/photo/1").as_deref(),
+            Some("20")
+        );
+        assert_eq!(
+            parse_tweet_id("twitter.com/i/web/status/20").as_deref(),
+            Some("20")
+        );
+        assert_eq!(parse_tweet_id("https://x.com/jack"), None);
+        assert_eq!(parse_tweet_id("https://example.com/status/20"), None);
+        assert_eq!(parse_tweet_id("not-an-id"), None);
+    }
+
+    #[test]
+    fn test_validate_tweet() {
+        let validation = validate_tweet(&request("hello https://example.com"));
+        assert!(validation.valid);
+        assert_eq!(validation.weighted_length, 6 + URL_LENGTH);
+        assert_eq!(
+            validation.remaining,
+            (MAX_TWEET_LENGTH - 6 - URL_LENGTH) as i64
+        );
+        assert_eq!(validation.warnings.len(), 1);
+
+        let validation = validate_tweet(&request(&"a".repeat(290)));
+        assert!(!validation.valid);
+        assert_eq!(validation.remaining, -10);
+        assert!(validation.warnings[0].contains("by 10"));
+
+        let validation = validate_tweet(&request("   "));
+        assert!(!validation.valid);
+
+        let mut reply = request("hi");
+        reply.reply = Some(TweetReply {
+            in_reply_to_tweet_id: "not-an-id".to_string(),
+            ..Default::default()
+        });
+        assert!(!validate_tweet(&reply).valid);
+    }
+
+    #[test]
+    fn test_check_tweet_attachments() {
+        let poll = TweetPoll {
+            options: vec!["yes".to_string(), "no".to_string()],
+            duration_minutes: 60,
+        };
+        let media = TweetMedia {
+            media_ids: vec!["1455952740635586573".to_string()],
+            tagged_user_ids: None,
+        };
+
+        let mut tweet = request("pick one");
+        tweet.poll = Some(poll.clone());
+        assert!(check_tweet_attachments(&tweet).is_ok());
+        tweet.media = Some(media.clone());
+        assert_eq!(
+            check_tweet_attachments(&tweet).unwrap_err(),
+            "a poll cannot be combined with media"
+        );
+
+        tweet.media = None;
+        tweet.poll = Some(TweetPoll {
+            duration_minutes: 2,
+            ..poll.clone()
+        });
+        assert!(check_tweet_attachments(&tweet)
+            .unwrap_err()
+            .contains("5 to 10080 minutes"));
+        tweet.poll = Some(TweetPoll {
+            options: vec!["only".to_string()],
+            ..poll
+        });
+        assert!(check_tweet_attachments(&tweet)
+            .unwrap_err()
+            .contains("2 to 4 options"));
+
+        let mut tweet = request("card");
+        tweet.card_uri = Some("card://1".to_string());
+        assert!(check_tweet_attachments(&tweet).is_ok());
+        tweet.media = Some(media);
+        assert_eq!(
+            check_tweet_attachments(&tweet).unwrap_err(),
+            "card_uri cannot be combined with media"
+        );
+
+        let mut tweet = request("tagged");
+        tweet.media = Some(TweetMedia {
+            media_ids: Vec::new(),
+            tagged_user_ids: Some(vec!["12".to_string()]),
+        });
+        assert!(!validate_tweet(&tweet).valid);
+    }
+}
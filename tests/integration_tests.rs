@@ -1,14 +1,28 @@
 //! Integration tests for X MCP Server
 
+use rmcp::{handler::server::tool::schema_for_type, model::CallToolResult, ServerHandler};
+use serde::Deserialize;
 use serde_json::json;
+use std::path::PathBuf;
+use std::time::Duration;
 use x_mcp_server::{
-    client::XClient,
-    server::{GetUserArgs, SearchTweetsArgs},
-    types::SearchTweetsParams,
+    api::{ApiFuture, XApi},
+    capabilities::Capabilities,
+    circuit::{CircuitBreakerConfig, CircuitState},
+    client::{capture_raw_responses, XClient},
+    metrics::Metrics,
+    replay::{request_key, RecordedExchange, ReplayMode},
+    scheduler::SchedulerConfig,
+    transport::LineTransport,
+    server::{GetUserArgs, PostTweetArgs, SearchTweetsArgs, ToolOutput, UserData},
+    types::{Includes, SearchPage, SearchTweetsParams, Tweet, TweetIdRange, User, XResponse},
+    validate::validate_tweet,
+    XMcpServer,
 };
 
 /// Test that we can create a client
 #[test]
+#[allow(clippy::assertions_on_constants)]
 fn test_client_creation() {
     let _client = XClient::new("test_bearer_token".to_string());
     // Just test that creation works - we can't test much without a real token
@@ -45,9 +59,16 @@ fn test_search_params() {
     let params = SearchTweetsParams {
         query: "test query".to_string(),
         max_results: Some(50),
+        sort_order: None,
+        start_time: None,
+        end_time: None,
+        since_id: None,
+        until_id: None,
         tweet_fields: Some(vec!["id".to_string(), "text".to_string()]),
         user_fields: Some(vec!["username".to_string()]),
         expansions: Some(vec!["author_id".to_string()]),
+        media_fields: None,
+        next_token: None,
     };
     
     assert_eq!(params.query, "test query");
@@ -56,3 +77,4517 @@ fn test_search_params() {
     assert!(params.user_fields.is_some());
     assert!(params.expansions.is_some());
 }
+
+/// Initialize result shape as defined by the MCP specification
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecInitializeResult {
+    #[serde(rename = "protocolVersion")]
+    protocol_version: String,
+    capabilities: SpecCapabilities,
+    #[serde(rename = "serverInfo")]
+    server_info: SpecImplementation,
+    instructions: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpecCapabilities {
+    tools: Option<SpecToolsCapability>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecToolsCapability {
+    #[serde(rename = "listChanged")]
+    list_changed: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpecImplementation {
+    name: String,
+    title: Option<String>,
+    version: String,
+}
+
+/// Test that the initialize response uses the camelCase keys from the MCP spec
+#[test]
+fn test_initialize_result_uses_spec_field_names() {
+    let server = XMcpServer::new(XClient::new("test_bearer_token".to_string()));
+    let value = serde_json::to_value(server.get_info()).unwrap();
+
+    assert!(value.get("protocol_version").is_none());
+    assert!(value.get("server_info").is_none());
+
+    let result: SpecInitializeResult = serde_json::from_value(value).unwrap();
+    assert!(!result.protocol_version.is_empty());
+    assert_eq!(result.server_info.name, "x-mcp-server");
+    assert_eq!(result.server_info.version, x_mcp_server::VERSION);
+    assert!(result.server_info.title.is_some());
+    assert!(result.instructions.is_some());
+
+    // The tool set is static, so the server never promises change notifications
+    let tools = result
+        .capabilities
+        .tools
+        .expect("tools capability advertised");
+    assert_ne!(tools.list_changed, Some(true));
+}
+
+/// Test the exact keys of the initialize response as sent to the client
+#[tokio::test]
+async fn test_initialize_response_keys() {
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(XClient::new(String::new())));
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+
+    let mut keys: Vec<&str> = response["result"].as_object().unwrap().keys().map(String::as_str).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, ["capabilities", "instructions", "protocolVersion", "serverInfo"]);
+    let mut server_keys: Vec<&str> = response["result"]["serverInfo"]
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect();
+    server_keys.sort_unstable();
+    assert_eq!(server_keys, ["name", "title", "version"]);
+}
+
+/// Test that embedders can override the initialize instructions
+#[test]
+fn test_custom_instructions() {
+    let server = XMcpServer::new(XClient::new("test_bearer_token".to_string()));
+    let default = server.get_info().instructions.unwrap();
+    assert!(default.contains("rate limit"));
+    assert!(default.contains("public"));
+
+    let server = server.with_instructions("Only use search_tweets.".to_string());
+    assert_eq!(
+        server.get_info().instructions.as_deref(),
+        Some("Only use search_tweets.")
+    );
+}
+
+/// Test that tool results carry structured content matching the text content
+#[test]
+fn test_tool_output_structured_content() {
+    let user: User = serde_json::from_value(json!({
+        "id": "12",
+        "name": "Jack",
+        "username": "jack"
+    }))
+    .unwrap();
+
+    let result: CallToolResult = ToolOutput::ok(UserData { user }).into();
+    let structured = result.structured_content.clone().unwrap();
+    assert_eq!(structured["success"], true);
+    assert_eq!(structured["user"]["username"], "jack");
+
+    let text = &result.content[0].as_text().unwrap().text;
+    let parsed: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert_eq!(parsed, structured);
+
+    let result: CallToolResult = ToolOutput::<UserData>::error("User not found").into();
+    let structured = result.structured_content.unwrap();
+    assert_eq!(
+        structured,
+        json!({ "success": false, "error": "User not found" })
+    );
+}
+
+/// Test that output schemas describe the success envelope and payload
+#[test]
+fn test_tool_output_schema() {
+    let schema = schema_for_type::<ToolOutput<UserData>>();
+    assert_eq!(schema["type"], "object");
+    assert!(schema["required"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("success")));
+    assert!(schema["properties"].get("user").is_some());
+    assert!(schema["properties"].get("error").is_some());
+}
+
+/// Test that previewing a draft validates the same body post_tweet would send
+#[test]
+fn test_preview_tweet_args() {
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Reading https://modelcontextprotocol.io today",
+        "reply_to": "1234567890"
+    }))
+    .unwrap();
+
+    let request = args.to_request();
+    assert_eq!(
+        request.reply.as_ref().unwrap().in_reply_to_tweet_id,
+        "1234567890"
+    );
+
+    let validation = validate_tweet(&request);
+    assert!(validation.valid);
+    assert_eq!(validation.weighted_length, 8 + 23 + 6);
+    assert!(validation.warnings[0].contains("shortened"));
+}
+
+/// Test that reply_to and quote_tweet_id accept tweet URLs as well as IDs
+#[test]
+fn test_post_tweet_args_accept_urls() {
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Agreed",
+        "reply_to": "https://x.com/jack/status/20?s=20",
+        "quote_tweet_id": "1234567890"
+    }))
+    .unwrap();
+    let request = args.to_request();
+    assert_eq!(request.reply.as_ref().unwrap().in_reply_to_tweet_id, "20");
+    assert_eq!(request.quote_tweet_id.as_deref(), Some("1234567890"));
+    assert!(validate_tweet(&request).valid);
+
+    let body = serde_json::to_value(&request).unwrap();
+    assert_eq!(body["quote_tweet_id"], "1234567890");
+
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Agreed",
+        "quote_tweet_id": "https://example.com/status/20"
+    }))
+    .unwrap();
+    let validation = validate_tweet(&args.to_request());
+    assert!(!validation.valid);
+    assert!(validation.warnings[0].contains("quote_tweet_id"));
+}
+
+/// Test that poll arguments become the request's poll and are validated
+#[test]
+fn test_post_tweet_args_poll() {
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Tabs or spaces?",
+        "poll_options": ["Tabs", "Spaces"]
+    }))
+    .unwrap();
+    let request = args.to_request();
+    assert!(validate_tweet(&request).valid);
+    let body = serde_json::to_value(&request).unwrap();
+    assert_eq!(
+        body["poll"],
+        json!({ "options": ["Tabs", "Spaces"], "duration_minutes": 1440 })
+    );
+
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Tabs or spaces?",
+        "poll_options": ["Tabs", "Spaces", "Both", "Neither", "Whatever"],
+        "poll_duration_minutes": 60
+    }))
+    .unwrap();
+    let validation = validate_tweet(&args.to_request());
+    assert!(!validation.valid);
+    assert!(validation.warnings[0].contains("2 to 4 options"));
+
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Tabs or spaces?",
+        "poll_options": ["Tabs", "Spaces"],
+        "poll_duration_minutes": 3
+    }))
+    .unwrap();
+    assert!(!validate_tweet(&args.to_request()).valid);
+
+    let args: PostTweetArgs = serde_json::from_value(json!({
+        "text": "Tabs or spaces?",
+        "poll_duration_minutes": 60
+    }))
+    .unwrap();
+    assert!(!validate_tweet(&args.to_request()).valid);
+}
+
+/// Test that XClient::post_tweet refuses a quote X would reject before
+/// calling X
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_tweet_rejects_inconsistent_quote() {
+    use x_mcp_server::types::{PostTweetRequest, TweetPoll};
+    use x_mcp_server::XError;
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(fixture_dir("no-quote-post")));
+    let request = PostTweetRequest {
+        text: "Which one?".to_string(),
+        quote_tweet_id: Some("20".to_string()),
+        poll: Some(TweetPoll {
+            options: vec!["This".to_string(), "That".to_string()],
+            duration_minutes: 60,
+        }),
+        ..Default::default()
+    };
+    match client.post_tweet(&request).await {
+        Err(XError::Generic(message)) => assert!(message.contains("quote"), "{}", message),
+        other => panic!("expected XError::Generic, got {:?}", other),
+    }
+
+    let request = PostTweetRequest {
+        text: "Agreed".to_string(),
+        quote_tweet_id: Some("https://example.com/status/20".to_string()),
+        ..Default::default()
+    };
+    match client.post_tweet(&request).await {
+        Err(XError::Generic(message)) => assert!(message.contains("quote_tweet_id"), "{}", message),
+        other => panic!("expected XError::Generic, got {:?}", other),
+    }
+}
+
+/// Test detection of tweets with attached media
+#[test]
+fn test_tweet_media_keys() {
+    let tweet: Tweet = serde_json::from_value(json!({
+        "id": "1",
+        "text": "look at this",
+        "attachments": { "media_keys": ["3_1", "3_2"] }
+    }))
+    .unwrap();
+    assert!(tweet.has_media());
+    assert_eq!(tweet.media_keys().collect::<Vec<_>>(), vec!["3_1", "3_2"]);
+
+    let tweet: Tweet = serde_json::from_value(json!({ "id": "2", "text": "plain" })).unwrap();
+    assert!(!tweet.has_media());
+}
+
+/// Test that bad thread resume tokens are rejected before anything is posted
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_thread_rejects_bad_resume_token() {
+    let client = XClient::new("test_bearer_token".to_string());
+    let texts = vec!["one".to_string(), "two".to_string()];
+
+    for token in ["garbage", "abc:1", "123:x", "123:5"] {
+        assert!(
+            client.post_thread(&texts, Some(token)).await.is_err(),
+            "token {:?} should be rejected",
+            token
+        );
+    }
+}
+
+/// Test that overlong tweets are refused before anything is posted
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_rejects_overlong_text() {
+    // Nothing recorded, so any request to X would fail differently
+    let client =
+        XClient::new(String::new()).with_replay(ReplayMode::Replay(fixture_dir("overlong-tweets")));
+    let overlong = "語".repeat(141);
+
+    let request = serde_json::from_value::<PostTweetArgs>(json!({ "text": overlong }))
+        .unwrap()
+        .to_request();
+    let error = client.post_tweet(&request).await.unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("tweet exceeds 280 characters by 2 (got 282)"));
+
+    let texts = vec!["one".to_string(), overlong];
+    let error = client.post_thread(&texts, None).await.unwrap_err();
+    assert!(error.to_string().contains("Tweet 2 of the thread"));
+}
+
+/// Test that resuming a finished thread posts nothing
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_thread_resume_at_end() {
+    let client = XClient::new("test_bearer_token".to_string());
+    let texts = vec!["one".to_string(), "two".to_string()];
+
+    let result = client.post_thread(&texts, Some("123:2")).await.unwrap();
+    assert!(result.tweets.is_empty());
+    assert!(result.error.is_none());
+    assert!(result.resume_token.is_none());
+}
+
+/// Test that a cancelled thread stops before posting and can be resumed
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_thread_cancelled() {
+    let client = XClient::new("test_bearer_token".to_string());
+    let texts = vec!["one".to_string(), "two".to_string()];
+
+    let result = client
+        .post_thread_until(&texts, Some("123:1"), std::future::ready(()))
+        .await
+        .unwrap();
+    assert!(result.tweets.is_empty());
+    assert!(result.error.unwrap().contains("Thread cancelled"));
+    assert_eq!(result.resume_token.as_deref(), Some("123:1"));
+}
+
+/// Test that a thread rate-limited partway waits for the window to reset and
+/// completes
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_thread_rate_limited() {
+    use x_mcp_server::config::RetryPolicy;
+
+    let reset = chrono::Utc::now().timestamp() + 2;
+    let rate_limited = format!(
+        "HTTP/1.1 429 Too Many Requests\r\nx-rate-limit-limit: 200\r\nx-rate-limit-remaining: 0\r\nx-rate-limit-reset: {}\r\ncontent-length: 2\r\n\r\n{{}}",
+        reset
+    );
+    let (address, served) = serve_responses(vec![
+        json_response(r#"{"data":{"id":"101","text":"one"}}"#),
+        rate_limited,
+        json_response(r#"{"data":{"id":"102","text":"two"}}"#),
+    ])
+    .await;
+    let base_url = format!("{}/2", address);
+    let client = XClient::new("token".to_string())
+        .with_base_urls(&base_url, &base_url)
+        .with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(100),
+            max_wait: Duration::from_secs(5),
+        });
+    let texts = vec!["one".to_string(), "two".to_string()];
+
+    let started = std::time::Instant::now();
+    let result = client.post_thread(&texts, None).await.unwrap();
+    assert_eq!(result.error, None);
+    let ids: Vec<&str> = result.tweets.iter().map(|tweet| tweet.id.as_str()).collect();
+    assert_eq!(ids, ["101", "102"]);
+    // Waited for the reset, not only the 10ms backoff
+    assert!(started.elapsed() >= Duration::from_secs(1), "{:?}", started.elapsed());
+
+    let requests = served.await.unwrap();
+    assert!(requests[1].contains(r#""in_reply_to_tweet_id":"101""#), "{}", requests[1]);
+    assert!(requests[2].contains(r#""in_reply_to_tweet_id":"101""#), "{}", requests[2]);
+}
+
+/// Test that partial metrics and unknown fields don't fail the whole response
+#[test]
+fn test_partial_response_parsing() {
+    let response: XResponse<User> = serde_json::from_value(json!({
+        "data": {
+            "id": "12",
+            "name": "Jack",
+            "username": "jack",
+            "public_metrics": {
+                "followers_count": 100,
+                "following_count": 5,
+                "tweet_count": 42,
+                "like_count": 7
+            },
+            "some_new_field": { "nested": true }
+        }
+    }))
+    .unwrap();
+    let metrics = response.data.unwrap().public_metrics.unwrap();
+    assert_eq!(metrics.followers_count, 100);
+    assert_eq!(metrics.listed_count, 0);
+
+    let tweet: Tweet = serde_json::from_value(json!({
+        "id": "1",
+        "text": "hi",
+        "public_metrics": { "like_count": 3, "impression_count": 50 },
+        "context_annotations": [{ "domain": { "id": "10" }, "entity": { "id": "20" } }]
+    }))
+    .unwrap();
+    let metrics = tweet.public_metrics.unwrap();
+    assert_eq!(metrics.like_count, 3);
+    assert_eq!(metrics.retweet_count, 0);
+    assert_eq!(tweet.context_annotations.unwrap()[0].domain.name, "");
+}
+
+/// Test the concurrency limit configuration
+#[test]
+fn test_max_concurrency() {
+    let client = XClient::new("test_bearer_token".to_string());
+    assert_eq!(
+        client.max_concurrency(),
+        x_mcp_server::client::DEFAULT_MAX_CONCURRENCY
+    );
+    assert_eq!(client.in_flight(), 0);
+
+    let client = client.with_max_concurrency(0);
+    assert_eq!(client.max_concurrency(), 1);
+
+    let clone = client.clone().with_max_concurrency(3);
+    assert_eq!(clone.max_concurrency(), 3);
+    assert_eq!(clone.in_flight(), 0);
+}
+
+/// Test HTML entity decoding of tweet text
+#[test]
+fn test_tweet_decoded_text() {
+    let tweet = |text: &str| -> Tweet {
+        serde_json::from_value(json!({ "id": "1", "text": text })).unwrap()
+    };
+
+    assert_eq!(tweet("Tom &amp; Jerry").decoded_text(), "Tom & Jerry");
+    assert_eq!(tweet("1 &lt; 2 &gt; 0").decoded_text(), "1 < 2 > 0");
+    assert_eq!(
+        tweet("&quot;hi&quot; it&#39;s").decoded_text(),
+        "\"hi\" it's"
+    );
+    // Decoded once only
+    assert_eq!(tweet("&amp;lt;b&amp;gt;").decoded_text(), "&lt;b&gt;");
+    // Stray ampersands and unknown entities are left alone
+    assert_eq!(tweet("AT&T &nbsp; &").decoded_text(), "AT&T &nbsp; &");
+
+    let args: SearchTweetsArgs = serde_json::from_value(json!({ "query": "q" })).unwrap();
+    assert!(args.output.decode_html);
+    // The shared output options are top-level arguments
+    let schema = schema_for_type::<SearchTweetsArgs>();
+    for option in ["decode_html", "human_times", "timezone", "raw"] {
+        assert!(schema["properties"].get(option).is_some(), "{} missing", option);
+    }
+}
+
+/// Fresh per-test directory for recorded fixtures
+fn fixture_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("x-mcp-server-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+/// Record a successful GET response for `url` in `dir`
+fn record_get(
+    dir: &std::path::Path,
+    url: &str,
+    query: &[(&str, &str)],
+    response: serde_json::Value,
+) {
+    record_get_status(dir, url, query, 200, response);
+}
+
+/// Record a GET response with the given status for `url` in `dir`
+fn record_get_status(
+    dir: &std::path::Path,
+    url: &str,
+    query: &[(&str, &str)],
+    status: u16,
+    response: serde_json::Value,
+) {
+    RecordedExchange {
+        method: "GET".to_string(),
+        url: url.to_string(),
+        query: query
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: None,
+        status,
+        response,
+    }
+    .save(dir)
+    .unwrap();
+}
+
+/// Query `XClient::get_tweet` sends
+const GET_TWEET_QUERY: [(&str, &str); 3] = [
+    (
+        "tweet.fields",
+        "id,text,author_id,created_at,public_metrics,context_annotations,entities,referenced_tweets,attachments,edit_history_tweet_ids,edit_controls",
+    ),
+    ("expansions", "author_id,attachments.media_keys"),
+    (
+        "media.fields",
+        "media_key,type,url,preview_image_url,width,height,duration_ms,alt_text",
+    ),
+];
+
+/// Test that replay mode serves recorded responses without the network
+#[tokio::test]
+async fn test_replay_serves_recorded_response() {
+    let dir = fixture_dir("replay");
+    RecordedExchange {
+        method: "GET".to_string(),
+        url: "https://api.twitter.com/2/users/by/username/jack".to_string(),
+        query: vec![(
+            "user.fields".to_string(),
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at"
+                .to_string(),
+        )],
+        body: None,
+        status: 200,
+        response: json!({ "data": { "id": "12", "name": "jack", "username": "jack" } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir.clone()));
+    let user = client.get_user_by_username("jack").await.unwrap().unwrap();
+    assert_eq!(user.id, "12");
+
+    // Requests that were never recorded fail instead of going to the network
+    let err = client.get_user_by_username("nobody").await.unwrap_err();
+    assert!(err.to_string().contains("No recorded response"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Test that recorded error responses replay as API errors
+#[tokio::test]
+async fn test_replay_error_status() {
+    let dir = fixture_dir("replay-error");
+    RecordedExchange {
+        method: "GET".to_string(),
+        url: "https://api.twitter.com/2/users/12".to_string(),
+        query: vec![(
+            "user.fields".to_string(),
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at"
+                .to_string(),
+        )],
+        body: None,
+        status: 503,
+        response: json!("Service Unavailable"),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir.clone()));
+    match client.get_user_by_id("12").await {
+        Err(x_mcp_server::XError::Api { status, message }) => {
+            assert_eq!(status, 503);
+            assert_eq!(message, "Service Unavailable");
+        }
+        other => panic!("expected API error, got {:?}", other),
+    }
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Test that X's error JSON becomes a readable message, keeping the status
+#[tokio::test]
+async fn test_api_error_messages() {
+    let dir = fixture_dir("api-error-messages");
+    let user_fields = [(
+        "user.fields",
+        "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+    )];
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/users/1",
+        &user_fields,
+        401,
+        json!({ "title": "Unauthorized", "type": "about:blank", "status": 401, "detail": "Unauthorized" }),
+    );
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/users/2",
+        &user_fields,
+        400,
+        json!({
+            "errors": [{ "parameters": { "id": ["x"] }, "message": "The `id` query parameter value [x] is not valid" }],
+            "title": "Invalid Request",
+            "detail": "One or more parameters to your request was invalid.",
+            "type": "https://api.twitter.com/2/problems/invalid-request"
+        }),
+    );
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/users/3",
+        &user_fields,
+        502,
+        json!({ "upstream": "down" }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let message = |id: &'static str| {
+        let client = client.clone();
+        async move {
+            match client.get_user_by_id(id).await {
+                Err(x_mcp_server::XError::Api { status, message }) => (status, message),
+                other => panic!("expected API error, got {:?}", other),
+            }
+        }
+    };
+    assert_eq!(message("1").await, (401, "Unauthorized".to_string()));
+    assert_eq!(
+        message("2").await,
+        (
+            400,
+            "Invalid Request: One or more parameters to your request was invalid. \
+             (The `id` query parameter value [x] is not valid)"
+                .to_string()
+        )
+    );
+    let (status, raw) = message("3").await;
+    assert_eq!(status, 502);
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&raw).unwrap(), json!({ "upstream": "down" }));
+}
+
+/// Test that fixture keys are stable and sensitive to every request part
+#[test]
+fn test_request_key() {
+    let query = [("query", "rust".to_string())];
+    let key = request_key(
+        "GET",
+        "https://api.twitter.com/2/tweets/search/recent",
+        &query,
+        None,
+    );
+    assert_eq!(
+        key,
+        request_key(
+            "get",
+            "https://api.twitter.com/2/tweets/search/recent",
+            &query,
+            None
+        )
+    );
+    assert_eq!(key.len(), 16);
+
+    let other_query = [("query", "rustlang".to_string())];
+    assert_ne!(
+        key,
+        request_key(
+            "GET",
+            "https://api.twitter.com/2/tweets/search/recent",
+            &other_query,
+            None
+        )
+    );
+    assert_ne!(
+        key,
+        request_key(
+            "POST",
+            "https://api.twitter.com/2/tweets/search/recent",
+            &query,
+            Some(&json!({}))
+        )
+    );
+}
+
+/// Test trend location lookup by place name and its cache
+#[tokio::test]
+async fn test_find_woeid() {
+    let dir = fixture_dir("trend-locations");
+    record_get(
+        &dir,
+        "https://api.twitter.com/1.1/trends/available.json",
+        &[],
+        json!([
+            { "name": "Worldwide", "woeid": 1, "country": "", "countryCode": null,
+              "placeType": { "code": 19, "name": "Supername" } },
+            { "name": "Tokyo", "woeid": 1118370, "country": "Japan", "countryCode": "JP",
+              "placeType": { "code": 7, "name": "Town" } },
+            { "name": "Brazil", "woeid": 23424768, "country": "Brazil", "countryCode": "BR",
+              "placeType": { "code": 12, "name": "Country" } },
+            { "name": "Rio de Janeiro", "woeid": 455825, "country": "Brazil", "countryCode": "BR",
+              "placeType": { "code": 7, "name": "Town" } }
+        ]),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir.clone()));
+    assert_eq!(
+        client.find_woeid("tokyo").await.unwrap().unwrap().woeid,
+        1118370
+    );
+    assert_eq!(
+        client.find_woeid(" BRAZIL ").await.unwrap().unwrap().woeid,
+        23424768
+    );
+    assert_eq!(
+        client.find_woeid("rio").await.unwrap().unwrap().woeid,
+        455825
+    );
+    assert!(client.find_woeid("Atlantis").await.unwrap().is_none());
+
+    // Served from the cache once fetched
+    std::fs::remove_dir_all(dir).unwrap();
+    assert_eq!(client.get_trend_locations().await.unwrap().len(), 4);
+}
+
+/// Test that reply context joins the author and the conversation's replies
+#[tokio::test]
+async fn test_get_reply_context() {
+    let dir = fixture_dir("reply-context");
+    let tweet_fields = "id,text,author_id,created_at,public_metrics,referenced_tweets,conversation_id,reply_settings";
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &[
+            ("tweet.fields", tweet_fields),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+        ],
+        json!({
+            "data": { "id": "42", "text": "hello", "author_id": "7", "conversation_id": "40",
+                      "reply_settings": "following" },
+            "includes": { "users": [{ "id": "7", "name": "Jack", "username": "jack" }] }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "conversation_id:40 is:reply"),
+            ("max_results", "10"),
+            ("tweet.fields", tweet_fields),
+        ],
+        json!({
+            "data": [
+                { "id": "44", "text": "third" },
+                { "id": "43", "text": "second" },
+                { "id": "41", "text": "first" }
+            ]
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let context = client.get_reply_context("42", 2).await.unwrap().unwrap();
+    assert_eq!(context.tweet.reply_settings.as_deref(), Some("following"));
+    assert_eq!(context.author.unwrap().username, "jack");
+    let replies: Vec<&str> = context.replies.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(replies, ["44", "43"]);
+}
+
+/// Test that conversation replies are nested under their parents
+#[tokio::test]
+async fn test_get_conversation_tree() {
+    let dir = fixture_dir("conversation-tree");
+    let tweet_fields = "id,text,author_id,created_at,public_metrics,referenced_tweets,conversation_id,reply_settings";
+    let user_fields =
+        "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    let lookup_query = [
+        ("tweet.fields", tweet_fields),
+        ("expansions", "author_id"),
+        ("user.fields", user_fields),
+    ];
+    let reply = |id: &str, parent: &str| {
+        json!({ "id": id, "text": format!("reply {}", id), "author_id": "8", "conversation_id": "40",
+                "referenced_tweets": [{ "type": "replied_to", "id": parent }] })
+    };
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &lookup_query,
+        json!({ "data": reply("42", "41") }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/40",
+        &lookup_query,
+        json!({
+            "data": { "id": "40", "text": "root", "author_id": "7", "conversation_id": "40",
+                      "created_at": "2020-01-01T00:00:00.000Z" },
+            "includes": { "users": [{ "id": "7", "name": "Jack", "username": "jack" }] }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "conversation_id:40"),
+            ("max_results", "10"),
+            ("tweet.fields", tweet_fields),
+            ("user.fields", user_fields),
+            ("expansions", "author_id"),
+        ],
+        json!({
+            "data": [reply("44", "42"), reply("43", "99"), reply("42", "41"), reply("41", "40")],
+            "includes": { "users": [{ "id": "8", "name": "Ann", "username": "ann" }] }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let tree = client
+        .get_conversation_tree("42", 2, 10)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(tree.root.tweet.as_ref().unwrap().text, "root");
+    assert!(tree.beyond_search_window);
+    assert_eq!(tree.authors.len(), 2);
+
+    // 41 -> 42 (44 is too deep), then the reply to deleted tweet 99
+    let replies = &tree.root.replies;
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0].id, "41");
+    assert_eq!(replies[0].replies[0].id, "42");
+    assert!(replies[0].replies[0].replies.is_empty());
+    assert_eq!(replies[1].id, "99");
+    assert!(replies[1].tweet.is_none());
+    assert_eq!(replies[1].replies[0].id, "43");
+    assert!(tree.truncated);
+    assert_eq!(tree.tweet_count, 4);
+}
+
+/// Test that `get_replies` lists a conversation's replies without its root
+#[tokio::test]
+async fn test_get_replies() {
+    let dir = fixture_dir("replies");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "conversation_id:40"),
+            ("max_results", "10"),
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,conversation_id,in_reply_to_user_id",
+            ),
+        ],
+        json!({
+            "data": [
+                { "id": "42", "text": "second &amp; last", "in_reply_to_user_id": "8" },
+                { "id": "41", "text": "first", "in_reply_to_user_id": "7" },
+                { "id": "40", "text": "root" }
+            ]
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let replies = client.get_conversation("40", None).await.unwrap();
+    let ids: Vec<&str> = replies.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["42", "41"]);
+
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let data = call_tool(
+        &mut input,
+        &mut output,
+        "get_replies",
+        json!({ "tweet_id": "https://x.com/jack/status/40", "max_results": 1 }),
+    )
+    .await;
+    assert_eq!(data["conversation_id"], "40");
+    assert_eq!(data["count"], 1);
+    assert_eq!(data["tweets"][0]["text"], "second & last");
+    assert_eq!(data["tweets"][0]["in_reply_to_user_id"], "8");
+}
+
+/// Test pinned tweet lookup, including users without one
+#[tokio::test]
+async fn test_get_pinned_tweet() {
+    let dir = fixture_dir("pinned-tweet");
+    let query = [
+        (
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at,pinned_tweet_id",
+        ),
+        ("expansions", "pinned_tweet_id"),
+        ("tweet.fields", "id,text,author_id,created_at,public_metrics,attachments"),
+    ];
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/jack",
+        &query,
+        json!({
+            "data": { "id": "7", "name": "Jack", "username": "jack", "pinned_tweet_id": "20" },
+            "includes": { "tweets": [{ "id": "20", "text": "just setting up my twttr" }] }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/8",
+        &query,
+        json!({ "data": { "id": "8", "name": "Quiet", "username": "quiet" } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let pinned = client.get_pinned_tweet("@jack").await.unwrap().unwrap();
+    assert_eq!(pinned.id, "20");
+    assert!(client.get_pinned_tweet("8").await.unwrap().is_none());
+}
+
+/// Test batch tweet lookup with deduplication and missing IDs
+#[tokio::test]
+async fn test_get_tweets() {
+    let dir = fixture_dir("get-tweets");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets",
+        &[
+            ("ids", "1,2,3"),
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+        ],
+        json!({
+            "data": [
+                { "id": "3", "text": "three", "author_id": "7" },
+                { "id": "1", "text": "one", "author_id": "7" }
+            ],
+            "includes": { "users": [{ "id": "7", "name": "Jack", "username": "jack" }] },
+            "errors": [{ "title": "Not Found Error", "value": "2", "resource_type": "tweet" }]
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let ids: Vec<String> = ["1", "2", "1", "oops", "3"]
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    let lookup = client.get_tweets(&ids).await.unwrap();
+
+    let found: Vec<&str> = lookup.tweets.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(found, ["1", "3"]);
+    assert!(!lookup.tweets[0].is_edited());
+    assert_eq!(lookup.authors.len(), 1);
+    assert_eq!(lookup.missing_ids, ["oops", "2"]);
+}
+
+/// Test the opt-in batch fallback when the single-tweet endpoint is rate limited
+#[tokio::test]
+async fn test_get_tweet_rate_limit_fallback() {
+    let dir = fixture_dir("tweet-fallback");
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        429,
+        json!({ "title": "Too Many Requests", "status": 429 }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets",
+        &[
+            ("ids", "42"),
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+        ],
+        json!({ "data": [{ "id": "42", "text": "found anyway" }] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    assert!(matches!(
+        client.get_tweet("42").await,
+        Err(x_mcp_server::XError::RateLimited { .. })
+    ));
+
+    let client = client.with_rate_limit_fallback(true);
+    let tweet = client.get_tweet("42").await.unwrap().unwrap();
+    assert_eq!(tweet.text, "found anyway");
+}
+
+/// Test that media fields are parsed from `includes` alongside the tweet
+#[tokio::test]
+async fn test_get_tweet_with_media() {
+    let dir = fixture_dir("tweet-media");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        json!({
+            "data": { "id": "42", "text": "look", "attachments": { "media_keys": ["3_1", "7_2"] },
+                      "edit_history_tweet_ids": ["41", "42"],
+                      "edit_controls": { "edits_remaining": 4, "is_edit_eligible": true,
+                                         "editable_until": "2024-05-01T12:33:00.000Z" } },
+            "includes": { "media": [
+                { "media_key": "3_1", "type": "photo", "url": "https://pbs.twimg.com/media/a.jpg",
+                  "width": 1200, "height": 800, "alt_text": "A cat" },
+                { "media_key": "7_2", "type": "video", "preview_image_url": "https://pbs.twimg.com/b.jpg",
+                  "width": 1920, "height": 1080, "duration_ms": 15000 }
+            ] }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (tweet, media) = client.get_tweet_with_media("42").await.unwrap();
+    let tweet = tweet.unwrap();
+    assert!(tweet.has_media());
+    assert!(tweet.is_edited());
+    assert_eq!(tweet.edit_controls.unwrap().edits_remaining, Some(4));
+    assert_eq!(media.len(), 2);
+    assert_eq!(media[0].alt_text.as_deref(), Some("A cat"));
+    assert_eq!((media[0].width, media[0].height), (Some(1200), Some(800)));
+    assert_eq!(media[1].duration_ms, Some(15000));
+    assert!(media[1].url.is_none());
+}
+
+/// Test that raw responses are captured unmodified only when asked for
+#[tokio::test]
+async fn test_capture_raw_responses() {
+    let dir = fixture_dir("tweet-raw");
+    let response = json!({
+        "data": { "id": "42", "text": "hi", "edit_history_tweet_ids": ["42"] }
+    });
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        response.clone(),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (tweet, raw) = capture_raw_responses(client.get_tweet("42")).await;
+    assert_eq!(tweet.unwrap().unwrap().text, "hi");
+    assert_eq!(raw, vec![response]);
+
+    // Without the scope only the typed value comes back
+    assert!(client.get_tweet("42").await.unwrap().is_some());
+
+    let output = serde_json::to_value(ToolOutput::ok(UserData {
+        user: serde_json::from_value(json!({ "id": "1", "name": "A", "username": "a" })).unwrap(),
+    }))
+    .unwrap();
+    assert!(output.get("raw").is_none());
+}
+
+/// Test that v1.1 user search results are mapped to the v2 user shape
+#[tokio::test]
+async fn test_search_users() {
+    let dir = fixture_dir("search-users");
+    record_get(
+        &dir,
+        "https://api.twitter.com/1.1/users/search.json",
+        &[
+            ("q", "rust"),
+            ("count", "20"),
+            ("include_entities", "false"),
+        ],
+        json!([{
+            "id": 12345,
+            "id_str": "12345",
+            "name": "Rust Language",
+            "screen_name": "rustlang",
+            "description": "",
+            "followers_count": 150000,
+            "friends_count": 10,
+            "statuses_count": 4000,
+            "listed_count": 2000,
+            "profile_image_url_https": "https://pbs.twimg.com/profile_images/rust.png",
+            "verified": false,
+            "created_at": "Wed Oct 10 20:19:24 +0000 2018"
+        }]),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let users = client.search_users("rust", 50).await.unwrap();
+    assert_eq!(users.len(), 1);
+    let user = &users[0];
+    assert_eq!(user.id, "12345");
+    assert_eq!(user.username, "rustlang");
+    assert!(user.description.is_none());
+    assert_eq!(user.public_metrics.as_ref().unwrap().following_count, 10);
+    assert_eq!(user.created_at.as_deref(), Some("2018-10-10T20:19:24.000Z"));
+}
+
+/// Test that API request latency and failures are exported per endpoint
+#[tokio::test]
+async fn test_api_metrics() {
+    let dir = fixture_dir("metrics");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        json!({ "data": { "id": "42", "text": "hi" } }),
+    );
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/tweets/43",
+        &GET_TWEET_QUERY,
+        503,
+        json!({ "title": "Service Unavailable" }),
+    );
+
+    let metrics = Metrics::new();
+    let client = XClient::new(String::new())
+        .with_replay(ReplayMode::Replay(dir))
+        .with_metrics(metrics.clone());
+    client.get_tweet("42").await.unwrap();
+    client.get_tweet("43").await.unwrap_err();
+
+    let text = metrics.render();
+    assert!(text.contains("x_mcp_api_requests_total{endpoint=\"GET /2/tweets/:id\"} 2"));
+    assert!(text.contains("x_mcp_api_errors_total{endpoint=\"GET /2/tweets/:id\"} 1"));
+    assert!(text.contains("x_mcp_api_duration_seconds_count{endpoint=\"GET /2/tweets/:id\"} 2"));
+
+    let server = XMcpServer::new(XClient::new(String::new()));
+    assert_eq!(server.metrics_text(), "");
+}
+
+/// Test that repeated server errors open the circuit and short-circuit requests
+#[tokio::test]
+async fn test_circuit_breaker() {
+    let dir = fixture_dir("circuit");
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        503,
+        json!({ "title": "Service Unavailable" }),
+    );
+
+    let client = XClient::new(String::new())
+        .with_replay(ReplayMode::Replay(dir))
+        .with_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(60),
+        });
+    for _ in 0..2 {
+        assert!(matches!(
+            client.get_tweet("42").await,
+            Err(x_mcp_server::XError::Api { status: 503, .. })
+        ));
+    }
+
+    assert_eq!(client.circuit_status().state, CircuitState::Open);
+    assert!(matches!(
+        client.get_tweet("42").await,
+        Err(x_mcp_server::XError::CircuitOpen { .. })
+    ));
+    // Clones share the breaker
+    assert_eq!(client.clone().circuit_status().state, CircuitState::Open);
+}
+
+type McpInput = tokio::io::WriteHalf<tokio::io::DuplexStream>;
+type McpOutput =
+    tokio::io::Lines<tokio::io::BufReader<tokio::io::ReadHalf<tokio::io::DuplexStream>>>;
+
+/// Serve `server` over an in-memory pipe, returning its stdin and stdout
+fn serve_in_memory(server: XMcpServer) -> (McpInput, McpOutput) {
+    use rmcp::ServiceExt;
+    use tokio::io::AsyncBufReadExt;
+
+    let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let (read, write) = tokio::io::split(server_end);
+        if let Ok(service) = server.serve(LineTransport::new(read, write)).await {
+            let _ = service.waiting().await;
+        }
+    });
+
+    let (read, write) = tokio::io::split(client_end);
+    (write, tokio::io::BufReader::new(read).lines())
+}
+
+/// Write one newline-delimited JSON-RPC message
+async fn send_message(input: &mut McpInput, message: serde_json::Value) {
+    use tokio::io::AsyncWriteExt;
+    input
+        .write_all(format!("{}\n", message).as_bytes())
+        .await
+        .unwrap();
+}
+
+/// Read the next JSON-RPC message the server writes
+async fn receive_message(output: &mut McpOutput) -> serde_json::Value {
+    let line = tokio::time::timeout(Duration::from_secs(5), output.next_line())
+        .await
+        .expect("server should answer")
+        .unwrap()
+        .unwrap();
+    serde_json::from_str(&line).unwrap()
+}
+
+/// Test that a request X never answers fails with a timeout message
+#[tokio::test]
+async fn test_request_timeout() {
+    // Accepts connections but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let base_url = format!("http://{}/2", address);
+    let client = XClient::new("token".to_string())
+        .with_base_url(base_url)
+        .with_timeout(Duration::from_millis(200))
+        .unwrap();
+    let error = client.get_user_by_username("jack").await.unwrap_err();
+    assert!(
+        matches!(&error, x_mcp_server::XError::Generic(message) if message == "request timed out after 200ms"),
+        "{:?}",
+        error
+    );
+}
+
+/// Test that `notifications/cancelled` stops a tool call that is waiting on X
+#[tokio::test]
+async fn test_cancel_tool_call() {
+    // Accepts connections but never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut connections = Vec::new();
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+
+    let base_url = format!("http://{}/2", address);
+    let client = XClient::new("token".to_string()).with_base_urls(&base_url, &base_url);
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+
+    let messages = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "get_tweet", "arguments": { "tweet_id": "42" } } }),
+    ];
+    for message in messages {
+        send_message(&mut input, message).await;
+    }
+    let initialized = output.next_line().await.unwrap().unwrap();
+    assert!(initialized.contains("\"id\":1"));
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "method": "notifications/cancelled",
+                "params": { "requestId": 2, "reason": "taking too long" } }),
+    )
+    .await;
+
+    let response = tokio::time::timeout(Duration::from_secs(5), output.next_line())
+        .await
+        .expect("cancelled call should finish")
+        .unwrap()
+        .unwrap();
+    let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["result"]["structuredContent"]["success"], false);
+    assert_eq!(
+        response["result"]["structuredContent"]["error"],
+        "Cancelled by the client"
+    );
+}
+
+/// Test that multi-step tools report progress when the client sends a token
+#[tokio::test]
+async fn test_progress_notifications() {
+    let dir = fixture_dir("progress");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets",
+        &[
+            ("ids", "1"),
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+        ],
+        json!({ "data": [{ "id": "1", "text": "one", "author_id": "7" }] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+
+    let call = |id: u32, meta: serde_json::Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": {
+            "name": "hydrate_tweets", "arguments": { "tweet_ids": ["1"] }, "_meta": meta } })
+    };
+    let messages = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        call(2, json!({ "progressToken": "lookup" })),
+    ];
+    for message in messages {
+        send_message(&mut input, message).await;
+    }
+    output.next_line().await.unwrap().unwrap();
+
+    let progress = receive_message(&mut output).await;
+    assert_eq!(progress["method"], "notifications/progress");
+    assert_eq!(progress["params"]["progressToken"], "lookup");
+    assert_eq!(progress["params"]["progress"], 1.0);
+    assert_eq!(progress["params"]["total"], 1.0);
+    assert_eq!(receive_message(&mut output).await["id"], 2);
+
+    // Without a token only the result is sent
+    send_message(&mut input, call(3, json!({}))).await;
+    assert_eq!(receive_message(&mut output).await["id"], 3);
+}
+
+/// Complete the MCP handshake
+async fn initialize(input: &mut McpInput, output: &mut McpOutput) {
+    send_message(
+        input,
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+    )
+    .await;
+    assert_eq!(receive_message(output).await["id"], 0);
+    send_message(
+        input,
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await;
+}
+
+/// Call a tool and return its structured result
+async fn call_tool(
+    input: &mut McpInput,
+    output: &mut McpOutput,
+    name: &str,
+    arguments: serde_json::Value,
+) -> serde_json::Value {
+    send_message(
+        input,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": name, "arguments": arguments } }),
+    )
+    .await;
+    let response = receive_message(output).await;
+    assert_eq!(response["id"], 1);
+    response["result"]["structuredContent"].clone()
+}
+
+/// Test that malformed lines get JSON-RPC error responses and leave the
+/// session running
+#[tokio::test]
+async fn test_malformed_messages() {
+    use tokio::io::AsyncWriteExt;
+
+    let client = XClient::new(String::new());
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    input.write_all(b"{\"jsonrpc\": \"2.0\", \"id\": \n").await.unwrap();
+    let response = receive_message(&mut output).await;
+    assert_eq!(
+        response,
+        json!({ "jsonrpc": "2.0", "id": null,
+                "error": { "code": -32700, "message": "Parse error" } })
+    );
+
+    send_message(&mut input, json!({ "id": 2, "method": "ping" })).await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["id"], 2);
+    assert_eq!(response["error"]["code"], -32600);
+
+    send_message(&mut input, json!(["not", "a", "request"])).await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["id"], serde_json::Value::Null);
+    assert_eq!(response["error"]["code"], -32600);
+
+    // Unparseable notifications go unanswered
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "method": "notifications/unknown" }),
+    )
+    .await;
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "no/such/method" }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["id"], 3);
+    assert_eq!(response["error"]["code"], -32600);
+    assert!(response["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("no/such/method"));
+
+    send_message(&mut input, json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" })).await;
+    assert_eq!(
+        receive_message(&mut output).await,
+        json!({ "jsonrpc": "2.0", "id": 4, "result": {} })
+    );
+}
+
+/// Test that failed tool calls set isError and successful ones do not
+#[tokio::test]
+async fn test_tool_error_sets_is_error() {
+    let client = XClient::new(String::new());
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "get_replies", "arguments": { "tweet_id": "not a tweet" } } }),
+    )
+    .await;
+    let result = receive_message(&mut output).await["result"].clone();
+    assert_eq!(result["isError"], true, "{}", result);
+    assert_eq!(result["structuredContent"]["success"], false);
+    assert!(result["content"][0]["text"].as_str().unwrap().contains("\"success\": false"));
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "preview_tweet", "arguments": { "text": "hello" } } }),
+    )
+    .await;
+    let result = receive_message(&mut output).await["result"].clone();
+    assert_eq!(result["structuredContent"]["success"], true, "{}", result);
+    assert_ne!(result["isError"], true);
+}
+
+/// Test that `ping` gets an empty result echoing the request id
+#[tokio::test]
+async fn test_ping() {
+    let client = XClient::new(String::new());
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    for id in [json!(7), json!("keep-alive")] {
+        send_message(&mut input, json!({ "jsonrpc": "2.0", "id": id, "method": "ping" })).await;
+        assert_eq!(
+            receive_message(&mut output).await,
+            json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+        );
+    }
+}
+
+/// Test that the binary keeps stdout to JSON-RPC messages over stdio, with
+/// its logs on stderr
+#[tokio::test]
+async fn test_stdio_binary_writes_only_messages() {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::process::Command;
+
+    let dir = fixture_dir("stdio-binary");
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_x-mcp-server"))
+        .env_clear()
+        .env("X_BEARER_TOKEN", "token")
+        .env("RUST_LOG", "debug")
+        .current_dir(&dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    for message in [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "preview_tweet", "arguments": { "text": "hello" } } }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }),
+    ] {
+        stdin.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+    }
+    stdin.flush().await.unwrap();
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let mut ids = Vec::new();
+    while ids.len() < 3 {
+        let line = tokio::time::timeout(Duration::from_secs(10), lines.next_line())
+            .await
+            .expect("no response from the server")
+            .unwrap()
+            .expect("stdout closed early");
+        let message: serde_json::Value = serde_json::from_str(&line)
+            .unwrap_or_else(|e| panic!("stdout line is not JSON ({}): {}", e, line));
+        assert!(message.get("error").is_none(), "{}", message);
+        ids.push(message["id"].clone());
+    }
+    ids.sort_by_key(|id| id.as_i64());
+    assert_eq!(ids, [json!(1), json!(2), json!(3)]);
+
+    drop(stdin);
+    let status = tokio::time::timeout(Duration::from_secs(10), child.wait())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(status.success());
+    while let Some(line) = lines.next_line().await.unwrap() {
+        assert!(serde_json::from_str::<serde_json::Value>(&line).is_ok(), "{}", line);
+    }
+    let mut stderr = String::new();
+    tokio::io::AsyncReadExt::read_to_string(&mut child.stderr.take().unwrap(), &mut stderr)
+        .await
+        .unwrap();
+    assert!(stderr.contains("Starting X MCP Server"), "{}", stderr);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Test that notifications, which have no id, are never answered
+#[tokio::test]
+async fn test_notifications_get_no_response() {
+    let client = XClient::new(String::new());
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    for method in [
+        "notifications/initialized",
+        "notifications/roots/list_changed",
+        "notifications/cancelled",
+    ] {
+        send_message(&mut input, json!({ "jsonrpc": "2.0", "method": method })).await;
+    }
+    send_message(&mut input, json!({ "jsonrpc": "2.0", "id": 5, "method": "ping" })).await;
+    assert_eq!(
+        receive_message(&mut output).await,
+        json!({ "jsonrpc": "2.0", "id": 5, "result": {} })
+    );
+}
+
+/// Test that `delete_tweet` checks the tweet's text before deleting it
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_delete_tweet() {
+    let dir = fixture_dir("delete-tweet");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        json!({ "data": { "id": "42", "text": "fish &amp; chips" } }),
+    );
+    RecordedExchange {
+        method: "DELETE".to_string(),
+        url: "https://api.twitter.com/2/tweets/42".to_string(),
+        query: Vec::new(),
+        body: None,
+        status: 200,
+        response: json!({ "data": { "deleted": true } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    assert!(client.delete_tweet("42").await.unwrap());
+
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "delete_tweet",
+        json!({ "tweet_id": "https://x.com/me/status/42" }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    assert_eq!(result["deleted"], true);
+    assert_eq!(result["was_text"], "fish & chips");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "delete_tweet",
+        json!({ "tweet_id": "42", "expected_text": "fish and chips" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("not deleted"));
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "delete_tweet",
+        json!({ "tweet_id": "42", "expected_text": " fish & chips" }),
+    )
+    .await;
+    assert_eq!(result["deleted"], true);
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "delete_tweet",
+        json!({ "tweet_id": "42", "fetch_first": false }),
+    )
+    .await;
+    assert_eq!(result["deleted"], true);
+    assert!(result.get("was_text").is_none());
+}
+
+/// Test that write tools are refused in read-only mode without calling X
+#[tokio::test]
+async fn test_read_only_refuses_write_tools() {
+    // Nothing recorded, so any request to X would fail differently
+    let dir = fixture_dir("read-only");
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let server = XMcpServer::new(client).read_only();
+    let (mut input, mut output) = serve_in_memory(server);
+    initialize(&mut input, &mut output).await;
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "post_tweet", "arguments": { "text": "hello" } } }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["result"]["isError"], true, "{}", response);
+    let result = &response["result"]["structuredContent"];
+    assert_eq!(result["success"], false);
+    assert_eq!(result["error"], "server is in read-only mode");
+}
+
+/// Test following and unfollowing by username, with the source ID looked up once
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_follow_and_unfollow_user() {
+    let dir = fixture_dir("follow");
+    let user_fields = [(
+        "user.fields",
+        "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+    )];
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/me",
+        &user_fields,
+        json!({ "data": { "id": "1", "name": "Me", "username": "me" } }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/rustlang",
+        &user_fields,
+        json!({ "data": { "id": "99", "name": "Rust", "username": "rustlang" } }),
+    );
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/users/1/following".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "target_user_id": "99" })),
+        status: 200,
+        response: json!({ "data": { "following": false, "pending_follow": true } }),
+    }
+    .save(&dir)
+    .unwrap();
+    RecordedExchange {
+        method: "DELETE".to_string(),
+        url: "https://api.twitter.com/2/users/1/following/99".to_string(),
+        query: Vec::new(),
+        body: None,
+        status: 200,
+        response: json!({ "data": { "following": false } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let followed = call_tool(
+        &mut input,
+        &mut output,
+        "follow_user",
+        json!({ "identifier": "@rustlang" }),
+    )
+    .await;
+    assert_eq!(followed["user_id"], "99");
+    assert_eq!(followed["following"], false);
+    assert_eq!(followed["pending_follow"], true);
+
+    let unfollowed = call_tool(
+        &mut input,
+        &mut output,
+        "unfollow_user",
+        json!({ "identifier": "99", "is_user_id": true }),
+    )
+    .await;
+    assert_eq!(unfollowed["success"], true);
+    assert_eq!(unfollowed["following"], false);
+    assert!(unfollowed.get("pending_follow").is_none());
+}
+
+/// Test sending a Direct Message to a username
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_send_dm() {
+    let dir = fixture_dir("send-dm");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/rustlang",
+        &[(
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+        )],
+        json!({ "data": { "id": "99", "name": "Rust", "username": "rustlang" } }),
+    );
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/dm_conversations/with/99/messages".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "text": "Thanks for the release!" })),
+        status: 201,
+        response: json!({ "data": { "dm_conversation_id": "1-99", "dm_event_id": "555" } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let sent = call_tool(
+        &mut input,
+        &mut output,
+        "send_dm",
+        json!({ "identifier": "@rustlang", "text": "Thanks for the release!" }),
+    )
+    .await;
+    assert_eq!(sent["success"], true, "{}", sent);
+    assert_eq!(sent["recipient_id"], "99");
+    assert_eq!(sent["dm_conversation_id"], "1-99");
+    assert_eq!(sent["dm_event_id"], "555");
+
+    let empty = call_tool(
+        &mut input,
+        &mut output,
+        "send_dm",
+        json!({ "identifier": "99", "is_user_id": true, "text": "  " }),
+    )
+    .await;
+    assert_eq!(empty["success"], false);
+}
+
+/// Test blocking and muting users, with usernames looked up first
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_block_and_mute() {
+    let dir = fixture_dir("block-mute");
+    let user_fields = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/me",
+        &[("user.fields", user_fields)],
+        json!({ "data": { "id": "1", "name": "Me", "username": "me" } }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/spammer",
+        &[("user.fields", user_fields)],
+        json!({ "data": { "id": "66", "name": "Spam", "username": "spammer" } }),
+    );
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/nobody",
+        &[("user.fields", user_fields)],
+        200,
+        json!({ "errors": [{ "title": "Not Found Error", "detail": "Could not find user with username: [nobody]." }] }),
+    );
+    for (method, url, body, response) in [
+        ("POST", "https://api.twitter.com/2/users/1/blocking", Some(json!({ "target_user_id": "66" })), json!({ "blocking": true })),
+        ("DELETE", "https://api.twitter.com/2/users/1/blocking/66", None, json!({ "blocking": false })),
+        ("POST", "https://api.twitter.com/2/users/1/muting", Some(json!({ "target_user_id": "66" })), json!({ "muting": true })),
+        ("DELETE", "https://api.twitter.com/2/users/1/muting/66", None, json!({ "muting": false })),
+    ] {
+        RecordedExchange {
+            method: method.to_string(),
+            url: url.to_string(),
+            query: Vec::new(),
+            body,
+            status: 200,
+            response: json!({ "data": response }),
+        }
+        .save(&dir)
+        .unwrap();
+    }
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let blocked = call_tool(&mut input, &mut output, "block_user", json!({ "identifier": "spammer" })).await;
+    assert_eq!(blocked["success"], true, "{}", blocked);
+    assert_eq!(blocked["user_id"], "66");
+    assert_eq!(blocked["blocking"], true);
+
+    let unblocked = call_tool(
+        &mut input,
+        &mut output,
+        "unblock_user",
+        json!({ "identifier": "66", "is_user_id": true }),
+    )
+    .await;
+    assert_eq!(unblocked["blocking"], false);
+
+    let muted = call_tool(&mut input, &mut output, "mute_user", json!({ "identifier": "@spammer" })).await;
+    assert_eq!(muted["success"], true, "{}", muted);
+    assert_eq!(muted["muting"], true);
+
+    let unmuted = call_tool(&mut input, &mut output, "unmute_user", json!({ "identifier": "spammer" })).await;
+    assert_eq!(unmuted["muting"], false);
+
+    // An unknown username fails before any block is attempted; replay has
+    // no exchange for blocking anyone else
+    let missing = call_tool(&mut input, &mut output, "block_user", json!({ "identifier": "nobody" })).await;
+    assert_eq!(missing["success"], false);
+    assert!(missing["error"].as_str().unwrap().contains("nobody"), "{}", missing);
+}
+
+/// Test listing, adding and removing bookmarks for the authenticated user
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_bookmarks() {
+    let dir = fixture_dir("bookmarks");
+    let user_fields = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/me",
+        &[("user.fields", user_fields)],
+        json!({ "data": { "id": "1", "name": "Me", "username": "me" } }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/1/bookmarks",
+        &[
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            ("user.fields", user_fields),
+            ("max_results", "2"),
+        ],
+        json!({
+            "data": [
+                { "id": "20", "text": "Rust 1.80 &amp; more", "author_id": "99" },
+                { "id": "10", "text": "older", "author_id": "99" }
+            ],
+            "includes": { "users": [{ "id": "99", "name": "Rust", "username": "rustlang" }] },
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/users/1/bookmarks".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "tweet_id": "30" })),
+        status: 200,
+        response: json!({ "data": { "bookmarked": true } }),
+    }
+    .save(&dir)
+    .unwrap();
+    RecordedExchange {
+        method: "DELETE".to_string(),
+        url: "https://api.twitter.com/2/users/1/bookmarks/20".to_string(),
+        query: Vec::new(),
+        body: None,
+        status: 200,
+        response: json!({ "data": { "bookmarked": false } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let listed = call_tool(
+        &mut input,
+        &mut output,
+        "get_bookmarks",
+        json!({ "max_results": 2 }),
+    )
+    .await;
+    assert_eq!(listed["success"], true, "{}", listed);
+    assert_eq!(listed["count"], 2);
+    assert_eq!(listed["tweets"][0]["text"], "Rust 1.80 & more");
+    assert_eq!(listed["tweets"][0]["author"]["username"], "rustlang");
+    assert_eq!(listed["next_token"], "page2");
+
+    let added = call_tool(
+        &mut input,
+        &mut output,
+        "bookmark_tweet",
+        json!({ "tweet_id": "https://x.com/rustlang/status/30" }),
+    )
+    .await;
+    assert_eq!(added["tweet_id"], "30");
+    assert_eq!(added["bookmarked"], true);
+
+    let removed = call_tool(
+        &mut input,
+        &mut output,
+        "unbookmark_tweet",
+        json!({ "tweet_id": "20" }),
+    )
+    .await;
+    assert_eq!(removed["success"], true);
+    assert_eq!(removed["bookmarked"], false);
+}
+
+/// Test hiding a reply and showing it again
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_hide_reply() {
+    let dir = fixture_dir("hide-reply");
+    for hidden in [true, false] {
+        RecordedExchange {
+            method: "PUT".to_string(),
+            url: "https://api.twitter.com/2/tweets/30/hidden".to_string(),
+            query: Vec::new(),
+            body: Some(json!({ "hidden": hidden })),
+            status: 200,
+            response: json!({ "data": { "hidden": hidden } }),
+        }
+        .save(&dir)
+        .unwrap();
+    }
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let hidden = call_tool(
+        &mut input,
+        &mut output,
+        "hide_reply",
+        json!({ "tweet_id": "https://x.com/troll/status/30" }),
+    )
+    .await;
+    assert_eq!(hidden["success"], true, "{}", hidden);
+    assert_eq!(hidden["tweet_id"], "30");
+    assert_eq!(hidden["hidden"], true);
+
+    let shown = call_tool(
+        &mut input,
+        &mut output,
+        "hide_reply",
+        json!({ "tweet_id": "30", "hidden": false }),
+    )
+    .await;
+    assert_eq!(shown["success"], true, "{}", shown);
+    assert_eq!(shown["hidden"], false);
+}
+
+/// Test creating a list and adding and removing members by username
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_manage_lists() {
+    let dir = fixture_dir("manage-lists");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/rustlang",
+        &[(
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+        )],
+        json!({ "data": { "id": "99", "name": "Rust", "username": "rustlang" } }),
+    );
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/lists".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "name": "Rustaceans", "description": "People writing Rust", "private": true })),
+        status: 200,
+        response: json!({ "data": { "id": "101", "name": "Rustaceans" } }),
+    }
+    .save(&dir)
+    .unwrap();
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/lists/101/members".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "user_id": "99" })),
+        status: 200,
+        response: json!({ "data": { "is_member": true } }),
+    }
+    .save(&dir)
+    .unwrap();
+    RecordedExchange {
+        method: "DELETE".to_string(),
+        url: "https://api.twitter.com/2/lists/101/members/99".to_string(),
+        query: Vec::new(),
+        body: None,
+        status: 200,
+        response: json!({ "data": { "is_member": false } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let created = call_tool(
+        &mut input,
+        &mut output,
+        "create_list",
+        json!({ "name": "Rustaceans", "description": "People writing Rust", "private": true }),
+    )
+    .await;
+    assert_eq!(created["success"], true, "{}", created);
+    assert_eq!(created["list_id"], "101");
+
+    // Too long a name is refused before anything is sent to X
+    let refused = call_tool(
+        &mut input,
+        &mut output,
+        "create_list",
+        json!({ "name": "Rustaceans and friends of Ferris" }),
+    )
+    .await;
+    assert_eq!(refused["success"], false);
+    assert!(refused["error"].as_str().unwrap().contains("1 to 25 characters"), "{}", refused);
+
+    let added = call_tool(
+        &mut input,
+        &mut output,
+        "add_list_member",
+        json!({ "list_id": "101", "identifier": "@rustlang" }),
+    )
+    .await;
+    assert_eq!(added["success"], true, "{}", added);
+    assert_eq!(added["user_id"], "99");
+    assert_eq!(added["is_member"], true);
+
+    let removed = call_tool(
+        &mut input,
+        &mut output,
+        "remove_list_member",
+        json!({ "list_id": "101", "identifier": "99", "is_user_id": true }),
+    )
+    .await;
+    assert_eq!(removed["success"], true, "{}", removed);
+    assert_eq!(removed["is_member"], false);
+}
+
+/// Test that tweet entities come through, with missing arrays left out
+#[tokio::test]
+async fn test_tweet_entities() {
+    let dir = fixture_dir("tweet-entities");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        json!({ "data": {
+            "id": "42",
+            "text": "@rustlang #rust 1.80 https://t.co/abc",
+            "entities": {
+                "mentions": [{ "start": 0, "end": 9, "username": "rustlang", "id": "99" }],
+                "hashtags": [{ "start": 10, "end": 15, "tag": "rust" }],
+                "urls": [{
+                    "start": 21,
+                    "end": 37,
+                    "url": "https://t.co/abc",
+                    "expanded_url": "https://blog.rust-lang.org/",
+                    "display_url": "blog.rust-lang.org"
+                }]
+            }
+        } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_tweet",
+        json!({ "tweet_id": "42" }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    let entities = &result["tweet"]["entities"];
+    assert_eq!(entities["mentions"][0]["username"], "rustlang");
+    assert_eq!(entities["hashtags"][0]["tag"], "rust");
+    assert_eq!(entities["urls"][0]["expanded_url"], "https://blog.rust-lang.org/");
+    assert_eq!(entities["urls"][0]["start"], 21);
+    assert!(entities["cashtags"].is_null());
+}
+
+/// Test readable tweet times in a requested timezone
+#[tokio::test]
+async fn test_human_times() {
+    let dir = fixture_dir("human-times");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42",
+        &GET_TWEET_QUERY,
+        json!({ "data": { "id": "42", "text": "hi", "created_at": "2024-05-01T12:03:00.000Z" } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_tweet",
+        json!({ "tweet_id": "42" }),
+    )
+    .await;
+    assert!(result["tweet"].get("created_at_relative").is_none());
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_tweet",
+        json!({ "tweet_id": "42", "timezone": "+02:00" }),
+    )
+    .await;
+    assert_eq!(result["tweet"]["created_at"], "2024-05-01T12:03:00.000Z");
+    assert_eq!(
+        result["tweet"]["created_at_local"],
+        "Wed 2024-05-01 14:03 UTC+02:00"
+    );
+    assert!(result["tweet"]["created_at_relative"]
+        .as_str()
+        .unwrap()
+        .ends_with(" ago"));
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_tweet",
+        json!({ "tweet_id": "42", "timezone": "Europe/Paris" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+}
+
+/// Test that search results keep X's order with a rank and pass sort_order on
+#[tokio::test]
+async fn test_search_sort_order_and_rank() {
+    let dir = fixture_dir("search-relevancy");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("sort_order", "relevancy"),
+            ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+            ("expansions", "attachments.media_keys"),
+            ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+        ],
+        json!({ "data": [
+            { "id": "5", "text": "best match" },
+            { "id": "9", "text": "newer, less relevant" }
+        ] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "sort_order": "relevancy" }),
+    )
+    .await;
+    let ranked: Vec<(&str, u64)> = result["tweets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tweet| {
+            (
+                tweet["id"].as_str().unwrap(),
+                tweet["rank"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(ranked, [("5", 1), ("9", 2)]);
+}
+
+/// Test that search passes a time window on in UTC and refuses malformed times
+#[tokio::test]
+async fn test_search_time_range() {
+    use x_mcp_server::XError;
+
+    let dir = fixture_dir("search-time-range");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("start_time", "2024-04-30T22:00:00Z"),
+            ("end_time", "2024-05-02T00:00:00Z"),
+            ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+            ("expansions", "attachments.media_keys"),
+            ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+        ],
+        json!({ "data": [{ "id": "5", "text": "in range" }] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client.clone()));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({
+            "query": "rust",
+            "start_time": "2024-05-01T00:00:00+02:00",
+            "end_time": "2024-05-02T00:00:00Z"
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["tweets"][0]["id"], "5");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "start_time": "yesterday" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("start_time must be an RFC 3339 time"), "{}", result);
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "start_time": "2024-05-02T00:00:00Z", "end_time": "2024-05-01T00:00:00Z" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+
+    let result = client
+        .search_tweets(SearchTweetsParams {
+            query: "rust".to_string(),
+            max_results: Some(10),
+            sort_order: None,
+            start_time: None,
+            end_time: Some("2024-05-02".to_string()),
+            since_id: None,
+            until_id: None,
+            tweet_fields: None,
+            user_fields: None,
+            expansions: None,
+            media_fields: None,
+            next_token: None,
+        })
+        .await;
+    match result {
+        Err(XError::Generic(message)) => assert!(message.contains("end_time"), "{}", message),
+        other => panic!("expected XError::Generic, got {:?}", other),
+    }
+}
+
+/// Test that a retried `post_tweet` with the same idempotency key posts once
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_tweet_idempotency_key() {
+    use x_mcp_server::idempotency::IdempotencyLog;
+
+    let dir = fixture_dir("idempotency");
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/tweets".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "text": "hello" })),
+        status: 201,
+        response: json!({ "data": { "id": "77", "text": "hello" } }),
+    }
+    .save(&dir)
+    .unwrap();
+    let log_file = dir.join("posted.jsonl");
+    let ttl = Duration::from_secs(60);
+
+    let client = XClient::new(String::new())
+        .with_replay(ReplayMode::Replay(dir.clone()))
+        .with_idempotency_log(IdempotencyLog::open(&log_file, ttl).unwrap());
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let post = json!({ "text": "hello", "idempotency_key": "greeting-1" });
+
+    let first = call_tool(&mut input, &mut output, "post_tweet", post.clone()).await;
+    assert_eq!(first["tweet"]["id"], "77");
+    assert!(first.get("already_posted").is_none());
+
+    let retry = call_tool(&mut input, &mut output, "post_tweet", post.clone()).await;
+    assert_eq!(retry["tweet"]["id"], "77");
+    assert_eq!(retry["already_posted"], true);
+
+    let reused = call_tool(
+        &mut input,
+        &mut output,
+        "post_tweet",
+        json!({ "text": "other", "idempotency_key": "greeting-1" }),
+    )
+    .await;
+    assert_eq!(reused["success"], false);
+
+    // The key survives a restart, with no recorded response left to post with
+    std::fs::remove_file(dir.join(format!(
+        "{}.json",
+        request_key(
+            "POST",
+            "https://api.twitter.com/2/tweets",
+            &[],
+            Some(&json!({ "text": "hello" }))
+        )
+    )))
+    .unwrap();
+    let client = XClient::new(String::new())
+        .with_replay(ReplayMode::Replay(dir))
+        .with_idempotency_log(IdempotencyLog::open(&log_file, ttl).unwrap());
+    let request = serde_json::from_value::<PostTweetArgs>(json!({ "text": "hello" }))
+        .unwrap()
+        .to_request();
+    let (tweet, already_posted) = client
+        .post_tweet_once(&request, "greeting-1")
+        .await
+        .unwrap();
+    assert_eq!(tweet.id, "77");
+    assert!(already_posted);
+}
+
+/// Test quote tweet lookup across pages and with no quotes
+#[tokio::test]
+async fn test_get_quote_tweets() {
+    let dir = fixture_dir("quote-tweets");
+    let query = |max_results: &'static str, token: Option<&'static str>| {
+        let mut query = vec![
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+            ("max_results", max_results),
+        ];
+        query.extend(token.map(|token| ("pagination_token", token)));
+        query
+    };
+    let url = "https://api.twitter.com/2/tweets/20/quote_tweets";
+    record_get(
+        &dir,
+        url,
+        &query("100", None),
+        json!({
+            "data": [
+                { "id": "31", "text": "so true", "author_id": "7" },
+                { "id": "30", "text": "disagree", "author_id": "8" }
+            ],
+            "includes": { "users": [
+                { "id": "7", "name": "Jack", "username": "jack" },
+                { "id": "8", "name": "Ev", "username": "ev" }
+            ] },
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+    record_get(
+        &dir,
+        url,
+        &query("100", Some("page2")),
+        json!({
+            "data": [{ "id": "29", "text": "again", "author_id": "7" }],
+            "includes": { "users": [{ "id": "7", "name": "Jack", "username": "jack" }] },
+            "meta": { "result_count": 1 }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/21/quote_tweets",
+        &query("10", None),
+        json!({ "meta": { "result_count": 0 } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let page = client
+        .get_quote_tweets_up_to("20", 150, None)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = page.tweets.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["31", "30", "29"]);
+    assert_eq!(page.authors.len(), 2);
+    assert!(page.next_token.is_none());
+
+    let first = client
+        .get_quote_tweets("20", Some(100), None)
+        .await
+        .unwrap();
+    assert_eq!(first.next_token.as_deref(), Some("page2"));
+
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_quote_tweets",
+        json!({ "tweet_id": "https://x.com/jack/status/21" }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    assert_eq!(result["count"], 0);
+}
+
+/// `(user_id, max_results)` of each timeline request
+type TimelineRequests = std::sync::Arc<std::sync::Mutex<Vec<(String, Option<u32>)>>>;
+
+/// Backend answering from canned data and recording the timeline requests
+#[derive(Default)]
+struct FakeApi {
+    timeline_requests: TimelineRequests,
+}
+
+impl XApi for FakeApi {
+    fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(async move {
+            Ok((username == "jack").then(|| {
+                serde_json::from_value(json!({ "id": "12", "name": "Jack", "username": "jack" }))
+                    .unwrap()
+            }))
+        })
+    }
+
+    fn get_user_tweets<'a>(
+        &'a self,
+        user_id: &'a str,
+        max_results: Option<u32>,
+        _ids: &'a TweetIdRange,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        self.timeline_requests
+            .lock()
+            .unwrap()
+            .push((user_id.to_string(), max_results));
+        Box::pin(async {
+            Ok(vec![serde_json::from_value(
+                json!({ "id": "1", "text": "just setting up my twttr" }),
+            )?])
+        })
+    }
+
+    fn search_tweets_with_includes(
+        &self,
+        _params: SearchTweetsParams,
+    ) -> ApiFuture<'_, (SearchPage, Includes)> {
+        Box::pin(async {
+            let tweets = serde_json::from_value(json!([
+                { "id": "3", "text": "nsfw link", "possibly_sensitive": true },
+                { "id": "2", "text": "Big SPOILER ahead" },
+                { "id": "1", "text": "all good", "possibly_sensitive": false }
+            ]))?;
+            let page = SearchPage {
+                tweets,
+                next_token: None,
+            };
+            Ok((page, Includes::default()))
+        })
+    }
+
+    fn get_user_original_tweets<'a>(
+        &'a self,
+        _user_id: &'a str,
+        _max_results: u32,
+    ) -> ApiFuture<'a, Vec<Tweet>> {
+        Box::pin(async { Ok(FakeApi::original_tweets()) })
+    }
+}
+
+impl FakeApi {
+    fn original_tweets() -> Vec<Tweet> {
+        let tweet = |id: &str, likes: u64, retweets: u64| {
+            serde_json::from_value(json!({
+                "id": id,
+                "text": format!("tweet {}", id),
+                "public_metrics": { "like_count": likes, "retweet_count": retweets },
+            }))
+            .unwrap()
+        };
+        vec![tweet("3", 2, 0), tweet("2", 10, 5), tweet("1", 3, 1)]
+    }
+}
+
+/// Backend whose lookups of "slow" wait until `release` is notified
+#[derive(Default)]
+struct GatedApi {
+    release: std::sync::Arc<tokio::sync::Notify>,
+}
+
+impl XApi for GatedApi {
+    fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(async move {
+            if username == "slow" {
+                self.release.notified().await;
+            }
+            Ok(Some(serde_json::from_value(
+                json!({ "id": username, "name": username, "username": username }),
+            )?))
+        })
+    }
+}
+
+/// Test that a slow tool call does not hold up the requests after it, and
+/// that each response keeps its request's ID
+#[tokio::test]
+async fn test_concurrent_tool_calls() {
+    let api = GatedApi::default();
+    let release = api.release.clone();
+    let (mut input, mut output) = serve_in_memory(XMcpServer::from_api(api));
+    initialize(&mut input, &mut output).await;
+
+    for (id, username) in [(1, "slow"), (2, "fast")] {
+        send_message(
+            &mut input,
+            json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                    "params": { "name": "get_user", "arguments": { "identifier": username } } }),
+        )
+        .await;
+    }
+    let first = receive_message(&mut output).await;
+    assert_eq!(first["id"], 2);
+    assert_eq!(first["result"]["structuredContent"]["user"]["username"], "fast");
+
+    release.notify_one();
+    let second = receive_message(&mut output).await;
+    assert_eq!(second["id"], 1);
+    assert_eq!(second["result"]["structuredContent"]["user"]["username"], "slow");
+}
+
+/// Backend whose user lookups wait at `barrier`, so they only finish once
+/// that many run at the same time
+struct BarrierApi {
+    barrier: tokio::sync::Barrier,
+}
+
+impl XApi for BarrierApi {
+    fn get_user_by_username<'a>(&'a self, username: &'a str) -> ApiFuture<'a, Option<User>> {
+        Box::pin(async move {
+            self.barrier.wait().await;
+            Ok(Some(serde_json::from_value(
+                json!({ "id": username, "name": username, "username": username }),
+            )?))
+        })
+    }
+}
+
+/// Test many get_user calls across sessions sharing one backend at once
+#[tokio::test]
+async fn test_shared_backend_across_sessions() {
+    const SESSIONS: usize = 4;
+    const CALLS: usize = 5;
+    let server = XMcpServer::from_api(BarrierApi {
+        barrier: tokio::sync::Barrier::new(SESSIONS * CALLS),
+    });
+
+    let mut sessions = tokio::task::JoinSet::new();
+    for session in 0..SESSIONS {
+        let (mut input, mut output) = serve_in_memory(server.clone());
+        sessions.spawn(async move {
+            initialize(&mut input, &mut output).await;
+            for call in 0..CALLS {
+                send_message(
+                    &mut input,
+                    json!({ "jsonrpc": "2.0", "id": call, "method": "tools/call", "params": {
+                        "name": "get_user",
+                        "arguments": { "identifier": format!("user{}_{}", session, call) } } }),
+                )
+                .await;
+            }
+            for _ in 0..CALLS {
+                let response = receive_message(&mut output).await;
+                let call = response["id"].as_u64().unwrap();
+                assert_eq!(
+                    response["result"]["structuredContent"]["user"]["username"],
+                    format!("user{}_{}", session, call)
+                );
+            }
+        });
+    }
+    while let Some(result) = sessions.join_next().await {
+        result.unwrap();
+    }
+}
+
+/// Test tool logic against a fake backend, without the network
+#[tokio::test]
+async fn test_tools_with_fake_api() {
+    let api = FakeApi::default();
+    let timeline_requests = api.timeline_requests.clone();
+    let (mut input, mut output) = serve_in_memory(XMcpServer::from_api(api));
+    initialize(&mut input, &mut output).await;
+
+    // The username is resolved to an ID and max_results passed on for paging
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets",
+        json!({ "identifier": "jack", "max_results": 500 }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    assert_eq!(result["user_id"], "12");
+    assert_eq!(result["count"], 1);
+    assert_eq!(
+        *timeline_requests.lock().unwrap(),
+        [("12".to_string(), Some(500))]
+    );
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets",
+        json!({ "identifier": "nobody" }),
+    )
+    .await;
+    assert_eq!(result["error"], "User not found");
+
+    // Operations the backend does not implement fail cleanly
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_tweet",
+        json!({ "tweet_id": "1" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+}
+
+/// Test that `profile_report` combines the profile, tweets and averages
+#[tokio::test]
+async fn test_profile_report() {
+    let (mut input, mut output) = serve_in_memory(XMcpServer::from_api(FakeApi::default()));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "profile_report",
+        json!({ "username": "@jack" }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    assert_eq!(result["user"]["id"], "12");
+    assert_eq!(result["recent_tweets"].as_array().unwrap().len(), 3);
+    assert_eq!(result["top_tweet"]["id"], "2");
+    assert_eq!(result["stats"]["sampled_tweets"], 3);
+    assert_eq!(result["stats"]["average_likes"], 5.0);
+    assert_eq!(result["stats"]["average_retweets"], 2.0);
+    assert_eq!(result["stats"]["average_replies"], 0.0);
+}
+
+/// Test that tier-restricted 403s name the endpoint and the tier it needs
+#[tokio::test]
+async fn test_tier_restricted_error() {
+    let dir = fixture_dir("tier-restricted");
+    let problem = json!({
+        "title": "Client Forbidden",
+        "detail": "When authenticating requests to the Twitter API v2 endpoints, you must use keys and tokens from a Twitter developer App that is attached to a Project.",
+        "reason": "client-not-enrolled",
+        "type": "https://api.twitter.com/2/problems/client-forbidden",
+    });
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/2/users/12/tweets",
+        &[(
+            "tweet.fields",
+            "id,text,author_id,created_at,public_metrics,possibly_sensitive",
+        )],
+        403,
+        problem.clone(),
+    );
+    record_get_status(
+        &dir,
+        "https://api.twitter.com/1.1/trends/available.json",
+        &[],
+        403,
+        problem,
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let error = client.get_user_tweets("12", None, &TweetIdRange::default()).await.unwrap_err();
+    assert!(matches!(
+        &error,
+        x_mcp_server::XError::TierRestricted { endpoint, required_tier: "Basic" }
+            if endpoint == "GET /2/users/:id/tweets"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "This operation requires X API Basic access; your current tier can't use GET /2/users/:id/tweets"
+    );
+
+    let error = client.get_trend_locations().await.unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("requires X API Basic/Pro access"));
+}
+
+/// Test trends at a place, worldwide by default
+#[tokio::test]
+async fn test_get_trends() {
+    let dir = fixture_dir("trends");
+    let trends = |names: &[&str]| {
+        json!([{
+            "trends": names.iter().map(|name| json!({
+                "name": name,
+                "url": format!("http://twitter.com/search?q={}", name),
+                "promoted_content": null,
+                "query": name,
+                "tweet_volume": if *name == "#RustLang" { json!(12000) } else { json!(null) }
+            })).collect::<Vec<_>>(),
+            "as_of": "2024-05-01T12:00:00Z",
+            "locations": [{ "name": "Worldwide", "woeid": 1 }]
+        }])
+    };
+    record_get(
+        &dir,
+        "https://api.twitter.com/1.1/trends/place.json",
+        &[("id", "1")],
+        trends(&["#RustLang", "Ferris"]),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/1.1/trends/place.json",
+        &[("id", "1118370")],
+        trends(&["Tokyo"]),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(&mut input, &mut output, "get_trends", json!({})).await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["woeid"], 1);
+    assert_eq!(result["count"], 2);
+    assert_eq!(result["trends"][0]["name"], "#RustLang");
+    assert_eq!(result["trends"][0]["tweet_volume"], 12000);
+    assert!(result["trends"][1]["tweet_volume"].is_null());
+
+    let result = call_tool(&mut input, &mut output, "get_trends", json!({ "woeid": 1118370 })).await;
+    assert_eq!(result["trends"][0]["name"], "Tokyo");
+
+    // WOEIDs are 32-bit
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 9, "method": "tools/call",
+                "params": { "name": "get_trends", "arguments": { "woeid": 4_294_967_296u64 } } }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert!(response.get("error").is_some(), "{}", response);
+}
+
+/// Test that `get_user_tweets` joins referenced tweets and their authors on request
+#[tokio::test]
+async fn test_user_tweets_with_references() {
+    let dir = fixture_dir("user-tweets-references");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/12/tweets",
+        &[
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,in_reply_to_user_id,possibly_sensitive",
+            ),
+            (
+                "expansions",
+                "referenced_tweets.id,referenced_tweets.id.author_id",
+            ),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+            ("max_results", "10"),
+        ],
+        json!({
+            "data": [
+                {
+                    "id": "3",
+                    "text": "RT @ev: big news",
+                    "author_id": "12",
+                    "referenced_tweets": [{ "type": "retweeted", "id": "2" }]
+                },
+                { "id": "1", "text": "plain tweet", "author_id": "12" }
+            ],
+            "includes": {
+                "tweets": [{ "id": "2", "text": "big news", "author_id": "8" }],
+                "users": [{ "id": "8", "name": "Ev", "username": "ev" }]
+            }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets",
+        json!({ "identifier": "12", "is_user_id": true, "include_references": true }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    let referenced = &result["tweets"][0]["referenced"][0];
+    assert_eq!(referenced["text"], "big news");
+    assert_eq!(referenced["author"]["username"], "ev");
+    assert!(result["tweets"][1].get("referenced").is_none());
+}
+
+/// Test that the safe-mode filter drops sensitive and blocklisted tweets
+#[tokio::test]
+async fn test_search_content_filter() {
+    let (mut input, mut output) = serve_in_memory(XMcpServer::from_api(FakeApi::default()));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "news", "exclude_sensitive": true, "blocklist": ["spoiler"] }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+    assert_eq!(result["count"], 1);
+    assert_eq!(result["filtered"], 2);
+    assert_eq!(result["tweets"][0]["id"], "1");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "news" }),
+    )
+    .await;
+    assert_eq!(result["count"], 3);
+    assert!(result.get("filtered").is_none());
+}
+
+/// Test that the rate limit status is flattened to one budget per endpoint
+#[tokio::test]
+async fn test_rate_limit_status() {
+    let dir = fixture_dir("rate-limit-status");
+    record_get(
+        &dir,
+        "https://api.twitter.com/1.1/application/rate_limit_status.json",
+        &[],
+        json!({
+            "rate_limit_context": { "access_token": "1-abc" },
+            "resources": {
+                "users": {
+                    "/users/search": { "limit": 900, "remaining": 899, "reset": 1714564800 }
+                },
+                "trends": {
+                    "/trends/available": { "limit": 75, "remaining": 75, "reset": 1714564800 }
+                }
+            }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let limits = client.get_rate_limit_status().await.unwrap();
+    assert_eq!(limits.len(), 2);
+    assert_eq!(limits["/users/search"].remaining, 899);
+
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let result = call_tool(&mut input, &mut output, "status", json!({})).await;
+    assert!(result.get("rate_limits").is_none());
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "status",
+        json!({ "include_rate_limits": true }),
+    )
+    .await;
+    assert_eq!(result["rate_limits"]["/trends/available"]["limit"], 75);
+}
+
+/// Test that compact output drops the pretty-printing from the text content
+#[tokio::test]
+async fn test_compact_output() {
+    for compact in [false, true] {
+        let server = XMcpServer::from_api(FakeApi::default()).with_compact_output(compact);
+        let (mut input, mut output) = serve_in_memory(server);
+        initialize(&mut input, &mut output).await;
+
+        send_message(
+            &mut input,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                    "params": { "name": "search_tweets", "arguments": { "query": "news" } } }),
+        )
+        .await;
+        let response = receive_message(&mut output).await;
+        let result = &response["result"];
+        let text = result["content"][0]["text"].as_str().unwrap();
+        assert_eq!(text.contains('\n'), !compact);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(text).unwrap(),
+            result["structuredContent"]
+        );
+    }
+}
+
+/// Test listing a user's lists across pages
+#[tokio::test]
+async fn test_get_users() {
+    let dir = fixture_dir("get-users");
+    let user_fields = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    let usernames: Vec<String> = (0..101).map(|i| format!("user{}", i)).collect();
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by",
+        &[("usernames", usernames[..100].join(",").as_str()), ("user.fields", user_fields)],
+        json!({
+            "data": [
+                { "id": "2", "name": "Two", "username": "User2" },
+                { "id": "0", "name": "Zero", "username": "user0" }
+            ],
+            "errors": [{
+                "value": "user1",
+                "detail": "Could not find user with usernames: [user1].",
+                "title": "Not Found Error",
+                "resource_type": "user",
+                "parameter": "usernames",
+                "type": "https://api.twitter.com/2/problems/resource-not-found"
+            }]
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by",
+        &[("usernames", "user100"), ("user.fields", user_fields)],
+        json!({ "data": [{ "id": "100", "name": "Hundred", "username": "user100" }] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let mut requested = usernames.clone();
+    requested.insert(1, "@USER0".to_string());
+    requested.push("not a handle".to_string());
+    let result = call_tool(&mut input, &mut output, "get_users", json!({ "usernames": requested })).await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 3);
+    let ids: Vec<&str> = result["users"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["0", "2", "100"]);
+
+    let not_found = result["not_found"].as_array().unwrap();
+    assert_eq!(not_found.len(), 99);
+    assert_eq!(not_found[0]["username"], "not a handle");
+    assert_eq!(not_found[0]["reason"], "Not a valid username");
+    assert_eq!(not_found[1]["username"], "user1");
+    assert_eq!(not_found[1]["reason"], "Could not find user with usernames: [user1].");
+    assert_eq!(not_found[2]["reason"], "User not found");
+}
+
+#[tokio::test]
+async fn test_followers_and_following() {
+    let dir = fixture_dir("followers");
+    let user_fields = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/rustlang",
+        &[("user.fields", user_fields)],
+        json!({ "data": { "id": "99", "name": "Rust", "username": "rustlang" } }),
+    );
+    let url = "https://api.twitter.com/2/users/99/followers";
+    record_get(
+        &dir,
+        url,
+        &[("user.fields", user_fields), ("max_results", "1000")],
+        json!({
+            "data": [
+                { "id": "1", "name": "One", "username": "one", "description": "first" },
+                { "id": "2", "name": "Two", "username": "two" }
+            ],
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+    record_get(
+        &dir,
+        url,
+        &[
+            ("user.fields", user_fields),
+            ("max_results", "998"),
+            ("pagination_token", "page2"),
+        ],
+        json!({
+            "data": [{ "id": "3", "name": "Three", "username": "three" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/99/following",
+        &[("user.fields", user_fields), ("max_results", "100")],
+        json!({
+            "data": [{ "id": "4", "name": "Four", "username": "four" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_followers",
+        json!({ "identifier": "@rustlang", "max_results": 1000 }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["user_id"], "99");
+    assert_eq!(result["count"], 3);
+    assert_eq!(result["users"][0]["description"], "first");
+    assert_eq!(result["users"][2]["username"], "three");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_following",
+        json!({ "identifier": "99", "is_user_id": true }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 1);
+    assert_eq!(result["users"][0]["id"], "4");
+}
+
+/// Test listing the users who liked a tweet across pages, and its retweeters
+#[tokio::test]
+async fn test_liking_users_and_retweeters() {
+    let dir = fixture_dir("engagement");
+    let user_fields = "id,name,username,description,public_metrics,profile_image_url,verified,created_at";
+    let url = "https://api.twitter.com/2/tweets/42/liking_users";
+    record_get(
+        &dir,
+        url,
+        &[("user.fields", user_fields), ("max_results", "100")],
+        json!({
+            "data": [
+                { "id": "1", "name": "One", "username": "one" },
+                { "id": "2", "name": "Two", "username": "two" }
+            ],
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+    record_get(
+        &dir,
+        url,
+        &[
+            ("user.fields", user_fields),
+            ("max_results", "98"),
+            ("pagination_token", "page2"),
+        ],
+        json!({
+            "data": [{ "id": "3", "name": "Three", "username": "three" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/42/retweeted_by",
+        &[("user.fields", user_fields), ("max_results", "5")],
+        json!({
+            "data": [{ "id": "4", "name": "Four", "username": "four" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_liking_users",
+        json!({ "tweet_id": "https://x.com/rustlang/status/42" }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["tweet_id"], "42");
+    assert_eq!(result["count"], 3);
+    assert_eq!(result["users"][2]["username"], "three");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_retweeters",
+        json!({ "tweet_id": "42", "max_results": 5 }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 1);
+    assert_eq!(result["users"][0]["id"], "4");
+}
+
+#[tokio::test]
+async fn test_user_lists() {
+    let dir = fixture_dir("user-lists");
+    let query = |max_results: &'static str, token: Option<&'static str>| {
+        let mut query = vec![
+            (
+                "list.fields",
+                "id,name,description,owner_id,private,follower_count,member_count,created_at",
+            ),
+            ("max_results", max_results),
+        ];
+        query.extend(token.map(|token| ("pagination_token", token)));
+        query
+    };
+    let url = "https://api.twitter.com/2/users/12/owned_lists";
+    record_get(
+        &dir,
+        url,
+        &query("50", None),
+        json!({
+            "data": [
+                { "id": "101", "name": "Rustaceans", "owner_id": "12", "member_count": 40 },
+                { "id": "102", "name": "News", "owner_id": "12", "private": true }
+            ],
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+    record_get(
+        &dir,
+        url,
+        &query("48", Some("page2")),
+        json!({
+            "data": [{ "id": "103", "name": "Friends", "owner_id": "12" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/12/list_memberships",
+        &query("100", None),
+        json!({ "meta": { "result_count": 0 } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_owned_lists",
+        json!({ "identifier": "12", "max_results": 50 }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["user_id"], "12");
+    assert_eq!(result["count"], 3);
+    assert_eq!(result["lists"][0]["name"], "Rustaceans");
+    assert_eq!(result["lists"][1]["private"], true);
+    assert!(result.get("next_token").is_none());
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_list_memberships",
+        json!({ "identifier": "@12" }),
+    )
+    .await;
+    assert_eq!(result["count"], 0);
+}
+
+/// Test that a list is looked up by ID
+#[tokio::test]
+async fn test_get_list() {
+    let dir = fixture_dir("get-list");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/lists/101",
+        &[(
+            "list.fields",
+            "id,name,description,owner_id,private,follower_count,member_count,created_at",
+        )],
+        json!({
+            "data": {
+                "id": "101",
+                "name": "Rustaceans",
+                "description": "People writing Rust",
+                "owner_id": "7",
+                "private": false,
+                "follower_count": 12,
+                "member_count": 30
+            }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(&mut input, &mut output, "get_list", json!({ "list_id": "101" })).await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["list"]["name"], "Rustaceans");
+    assert_eq!(result["list"]["owner_id"], "7");
+    assert_eq!(result["list"]["member_count"], 30);
+
+    let result = call_tool(&mut input, &mut output, "get_list", json!({ "list_id": "rustaceans" })).await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("numeric"), "{}", result);
+}
+
+/// Test that a list's timeline comes back with its authors joined
+#[tokio::test]
+async fn test_get_list_tweets() {
+    let dir = fixture_dir("list-tweets");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/lists/101/tweets",
+        &[
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,referenced_tweets,edit_history_tweet_ids,edit_controls",
+            ),
+            ("expansions", "author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+            ("max_results", "2"),
+        ],
+        json!({
+            "data": [
+                { "id": "41", "text": "cargo &amp; clippy", "author_id": "7" },
+                { "id": "40", "text": "hello", "author_id": "8" }
+            ],
+            "includes": { "users": [
+                { "id": "7", "name": "Jack", "username": "jack" },
+                { "id": "8", "name": "Ev", "username": "ev" }
+            ] },
+            "meta": { "result_count": 2, "next_token": "page2" }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_list_tweets",
+        json!({ "list_id": "101", "max_results": 2 }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 2);
+    assert_eq!(result["tweets"][0]["text"], "cargo & clippy");
+    assert_eq!(result["tweets"][1]["author"]["username"], "ev");
+    assert_eq!(result["next_token"], "page2");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_list_tweets",
+        json!({ "list_id": "rustaceans" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+}
+
+/// Test that calls beyond the session's budget wait, and can be cancelled
+/// while waiting
+#[tokio::test]
+async fn test_call_budget() {
+    let server = XMcpServer::from_api(FakeApi::default()).with_scheduler(SchedulerConfig {
+        budget: 1,
+        window: Duration::from_secs(3600),
+    });
+    let (mut input, mut output) = serve_in_memory(server);
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "news" }),
+    )
+    .await;
+    assert_eq!(result["success"], true);
+
+    // The budget is used up, so this call waits; status is never queued
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+                "params": { "name": "search_tweets", "arguments": { "query": "news" },
+                            "_meta": { "priority": "low" } } }),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let status = call_tool(&mut input, &mut output, "status", json!({})).await;
+    assert_eq!(status["scheduler"]["used"], 1);
+    assert_eq!(status["scheduler"]["waiting"], 1);
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "method": "notifications/cancelled",
+                "params": { "requestId": 2 } }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["id"], 2);
+    assert_eq!(
+        response["result"]["structuredContent"]["error"],
+        "Cancelled by the client"
+    );
+}
+
+/// Test that only the advertised capabilities are served
+#[tokio::test]
+async fn test_capabilities() {
+    let server = XMcpServer::from_api(FakeApi::default()).with_capabilities(Capabilities {
+        logging: true,
+        ..Capabilities::default()
+    });
+    let info = serde_json::to_value(server.get_info()).unwrap();
+    assert!(info["capabilities"].get("tools").is_some());
+    assert!(info["capabilities"].get("logging").is_some());
+    assert!(info["capabilities"].get("prompts").is_none());
+
+    let (mut input, mut output) = serve_in_memory(server);
+    initialize(&mut input, &mut output).await;
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "prompts/list", "params": {} }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["error"]["code"], -32601);
+
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "logging/setLevel",
+                "params": { "level": "warning" } }),
+    )
+    .await;
+    let response = receive_message(&mut output).await;
+    assert_eq!(response["id"], 2);
+    assert!(response.get("error").is_none());
+
+    // A failed call is reported as a log message before its result
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": { "name": "get_user", "arguments": { "identifier": "nobody" } } }),
+    )
+    .await;
+    let message = receive_message(&mut output).await;
+    assert_eq!(message["method"], "notifications/message");
+    assert_eq!(message["params"]["level"], "warning");
+    assert_eq!(message["params"]["data"]["tool"], "get_user");
+    assert_eq!(message["params"]["data"]["error"], "User not found");
+    assert_eq!(receive_message(&mut output).await["id"], 3);
+
+    let server = XMcpServer::from_api(FakeApi::default()).with_capabilities(Capabilities {
+        tools: false,
+        ..Capabilities::default()
+    });
+    let (mut input, mut output) = serve_in_memory(server);
+    initialize(&mut input, &mut output).await;
+    send_message(
+        &mut input,
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list", "params": {} }),
+    )
+    .await;
+    assert_eq!(receive_message(&mut output).await["error"]["code"], -32601);
+}
+
+/// Test that a date range is followed across pages and its coverage reported
+#[tokio::test]
+async fn test_get_user_tweets_range() {
+    let dir = fixture_dir("user-tweets-range");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/jack",
+        &[(
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+        )],
+        json!({ "data": { "id": "12", "name": "Jack", "username": "jack", "public_metrics": {
+            "followers_count": 1, "following_count": 1, "tweet_count": 30000, "listed_count": 0
+        } } }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/12",
+        &[(
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+        )],
+        json!({ "data": { "id": "12", "name": "Jack", "username": "jack" } }),
+    );
+    let query = |max_results: &'static str, token: Option<&'static str>| {
+        let mut query = vec![
+            (
+                "tweet.fields",
+                "id,text,author_id,created_at,public_metrics,possibly_sensitive",
+            ),
+            ("start_time", "2024-05-01T00:00:00Z"),
+            ("end_time", "2024-05-08T00:00:00Z"),
+            ("max_results", max_results),
+        ];
+        query.extend(token.map(|token| ("pagination_token", token)));
+        query
+    };
+    let url = "https://api.twitter.com/2/users/12/tweets";
+    record_get(
+        &dir,
+        url,
+        &query("100", None),
+        json!({
+            "data": [{ "id": "52", "text": "late", "created_at": "2024-05-07T10:00:00.000Z" }],
+            "meta": { "result_count": 1, "next_token": "page2" }
+        }),
+    );
+    record_get(
+        &dir,
+        url,
+        &query("100", Some("page2")),
+        json!({
+            "data": [{ "id": "51", "text": "early", "created_at": "2024-05-04T10:00:00.000Z" }],
+            "meta": { "result_count": 1 }
+        }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets_range",
+        json!({
+            "identifier": "@jack",
+            "start_time": "2024-05-01T00:00:00Z",
+            "end_time": "2024-05-08T00:00:00+00:00"
+        }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 2);
+    assert_eq!(result["complete"], true);
+    assert_eq!(result["covered_start"], "2024-05-04T10:00:00.000Z");
+    assert_eq!(result["covered_end"], "2024-05-07T10:00:00.000Z");
+    // The account has far more than 3200 tweets and the range start was not reached
+    assert!(result["warning"].as_str().unwrap().contains("3200"));
+
+    // The page cap stops before the second page
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets_range",
+        json!({
+            "identifier": "12",
+            "start_time": "2024-05-01T00:00:00Z",
+            "end_time": "2024-05-08T00:00:00Z",
+            "max_pages": 1
+        }),
+    )
+    .await;
+    assert_eq!(result["count"], 1);
+    assert_eq!(result["complete"], false);
+    assert_eq!(result["next_token"], "page2");
+    assert!(result["warning"].as_str().unwrap().contains("next_token"));
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets_range",
+        json!({ "identifier": "jack", "start_time": "2024-05-08T00:00:00Z",
+                "end_time": "2024-05-01T00:00:00Z" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+}
+
+/// Test that `post_tweet` uploads media_paths in chunks and attaches them
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_post_tweet_media_paths() {
+    use x_mcp_server::media::UPLOAD_URL;
+
+    let dir = fixture_dir("media-upload");
+    std::fs::create_dir_all(&dir).unwrap();
+    let image = dir.join("cat.png");
+    std::fs::write(&image, b"PNGDATA").unwrap();
+    let exchanges = [
+        (
+            json!({ "command": "INIT", "total_bytes": "7", "media_type": "image/png", "media_category": "tweet_image" }),
+            202,
+            json!({ "media_id": 900, "media_id_string": "900" }),
+        ),
+        (
+            json!({ "command": "APPEND", "media_id": "900", "segment_index": "0", "media_data": "UE5HREFUQQ==" }),
+            204,
+            json!(""),
+        ),
+        (
+            json!({ "command": "FINALIZE", "media_id": "900" }),
+            201,
+            json!({ "media_id": 900, "media_id_string": "900", "size": 7 }),
+        ),
+    ];
+    for (body, status, response) in exchanges {
+        RecordedExchange {
+            method: "POST".to_string(),
+            url: UPLOAD_URL.to_string(),
+            query: Vec::new(),
+            body: Some(body),
+            status,
+            response,
+        }
+        .save(&dir)
+        .unwrap();
+    }
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: "https://api.twitter.com/2/tweets".to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "text": "look", "media": { "media_ids": ["5", "900"] } })),
+        status: 201,
+        response: json!({ "data": { "id": "78", "text": "look https://t.co/x" } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    // Without a media directory no file is read
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir.clone()));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "post_tweet",
+        json!({ "text": "look", "media_paths": [image] }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("X_MCP_MEDIA_DIR"), "{}", result);
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir.clone()));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client).with_media_dir(&dir));
+    initialize(&mut input, &mut output).await;
+
+    // Relative paths are resolved against the media directory
+    let post = json!({
+        "text": "look",
+        "media_ids": ["5"],
+        "media_paths": ["cat.png"],
+        "idempotency_key": "look-1"
+    });
+    let result = call_tool(&mut input, &mut output, "post_tweet", post.clone()).await;
+    assert_eq!(result["tweet"]["id"], "78");
+
+    // Files outside the media directory are refused, however they are named
+    let other = fixture_dir("media-outside");
+    std::fs::create_dir_all(&other).unwrap();
+    let outside = other.join("cat.png");
+    std::fs::write(&outside, b"PNGDATA").unwrap();
+    let escaping = format!("../{}/cat.png", other.file_name().unwrap().to_str().unwrap());
+    for path in [outside.to_str().unwrap(), escaping.as_str()] {
+        let result = call_tool(
+            &mut input,
+            &mut output,
+            "post_tweet",
+            json!({ "text": "look", "media_paths": [path] }),
+        )
+        .await;
+        assert_eq!(result["success"], false);
+        let error = result["error"].as_str().unwrap();
+        assert!(error.contains("outside the media directory"), "{}", error);
+    }
+    std::fs::remove_dir_all(&other).unwrap();
+
+    // Files are checked before anything is uploaded
+    let notes = dir.join("notes.txt");
+    std::fs::write(&notes, "hi").unwrap();
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "post_tweet",
+        json!({ "text": "look", "media_paths": [notes] }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("notes.txt"));
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "post_tweet",
+        json!({ "text": "look", "media_ids": ["1", "2", "3"], "media_paths": [image, image] }),
+    )
+    .await;
+    assert!(result["error"]
+        .as_str()
+        .unwrap()
+        .contains("at most 4 media"));
+
+    // Attachments X refuses are caught before uploading; this file has no
+    // recorded upload, so uploading it would fail differently
+    let dog = dir.join("dog.png");
+    std::fs::write(&dog, b"OTHERPNG").unwrap();
+    for arguments in [
+        json!({ "text": "vote", "media_paths": [dog], "poll_options": ["yes", "no"] }),
+        json!({ "text": "card", "media_paths": [dog], "card_uri": "card://1" }),
+    ] {
+        let result = call_tool(&mut input, &mut output, "post_tweet", arguments).await;
+        assert_eq!(result["success"], false);
+        let error = result["error"].as_str().unwrap();
+        assert!(error.contains("cannot be combined with media"), "{}", error);
+    }
+
+    // A retry with the same key returns the tweet without uploading again
+    std::fs::remove_file(dir.join("cat.png")).unwrap();
+    let retry = call_tool(&mut input, &mut output, "post_tweet", post).await;
+    assert_eq!(retry["tweet"]["id"], "78");
+    assert_eq!(retry["already_posted"], true);
+}
+
+/// Serve `responses` in order, one per request, on a local port
+///
+/// Returns the port's base URL and a handle yielding the requests read,
+/// head and body. A connection is read until the client closes it, so a
+/// response with `connection: close` moves on to the next connection.
+async fn serve_responses(responses: Vec<String>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let served = tokio::spawn(async move {
+        let mut connection = None;
+        let mut requests = Vec::new();
+        for response in responses {
+            let request = loop {
+                let stream = match &mut connection {
+                    Some(stream) => stream,
+                    None => connection.insert(listener.accept().await.unwrap().0),
+                };
+                match read_request(stream).await {
+                    Some(request) => break request,
+                    None => connection = None,
+                }
+            };
+            let stream = connection.as_mut().unwrap();
+            stream.write_all(response.as_bytes()).await.unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (base_url, served)
+}
+
+/// Read one HTTP request from `stream`, or `None` if the client closes the
+/// connection first
+async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    use tokio::io::AsyncReadExt;
+
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        if let Some(head_end) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+            let head = String::from_utf8_lossy(&request[..head_end]).to_lowercase();
+            let body_len = head
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length: "))
+                .map_or(0, |len| len.trim().parse().unwrap());
+            if request.len() >= head_end + 4 + body_len {
+                return Some(String::from_utf8(request).unwrap());
+            }
+        }
+        let read = stream.read(&mut buffer).await.unwrap();
+        if read == 0 {
+            return None;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+}
+
+/// A 200 response carrying `body` as JSON
+fn json_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+}
+
+/// Test that rate-limited requests are retried once X's window resets
+#[tokio::test]
+async fn test_rate_limit_retry() {
+    use x_mcp_server::config::RetryPolicy;
+
+    let now = chrono::Utc::now().timestamp();
+    let rate_limited = |reset: i64| {
+        format!(
+            "HTTP/1.1 429 Too Many Requests\r\nx-rate-limit-limit: 300\r\nx-rate-limit-remaining: 0\r\nx-rate-limit-reset: {}\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{{}}",
+            reset
+        )
+    };
+    let user = r#"{"data":{"id":"12","name":"Jack","username":"jack"}}"#;
+    let responses = vec![
+        // A reset already past, as with a skewed clock, still backs off
+        rate_limited(now - 3600),
+        rate_limited(now),
+        json_response(user),
+        rate_limited(now + 3600),
+    ];
+    let (address, served) = serve_responses(responses).await;
+    let base_url = format!("{}/2", address);
+
+    let client = XClient::new("token".to_string())
+        .with_base_urls(&base_url, &base_url)
+        .with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(5),
+            max_wait: Duration::from_secs(5),
+        });
+    let started = std::time::Instant::now();
+    let user = client.get_user_by_username("jack").await.unwrap().unwrap();
+    assert_eq!(user.id, "12");
+    // 10ms, then 20ms
+    assert!(started.elapsed() >= Duration::from_millis(30));
+
+    // A reset further away than the longest wait fails at once with the headers
+    let error = client.get_user_by_username("jack").await.unwrap_err();
+    assert!(matches!(
+        error,
+        x_mcp_server::XError::RateLimited { limit: Some(300), remaining: Some(0), reset: Some(reset) }
+            if reset == now + 3600
+    ));
+    assert!(error.to_string().contains(", resets at "), "{}", error);
+    served.await.unwrap();
+}
+
+/// Test that an overridden base URL is requested, and signed, as given
+#[tokio::test]
+async fn test_base_url_override_signed() {
+    use x_mcp_server::auth::OAuthCredentials;
+
+    let user = r#"{"data":{"id":"12","name":"jack","username":"jack"}}"#;
+    let (address, served) = serve_responses(vec![json_response(user)]).await;
+    let base_url = format!("{}/proxy/2/", address);
+
+    let credentials = || {
+        OAuthCredentials::new(
+            "consumer".to_string(),
+            "consumer-secret".to_string(),
+            "token".to_string(),
+            "token-secret".to_string(),
+        )
+    };
+    let client = XClient::new(String::new())
+        .with_base_url(base_url.clone())
+        .with_oauth(credentials());
+    let user = client.get_user_by_username("jack").await.unwrap().unwrap();
+    assert_eq!(user.id, "12");
+
+    let request = served.await.unwrap().remove(0);
+    let target = request.split(' ').nth(1).unwrap();
+    let (path, query) = target.split_once('?').unwrap();
+    assert_eq!(path, "/proxy/2/users/by/username/jack");
+
+    // Recompute the signature over the overridden URL from the sent values
+    let decode = |value: &str| {
+        percent_encoding::percent_decode_str(value)
+            .decode_utf8()
+            .unwrap()
+            .into_owned()
+    };
+    let header = request
+        .lines()
+        .find_map(|line| line.strip_prefix("authorization: OAuth "))
+        .expect("request should be signed");
+    let mut params: Vec<(String, String)> = header
+        .split(", ")
+        .map(|field| {
+            let (name, value) = field.split_once('=').unwrap();
+            (name.to_string(), decode(value.trim_matches('"')))
+        })
+        .collect();
+    let signature = params
+        .iter()
+        .position(|(name, _)| name == "oauth_signature")
+        .map(|index| params.remove(index).1)
+        .unwrap();
+    params.extend(query.split('&').map(|pair| {
+        let (name, value) = pair.split_once('=').unwrap();
+        (decode(name), decode(value))
+    }));
+    let params: Vec<(&str, &str)> = params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let url = format!("{}users/by/username/jack", base_url);
+    assert_eq!(credentials().generate_signature("GET", &url, &params), signature);
+}
+
+/// Test paging through search results with next_token
+#[tokio::test]
+async fn test_search_next_token() {
+    let dir = fixture_dir("search-pages");
+    let query = [
+        ("query", "rust"),
+        ("max_results", "10"),
+        ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+        ("expansions", "attachments.media_keys"),
+        ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+    ];
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &query,
+        json!({ "data": [{ "id": "9", "text": "newest" }],
+                "meta": { "result_count": 1, "next_token": "page2" } }),
+    );
+    let mut second_page = query.to_vec();
+    second_page.push(("next_token", "page2"));
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &second_page,
+        json!({ "data": [{ "id": "5", "text": "older" }], "meta": { "result_count": 1 } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let first = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust" }),
+    )
+    .await;
+    assert_eq!(first["tweets"][0]["id"], "9");
+    assert_eq!(first["next_token"], "page2");
+
+    let second = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "next_token": "page2" }),
+    )
+    .await;
+    assert_eq!(second["tweets"][0]["id"], "5");
+    assert!(second.get("next_token").is_none());
+}
+
+/// Test pairing search results with their authors, in the client and the tool
+#[tokio::test]
+async fn test_search_with_authors() {
+    let dir = fixture_dir("search-authors");
+    let response = json!({
+        "data": [
+            { "id": "9", "text": "newest", "author_id": "99" },
+            { "id": "5", "text": "older", "author_id": "7" }
+        ],
+        "includes": { "users": [{ "id": "99", "name": "Rust", "username": "rustlang" }] }
+    });
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("tweet.fields", "id,text,author_id"),
+            (
+                "user.fields",
+                "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+            ),
+            ("expansions", "author_id"),
+        ],
+        response.clone(),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+            ("user.fields", "id,name,username"),
+            ("expansions", "author_id,attachments.media_keys"),
+            ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+        ],
+        response,
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let page = client
+        .search_tweets_with_authors(SearchTweetsParams {
+            query: "rust".to_string(),
+            max_results: Some(10),
+            sort_order: None,
+            start_time: None,
+            end_time: None,
+            since_id: None,
+            until_id: None,
+            tweet_fields: None,
+            user_fields: None,
+            expansions: None,
+            media_fields: None,
+            next_token: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.tweets.len(), 2);
+    assert_eq!(page.tweets[0].1.as_ref().unwrap().username, "rustlang");
+    assert!(page.tweets[1].1.is_none());
+
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "include_users": true }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["tweets"][0]["author"]["username"], "rustlang");
+    assert!(result["tweets"][1].get("author").is_none());
+}
+
+/// Test that asking search_tweets for fewer than X's minimum of 10 results
+/// requests 10 and returns only as many as asked for
+#[tokio::test]
+async fn test_search_below_minimum() {
+    let dir = fixture_dir("search-minimum");
+    let tweets: Vec<_> = (0..10)
+        .map(|id| json!({ "id": id.to_string(), "text": "rust" }))
+        .collect();
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+            ("expansions", "attachments.media_keys"),
+            ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+        ],
+        json!({ "data": tweets, "meta": { "result_count": 10 } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "max_results": 5 }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["count"], 5);
+    assert_eq!(result["tweets"][4]["id"], "4");
+
+    let schema = schema_for_type::<SearchTweetsArgs>();
+    assert_eq!(schema["properties"]["max_results"]["minimum"], 1);
+    assert_eq!(schema["properties"]["max_results"]["maximum"], 100);
+}
+
+/// Test that `get_user_tweets` follows pages past X's 100-tweet limit
+#[tokio::test]
+async fn test_get_user_tweets_pages() {
+    let dir = fixture_dir("user-tweets-pages");
+    let url = "https://api.twitter.com/2/users/12/tweets";
+    let fields = (
+        "tweet.fields",
+        "id,text,author_id,created_at,public_metrics,possibly_sensitive",
+    );
+    let tweets = |ids: std::ops::Range<u32>| {
+        ids.map(|id| json!({ "id": id.to_string(), "text": "tweet" }))
+            .collect::<Vec<_>>()
+    };
+    record_get(
+        &dir,
+        url,
+        &[fields, ("max_results", "100")],
+        json!({ "data": tweets(200..300), "meta": { "next_token": "p2" } }),
+    );
+    // The last page asks for X's minimum of 5 and is trimmed to the 2 needed
+    record_get(
+        &dir,
+        url,
+        &[fields, ("max_results", "5"), ("pagination_token", "p2")],
+        json!({ "data": tweets(195..200), "meta": { "next_token": "p3" } }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets",
+        json!({ "identifier": "12", "is_user_id": true, "max_results": 102 }),
+    )
+    .await;
+    assert_eq!(result["count"], 102);
+    assert_eq!(result["tweets"][101]["id"], "196");
+}
+
+/// Test that since_id and until_id reach X on timelines and search, and
+/// that they must be tweet IDs
+#[tokio::test]
+async fn test_since_id_and_until_id() {
+    let dir = fixture_dir("since-until-id");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/12/tweets",
+        &[
+            ("tweet.fields", "id,text,author_id,created_at,public_metrics,possibly_sensitive"),
+            ("max_results", "5"),
+            ("since_id", "100"),
+            ("until_id", "200"),
+        ],
+        json!({ "data": [{ "id": "150", "text": "new since last time" }] }),
+    );
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/tweets/search/recent",
+        &[
+            ("query", "rust"),
+            ("max_results", "10"),
+            ("since_id", "100"),
+            ("tweet.fields", "id,text,author_id,created_at,entities,attachments"),
+            ("expansions", "attachments.media_keys"),
+            ("media.fields", x_mcp_server::client::MEDIA_FIELDS),
+        ],
+        json!({ "data": [{ "id": "170", "text": "fresh" }] }),
+    );
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "get_user_tweets",
+        json!({ "identifier": "12", "is_user_id": true, "max_results": 5, "since_id": "100", "until_id": "200" }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["tweets"][0]["id"], "150");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "since_id": "100" }),
+    )
+    .await;
+    assert_eq!(result["success"], true, "{}", result);
+    assert_eq!(result["tweets"][0]["id"], "170");
+
+    let result = call_tool(
+        &mut input,
+        &mut output,
+        "search_tweets",
+        json!({ "query": "rust", "since_id": "https://x.com/jack/status/20" }),
+    )
+    .await;
+    assert_eq!(result["success"], false);
+    assert!(result["error"].as_str().unwrap().contains("since_id must be a tweet ID"), "{}", result);
+}
+
+
+/// POST a JSON-RPC message to the HTTP transport and return the message in
+/// its event stream, if any, with the session ID the server answered with
+#[cfg(feature = "http")]
+async fn post_http_message(
+    url: &str,
+    session_id: Option<&str>,
+    message: serde_json::Value,
+) -> (Option<serde_json::Value>, Option<String>) {
+    let mut request = reqwest::Client::new()
+        .post(url)
+        .header("Accept", "application/json, text/event-stream")
+        .json(&message);
+    if let Some(session_id) = session_id {
+        request = request.header("Mcp-Session-Id", session_id);
+    }
+    let response = request.send().await.unwrap();
+    assert!(response.status().is_success(), "{}", response.status());
+    let session_id = response
+        .headers()
+        .get("mcp-session-id")
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = response.text().await.unwrap();
+    let message = body
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(str::trim)
+        .find(|data| !data.is_empty())
+        .map(|data| serde_json::from_str(data).unwrap());
+    (message, session_id)
+}
+
+/// Test two clients initializing and calling tools over HTTP at the same time
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_http_transport() {
+    let dir = fixture_dir("http-transport");
+    record_get(
+        &dir,
+        "https://api.twitter.com/2/users/by/username/jack",
+        &[(
+            "user.fields",
+            "id,name,username,description,public_metrics,profile_image_url,verified,created_at",
+        )],
+        json!({ "data": { "id": "12", "name": "jack", "username": "jack" } }),
+    );
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(XMcpServer::new(client).serve_http(listener));
+
+    let session = |name: &'static str| {
+        let url = url.clone();
+        async move {
+            let (response, session_id) = post_http_message(
+                &url,
+                None,
+                json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {
+                    "protocolVersion": "2025-06-18", "capabilities": {},
+                    "clientInfo": { "name": name, "version": "1.0" } } }),
+            )
+            .await;
+            assert_eq!(response.unwrap()["result"]["serverInfo"]["name"], "x-mcp-server");
+            let session_id = session_id.expect("initialize starts a session");
+            post_http_message(
+                &url,
+                Some(&session_id),
+                json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            )
+            .await;
+            let (response, _) = post_http_message(
+                &url,
+                Some(&session_id),
+                json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                        "params": { "name": "get_user", "arguments": { "identifier": "jack" } } }),
+            )
+            .await;
+            let response = response.unwrap();
+            assert_eq!(response["id"], 1);
+            assert_eq!(response["result"]["structuredContent"]["user"]["id"], "12");
+            session_id
+        }
+    };
+    let (first, second) = tokio::join!(session("first"), session("second"));
+    assert_ne!(first, second);
+}
+
+/// Test that the HTTP transport serves the metrics at /metrics
+#[cfg(feature = "http")]
+#[tokio::test]
+async fn test_http_metrics() {
+    let client = XClient::new(String::new());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(XMcpServer::new(client).serve_http(listener));
+
+    let url = format!("http://{}/mcp", address);
+    let (_, session_id) = post_http_message(
+        &url,
+        None,
+        json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": {
+            "protocolVersion": "2025-06-18", "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" } } }),
+    )
+    .await;
+    let session_id = session_id.unwrap();
+    post_http_message(
+        &url,
+        Some(&session_id),
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await;
+    post_http_message(
+        &url,
+        Some(&session_id),
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "preview_tweet", "arguments": { "text": "hi" } } }),
+    )
+    .await;
+
+    let response = reqwest::get(format!("http://{}/metrics", address)).await.unwrap();
+    assert!(response.status().is_success());
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let text = response.text().await.unwrap();
+    assert!(text.contains("x_mcp_tool_calls_total{tool=\"preview_tweet\"} 1"), "{}", text);
+}
+
+/// Serve a filtered stream that sends `chunks` with pauses between them,
+/// returning the v2 base URL
+async fn serve_stream(chunks: Vec<&'static str>) -> String {
+    use tokio::io::AsyncWriteExt;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}/2", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let request = read_request(&mut stream).await.unwrap();
+        assert!(request.starts_with("GET /2/tweets/search/stream?"), "{}", request);
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\nconnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        for chunk in chunks {
+            stream.write_all(chunk.as_bytes()).await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+    base_url
+}
+
+/// Tweets, keep-alives and errors on the stream as X sends them
+const STREAM_CHUNKS: [&str; 5] = [
+    "{\"data\":{\"id\":\"1\",\"text\":\"first\"},\"matching_rules\":[{\"id\":\"7\",\"tag\":\"rust\"}]}\r\n",
+    "\r\n",
+    "{\"data\":{\"id\":\"2\",\"text\":\"sec",
+    "ond &amp; more\"}}\r\n{\"errors\":[{\"title\":\"operational-disconnect\",\"detail\":\"Reconnect\"}]}\r\n",
+    "{\"data\":{\"id\":\"3\",\"text\":\"third\"}}\r\n",
+];
+
+/// Test reading tweets split across chunks, with keep-alives and errors between them
+#[tokio::test]
+async fn test_filtered_stream() {
+    let base_url = serve_stream(STREAM_CHUNKS.to_vec()).await;
+    let client = XClient::new("token".to_string()).with_base_urls(&base_url, &base_url);
+    let mut stream = client.connect_filtered_stream().await.unwrap();
+
+    assert_eq!(stream.next().await.unwrap().unwrap().id, "1");
+    assert_eq!(stream.next().await.unwrap().unwrap().text, "second &amp; more");
+    let error = stream.next().await.unwrap().unwrap_err();
+    assert!(error.to_string().contains("operational-disconnect"), "{}", error);
+    assert_eq!(stream.next().await.unwrap().unwrap().id, "3");
+    assert!(stream.next().await.is_none());
+}
+
+/// Test that poll_stream connects on the first call and drains buffered tweets
+#[tokio::test]
+async fn test_poll_stream() {
+    let base_url = serve_stream(STREAM_CHUNKS.to_vec()).await;
+    let client = XClient::new("token".to_string()).with_base_urls(&base_url, &base_url);
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let mut tweets = Vec::new();
+    let mut error = None;
+    for _ in 0..100 {
+        let poll = call_tool(&mut input, &mut output, "poll_stream", json!({ "max_results": 1 })).await;
+        assert_eq!(poll["success"], true, "{}", poll);
+        assert!(poll["count"].as_u64().unwrap() <= 1);
+        tweets.extend(poll["tweets"].as_array().unwrap().iter().cloned());
+        if let Some(message) = poll.get("error") {
+            error = Some(message.clone());
+        }
+        if tweets.len() == 3 && error.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let ids: Vec<&str> = tweets.iter().map(|tweet| tweet["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, ["1", "2", "3"]);
+    assert_eq!(tweets[1]["text"], "second & more");
+    assert_eq!(error.unwrap(), "stream closed by X");
+}
+
+/// Test listing, adding and deleting filtered stream rules
+#[cfg(feature = "write-ops")]
+#[tokio::test]
+async fn test_stream_rules() {
+    let dir = fixture_dir("stream-rules");
+    let rules_url = "https://api.twitter.com/2/tweets/search/stream/rules";
+    record_get(
+        &dir,
+        rules_url,
+        &[],
+        json!({ "data": [{ "id": "7", "value": "from:rustlang", "tag": "rust" }],
+                "meta": { "result_count": 1 } }),
+    );
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: rules_url.to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "add": [{ "value": "#rustlang -is:retweet" }] })),
+        status: 201,
+        response: json!({ "data": [{ "id": "8", "value": "#rustlang -is:retweet" }],
+                          "meta": { "summary": { "created": 1, "not_created": 0 } } }),
+    }
+    .save(&dir)
+    .unwrap();
+    RecordedExchange {
+        method: "POST".to_string(),
+        url: rules_url.to_string(),
+        query: Vec::new(),
+        body: Some(json!({ "delete": { "ids": ["7", "8"] } })),
+        status: 200,
+        response: json!({ "meta": { "summary": { "deleted": 2, "not_deleted": 0 } } }),
+    }
+    .save(&dir)
+    .unwrap();
+
+    let client = XClient::new(String::new()).with_replay(ReplayMode::Replay(dir));
+    let (mut input, mut output) = serve_in_memory(XMcpServer::new(client));
+    initialize(&mut input, &mut output).await;
+
+    let rules = call_tool(&mut input, &mut output, "get_stream_rules", json!({})).await;
+    assert_eq!(rules["success"], true, "{}", rules);
+    assert_eq!(rules["count"], 1);
+    assert_eq!(rules["rules"][0]["tag"], "rust");
+
+    let added = call_tool(
+        &mut input,
+        &mut output,
+        "add_stream_rule",
+        json!({ "value": "#rustlang -is:retweet" }),
+    )
+    .await;
+    assert_eq!(added["success"], true, "{}", added);
+    assert_eq!(added["rule"]["id"], "8");
+
+    let empty = call_tool(&mut input, &mut output, "delete_stream_rules", json!({ "rule_ids": [] })).await;
+    assert_eq!(empty["success"], false);
+
+    let deleted = call_tool(
+        &mut input,
+        &mut output,
+        "delete_stream_rules",
+        json!({ "rule_ids": ["7", "8"] }),
+    )
+    .await;
+    assert_eq!(deleted["success"], true, "{}", deleted);
+    assert_eq!(deleted["deleted"], 2);
+}
+
+/// Test that a built client requests its base URL with its User-Agent and
+/// reuses the pooled connection
+#[tokio::test]
+async fn test_client_builder() {
+    // Both requests arrive on the one connection: a second connection would
+    // not be accepted while the first stays open
+    let user = r#"{"data":{"id":"12","name":"jack","username":"jack"}}"#;
+    let (address, served) = serve_responses(vec![json_response(user), json_response(user)]).await;
+    let base_url = format!("{}/2/", address);
+
+    let client = XClient::builder()
+        .bearer_token("token")
+        .base_url(base_url)
+        .timeout(Duration::from_secs(5))
+        .user_agent("my-agent/1.0")
+        .build()
+        .unwrap();
+    for _ in 0..2 {
+        let user = client.get_user_by_username("jack").await.unwrap().unwrap();
+        assert_eq!(user.id, "12");
+    }
+
+    for request in served.await.unwrap() {
+        assert!(request.starts_with("GET /2/users/by/username/jack?"), "{}", request);
+        assert!(request.contains("\r\nuser-agent: my-agent/1.0\r\n"), "{}", request);
+        assert!(request.contains("\r\nauthorization: Bearer token\r\n"), "{}", request);
+    }
+}
+
+/// Test that requests name the crate and its version in the User-Agent
+/// header unless it is replaced
+#[tokio::test]
+async fn test_user_agent() {
+    let user = r#"{"data":{"id":"12","name":"jack","username":"jack"}}"#;
+    let (address, served) = serve_responses(vec![json_response(user), json_response(user)]).await;
+    let base_url = format!("{}/2", address);
+
+    let client = XClient::new("token".to_string()).with_base_urls(&base_url, &base_url);
+    client.get_user_by_username("jack").await.unwrap();
+    let client = client.with_user_agent("my-agent/1.0");
+    client.get_user_by_username("jack").await.unwrap();
+
+    let user_agents: Vec<Option<String>> = served
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            request
+                .lines()
+                .find_map(|line| line.strip_prefix("user-agent: "))
+                .map(str::to_string)
+        })
+        .collect();
+    assert_eq!(user_agents[0], Some(format!("x-mcp-server/{}", x_mcp_server::VERSION)));
+    assert_eq!(user_agents[1].as_deref(), Some("my-agent/1.0"));
+}
+
+/// Test the tools against the offline mock and its fixtures
+#[cfg(feature = "mock")]
+#[tokio::test]
+async fn test_mock_client() {
+    use x_mcp_server::mock::{self, MockXClient};
+
+    let mock = MockXClient::new();
+    let (mut input, mut output) = serve_in_memory(XMcpServer::from_api(mock.clone()));
+    initialize(&mut input, &mut output).await;
+
+    let user = call_tool(&mut input, &mut output, "get_user", json!({ "identifier": "xdevelopers" })).await;
+    assert_eq!(user["success"], true, "{}", user);
+    assert_eq!(user["user"]["id"], mock::USER_ID);
+
+    let found = call_tool(&mut input, &mut output, "search_tweets", json!({ "query": "rust" })).await;
+    assert_eq!(found["success"], true, "{}", found);
+    let ids: Vec<&str> = found["tweets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tweet| tweet["id"].as_str().unwrap())
+        .collect();
+    let expected: Vec<String> = mock::search_page().tweets.into_iter().map(|tweet| tweet.id).collect();
+    assert_eq!(ids, expected);
+
+    let tweet = call_tool(&mut input, &mut output, "get_tweet", json!({ "tweet_id": mock::TWEET_ID })).await;
+    assert_eq!(tweet["success"], true, "{}", tweet);
+    assert_eq!(tweet["tweet"]["text"], mock::tweet().text);
+
+    // Outside the mock's subset tools fail plainly rather than with bad data
+    let followers = call_tool(&mut input, &mut output, "get_followers", json!({ "identifier": "XDevelopers" })).await;
+    assert_eq!(followers["success"], false);
+    assert!(followers["error"].as_str().unwrap().contains("not supported by this backend"), "{}", followers);
+
+    #[cfg(feature = "write-ops")]
+    {
+        let posted = call_tool(&mut input, &mut output, "post_tweet", json!({ "text": "Hello from the mock" })).await;
+        assert_eq!(posted["success"], true, "{}", posted);
+        assert_eq!(mock.posted_tweets()[0].text, "Hello from the mock");
+        let timeline = call_tool(&mut input, &mut output, "get_user_tweets", json!({ "identifier": "XDevelopers" })).await;
+        assert_eq!(timeline["tweets"][0]["text"], "Hello from the mock", "{}", timeline);
+    }
+}